@@ -19,8 +19,10 @@ impl Actor for HomeAssistantClient {
 
     fn stopped(&mut self, _ctx: &mut Self::Context) {
         debug!("[{}] HA client stopped", self.id);
+        self.pending_requests.clear();
         self.controller_actor.do_send(ConnectionEvent {
             client_id: self.id.clone(),
+            device_id: self.device_id.clone(),
             state: ConnectionState::Closed,
         });
     }
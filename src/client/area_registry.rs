@@ -0,0 +1,108 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Home Assistant area registry handling with the `config/area_registry/list` request.
+//!
+//! The area names are combined with the area assignments of the entity and device registries to set
+//! the area of the available entities.
+
+use crate::client::device_registry::entity_area_id;
+use crate::client::HomeAssistantClient;
+use actix::Context;
+use log::{error, info};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+impl HomeAssistantClient {
+    /// Request the HA area registry. The result is handled in [`Self::handle_area_registry_result`].
+    pub(crate) fn send_area_registry_list(&mut self, ctx: &mut Context<HomeAssistantClient>) {
+        let id = self.new_msg_id();
+        self.area_registry_id = Some(id);
+        if let Err(e) = self.send_json(json!({"id": id, "type": "config/area_registry/list"}), ctx)
+        {
+            error!(
+                "[{}] Error sending config/area_registry/list to HA: {:?}",
+                self.id, e
+            );
+        }
+    }
+
+    /// Cache the area names of the HA area registry.
+    pub(crate) fn handle_area_registry_result(&mut self, result: Option<&Value>) {
+        self.area_names = area_names(result);
+        info!("[{}] Received {} areas", self.id, self.area_names.len());
+    }
+
+    /// Get the area name of an entity, or of its device if the entity has no own area.
+    ///
+    /// Returns `None` if the entity is not assigned to an area, or if the area is unknown.
+    pub(crate) fn entity_area(&self, entity_id: &str) -> Option<String> {
+        let area_id = entity_area_id(
+            entity_id,
+            &self.entity_areas,
+            &self.entity_devices,
+            &self.device_areas,
+        );
+        area_name(area_id, &self.area_names)
+    }
+}
+
+/// Get the area id to area name mapping from an area registry list result.
+fn area_names(result: Option<&Value>) -> HashMap<String, String> {
+    result
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let area_id = entry.get("area_id").and_then(|v| v.as_str())?;
+            let name = entry.get("name").and_then(|v| v.as_str())?;
+            Some((area_id.to_string(), name.to_string()))
+        })
+        .collect()
+}
+
+fn area_name(area_id: Option<&str>, area_names: &HashMap<String, String>) -> Option<String> {
+    area_id.and_then(|area_id| area_names.get(area_id)).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> Value {
+        json!([
+            { "area_id": "living_room", "name": "Living Room", "picture": null },
+            { "area_id": "kitchen", "name": "Kitchen" },
+            { "area_id": "attic" }
+        ])
+    }
+
+    #[test]
+    fn area_names_are_collected() {
+        let result = area_names(Some(&registry()));
+
+        assert_eq!(2, result.len());
+        assert_eq!(Some(&"Living Room".to_string()), result.get("living_room"));
+        assert_eq!(Some(&"Kitchen".to_string()), result.get("kitchen"));
+    }
+
+    #[test]
+    fn invalid_registry_result_has_no_areas() {
+        assert!(area_names(None).is_empty());
+        assert!(area_names(Some(&json!({ "code": "unauthorized" }))).is_empty());
+    }
+
+    #[test]
+    fn entity_area_name_is_resolved() {
+        let areas = area_names(Some(&registry()));
+
+        assert_eq!(
+            Some("Kitchen".to_string()),
+            area_name(Some("kitchen"), &areas)
+        );
+        // unknown area
+        assert_eq!(None, area_name(Some("garage"), &areas));
+        // no assigned area
+        assert_eq!(None, area_name(None, &areas));
+    }
+}
@@ -0,0 +1,162 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Handling of HA entities with an assumed state.
+//!
+//! Entities with `assumed_state: true` don't report reliable state feedback, e.g. fire-and-forget
+//! RF switches. The flag is exposed as entity option, and an optimistic state can be sent to the
+//! remote right after an on / off command.
+
+use crate::client::model::EventState;
+use serde_json::{Map, Value};
+use uc_api::intg::{AvailableIntgEntity, EntityChange, EntityCommand};
+use uc_api::EntityType;
+
+/// Entity option to indicate that the entity state is assumed.
+pub const OPTION_ASSUMED_STATE: &str = "assumed_state";
+
+/// Check if the HA entity attributes have the `assumed_state` flag set.
+pub(crate) fn is_assumed_state(ha_attr: Option<&Map<String, Value>>) -> bool {
+    ha_attr
+        .and_then(|attr| attr.get("assumed_state"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or_default()
+}
+
+/// Add the assumed state option to an available entity if the HA entity has an assumed state.
+pub(crate) fn with_assumed_state(entity: &mut AvailableIntgEntity, ha_state: &EventState) {
+    if is_assumed_state(ha_state.attributes.as_ref()) {
+        entity
+            .options
+            .get_or_insert_with(Default::default)
+            .insert(OPTION_ASSUMED_STATE.into(), true.into());
+    }
+}
+
+/// Create an optimistic entity change for an on / off command of an assumed state entity.
+///
+/// Toggle commands require the last known state. Other commands and entities with a reported
+/// state are ignored.
+pub(crate) fn optimistic_entity_change(
+    command: &EntityCommand,
+    ha_state: Option<&EventState>,
+) -> Option<EntityChange> {
+    let ha_state = ha_state.filter(|s| is_assumed_state(s.attributes.as_ref()))?;
+    if !matches!(command.entity_type, EntityType::Switch | EntityType::Light) {
+        return None;
+    }
+
+    let state = match (command.cmd_id.as_str(), ha_state.state.as_str()) {
+        ("on", _) | ("toggle", "off") => "ON",
+        ("off", _) | ("toggle", "on") => "OFF",
+        _ => return None,
+    };
+
+    let mut attributes = Map::with_capacity(1);
+    attributes.insert("state".into(), state.into());
+    Some(EntityChange {
+        device_id: None,
+        entity_type: command.entity_type.clone(),
+        entity_id: command.entity_id.clone(),
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn ha_state(state: &str, assumed_state: bool) -> EventState {
+        serde_json::from_value(json!({
+            "state": state,
+            "attributes": { "assumed_state": assumed_state }
+        }))
+        .expect("invalid test data")
+    }
+
+    #[rstest]
+    #[case("on", "off", "ON")]
+    #[case("on", "on", "ON")]
+    #[case("off", "on", "OFF")]
+    #[case("toggle", "off", "ON")]
+    #[case("toggle", "on", "OFF")]
+    fn optimistic_state_is_emitted(
+        #[case] cmd_id: &str,
+        #[case] last_state: &str,
+        #[case] expected: &str,
+    ) {
+        let result = optimistic_entity_change(
+            &new_entity_command("switch", "switch.rf_plug", cmd_id, None),
+            Some(&ha_state(last_state, true)),
+        );
+
+        let entity_change = result.expect("Expected an optimistic entity change");
+        assert_eq!(EntityType::Switch, entity_change.entity_type);
+        assert_eq!("switch.rf_plug", entity_change.entity_id);
+        assert_eq!(
+            Some(&json!(expected)),
+            entity_change.attributes.get("state")
+        );
+    }
+
+    #[test]
+    fn optimistic_state_for_light() {
+        let result = optimistic_entity_change(
+            &new_entity_command("light", "light.rf_plug", "off", None),
+            Some(&ha_state("on", true)),
+        );
+
+        assert_eq!(
+            Some(&json!("OFF")),
+            result.as_ref().and_then(|c| c.attributes.get("state"))
+        );
+    }
+
+    #[rstest]
+    #[case(
+        new_entity_command("switch", "switch.rf_plug", "on", None),
+        Some(ha_state("off", false))
+    )]
+    #[case(new_entity_command("switch", "switch.rf_plug", "on", None), None)]
+    #[case(
+        new_entity_command("switch", "switch.rf_plug", "toggle", None),
+        Some(ha_state("unknown", true))
+    )]
+    #[case(
+        new_entity_command("cover", "cover.rf_plug", "on", None),
+        Some(ha_state("closed", true))
+    )]
+    fn no_optimistic_state(#[case] cmd: EntityCommand, #[case] ha_state: Option<EventState>) {
+        assert!(optimistic_entity_change(&cmd, ha_state.as_ref()).is_none());
+    }
+
+    #[test]
+    fn assumed_state_option() {
+        let mut entity = AvailableIntgEntity {
+            entity_id: "switch.rf_plug".into(),
+            device_id: None,
+            entity_type: EntityType::Switch,
+            device_class: None,
+            name: Default::default(),
+            features: None,
+            area: None,
+            options: None,
+            attributes: None,
+        };
+
+        with_assumed_state(&mut entity, &ha_state("off", false));
+        assert!(entity.options.is_none());
+
+        with_assumed_state(&mut entity, &ha_state("off", true));
+        assert_eq!(
+            Some(&json!(true)),
+            entity
+                .options
+                .as_ref()
+                .and_then(|o| o.get(OPTION_ASSUMED_STATE))
+        );
+    }
+}
@@ -0,0 +1,261 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Attribute entities: selected HA entity attributes exposed as derived read-only sensors.
+//!
+//! The entity id of a derived entity is the HA entity id with the attribute name appended after a
+//! colon, e.g. `sensor.outdoor:battery`. The value is updated with the state changes of the HA
+//! entity.
+
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
+use uc_api::EntityType;
+
+/// Separator between the HA entity id and the attribute name of a derived entity id.
+pub const ATTRIBUTE_ENTITY_SEPARATOR: char = ':';
+
+/// Create the entity id of a derived attribute entity.
+pub(crate) fn attribute_entity_id(entity_id: &str, attribute: &str) -> String {
+    format!("{entity_id}{ATTRIBUTE_ENTITY_SEPARATOR}{attribute}")
+}
+
+/// Replace derived attribute entity ids with the entity id of their HA entity.
+///
+/// HA only knows the original entity, which must be subscribed to get the attribute changes.
+pub(crate) fn with_parent_entities(entity_ids: HashSet<String>) -> HashSet<String> {
+    entity_ids
+        .into_iter()
+        .map(|id| match id.split_once(ATTRIBUTE_ENTITY_SEPARATOR) {
+            Some((parent, _)) => parent.to_string(),
+            None => id,
+        })
+        .collect()
+}
+
+/// Create the derived attribute entities of an available entity.
+///
+/// Configured attributes not present in the HA entity attributes are skipped.
+///
+/// # Arguments
+///
+/// * `entity`: converted available entity of the HA entity.
+/// * `state`: HA entity state.
+/// * `ha_attr`: HA entity attributes.
+/// * `config`: configured attribute names per HA entity id.
+pub(crate) fn attribute_entities(
+    entity: &AvailableIntgEntity,
+    state: &str,
+    ha_attr: Option<&Map<String, Value>>,
+    config: &HashMap<String, Vec<String>>,
+) -> Vec<AvailableIntgEntity> {
+    let ha_attr = match (config.get(&entity.entity_id), ha_attr) {
+        (Some(_), Some(ha_attr)) => ha_attr,
+        _ => return Vec::new(),
+    };
+
+    map_attribute_entities(&entity.entity_id, state, ha_attr, config)
+        .into_iter()
+        .map(|(entity_id, attribute, attributes)| {
+            let name = entity
+                .name
+                .iter()
+                .map(|(lang, name)| (lang.clone(), format!("{name} {attribute}")))
+                .collect();
+            AvailableIntgEntity {
+                entity_id,
+                device_id: None,
+                entity_type: EntityType::Sensor,
+                device_class: Some("custom".into()),
+                name,
+                features: None,
+                area: entity.area.clone(),
+                options: None,
+                attributes: Some(attributes),
+            }
+        })
+        .collect()
+}
+
+/// Create the entity changes of the derived attribute entities of a HA entity state change.
+pub(crate) fn attribute_entity_changes(
+    entity_id: &str,
+    state: &str,
+    ha_attr: Option<&Map<String, Value>>,
+    config: &HashMap<String, Vec<String>>,
+) -> Vec<EntityChange> {
+    let ha_attr = match ha_attr {
+        Some(ha_attr) => ha_attr,
+        None => return Vec::new(),
+    };
+
+    map_attribute_entities(entity_id, state, ha_attr, config)
+        .into_iter()
+        .map(|(entity_id, _, attributes)| EntityChange {
+            device_id: None,
+            entity_type: EntityType::Sensor,
+            entity_id,
+            attributes,
+        })
+        .collect()
+}
+
+/// Map the configured attributes of a HA entity to sensor attributes.
+///
+/// returns: derived entity id, attribute name and sensor attributes of each present attribute.
+fn map_attribute_entities<'a>(
+    entity_id: &str,
+    state: &str,
+    ha_attr: &Map<String, Value>,
+    config: &'a HashMap<String, Vec<String>>,
+) -> Vec<(String, &'a str, Map<String, Value>)> {
+    let attributes = match config.get(entity_id) {
+        Some(attributes) => attributes,
+        None => return Vec::new(),
+    };
+
+    attributes
+        .iter()
+        .filter_map(|attribute| {
+            let value = ha_attr.get(attribute)?;
+            let mut attributes = Map::with_capacity(2);
+            match state {
+                "unavailable" | "unknown" => {
+                    attributes.insert("state".into(), state.to_uppercase().into());
+                }
+                _ => {
+                    attributes.insert("state".into(), "ON".into());
+                    attributes.insert("value".into(), value.clone());
+                }
+            }
+            Some((
+                attribute_entity_id(entity_id, attribute),
+                attribute.as_str(),
+                attributes,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config() -> HashMap<String, Vec<String>> {
+        HashMap::from([(
+            "sensor.outdoor".to_string(),
+            vec![
+                "battery".to_string(),
+                "signal_strength".to_string(),
+                "missing".to_string(),
+            ],
+        )])
+    }
+
+    fn ha_attr() -> Map<String, Value> {
+        json!({
+            "battery": 87,
+            "signal_strength": -67,
+            "firmware": "1.2.3",
+            "unit_of_measurement": "°C",
+            "friendly_name": "Outdoor"
+        })
+        .as_object()
+        .cloned()
+        .unwrap()
+    }
+
+    fn entity(entity_id: &str) -> AvailableIntgEntity {
+        AvailableIntgEntity {
+            entity_id: entity_id.into(),
+            device_id: None,
+            entity_type: EntityType::Sensor,
+            device_class: None,
+            name: HashMap::from([("en".into(), "Outdoor".into())]),
+            features: None,
+            area: Some("Garden".into()),
+            options: None,
+            attributes: None,
+        }
+    }
+
+    #[test]
+    fn configured_attributes_are_split_into_entities() {
+        let entities = attribute_entities(
+            &entity("sensor.outdoor"),
+            "12.5",
+            Some(&ha_attr()),
+            &config(),
+        );
+
+        assert_eq!(2, entities.len());
+        assert_eq!("sensor.outdoor:battery", entities[0].entity_id);
+        assert_eq!(EntityType::Sensor, entities[0].entity_type);
+        assert_eq!(
+            Some(&"Outdoor battery".to_string()),
+            entities[0].name.get("en")
+        );
+        assert_eq!(Some("Garden".to_string()), entities[0].area);
+        assert_eq!(
+            Some(json!({ "state": "ON", "value": 87 })),
+            entities[0].attributes.clone().map(Value::Object)
+        );
+        assert_eq!("sensor.outdoor:signal_strength", entities[1].entity_id);
+        assert_eq!(
+            Some(json!({ "state": "ON", "value": -67 })),
+            entities[1].attributes.clone().map(Value::Object)
+        );
+    }
+
+    #[test]
+    fn entity_without_configured_attributes_is_not_split() {
+        let entities = attribute_entities(
+            &entity("sensor.indoor"),
+            "21.0",
+            Some(&ha_attr()),
+            &config(),
+        );
+
+        assert!(entities.is_empty());
+    }
+
+    #[test]
+    fn state_change_updates_attribute_entities() {
+        let changes =
+            attribute_entity_changes("sensor.outdoor", "12.0", Some(&ha_attr()), &config());
+
+        let entity_ids: Vec<&str> = changes.iter().map(|c| c.entity_id.as_str()).collect();
+        assert_eq!(
+            vec!["sensor.outdoor:battery", "sensor.outdoor:signal_strength"],
+            entity_ids
+        );
+        assert_eq!(Some(&json!(87)), changes[0].attributes.get("value"));
+    }
+
+    #[test]
+    fn unavailable_entity_has_unavailable_attribute_entities() {
+        let changes =
+            attribute_entity_changes("sensor.outdoor", "unavailable", Some(&ha_attr()), &config());
+
+        assert_eq!(2, changes.len());
+        assert_eq!(
+            json!({ "state": "UNAVAILABLE" }),
+            Value::Object(changes[0].attributes.clone())
+        );
+    }
+
+    #[test]
+    fn attribute_entity_subscriptions_are_mapped_to_parent() {
+        let result = with_parent_entities(HashSet::from([
+            "sensor.outdoor:battery".to_string(),
+            "sensor.outdoor:signal_strength".to_string(),
+            "light.kitchen".to_string(),
+        ]));
+
+        assert_eq!(
+            HashSet::from(["sensor.outdoor".to_string(), "light.kitchen".to_string()]),
+            result
+        );
+    }
+}
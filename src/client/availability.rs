@@ -0,0 +1,104 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Dedicated `entity_available` events when the availability of an entity flips.
+//!
+//! The availability is tracked per entity as it was last forwarded to the remotes, so that a
+//! debounced `unavailable` state doesn't result in an availability event.
+
+use std::collections::HashMap;
+
+/// Availability tracking of the forwarded entity changes.
+pub(crate) struct AvailabilityEvents {
+    enabled: bool,
+    /// Last forwarded availability by entity id
+    available: HashMap<String, bool>,
+}
+
+impl AvailabilityEvents {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            available: Default::default(),
+        }
+    }
+
+    /// Track the availability of a forwarded entity change.
+    ///
+    /// # Arguments
+    ///
+    /// * `entity_id`: entity identifier.
+    /// * `available`: availability of the forwarded entity change.
+    /// * `previous`: availability of the previous HA entity state, used if no entity change has
+    ///   been forwarded yet.
+    ///
+    /// returns: the new availability if it flipped, `None` otherwise or if disabled.
+    pub fn changed(
+        &mut self,
+        entity_id: &str,
+        available: bool,
+        previous: Option<bool>,
+    ) -> Option<bool> {
+        if !self.enabled {
+            return None;
+        }
+        let last = self
+            .available
+            .insert(entity_id.to_string(), available)
+            .or(previous);
+
+        match last {
+            Some(last) if last != available => Some(available),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn availability_flip() {
+        let mut events = AvailabilityEvents::new(true);
+
+        assert_eq!(None, events.changed("light.foo", true, Some(true)));
+        assert_eq!(Some(false), events.changed("light.foo", false, Some(true)));
+        assert_eq!(None, events.changed("light.foo", false, Some(false)));
+        assert_eq!(Some(true), events.changed("light.foo", true, Some(false)));
+    }
+
+    #[test]
+    fn initial_state_uses_previous_ha_state() {
+        let mut events = AvailabilityEvents::new(true);
+
+        assert_eq!(Some(true), events.changed("light.foo", true, Some(false)));
+        assert_eq!(None, events.changed("light.bar", false, None));
+    }
+
+    #[test]
+    fn forwarded_availability_takes_precedence() {
+        let mut events = AvailabilityEvents::new(true);
+        assert_eq!(None, events.changed("light.foo", true, Some(true)));
+
+        // debounced unavailable state was never forwarded
+        assert_eq!(None, events.changed("light.foo", true, Some(false)));
+    }
+
+    #[test]
+    fn entities_are_tracked_separately() {
+        let mut events = AvailabilityEvents::new(true);
+        assert_eq!(Some(false), events.changed("light.foo", false, Some(true)));
+
+        assert_eq!(None, events.changed("light.bar", true, Some(true)));
+        assert_eq!(Some(true), events.changed("light.foo", true, None));
+    }
+
+    #[test]
+    fn disabled() {
+        let mut events = AvailabilityEvents::new(false);
+
+        assert_eq!(None, events.changed("light.foo", false, Some(true)));
+        assert_eq!(None, events.changed("light.foo", true, None));
+    }
+}
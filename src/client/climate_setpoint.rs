@@ -0,0 +1,208 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Climate setpoint numbers: thermostats implemented as a `climate` entity with the target
+//! temperature in a separate `number` or `input_number` entity.
+//!
+//! The number entity is still exposed as a standalone entity. With a configured association, the
+//! number value is also presented as target temperature of the climate entity, and the target
+//! temperature command of the climate entity sets the number value.
+
+use crate::client::model::EventState;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
+use uc_api::{ClimateFeature, EntityType};
+
+/// Add the associated setpoint numbers of the subscribed climate entities.
+///
+/// HA only sends the number state changes if the number entity is subscribed.
+pub(crate) fn with_setpoint_numbers(
+    mut entity_ids: HashSet<String>,
+    config: &HashMap<String, String>,
+) -> HashSet<String> {
+    let numbers: Vec<String> = config
+        .iter()
+        .filter(|(climate_id, _)| entity_ids.contains(*climate_id))
+        .map(|(_, number_id)| number_id.clone())
+        .collect();
+    entity_ids.extend(numbers);
+    entity_ids
+}
+
+/// Create the climate entity changes of a setpoint number state change.
+///
+/// # Arguments
+///
+/// * `entity_id`: HA entity id of the changed entity.
+/// * `state`: new HA entity state.
+/// * `config`: associated setpoint number per climate entity id.
+///
+/// returns: target temperature changes of the associated climate entities. Empty if the entity
+/// isn't an associated number or the state isn't numeric.
+pub(crate) fn setpoint_entity_changes(
+    entity_id: &str,
+    state: &str,
+    config: &HashMap<String, String>,
+) -> Vec<EntityChange> {
+    let temperature = match setpoint_value(state) {
+        Some(temperature) => temperature,
+        None => return Vec::new(),
+    };
+
+    config
+        .iter()
+        .filter(|(_, number_id)| *number_id == entity_id)
+        .map(|(climate_id, _)| EntityChange {
+            device_id: None,
+            entity_type: EntityType::Climate,
+            entity_id: climate_id.clone(),
+            attributes: serde_json::Map::from_iter([(
+                "target_temperature".into(),
+                temperature.clone(),
+            )]),
+        })
+        .collect()
+}
+
+/// Present the associated setpoint number of a climate entity as target temperature.
+///
+/// # Arguments
+///
+/// * `entity`: converted climate entity.
+/// * `entity_states`: last known HA states of all entities.
+/// * `config`: associated setpoint number per climate entity id.
+pub(crate) fn with_climate_setpoint(
+    entity: &mut AvailableIntgEntity,
+    entity_states: &HashMap<String, EventState>,
+    config: &HashMap<String, String>,
+) {
+    if entity.entity_type != EntityType::Climate {
+        return;
+    }
+    let number_state = match config
+        .get(&entity.entity_id)
+        .and_then(|number_id| entity_states.get(number_id))
+    {
+        Some(number_state) => number_state,
+        None => return,
+    };
+
+    let feature = ClimateFeature::TargetTemperature.to_string();
+    let features = entity.features.get_or_insert_with(Vec::new);
+    if !features.contains(&feature) {
+        features.push(feature);
+    }
+    if let Some(temperature) = setpoint_value(&number_state.state) {
+        entity
+            .attributes
+            .get_or_insert_with(Default::default)
+            .insert("target_temperature".into(), temperature);
+    }
+}
+
+/// Get the numeric target temperature of a number state.
+fn setpoint_value(state: &str) -> Option<Value> {
+    state
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .map(Value::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config() -> HashMap<String, String> {
+        HashMap::from([(
+            "climate.floor_heating".to_string(),
+            "number.floor_heating_setpoint".to_string(),
+        )])
+    }
+
+    #[test]
+    fn subscribed_climate_adds_setpoint_number() {
+        let entity_ids = HashSet::from(["climate.floor_heating".to_string()]);
+
+        let result = with_setpoint_numbers(entity_ids, &config());
+
+        assert_eq!(
+            HashSet::from([
+                "climate.floor_heating".to_string(),
+                "number.floor_heating_setpoint".to_string()
+            ]),
+            result
+        );
+    }
+
+    #[test]
+    fn unsubscribed_climate_does_not_add_setpoint_number() {
+        let entity_ids = HashSet::from(["light.kitchen".to_string()]);
+
+        let result = with_setpoint_numbers(entity_ids.clone(), &config());
+
+        assert_eq!(entity_ids, result);
+    }
+
+    #[test]
+    fn number_change_updates_climate_target_temperature() {
+        let changes = setpoint_entity_changes("number.floor_heating_setpoint", "21.5", &config());
+
+        assert_eq!(1, changes.len());
+        assert_eq!(EntityType::Climate, changes[0].entity_type);
+        assert_eq!("climate.floor_heating", changes[0].entity_id);
+        assert_eq!(
+            Some(&json!(21.5)),
+            changes[0].attributes.get("target_temperature")
+        );
+    }
+
+    #[test]
+    fn non_numeric_number_state_is_ignored() {
+        assert!(
+            setpoint_entity_changes("number.floor_heating_setpoint", "unavailable", &config())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn standalone_number_change_is_ignored() {
+        assert!(setpoint_entity_changes("number.volume", "10", &config()).is_empty());
+    }
+
+    #[test]
+    fn climate_entity_presents_setpoint() {
+        let mut entity = AvailableIntgEntity {
+            entity_id: "climate.floor_heating".into(),
+            device_id: None,
+            entity_type: EntityType::Climate,
+            device_class: None,
+            name: HashMap::from([("en".into(), "Floor heating".into())]),
+            features: Some(vec![ClimateFeature::OnOff.to_string()]),
+            area: None,
+            options: None,
+            attributes: Some(serde_json::Map::from_iter([(
+                "state".into(),
+                json!("HEAT"),
+            )])),
+        };
+        let entity_states = HashMap::from([(
+            "number.floor_heating_setpoint".to_string(),
+            serde_json::from_value(json!({
+                "state": "22",
+                "attributes": { "min": 5, "max": 30, "step": 0.5 }
+            }))
+            .expect("invalid test data"),
+        )]);
+
+        with_climate_setpoint(&mut entity, &entity_states, &config());
+
+        let features = entity.features.expect("features must be set");
+        assert!(features.contains(&ClimateFeature::TargetTemperature.to_string()));
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!(22.0)), attributes.get("target_temperature"));
+    }
+}
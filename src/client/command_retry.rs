@@ -0,0 +1,133 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Retry of entity commands failing with a transient HA error.
+//!
+//! A service call may fail with a `home_assistant_error` if a device is momentarily unreachable.
+//! Such a command is retried once after a short delay before the failure is reported.
+
+use crate::client::pending_requests::RequestResult;
+use crate::errors::ServiceError;
+use actix::clock::sleep;
+use log::warn;
+use std::future::Future;
+use std::time::Duration;
+
+/// Retry a failed command once if it failed with a transient error.
+///
+/// # Arguments
+///
+/// * `result`: result of the first command execution.
+/// * `delay`: delay before the retry. A zero delay disables the retry.
+/// * `retry`: retry of the command.
+///
+/// returns: the first result if successful or not retried, otherwise the result of the retry.
+pub(crate) async fn retry_transient<F, Fut>(
+    result: RequestResult,
+    delay: Duration,
+    retry: F,
+) -> Result<(), ServiceError>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<(), ServiceError>>,
+{
+    match result {
+        Err(e) if !delay.is_zero() && e.is_transient() => {
+            warn!("Command failed with transient error, retrying in {delay:?}: {e:?}");
+            sleep(delay).await;
+            retry().await
+        }
+        result => result.map_err(ServiceError::from),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::pending_requests::RequestError;
+    use std::cell::Cell;
+
+    const DELAY: Duration = Duration::from_millis(10);
+
+    fn ha_error(code: &str) -> RequestError {
+        RequestError::Ha {
+            code: code.into(),
+            message: "Device not reachable".into(),
+        }
+    }
+
+    #[actix::test]
+    async fn retry_then_success() {
+        let retries = Cell::new(0);
+
+        let result = retry_transient(Err(ha_error("home_assistant_error")), DELAY, || async {
+            retries.set(retries.get() + 1);
+            Ok(())
+        })
+        .await;
+
+        assert_eq!(Ok(()), result);
+        assert_eq!(1, retries.get());
+    }
+
+    #[actix::test]
+    async fn retry_exhausted() {
+        let retries = Cell::new(0);
+
+        let result = retry_transient(Err(ha_error("home_assistant_error")), DELAY, || async {
+            retries.set(retries.get() + 1);
+            Err(ha_error("home_assistant_error").into())
+        })
+        .await;
+
+        assert_eq!(
+            Err(ServiceError::InternalServerError(
+                "Device not reachable".into()
+            )),
+            result
+        );
+        assert_eq!(1, retries.get(), "Command must only be retried once");
+    }
+
+    #[actix::test]
+    async fn successful_command_is_not_retried() {
+        let result = retry_transient(Ok(()), DELAY, || async {
+            panic!("Successful command must not be retried")
+        })
+        .await;
+
+        assert_eq!(Ok(()), result);
+    }
+
+    #[actix::test]
+    async fn permanent_error_is_not_retried() {
+        let result = retry_transient(Err(ha_error("service_validation_error")), DELAY, || async {
+            panic!("Permanent error must not be retried")
+        })
+        .await;
+
+        assert!(matches!(result, Err(ServiceError::BadRequest(_))));
+    }
+
+    #[actix::test]
+    async fn timeout_is_not_retried() {
+        let result = retry_transient(Err(RequestError::Timeout(DELAY)), DELAY, || async {
+            panic!("Timed out command might have been executed and must not be retried")
+        })
+        .await;
+
+        assert!(matches!(result, Err(ServiceError::ServiceUnavailable(_))));
+    }
+
+    #[actix::test]
+    async fn disabled_retry() {
+        let result = retry_transient(
+            Err(ha_error("home_assistant_error")),
+            Duration::ZERO,
+            || async { panic!("Retry is disabled") },
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,208 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Debounce `unavailable` entity states after (re)connecting to Home Assistant.
+//!
+//! When HA restarts, most entities are reported as `unavailable` until their integration is
+//! loaded. Forwarding all these short-lived state changes would flood the remote with
+//! unavailable → available flaps.
+
+use crate::configuration::UnavailableDebounceSettings;
+use std::collections::HashMap;
+use std::time::Instant;
+use uc_api::intg::EntityChange;
+
+/// Hold back `unavailable` entity changes within the debounce window after connecting to HA.
+///
+/// A held back change is dropped if the entity recovers before the debounce delay expires.
+pub(crate) struct UnavailableDebounce {
+    settings: UnavailableDebounceSettings,
+    /// Start of the debounce window
+    start: Option<Instant>,
+    /// Held back unavailable entity changes
+    pending: HashMap<String, EntityChange>,
+}
+
+impl UnavailableDebounce {
+    pub fn new(settings: UnavailableDebounceSettings) -> Self {
+        Self {
+            settings,
+            start: None,
+            pending: Default::default(),
+        }
+    }
+
+    /// Start the debounce window, e.g. after authenticating with HA.
+    pub fn start(&mut self, now: Instant) {
+        self.start = Some(now);
+        self.pending.clear();
+    }
+
+    /// Check if the debounce window is active at the given time.
+    pub fn is_active(&self, now: Instant) -> bool {
+        match self.start {
+            Some(start) if !self.settings.delay.is_zero() => {
+                now.saturating_duration_since(start) < self.settings.window
+            }
+            _ => false,
+        }
+    }
+
+    /// Filter an entity change.
+    ///
+    /// # Arguments
+    ///
+    /// * `entity_change`: converted entity change.
+    /// * `unavailable`: true if the HA entity state is `unavailable`.
+    /// * `now`: time of the entity change.
+    ///
+    /// returns: the entity change if it should be forwarded immediately, `None` if it is held
+    /// back. A held back change must be retrieved with [`Self::take_pending`] after the debounce
+    /// delay.
+    pub fn filter(
+        &mut self,
+        entity_change: EntityChange,
+        unavailable: bool,
+        now: Instant,
+    ) -> Option<EntityChange> {
+        if !unavailable {
+            // entity recovered: drop a held back unavailable state
+            self.pending.remove(&entity_change.entity_id);
+            return Some(entity_change);
+        }
+
+        if !self.is_active(now) {
+            return Some(entity_change);
+        }
+
+        self.pending
+            .insert(entity_change.entity_id.clone(), entity_change);
+        None
+    }
+
+    /// Take a held back entity change after the debounce delay expired.
+    ///
+    /// returns: `None` if the entity recovered in the meantime.
+    pub fn take_pending(&mut self, entity_id: &str) -> Option<EntityChange> {
+        self.pending.remove(entity_id)
+    }
+
+    /// Delay before a held back unavailable state is forwarded.
+    pub fn delay(&self) -> std::time::Duration {
+        self.settings.delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+    use uc_api::EntityType;
+
+    fn debounce() -> UnavailableDebounce {
+        UnavailableDebounce::new(UnavailableDebounceSettings {
+            window: Duration::from_secs(60),
+            delay: Duration::from_secs(5),
+        })
+    }
+
+    fn entity_change(entity_id: &str, state: &str) -> EntityChange {
+        EntityChange {
+            device_id: None,
+            entity_type: EntityType::Light,
+            entity_id: entity_id.into(),
+            attributes: json!({ "state": state }).as_object().unwrap().clone(),
+        }
+    }
+
+    #[test]
+    fn unavailable_is_held_back_within_window() {
+        let now = Instant::now();
+        let mut debounce = debounce();
+        debounce.start(now);
+
+        let result = debounce.filter(
+            entity_change("light.kitchen", "UNAVAILABLE"),
+            true,
+            now + Duration::from_secs(1),
+        );
+
+        assert!(result.is_none(), "Expected held back change: {result:?}");
+        assert!(debounce.take_pending("light.kitchen").is_some());
+    }
+
+    #[test]
+    fn recovered_entity_drops_held_back_unavailable() {
+        let now = Instant::now();
+        let mut debounce = debounce();
+        debounce.start(now);
+
+        let _ = debounce.filter(entity_change("light.kitchen", "UNAVAILABLE"), true, now);
+        let result = debounce.filter(
+            entity_change("light.kitchen", "ON"),
+            false,
+            now + Duration::from_secs(2),
+        );
+
+        assert!(result.is_some(), "Available state must be forwarded");
+        assert!(
+            debounce.take_pending("light.kitchen").is_none(),
+            "Unavailable flap must be dropped"
+        );
+    }
+
+    #[test]
+    fn unavailable_is_forwarded_after_window() {
+        let now = Instant::now();
+        let mut debounce = debounce();
+        debounce.start(now);
+
+        let result = debounce.filter(
+            entity_change("light.kitchen", "UNAVAILABLE"),
+            true,
+            now + Duration::from_secs(61),
+        );
+
+        assert!(result.is_some(), "Expected forwarded change after window");
+    }
+
+    #[test]
+    fn unavailable_is_forwarded_without_started_window() {
+        let mut debounce = debounce();
+
+        let result = debounce.filter(
+            entity_change("light.kitchen", "UNAVAILABLE"),
+            true,
+            Instant::now(),
+        );
+
+        assert!(result.is_some(), "Expected forwarded change");
+    }
+
+    #[test]
+    fn zero_delay_disables_debounce() {
+        let now = Instant::now();
+        let mut debounce = UnavailableDebounce::new(UnavailableDebounceSettings {
+            window: Duration::from_secs(60),
+            delay: Duration::ZERO,
+        });
+        debounce.start(now);
+
+        let result = debounce.filter(entity_change("light.kitchen", "UNAVAILABLE"), true, now);
+
+        assert!(result.is_some(), "Expected forwarded change");
+    }
+
+    #[test]
+    fn other_entities_are_not_affected() {
+        let now = Instant::now();
+        let mut debounce = debounce();
+        debounce.start(now);
+
+        let _ = debounce.filter(entity_change("light.kitchen", "UNAVAILABLE"), true, now);
+        let _ = debounce.filter(entity_change("light.office", "ON"), false, now);
+
+        assert!(debounce.take_pending("light.kitchen").is_some());
+    }
+}
@@ -0,0 +1,139 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Home Assistant device registry handling with the `config/device_registry/list` request.
+//!
+//! Entities without an own area assignment in the entity registry inherit the area of their
+//! device, as in the HA frontend.
+
+use crate::client::HomeAssistantClient;
+use actix::Context;
+use log::{error, info};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+impl HomeAssistantClient {
+    /// Request the HA device registry for the device areas.
+    ///
+    /// The result is handled in [`Self::handle_device_registry_result`].
+    pub(crate) fn send_device_registry_list(&mut self, ctx: &mut Context<HomeAssistantClient>) {
+        let id = self.new_msg_id();
+        self.device_registry_id = Some(id);
+        if let Err(e) = self.send_json(
+            json!({"id": id, "type": "config/device_registry/list"}),
+            ctx,
+        ) {
+            error!(
+                "[{}] Error sending config/device_registry/list to HA: {:?}",
+                self.id, e
+            );
+        }
+    }
+
+    /// Cache the device areas of the HA device registry.
+    pub(crate) fn handle_device_registry_result(&mut self, result: Option<&Value>) {
+        self.device_areas = device_area_ids(result);
+        info!(
+            "[{}] Received {} devices with an area",
+            self.id,
+            self.device_areas.len()
+        );
+    }
+}
+
+/// Get the device id to area id mapping from a device registry list result.
+///
+/// Devices without an assigned area are skipped.
+fn device_area_ids(result: Option<&Value>) -> HashMap<String, String> {
+    result
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let device_id = entry.get("id").and_then(|v| v.as_str())?;
+            let area_id = entry.get("area_id").and_then(|v| v.as_str())?;
+            Some((device_id.to_string(), area_id.to_string()))
+        })
+        .collect()
+}
+
+/// Get the area id of an entity.
+///
+/// The area of the entity's device is used if the entity isn't assigned to an area.
+///
+/// # Arguments
+///
+/// * `entity_id`: entity identifier.
+/// * `entity_areas`: entity id to area id mapping of the entity registry.
+/// * `entity_devices`: entity id to device id mapping of the entity registry.
+/// * `device_areas`: device id to area id mapping of the device registry.
+pub(crate) fn entity_area_id<'a>(
+    entity_id: &str,
+    entity_areas: &'a HashMap<String, String>,
+    entity_devices: &HashMap<String, String>,
+    device_areas: &'a HashMap<String, String>,
+) -> Option<&'a str> {
+    entity_areas
+        .get(entity_id)
+        .or_else(|| {
+            entity_devices
+                .get(entity_id)
+                .and_then(|device_id| device_areas.get(device_id))
+        })
+        .map(|v| v.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> Value {
+        json!([
+            { "id": "d1", "name": "Kitchen light", "area_id": "kitchen" },
+            { "id": "d2", "name": "Plug", "area_id": null },
+            { "id": "d3", "name": "Bridge" }
+        ])
+    }
+
+    fn map(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn device_areas_are_collected() {
+        let result = device_area_ids(Some(&registry()));
+
+        assert_eq!(map(&[("d1", "kitchen")]), result);
+    }
+
+    #[test]
+    fn invalid_registry_result_has_no_device_areas() {
+        assert!(device_area_ids(None).is_empty());
+        assert!(device_area_ids(Some(&json!({ "code": "unauthorized" }))).is_empty());
+    }
+
+    #[test]
+    fn entity_without_area_uses_device_area() {
+        let entity_areas = map(&[("light.ceiling", "living_room")]);
+        let entity_devices = map(&[
+            ("light.ceiling", "d1"),
+            ("light.kitchen", "d1"),
+            ("switch.plug", "d2"),
+        ]);
+        let device_areas = device_area_ids(Some(&registry()));
+
+        let area =
+            |entity_id| entity_area_id(entity_id, &entity_areas, &entity_devices, &device_areas);
+
+        // entity area has priority over the device area
+        assert_eq!(Some("living_room"), area("light.ceiling"));
+        assert_eq!(Some("kitchen"), area("light.kitchen"));
+        // device without area
+        assert_eq!(None, area("switch.plug"));
+        // entity without device
+        assert_eq!(None, area("sensor.uptime"));
+    }
+}
@@ -0,0 +1,306 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Suppress state change events caused by service calls of the integration.
+//!
+//! HA returns the context of an executed service call in the `call_service` result. The
+//! `state_changed` events triggered by the service call carry the same context id. The event is
+//! usually received before the result, therefore events of entities with a pending service call
+//! are buffered until the result is received.
+
+use crate::client::model::Event;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Time to keep pending requests and service call contexts for the correlation.
+const CONTEXT_TTL: Duration = Duration::from_secs(30);
+
+/// Pending `call_service` request.
+struct PendingRequest {
+    /// Target entity id of the service call
+    entity_id: String,
+    created: Instant,
+}
+
+/// Correlate `state_changed` events with the service calls issued by the integration.
+pub(crate) struct EchoFilter {
+    enabled: bool,
+    /// Pending `call_service` requests by request id
+    pending_requests: HashMap<u32, PendingRequest>,
+    /// Context ids of executed service calls
+    contexts: HashMap<String, Instant>,
+    /// Events of entities with a pending service call, waiting for the service call result
+    buffered_events: Vec<Event>,
+}
+
+impl EchoFilter {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            pending_requests: Default::default(),
+            contexts: Default::default(),
+            buffered_events: Default::default(),
+        }
+    }
+
+    /// Track a sent `call_service` request for the given target entity.
+    pub fn track_request(&mut self, id: u32, entity_id: &str, now: Instant) {
+        if !self.enabled {
+            return;
+        }
+        self.expire(now);
+        self.pending_requests.insert(
+            id,
+            PendingRequest {
+                entity_id: entity_id.to_string(),
+                created: now,
+            },
+        );
+    }
+
+    /// Handle a result message.
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: request id of the result message.
+    /// * `result`: `result` field of the result message containing the service call context.
+    /// * `now`: time of the result message.
+    ///
+    /// returns: `None` if the result doesn't belong to a tracked `call_service` request, otherwise
+    /// the released events which have been received before the result.
+    pub fn handle_result(
+        &mut self,
+        id: u32,
+        result: Option<&Value>,
+        now: Instant,
+    ) -> Option<Vec<Event>> {
+        self.pending_requests.remove(&id)?;
+        if let Some(context_id) = result
+            .and_then(|v| v.pointer("/context/id"))
+            .and_then(|v| v.as_str())
+        {
+            self.contexts.insert(context_id.to_string(), now);
+        }
+        Some(self.release_events())
+    }
+
+    /// Filter a received `state_changed` event.
+    ///
+    /// The event is buffered if a service call for the entity is pending and the service call
+    /// context is not yet known.
+    ///
+    /// returns: the events to handle now, including released events of expired service calls.
+    pub fn filter_event(&mut self, event: Event, now: Instant) -> Vec<Event> {
+        if !self.enabled {
+            return vec![event];
+        }
+        self.expire(now);
+        let mut events = self.release_events();
+
+        let known_context = event
+            .data
+            .new_state
+            .context
+            .as_ref()
+            .is_some_and(|c| self.contexts.contains_key(&c.id));
+        if !known_context && self.is_pending(&event.data.entity_id) {
+            self.buffered_events.push(event);
+        } else {
+            events.push(event);
+        }
+        events
+    }
+
+    /// Check if a state change event with the given context id was caused by a service call of
+    /// the integration.
+    pub fn is_echo(&self, context_id: Option<&str>, now: Instant) -> bool {
+        match context_id.and_then(|id| self.contexts.get(id)) {
+            Some(created) => now.saturating_duration_since(*created) < CONTEXT_TTL,
+            None => false,
+        }
+    }
+
+    fn is_pending(&self, entity_id: &str) -> bool {
+        self.pending_requests
+            .values()
+            .any(|request| request.entity_id == entity_id)
+    }
+
+    /// Release the buffered events of entities without pending service calls, in received order.
+    fn release_events(&mut self) -> Vec<Event> {
+        let (buffered, released) = std::mem::take(&mut self.buffered_events)
+            .into_iter()
+            .partition(|event| self.is_pending(&event.data.entity_id));
+        self.buffered_events = buffered;
+        released
+    }
+
+    fn expire(&mut self, now: Instant) {
+        self.pending_requests
+            .retain(|_, request| now.saturating_duration_since(request.created) < CONTEXT_TTL);
+        self.contexts
+            .retain(|_, created| now.saturating_duration_since(*created) < CONTEXT_TTL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn service_result(context_id: &str) -> Value {
+        json!({
+            "context": {
+                "id": context_id,
+                "parent_id": null,
+                "user_id": "b1f6a0e0"
+            }
+        })
+    }
+
+    fn state_changed(entity_id: &str, context_id: &str) -> Event {
+        serde_json::from_value(json!({
+            "data": {
+                "entity_id": entity_id,
+                "new_state": {
+                    "state": "on",
+                    "attributes": {},
+                    "context": { "id": context_id }
+                }
+            }
+        }))
+        .expect("invalid test data")
+    }
+
+    fn entity_ids(events: &[Event]) -> Vec<&str> {
+        events.iter().map(|e| e.data.entity_id.as_str()).collect()
+    }
+
+    #[test]
+    fn event_of_service_call_is_echo() {
+        let now = Instant::now();
+        let mut filter = EchoFilter::new(true);
+        filter.track_request(5, "light.kitchen", now);
+
+        assert!(filter
+            .handle_result(5, Some(&service_result("01HX")), now)
+            .is_some());
+        assert!(filter.is_echo(Some("01HX"), now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn event_with_other_context_is_no_echo() {
+        let now = Instant::now();
+        let mut filter = EchoFilter::new(true);
+        filter.track_request(5, "light.kitchen", now);
+        filter.handle_result(5, Some(&service_result("01HX")), now);
+
+        assert!(!filter.is_echo(Some("01HY"), now));
+        assert!(!filter.is_echo(None, now));
+    }
+
+    #[test]
+    fn result_of_untracked_request_is_ignored() {
+        let now = Instant::now();
+        let mut filter = EchoFilter::new(true);
+
+        assert!(filter
+            .handle_result(7, Some(&service_result("01HX")), now)
+            .is_none());
+        assert!(!filter.is_echo(Some("01HX"), now));
+    }
+
+    #[test]
+    fn disabled_filter_never_suppresses_events() {
+        let now = Instant::now();
+        let mut filter = EchoFilter::new(false);
+        filter.track_request(5, "light.kitchen", now);
+
+        assert!(filter
+            .handle_result(5, Some(&service_result("01HX")), now)
+            .is_none());
+        assert!(!filter.is_echo(Some("01HX"), now));
+    }
+
+    #[test]
+    fn expired_context_is_no_echo() {
+        let now = Instant::now();
+        let mut filter = EchoFilter::new(true);
+        filter.track_request(5, "light.kitchen", now);
+        filter.handle_result(5, Some(&service_result("01HX")), now);
+
+        assert!(!filter.is_echo(Some("01HX"), now + CONTEXT_TTL));
+    }
+
+    #[test]
+    fn event_after_result_is_not_buffered() {
+        let now = Instant::now();
+        let mut filter = EchoFilter::new(true);
+        filter.track_request(5, "light.kitchen", now);
+        filter.handle_result(5, Some(&service_result("01HX")), now);
+
+        let events = filter.filter_event(state_changed("light.kitchen", "01HX"), now);
+
+        assert_eq!(vec!["light.kitchen"], entity_ids(&events));
+        assert!(filter.is_echo(Some("01HX"), now));
+    }
+
+    #[test]
+    fn event_before_result_is_buffered_until_result() {
+        let now = Instant::now();
+        let mut filter = EchoFilter::new(true);
+        filter.track_request(5, "light.kitchen", now);
+
+        let events = filter.filter_event(state_changed("light.kitchen", "01HX"), now);
+        assert!(events.is_empty(), "Event must be buffered until the result");
+
+        let events = filter
+            .handle_result(5, Some(&service_result("01HX")), now)
+            .expect("Expected a tracked request");
+        assert_eq!(vec!["light.kitchen"], entity_ids(&events));
+        assert!(filter.is_echo(Some("01HX"), now));
+    }
+
+    #[test]
+    fn event_before_result_of_other_context_is_no_echo() {
+        let now = Instant::now();
+        let mut filter = EchoFilter::new(true);
+        filter.track_request(5, "light.kitchen", now);
+
+        assert!(filter
+            .filter_event(state_changed("light.kitchen", "01HY"), now)
+            .is_empty());
+        let events = filter
+            .handle_result(5, Some(&service_result("01HX")), now)
+            .expect("Expected a tracked request");
+
+        assert_eq!(vec!["light.kitchen"], entity_ids(&events));
+        assert!(!filter.is_echo(Some("01HY"), now));
+    }
+
+    #[test]
+    fn event_of_other_entity_is_not_buffered() {
+        let now = Instant::now();
+        let mut filter = EchoFilter::new(true);
+        filter.track_request(5, "light.kitchen", now);
+
+        let events = filter.filter_event(state_changed("switch.fan", "01HY"), now);
+
+        assert_eq!(vec!["switch.fan"], entity_ids(&events));
+    }
+
+    #[test]
+    fn buffered_event_is_released_when_request_expires() {
+        let now = Instant::now();
+        let mut filter = EchoFilter::new(true);
+        filter.track_request(5, "light.kitchen", now);
+        assert!(filter
+            .filter_event(state_changed("light.kitchen", "01HX"), now)
+            .is_empty());
+
+        let events = filter.filter_event(state_changed("switch.fan", "01HY"), now + CONTEXT_TTL);
+
+        assert_eq!(vec!["light.kitchen", "switch.fan"], entity_ids(&events));
+    }
+}
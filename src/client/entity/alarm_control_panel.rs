@@ -0,0 +1,250 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Alarm control panel entity specific logic.
+//!
+//! The Integration-API doesn't define an alarm entity yet. An alarm control panel is exposed as a
+//! remote entity with arm & disarm simple commands and the alarm state as additional attribute.
+
+use crate::client::model::EventData;
+use crate::errors::ServiceError;
+use log::warn;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uc_api::intg::{AvailableIntgEntity, EntityChange, IntgRemoteFeature};
+use uc_api::EntityType;
+
+// https://developers.home-assistant.io/docs/core/entity/alarm-control-panel#supported-features
+pub const ALARM_SUPPORT_ARM_HOME: u32 = 1;
+pub const ALARM_SUPPORT_ARM_AWAY: u32 = 2;
+pub const ALARM_SUPPORT_ARM_NIGHT: u32 = 4;
+/* not yet used constants
+pub const ALARM_SUPPORT_TRIGGER: u32 = 8;
+pub const ALARM_SUPPORT_ARM_CUSTOM_BYPASS: u32 = 16;
+pub const ALARM_SUPPORT_ARM_VACATION: u32 = 32;
+*/
+
+/// Alarm commands, also used as remote entity simple commands.
+pub const ALARM_CMD_ARM_HOME: &str = "ARM_HOME";
+pub const ALARM_CMD_ARM_AWAY: &str = "ARM_AWAY";
+pub const ALARM_CMD_ARM_NIGHT: &str = "ARM_NIGHT";
+pub const ALARM_CMD_DISARM: &str = "DISARM";
+/// Alarm feature in addition to the remote entity features: a code is required.
+pub const ALARM_FEATURE_CODE: &str = "code";
+
+pub(crate) fn map_alarm_control_panel_attributes(
+    entity_id: &str,
+    state: &str,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut attributes = serde_json::Map::with_capacity(2);
+
+    // remote entity state: ON while the alarm is armed or about to be armed
+    let remote_state = match state {
+        "unavailable" | "unknown" => state.to_uppercase(),
+        "armed_home"
+        | "armed_away"
+        | "armed_night"
+        | "armed_vacation"
+        | "armed_custom_bypass"
+        | "arming"
+        | "pending"
+        | "triggered" => "ON".into(),
+        "disarmed" | "disarming" => "OFF".into(),
+        state => {
+            warn!("{} Not supported alarm state: {}", entity_id, state);
+            "UNKNOWN".into()
+        }
+    };
+    attributes.insert("state".into(), remote_state.into());
+    attributes.insert("alarm_state".into(), state.to_uppercase().into());
+
+    Ok(attributes)
+}
+
+pub(crate) fn alarm_control_panel_event_to_entity_change(
+    data: EventData,
+) -> Result<EntityChange, ServiceError> {
+    let attributes = map_alarm_control_panel_attributes(&data.entity_id, &data.new_state.state)?;
+
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Remote,
+        entity_id: data.entity_id,
+        attributes,
+    })
+}
+
+pub(crate) fn convert_alarm_control_panel_entity(
+    entity_id: String,
+    state: String,
+    ha_attr: &mut Map<String, Value>,
+) -> Result<AvailableIntgEntity, ServiceError> {
+    let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
+    let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
+
+    // handle features
+    let supported_features = ha_attr
+        .get("supported_features")
+        .and_then(|v| v.as_u64())
+        .unwrap_or_default() as u32;
+    let mut features = vec![IntgRemoteFeature::SendCmd.to_string()];
+    let mut commands = Vec::with_capacity(4);
+    if supported_features & ALARM_SUPPORT_ARM_HOME > 0 {
+        commands.push(ALARM_CMD_ARM_HOME);
+    }
+    if supported_features & ALARM_SUPPORT_ARM_AWAY > 0 {
+        commands.push(ALARM_CMD_ARM_AWAY);
+    }
+    if supported_features & ALARM_SUPPORT_ARM_NIGHT > 0 {
+        commands.push(ALARM_CMD_ARM_NIGHT);
+    }
+    // disarming is always supported
+    commands.push(ALARM_CMD_DISARM);
+
+    let code_format = ha_attr.get("code_format").filter(|v| v.is_string());
+    if code_format.is_some() {
+        features.push(ALARM_FEATURE_CODE.into());
+    }
+
+    // handle options
+    let mut options = serde_json::Map::new();
+    options.insert("simple_commands".into(), commands.into());
+    if let Some(code_format) = code_format {
+        options.insert("code_format".into(), code_format.clone());
+    }
+
+    // convert attributes
+    let attributes = Some(map_alarm_control_panel_attributes(&entity_id, &state)?);
+
+    Ok(AvailableIntgEntity {
+        entity_id,
+        device_id: None, // prepared for device_id handling
+        entity_type: EntityType::Remote,
+        device_class: None,
+        name,
+        features: Some(features),
+        area: None,
+        options: Some(options),
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case("armed_home", "ON")]
+    #[case("armed_away", "ON")]
+    #[case("armed_night", "ON")]
+    #[case("arming", "ON")]
+    #[case("pending", "ON")]
+    #[case("triggered", "ON")]
+    #[case("disarmed", "OFF")]
+    #[case("unavailable", "UNAVAILABLE")]
+    #[case("foobar", "UNKNOWN")]
+    fn alarm_states(#[case] ha_state: &str, #[case] state: &str) {
+        let attributes = map_alarm_control_panel_attributes("alarm_control_panel.home", ha_state)
+            .expect("Expected successful attribute mapping");
+
+        assert_eq!(Some(&json!(state)), attributes.get("state"));
+        assert_eq!(
+            Some(&json!(ha_state.to_uppercase())),
+            attributes.get("alarm_state")
+        );
+    }
+
+    #[test]
+    fn alarm_event() {
+        let data = EventData {
+            entity_id: "alarm_control_panel.home".into(),
+            new_state: serde_json::from_value(json!({
+                "state": "armed_away",
+                "attributes": {
+                    "code_format": "number",
+                    "friendly_name": "Home Alarm",
+                    "supported_features": 3
+                }
+            }))
+            .unwrap(),
+        };
+
+        let result = alarm_control_panel_event_to_entity_change(data);
+        assert!(
+            result.is_ok(),
+            "Expected successful entity change but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity_change = result.unwrap();
+
+        assert_eq!(EntityType::Remote, entity_change.entity_type);
+        assert_eq!(Some(&json!("ON")), entity_change.attributes.get("state"));
+        assert_eq!(
+            Some(&json!("ARMED_AWAY")),
+            entity_change.attributes.get("alarm_state")
+        );
+    }
+
+    #[test]
+    fn convert_alarm_with_code() {
+        let mut attr = json!({
+            "code_format": "number",
+            "changed_by": null,
+            "code_arm_required": true,
+            "friendly_name": "Home Alarm",
+            // ARM_HOME | ARM_AWAY | TRIGGER
+            "supported_features": 11
+        });
+        let result = convert_alarm_control_panel_entity(
+            "alarm_control_panel.home".into(),
+            "disarmed".into(),
+            attr.as_object_mut().unwrap(),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+
+        assert_eq!(EntityType::Remote, entity.entity_type);
+        let features = entity.features.expect("features must be set");
+        assert!(features.contains(&IntgRemoteFeature::SendCmd.to_string()));
+        assert!(features.contains(&ALARM_FEATURE_CODE.to_string()));
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!(["ARM_HOME", "ARM_AWAY", "DISARM"])),
+            options.get("simple_commands")
+        );
+        assert_eq!(Some(&json!("number")), options.get("code_format"));
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("OFF")), attributes.get("state"));
+        assert_eq!(Some(&json!("DISARMED")), attributes.get("alarm_state"));
+    }
+
+    #[test]
+    fn convert_alarm_without_code() {
+        let mut attr = json!({
+            "code_format": null,
+            "friendly_name": "Home Alarm",
+            "supported_features": 4
+        });
+        let entity = convert_alarm_control_panel_entity(
+            "alarm_control_panel.home".into(),
+            "armed_night".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        let features = entity.features.expect("features must be set");
+        assert!(!features.contains(&ALARM_FEATURE_CODE.to_string()));
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!(["ARM_NIGHT", "DISARM"])),
+            options.get("simple_commands")
+        );
+        assert_eq!(None, options.get("code_format"));
+    }
+}
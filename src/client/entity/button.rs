@@ -2,21 +2,69 @@
 // SPDX-License-Identifier: MPL-2.0
 
 //! Button entity specific logic.
+//!
+//! The state of a HA button and input_button entity is the timestamp of the last press. It's
+//! forwarded as additional `last_pressed` attribute together with the availability.
 
+use crate::client::model::EventData;
 use crate::errors::ServiceError;
 use serde_json::{Map, Value};
 use std::collections::HashMap;
-use uc_api::intg::AvailableIntgEntity;
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
 use uc_api::EntityType;
 
+/// Last pressed timestamp attribute. Not defined in the Integration-API button entity.
+pub const BUTTON_ATTR_LAST_PRESSED: &str = "last_pressed";
+
+/// Check if the entity is a HA button or input_button entity with a last pressed timestamp state.
+pub(crate) fn is_pressable_button_entity(entity_id: &str) -> bool {
+    entity_id.starts_with("button.") || entity_id.starts_with("input_button.")
+}
+
+pub(crate) fn map_button_attributes(state: &str) -> Map<String, Value> {
+    let mut attributes = serde_json::Map::with_capacity(2);
+
+    match state {
+        "unavailable" => {
+            attributes.insert("state".into(), "UNAVAILABLE".into());
+        }
+        // never pressed
+        "unknown" => {
+            attributes.insert("state".into(), "AVAILABLE".into());
+        }
+        _ => {
+            attributes.insert("state".into(), "AVAILABLE".into());
+            attributes.insert(BUTTON_ATTR_LAST_PRESSED.into(), state.into());
+        }
+    }
+
+    attributes
+}
+
+pub(crate) fn button_event_to_entity_change(data: EventData) -> Result<EntityChange, ServiceError> {
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Button,
+        attributes: map_button_attributes(&data.new_state.state),
+        entity_id: data.entity_id,
+    })
+}
+
 pub(crate) fn convert_button_entity(
     entity_id: String,
-    _state: String,
+    state: String,
     ha_attr: &mut Map<String, Value>,
 ) -> Result<AvailableIntgEntity, ServiceError> {
     let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
     let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
 
+    // the state of a script is not a timestamp
+    let attributes = if is_pressable_button_entity(&entity_id) {
+        Some(map_button_attributes(&state))
+    } else {
+        None
+    };
+
     Ok(AvailableIntgEntity {
         entity_id,
         device_id: None, // prepared for device_id handling
@@ -26,6 +74,84 @@ pub(crate) fn convert_button_entity(
         features: None, // no optional features, default = "press"
         area: None,
         options: None,
-        attributes: None,
+        attributes,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[test]
+    fn convert_button_with_last_pressed_timestamp() {
+        let mut attr = json!({
+            "device_class": "restart",
+            "friendly_name": "Restart router"
+        });
+        let result = convert_button_entity(
+            "button.restart_router".into(),
+            "2024-06-12T08:15:30.123456+00:00".into(),
+            attr.as_object_mut().unwrap(),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+
+        assert_eq!(EntityType::Button, entity.entity_type);
+        assert_eq!(
+            Some(json!({
+                "state": "AVAILABLE",
+                "last_pressed": "2024-06-12T08:15:30.123456+00:00"
+            })),
+            entity.attributes.map(Value::Object)
+        );
+    }
+
+    #[test]
+    fn convert_script_without_attributes() {
+        let mut attr = json!({ "friendly_name": "Good night" });
+        let entity = convert_button_entity(
+            "script.good_night".into(),
+            "off".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        assert_eq!(None, entity.attributes);
+    }
+
+    #[rstest]
+    #[case("unknown", "AVAILABLE")]
+    #[case("unavailable", "UNAVAILABLE")]
+    fn button_states_without_timestamp(#[case] ha_state: &str, #[case] state: &str) {
+        let attributes = map_button_attributes(ha_state);
+
+        assert_eq!(Some(&json!(state)), attributes.get("state"));
+        assert_eq!(None, attributes.get(BUTTON_ATTR_LAST_PRESSED));
+    }
+
+    #[test]
+    fn input_button_event_with_timestamp() {
+        let data = EventData {
+            entity_id: "input_button.doorbell".into(),
+            new_state: serde_json::from_value(json!({
+                "state": "2024-06-12T19:02:11.000521+00:00",
+                "attributes": { "friendly_name": "Doorbell" }
+            }))
+            .expect("invalid test data"),
+        };
+        let entity_change =
+            button_event_to_entity_change(data).expect("Expected successful event mapping");
+
+        assert_eq!(EntityType::Button, entity_change.entity_type);
+        assert_eq!(
+            Some(&json!("2024-06-12T19:02:11.000521+00:00")),
+            entity_change.attributes.get(BUTTON_ATTR_LAST_PRESSED)
+        );
+    }
+}
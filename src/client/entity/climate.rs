@@ -16,14 +16,35 @@ use uc_api::{ClimateFeature, ClimateOptionField, EntityType};
 // https://developers.home-assistant.io/docs/core/entity/climate#supported-features
 pub const SUPPORT_TARGET_TEMPERATURE: u32 = 1;
 pub const SUPPORT_TARGET_TEMPERATURE_RANGE: u32 = 2;
-/* not yet used constants
-pub const SUPPORT_TARGET_HUMIDITY: u32 = 4;
 pub const SUPPORT_FAN_MODE: u32 = 8;
 pub const SUPPORT_PRESET_MODE: u32 = 16;
 pub const SUPPORT_SWING_MODE: u32 = 32;
+/* not yet used constants
+pub const SUPPORT_TARGET_HUMIDITY: u32 = 4;
 pub const SUPPORT_AUX_HEAT: u32 = 64;
 */
 
+/// Supported HVAC modes entity option, using the Integration-API climate state names. Allows mode
+/// selection of simple thermostats without target temperature support.
+pub const OPTION_HVAC_MODES: &str = "hvac_modes";
+/// Preset mode feature. Not yet defined in the Integration-API `ClimateFeature` enum.
+pub const FEATURE_PRESET_MODE: &str = "preset_mode";
+/// Available preset modes entity option.
+pub const OPTION_PRESET_MODES: &str = "preset_modes";
+/// Localized labels of common preset modes entity option: preset mode -> language -> label.
+pub const OPTION_PRESET_MODE_LABELS: &str = "preset_mode_labels";
+/// Icons of common preset modes entity option: preset mode -> Material Design icon as used by HA.
+pub const OPTION_PRESET_MODE_ICONS: &str = "preset_mode_icons";
+/// Fan mode feature. Not yet defined in the Integration-API `ClimateFeature` enum.
+pub const FEATURE_FAN_MODE: &str = "fan_mode";
+/// Available fan modes entity option. Fan modes are upper-cased like the `fan_mode` attribute.
+pub const OPTION_FAN_MODES: &str = "fan_modes";
+/// Swing mode feature. Not yet defined in the Integration-API `ClimateFeature` enum.
+pub const FEATURE_SWING_MODE: &str = "swing_mode";
+/// Available swing modes entity option. Swing modes are upper-cased like the `swing_mode`
+/// attribute.
+pub const OPTION_SWING_MODES: &str = "swing_modes";
+
 pub(crate) fn map_climate_attributes(
     entity_id: &str,
     state: &str,
@@ -31,17 +52,30 @@ pub(crate) fn map_climate_attributes(
 ) -> Result<Map<String, Value>, ServiceError> {
     let mut attributes = serde_json::Map::with_capacity(6);
 
-    match state {
+    // Some climate devices report the setpoint as entity state instead of the hvac mode
+    let state_temperature = state_temperature(state);
+    let hvac_mode = if state_temperature.is_some() {
+        ha_attr
+            .as_deref()
+            .and_then(|a| a.get("hvac_mode"))
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+    } else {
+        Some(state.to_string())
+    };
+
+    match hvac_mode.as_deref() {
         // general states
-        "unavailable" | "unknown" |
+        Some(mode @ ("unavailable" | "unknown" |
         // hvac states
-        "off" | "heat" | "cool" | "heat_cool" | "auto" => {
-            attributes.insert("state".into(), state.to_uppercase().into());
+        "off" | "heat" | "cool" | "heat_cool" | "auto")) => {
+            attributes.insert("state".into(), mode.to_uppercase().into());
         }
-        "fan_only" => {
+        Some("fan_only") => {
             attributes.insert("state".into(), "FAN".into());
         }
-        state => warn!("{} Not supported climate state: {}", entity_id, state),
+        Some(state) => warn!("{} Not supported climate state: {}", entity_id, state),
+        None => {}
     };
 
     if let Some(ha_attr) = ha_attr {
@@ -56,14 +90,60 @@ pub(crate) fn map_climate_attributes(
         json::move_entry(ha_attr, &mut attributes, "target_temperature_high");
         json::move_entry(ha_attr, &mut attributes, "target_temperature_low");
         if let Some(value) = ha_attr.get("fan_mode").and_then(|v| v.as_str()) {
-            // TODO test and filter fan modes?
-            attributes.insert("fan_mode".into(), value.to_uppercase().into());
+            // upper-cased fan modes are mapped back with the advertised HA fan_modes in the service call
+            attributes.insert("fan_mode".into(), normalize_mode(value).into());
+        }
+        if let Some(value) = ha_attr.get("swing_mode").and_then(|v| v.as_str()) {
+            // same as fan modes: mapped back with the advertised HA swing_modes
+            attributes.insert("swing_mode".into(), normalize_mode(value).into());
+        }
+        // preset names are device specific and passed through as is
+        if let Some(value) = ha_attr.get("preset_mode").and_then(|v| v.as_str()) {
+            attributes.insert("preset_mode".into(), value.into());
+        }
+    }
+
+    // the setpoint attribute has priority over the state
+    if let Some(value) = state_temperature {
+        if attributes
+            .get("target_temperature")
+            .map_or(true, |v| v.is_null())
+        {
+            attributes.insert("target_temperature".into(), value.into());
         }
     }
 
     Ok(attributes)
 }
 
+/// Normalize a HA fan or swing mode for the remote: upper-cased without surrounding whitespace.
+///
+/// The HA value is looked up again with the advertised mode list in the service call.
+pub(crate) fn normalize_mode(mode: &str) -> String {
+    mode.trim().to_uppercase()
+}
+
+/// Get the normalized values of a HA mode list attribute without duplicates.
+fn upper_case_modes(ha_attr: &Map<String, Value>, modes_attr: &str) -> Option<Vec<Value>> {
+    ha_attr
+        .get(modes_attr)
+        .and_then(|v| v.as_array())
+        .map(|modes| {
+            let mut result: Vec<Value> = Vec::with_capacity(modes.len());
+            for mode in modes.iter().filter_map(|v| v.as_str()).map(normalize_mode) {
+                if !mode.is_empty() && !result.iter().any(|v| v == &mode) {
+                    result.push(mode.into());
+                }
+            }
+            result
+        })
+}
+
+/// Get the setpoint temperature from a numeric entity state.
+fn state_temperature(state: &str) -> Option<serde_json::Number> {
+    serde_json::from_str(state.trim()).ok()
+}
+
 pub(crate) fn climate_event_to_entity_change(
     mut data: EventData,
 ) -> Result<EntityChange, ServiceError> {
@@ -81,10 +161,15 @@ pub(crate) fn climate_event_to_entity_change(
     })
 }
 
+/// Convert a HA climate entity.
+///
+/// The optional `temperature_unit` is used if the entity doesn't provide a `temperature_unit`
+/// attribute. Otherwise the remote uses its configured temperature unit.
 pub(crate) fn convert_climate_entity(
     entity_id: String,
     state: String,
     ha_attr: &mut Map<String, Value>,
+    temperature_unit: Option<&str>,
 ) -> Result<AvailableIntgEntity, ServiceError> {
     let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
     let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
@@ -99,8 +184,12 @@ pub(crate) fn convert_climate_entity(
     // TODO not completely tested, need to test "cool"! #11
     // https://developers.home-assistant.io/docs/core/entity/climate#hvac-modes
     // Need to find some real climate devices to test...
+    let mut hvac_mode_states = Vec::new();
     if let Some(hvac_modes) = ha_attr.get("hvac_modes").and_then(|v| v.as_array()) {
         for hvac_mode in hvac_modes {
+            if let Some(state) = hvac_mode.as_str().and_then(hvac_mode_state) {
+                hvac_mode_states.push(Value::String(state.into()));
+            }
             let feature = match hvac_mode.as_str().unwrap_or_default() {
                 "off" => ClimateFeature::OnOff,
                 "heat" => ClimateFeature::Heat,
@@ -126,6 +215,35 @@ pub(crate) fn convert_climate_entity(
         climate_feats.push(ClimateFeature::CurrentTemperature);
     }
 
+    let mut features: Vec<String> = climate_feats.into_iter().map(|v| v.to_string()).collect();
+    let preset_modes = if supported_features & SUPPORT_PRESET_MODE > 0 {
+        ha_attr
+            .get("preset_modes")
+            .filter(|v| v.is_array())
+            .cloned()
+    } else {
+        None
+    };
+    if preset_modes.is_some() {
+        features.push(FEATURE_PRESET_MODE.into());
+    }
+    let fan_modes = if supported_features & SUPPORT_FAN_MODE > 0 {
+        upper_case_modes(ha_attr, "fan_modes")
+    } else {
+        None
+    };
+    if fan_modes.is_some() {
+        features.push(FEATURE_FAN_MODE.into());
+    }
+    let swing_modes = if supported_features & SUPPORT_SWING_MODE > 0 {
+        upper_case_modes(ha_attr, "swing_modes")
+    } else {
+        None
+    };
+    if swing_modes.is_some() {
+        features.push(FEATURE_SWING_MODE.into());
+    }
+
     // handle options. TODO untested! Only based on some GitHub issue logs :-) #12
     let mut options = serde_json::Map::new();
     if let Some(v) = number_value(ha_attr, "min_temp") {
@@ -137,9 +255,46 @@ pub(crate) fn convert_climate_entity(
     if let Some(v) = number_value(ha_attr, "target_temp_step") {
         options.insert(ClimateOptionField::TargetTemperatureStep.to_string(), v);
     }
-    // TODO how do we get the HA temperature_unit attribute? Couldn't find an example... #10
-    if let Some(v) = ha_attr.get("temperature_unit") {
-        options.insert(ClimateOptionField::TemperatureUnit.to_string(), v.clone());
+    // Most climate entities don't provide a temperature_unit attribute (e.g. generic thermostat)
+    if let Some(v) = ha_attr
+        .get("temperature_unit")
+        .and_then(|v| v.as_str())
+        .or(temperature_unit)
+    {
+        options.insert(
+            ClimateOptionField::TemperatureUnit.to_string(),
+            convert_temperature_unit(v).into(),
+        );
+    }
+    if let Some(v) = preset_modes {
+        let modes = || {
+            v.as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v.as_str())
+        };
+        let labels: Map<String, Value> = modes()
+            .filter_map(|mode| preset_mode_labels(mode).map(|labels| (mode.to_string(), labels)))
+            .collect();
+        if !labels.is_empty() {
+            options.insert(OPTION_PRESET_MODE_LABELS.into(), labels.into());
+        }
+        let icons: Map<String, Value> = modes()
+            .filter_map(|mode| preset_mode_icon(mode).map(|icon| (mode.to_string(), icon.into())))
+            .collect();
+        if !icons.is_empty() {
+            options.insert(OPTION_PRESET_MODE_ICONS.into(), icons.into());
+        }
+        options.insert(OPTION_PRESET_MODES.into(), v);
+    }
+    if let Some(v) = fan_modes {
+        options.insert(OPTION_FAN_MODES.into(), v.into());
+    }
+    if let Some(v) = swing_modes {
+        options.insert(OPTION_SWING_MODES.into(), v.into());
+    }
+    if !hvac_mode_states.is_empty() {
+        options.insert(OPTION_HVAC_MODES.into(), hvac_mode_states.into());
     }
 
     // convert attributes
@@ -151,7 +306,7 @@ pub(crate) fn convert_climate_entity(
         entity_type: EntityType::Climate,
         device_class: None,
         name,
-        features: Some(climate_feats.into_iter().map(|v| v.to_string()).collect()),
+        features: Some(features),
         area: None,
         options: if options.is_empty() {
             None
@@ -162,13 +317,72 @@ pub(crate) fn convert_climate_entity(
     })
 }
 
+/// Get the Integration-API climate state of a HA hvac mode.
+fn hvac_mode_state(hvac_mode: &str) -> Option<&'static str> {
+    match hvac_mode {
+        "off" => Some("OFF"),
+        "heat" => Some("HEAT"),
+        "cool" => Some("COOL"),
+        "heat_cool" => Some("HEAT_COOL"),
+        "auto" => Some("AUTO"),
+        "fan_only" => Some("FAN"),
+        _ => None,
+    }
+}
+
+/// Get the localized labels of a common HA preset mode.
+///
+/// Custom preset modes of an integration are not translated.
+fn preset_mode_labels(preset_mode: &str) -> Option<Value> {
+    let (en, de, fr) = match preset_mode {
+        "eco" => ("Eco", "Eco", "Éco"),
+        "away" => ("Away", "Abwesend", "Absent"),
+        "comfort" => ("Comfort", "Komfort", "Confort"),
+        "boost" => ("Boost", "Boost", "Boost"),
+        "home" => ("Home", "Zuhause", "Maison"),
+        "sleep" => ("Sleep", "Schlafen", "Nuit"),
+        "activity" => ("Activity", "Aktivität", "Activité"),
+        _ => return None,
+    };
+    Some(serde_json::json!({ "en": en, "de": de, "fr": fr }))
+}
+
+/// Get the icon of a common HA preset mode, using the same icons as the HA frontend.
+fn preset_mode_icon(preset_mode: &str) -> Option<&'static str> {
+    match preset_mode {
+        "eco" => Some("mdi:leaf"),
+        "away" => Some("mdi:account-arrow-right"),
+        "comfort" => Some("mdi:sofa"),
+        "boost" => Some("mdi:rocket-launch"),
+        "home" => Some("mdi:home"),
+        "sleep" => Some("mdi:bed"),
+        "activity" => Some("mdi:motion-sensor"),
+        _ => None,
+    }
+}
+
+/// Convert a HA temperature unit symbol to the Integration-API temperature unit.
+pub(crate) fn convert_temperature_unit(unit: &str) -> &str {
+    match unit {
+        "°C" => "CELSIUS",
+        "°F" => "FAHRENHEIT",
+        v => v,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::client::entity::climate_event_to_entity_change;
+    use crate::client::entity::{
+        climate_event_to_entity_change, convert_climate_entity, FEATURE_FAN_MODE,
+        FEATURE_PRESET_MODE, FEATURE_SWING_MODE, OPTION_FAN_MODES, OPTION_HVAC_MODES,
+        OPTION_PRESET_MODES, OPTION_PRESET_MODE_ICONS, OPTION_PRESET_MODE_LABELS,
+        OPTION_SWING_MODES,
+    };
     use crate::client::model::EventData;
+    use rstest::rstest;
     use serde_json::{json, Value};
-    use uc_api::intg::EntityChange;
-    use uc_api::EntityType;
+    use uc_api::intg::{AvailableIntgEntity, EntityChange};
+    use uc_api::{ClimateFeature, ClimateOptionField, EntityType};
 
     #[test]
     fn climate_event_heat() {
@@ -244,6 +458,478 @@ mod tests {
         );
     }
 
+    #[test]
+    fn climate_event_with_setpoint_in_state() {
+        let new_state = json!({
+            "entity_id": "climate.radiator",
+            "state": "21.5",
+            "attributes": {
+                "hvac_mode": "heat",
+                "current_temperature": 20.1,
+                "friendly_name": "Radiator",
+                "supported_features": 1
+            }
+        });
+        let event = map_new_state(new_state);
+
+        assert_eq!(Some(&json!("HEAT")), event.attributes.get("state"));
+        assert_eq!(
+            Some(&json!(21.5)),
+            event.attributes.get("target_temperature")
+        );
+        assert_eq!(
+            Some(&json!(20.1)),
+            event.attributes.get("current_temperature")
+        );
+    }
+
+    #[test]
+    fn climate_event_with_setpoint_in_state_without_hvac_mode() {
+        let new_state = json!({
+            "entity_id": "climate.radiator",
+            "state": "19",
+            "attributes": {
+                "temperature": null,
+                "supported_features": 1
+            }
+        });
+        let event = map_new_state(new_state);
+
+        assert_eq!(None, event.attributes.get("state"));
+        assert_eq!(Some(&json!(19)), event.attributes.get("target_temperature"));
+    }
+
+    #[test]
+    fn climate_event_setpoint_attribute_has_priority_over_state() {
+        let new_state = json!({
+            "entity_id": "climate.radiator",
+            "state": "19",
+            "attributes": {
+                "hvac_mode": "heat",
+                "temperature": 22,
+                "supported_features": 1
+            }
+        });
+        let event = map_new_state(new_state);
+
+        assert_eq!(Some(&json!(22)), event.attributes.get("target_temperature"));
+    }
+
+    #[test]
+    fn convert_entity_with_setpoint_in_state() {
+        let entity = convert_entity(json!({
+            "entity_id": "climate.radiator",
+            "state": "20.5",
+            "attributes": {
+                "hvac_modes": ["off", "heat"],
+                "hvac_mode": "heat",
+                "friendly_name": "Radiator",
+                "supported_features": 1
+            }
+        }));
+
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("HEAT")), attributes.get("state"));
+        assert_eq!(Some(&json!(20.5)), attributes.get("target_temperature"));
+    }
+
+    #[test]
+    fn climate_event_preset_mode() {
+        let new_state = json!({
+            "entity_id": "climate.bathroom_floor_heating_mode",
+            "state": "heat",
+            "attributes": {
+                "preset_modes": [
+                    "none",
+                    "Energy heat"
+                ],
+                "preset_mode": "Energy heat",
+                "supported_features": 16
+            }
+        });
+        let event = map_new_state(new_state);
+
+        assert_eq!(
+            Some(&json!("Energy heat")),
+            event.attributes.get("preset_mode")
+        );
+    }
+
+    #[test]
+    fn convert_entity_with_presets_without_target_temperature() {
+        let entity = convert_entity(json!({
+            "entity_id": "climate.bathroom_floor_heating_mode",
+            "state": "heat",
+            "attributes": {
+                "hvac_modes": [
+                    "off",
+                    "heat"
+                ],
+                "preset_modes": [
+                    "none",
+                    "Energy heat"
+                ],
+                "preset_mode": "none",
+                "current_temperature": 22.6,
+                "friendly_name": "Bathroom floor heating",
+                "supported_features": 16
+            }
+        }));
+
+        let features = entity.features.expect("features must be set");
+        assert!(features.contains(&FEATURE_PRESET_MODE.to_string()));
+        assert!(!features.contains(&ClimateFeature::TargetTemperature.to_string()));
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!(["none", "Energy heat"])),
+            options.get(OPTION_PRESET_MODES)
+        );
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("none")), attributes.get("preset_mode"));
+    }
+
+    #[test]
+    fn convert_entity_with_hvac_modes_only() {
+        let entity = convert_entity(json!({
+            "entity_id": "climate.hallway",
+            "state": "auto",
+            "attributes": {
+                "hvac_modes": ["off", "heat", "auto", "dry"],
+                "min_temp": 7,
+                "max_temp": 35,
+                "friendly_name": "Hallway",
+                "supported_features": 384
+            }
+        }));
+
+        assert_eq!(
+            Some(vec![
+                ClimateFeature::OnOff.to_string(),
+                ClimateFeature::Heat.to_string()
+            ]),
+            entity.features
+        );
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!(["OFF", "HEAT", "AUTO"])),
+            options.get(OPTION_HVAC_MODES)
+        );
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("AUTO")), attributes.get("state"));
+        assert_eq!(None, attributes.get("target_temperature"));
+    }
+
+    #[test]
+    fn convert_entity_with_common_presets_provides_labels() {
+        let entity = convert_entity(json!({
+            "entity_id": "climate.living_room",
+            "state": "heat",
+            "attributes": {
+                "hvac_modes": ["off", "heat"],
+                "preset_modes": ["eco", "comfort", "Energy heat"],
+                "preset_mode": "eco",
+                "friendly_name": "Living room",
+                "supported_features": 16
+            }
+        }));
+
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!({
+                "eco": { "en": "Eco", "de": "Eco", "fr": "Éco" },
+                "comfort": { "en": "Comfort", "de": "Komfort", "fr": "Confort" }
+            })),
+            options.get(OPTION_PRESET_MODE_LABELS)
+        );
+        assert_eq!(
+            Some(&json!({
+                "eco": "mdi:leaf",
+                "comfort": "mdi:sofa"
+            })),
+            options.get(OPTION_PRESET_MODE_ICONS)
+        );
+    }
+
+    #[test]
+    fn convert_entity_with_custom_presets_has_no_labels() {
+        let entity = convert_entity(json!({
+            "entity_id": "climate.bathroom_floor_heating_mode",
+            "state": "heat",
+            "attributes": {
+                "hvac_modes": ["off", "heat"],
+                "preset_modes": ["none", "Energy heat"],
+                "preset_mode": "none",
+                "supported_features": 16
+            }
+        }));
+
+        let options = entity.options.expect("options must be set");
+        assert_eq!(None, options.get(OPTION_PRESET_MODE_LABELS));
+        assert_eq!(None, options.get(OPTION_PRESET_MODE_ICONS));
+    }
+
+    #[rstest]
+    #[case("eco", Some("Eco"))]
+    #[case("away", Some("Away"))]
+    #[case("comfort", Some("Comfort"))]
+    #[case("boost", Some("Boost"))]
+    #[case("home", Some("Home"))]
+    #[case("sleep", Some("Sleep"))]
+    #[case("activity", Some("Activity"))]
+    #[case("Eco", None)]
+    #[case("none", None)]
+    fn preset_mode_label_mapping(#[case] preset_mode: &str, #[case] en_label: Option<&str>) {
+        let labels = super::preset_mode_labels(preset_mode);
+
+        assert_eq!(
+            en_label,
+            labels
+                .as_ref()
+                .and_then(|v| v.get("en"))
+                .and_then(|v| v.as_str())
+        );
+        if let Some(labels) = labels {
+            assert!(labels.get("de").is_some(), "Missing de label");
+            assert!(labels.get("fr").is_some(), "Missing fr label");
+        }
+        // every translated preset mode has an icon
+        assert_eq!(
+            en_label.is_some(),
+            super::preset_mode_icon(preset_mode).is_some()
+        );
+    }
+
+    #[test]
+    fn convert_entity_without_preset_support_ignores_presets() {
+        let entity = convert_entity(json!({
+            "entity_id": "climate.bathroom_floor_heating_mode",
+            "state": "heat",
+            "attributes": {
+                "preset_modes": [
+                    "none",
+                    "Energy heat"
+                ],
+                "temperature": 21,
+                "supported_features": 1
+            }
+        }));
+
+        let features = entity.features.expect("features must be set");
+        assert!(!features.contains(&FEATURE_PRESET_MODE.to_string()));
+        assert!(features.contains(&ClimateFeature::TargetTemperature.to_string()));
+    }
+
+    #[test]
+    fn convert_entity_with_fan_modes() {
+        let entity = convert_entity(json!({
+            "entity_id": "climate.living_room_ac",
+            "state": "cool",
+            "attributes": {
+                "hvac_modes": [
+                    "off",
+                    "cool"
+                ],
+                "fan_modes": [
+                    "auto",
+                    "low",
+                    "High"
+                ],
+                "fan_mode": "auto",
+                "temperature": 21,
+                "supported_features": 9
+            }
+        }));
+
+        let features = entity.features.expect("features must be set");
+        assert!(features.contains(&FEATURE_FAN_MODE.to_string()));
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!(["AUTO", "LOW", "HIGH"])),
+            options.get(OPTION_FAN_MODES)
+        );
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("AUTO")), attributes.get("fan_mode"));
+    }
+
+    #[test]
+    fn convert_entity_with_mixed_case_fan_modes() {
+        let entity = convert_entity(json!({
+            "entity_id": "climate.living_room_ac",
+            "state": "cool",
+            "attributes": {
+                "hvac_modes": [
+                    "off",
+                    "cool"
+                ],
+                "fan_modes": [
+                    "On",
+                    "AUTO",
+                    "low",
+                    "Medium",
+                    "hIgh",
+                    "auto",
+                    " high "
+                ],
+                "fan_mode": "Medium",
+                "temperature": 21,
+                "supported_features": 9
+            }
+        }));
+
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!(["ON", "AUTO", "LOW", "MEDIUM", "HIGH"])),
+            options.get(OPTION_FAN_MODES)
+        );
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("MEDIUM")), attributes.get("fan_mode"));
+    }
+
+    #[rstest]
+    #[case("on", "ON")]
+    #[case("Auto", "AUTO")]
+    #[case("LOW", "LOW")]
+    #[case("medium ", "MEDIUM")]
+    #[case("High", "HIGH")]
+    fn normalize_fan_mode(#[case] ha_mode: &str, #[case] expected: &str) {
+        assert_eq!(expected, normalize_mode(ha_mode));
+    }
+
+    #[test]
+    fn convert_entity_with_swing_modes() {
+        let entity = convert_entity(json!({
+            "entity_id": "climate.living_room_ac",
+            "state": "cool",
+            "attributes": {
+                "hvac_modes": [
+                    "off",
+                    "cool"
+                ],
+                "swing_modes": [
+                    "off",
+                    "3D Auto",
+                    "vertical"
+                ],
+                "swing_mode": "3D Auto",
+                "temperature": 21,
+                "supported_features": 33
+            }
+        }));
+
+        let features = entity.features.expect("features must be set");
+        assert!(features.contains(&FEATURE_SWING_MODE.to_string()));
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!(["OFF", "3D AUTO", "VERTICAL"])),
+            options.get(OPTION_SWING_MODES)
+        );
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("3D AUTO")), attributes.get("swing_mode"));
+    }
+
+    #[test]
+    fn convert_entity_without_fan_mode_support_ignores_fan_modes() {
+        let entity = convert_entity(json!({
+            "entity_id": "climate.living_room_ac",
+            "state": "cool",
+            "attributes": {
+                "fan_modes": [
+                    "auto",
+                    "low"
+                ],
+                "temperature": 21,
+                "supported_features": 1
+            }
+        }));
+
+        let features = entity.features.expect("features must be set");
+        assert!(!features.contains(&FEATURE_FAN_MODE.to_string()));
+        assert!(entity
+            .options
+            .map(|o| !o.contains_key(OPTION_FAN_MODES))
+            .unwrap_or(true));
+    }
+
+    #[test]
+    fn convert_entity_without_temperature_unit_uses_ha_unit() {
+        let entity = convert_entity_with_unit(generic_thermostat(), Some("°F"));
+
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!("FAHRENHEIT")),
+            options.get(&ClimateOptionField::TemperatureUnit.to_string())
+        );
+    }
+
+    #[test]
+    fn convert_entity_with_temperature_unit_ignores_ha_unit() {
+        let mut ha_entity = generic_thermostat();
+        ha_entity["attributes"]["temperature_unit"] = json!("°C");
+        let entity = convert_entity_with_unit(ha_entity, Some("°F"));
+
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!("CELSIUS")),
+            options.get(&ClimateOptionField::TemperatureUnit.to_string())
+        );
+    }
+
+    #[test]
+    fn convert_entity_without_any_temperature_unit_uses_remote_unit() {
+        let entity = convert_entity(generic_thermostat());
+
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            None,
+            options.get(&ClimateOptionField::TemperatureUnit.to_string())
+        );
+    }
+
+    fn generic_thermostat() -> Value {
+        json!({
+            "entity_id": "climate.study",
+            "state": "heat",
+            "attributes": {
+                "hvac_modes": ["heat", "off"],
+                "min_temp": 7,
+                "max_temp": 35,
+                "target_temp_step": 0.5,
+                "current_temperature": 20.5,
+                "temperature": 21,
+                "friendly_name": "Study",
+                "supported_features": 1
+            }
+        })
+    }
+
+    fn convert_entity(ha_entity: Value) -> AvailableIntgEntity {
+        convert_entity_with_unit(ha_entity, None)
+    }
+
+    fn convert_entity_with_unit(
+        mut ha_entity: Value,
+        temperature_unit: Option<&str>,
+    ) -> AvailableIntgEntity {
+        let ha_entity = ha_entity.as_object_mut().unwrap();
+        let entity_id = ha_entity["entity_id"].as_str().unwrap().to_string();
+        let state = ha_entity["state"].as_str().unwrap().to_string();
+        let attr = ha_entity
+            .get_mut("attributes")
+            .and_then(|v| v.as_object_mut())
+            .unwrap();
+
+        let result = convert_climate_entity(entity_id, state, attr, temperature_unit);
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+        assert_eq!(EntityType::Climate, entity.entity_type);
+
+        entity
+    }
+
     fn map_new_state(new_state: Value) -> EntityChange {
         let data = EventData {
             entity_id: "test".into(),
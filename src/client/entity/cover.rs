@@ -16,10 +16,20 @@ pub const COVER_SUPPORT_OPEN: u32 = 1;
 pub const COVER_SUPPORT_CLOSE: u32 = 2;
 pub const COVER_SUPPORT_SET_POSITION: u32 = 4;
 pub const COVER_SUPPORT_STOP: u32 = 8;
-// pub const COVER_SUPPORT_OPEN_TILT: u32 = 16;
-// pub const COVER_SUPPORT_CLOSE_TILT: u32 = 32;
-// pub const COVER_SUPPORT_STOP_TILT: u32 = 64;
-// pub const COVER_SUPPORT_SET_TILT_POSITION: u32 = 128;
+pub const COVER_SUPPORT_OPEN_TILT: u32 = 16;
+pub const COVER_SUPPORT_CLOSE_TILT: u32 = 32;
+pub const COVER_SUPPORT_STOP_TILT: u32 = 64;
+pub const COVER_SUPPORT_SET_TILT_POSITION: u32 = 128;
+
+/// Cover tilt features. Not yet defined in the Integration-API `CoverFeature` enum.
+pub const COVER_FEATURE_TILT: &str = "tilt";
+pub const COVER_FEATURE_TILT_STOP: &str = "tilt_stop";
+pub const COVER_FEATURE_TILT_POSITION: &str = "tilt_position";
+/// Cover tilt commands. Not yet defined in the Integration-API `CoverCommand` enum.
+pub const COVER_CMD_TILT: &str = "tilt";
+pub const COVER_CMD_TILT_UP: &str = "tilt_up";
+pub const COVER_CMD_TILT_DOWN: &str = "tilt_down";
+pub const COVER_CMD_TILT_STOP: &str = "tilt_stop";
 
 pub(crate) fn map_cover_attributes(
     _entity_id: &str,
@@ -34,6 +44,8 @@ pub(crate) fn map_cover_attributes(
     };
     attributes.insert("state".into(), state);
 
+    // The position is forwarded independent of the supported features: a cover may report its
+    // position without supporting to set it.
     if let Some(ha_attr) = ha_attr {
         if let Some(value @ 0..=100) = ha_attr.get("current_position").and_then(|v| v.as_u64()) {
             attributes.insert("position".into(), value.into());
@@ -97,10 +109,19 @@ pub(crate) fn convert_cover_entity(
     if supported_features & COVER_SUPPORT_STOP > 0 {
         cover_feats.push(CoverFeature::Stop);
     }
+    // only advertise position for covers which can be positioned, read-only position is still
+    // forwarded as attribute
     if supported_features & COVER_SUPPORT_SET_POSITION > 0 {
         cover_feats.push(CoverFeature::Position);
     }
 
+    let mut features: Vec<String> = cover_feats.into_iter().map(|v| v.to_string()).collect();
+    features.extend(
+        tilt_features(supported_features)
+            .into_iter()
+            .map(String::from),
+    );
+
     // convert attributes
     let attributes = Some(map_cover_attributes(&entity_id, &state, Some(ha_attr))?);
 
@@ -110,9 +131,126 @@ pub(crate) fn convert_cover_entity(
         entity_type: EntityType::Cover,
         device_class,
         name,
-        features: Some(cover_feats.into_iter().map(|v| v.to_string()).collect()),
+        features: Some(features),
         area: None,
         options: None,
         attributes,
     })
 }
+
+/// Decode the tilt features from the HA supported features.
+fn tilt_features(supported_features: u32) -> Vec<&'static str> {
+    let mut features = Vec::with_capacity(3);
+    if supported_features & (COVER_SUPPORT_OPEN_TILT | COVER_SUPPORT_CLOSE_TILT) > 0 {
+        features.push(COVER_FEATURE_TILT);
+    }
+    if supported_features & COVER_SUPPORT_STOP_TILT > 0 {
+        features.push(COVER_FEATURE_TILT_STOP);
+    }
+    if supported_features & COVER_SUPPORT_SET_TILT_POSITION > 0 {
+        features.push(COVER_FEATURE_TILT_POSITION);
+    }
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case(15, vec![])]
+    #[case(16, vec![COVER_FEATURE_TILT])]
+    #[case(32, vec![COVER_FEATURE_TILT])]
+    #[case(64, vec![COVER_FEATURE_TILT_STOP])]
+    #[case(128, vec![COVER_FEATURE_TILT_POSITION])]
+    #[case(255, vec![COVER_FEATURE_TILT, COVER_FEATURE_TILT_STOP, COVER_FEATURE_TILT_POSITION])]
+    fn tilt_feature_decoding(#[case] supported_features: u32, #[case] expected: Vec<&str>) {
+        assert_eq!(expected, tilt_features(supported_features));
+    }
+
+    #[test]
+    fn convert_cover_with_tilt() {
+        let mut attr = json!({
+            "current_position": 100,
+            "current_tilt_position": 50,
+            "device_class": "blind",
+            "friendly_name": "Living room blind",
+            // OPEN | CLOSE | SET_POSITION | STOP | OPEN_TILT | CLOSE_TILT | STOP_TILT | SET_TILT_POSITION
+            "supported_features": 255
+        });
+        let entity = convert_cover_entity(
+            "cover.living_room".into(),
+            "open".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        let features = entity.features.expect("features must be set");
+        assert!(features.contains(&CoverFeature::Position.to_string()));
+        assert!(features.contains(&COVER_FEATURE_TILT.to_string()));
+        assert!(features.contains(&COVER_FEATURE_TILT_STOP.to_string()));
+        assert!(features.contains(&COVER_FEATURE_TILT_POSITION.to_string()));
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!(50)), attributes.get("tilt_position"));
+    }
+
+    #[test]
+    fn convert_cover_with_read_only_position() {
+        let mut attr = json!({
+            "current_position": 42,
+            "device_class": "garage",
+            "friendly_name": "Garage door",
+            // OPEN | CLOSE | STOP
+            "supported_features": 11
+        });
+        let result = convert_cover_entity(
+            "cover.garage".into(),
+            "open".into(),
+            attr.as_object_mut().unwrap(),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+
+        let features = entity.features.expect("features must be set");
+        assert!(
+            !features.contains(&CoverFeature::Position.to_string()),
+            "Position feature must not be advertised: {features:?}"
+        );
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!(42)), attributes.get("position"));
+    }
+
+    #[test]
+    fn cover_event_with_read_only_position() {
+        let data = EventData {
+            entity_id: "cover.garage".into(),
+            new_state: serde_json::from_value(json!({
+                "state": "closing",
+                "attributes": {
+                    "current_position": 10,
+                    "supported_features": 11
+                }
+            }))
+            .expect("invalid test data"),
+        };
+        let result = cover_event_to_entity_change(data);
+        assert!(
+            result.is_ok(),
+            "Expected successful event mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity_change = result.unwrap();
+
+        assert_eq!(
+            Some(&json!("CLOSING")),
+            entity_change.attributes.get("state")
+        );
+        assert_eq!(Some(&json!(10)), entity_change.attributes.get("position"));
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Fan entity specific logic.
+//!
+//! The Integration-API doesn't define a fan entity yet. A fan is exposed as a switch entity with
+//! additional fan attributes and commands.
+
+use crate::client::event::convert_ha_onoff_state;
+use crate::client::model::EventData;
+use crate::errors::ServiceError;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
+use uc_api::EntityType;
+
+// https://developers.home-assistant.io/docs/core/entity/fan#supported-features
+pub const FAN_SUPPORT_DIRECTION: u32 = 4;
+/* not yet used constants
+pub const FAN_SUPPORT_SET_SPEED: u32 = 1;
+pub const FAN_SUPPORT_OSCILLATE: u32 = 2;
+pub const FAN_SUPPORT_PRESET_MODE: u32 = 8;
+*/
+
+/// Fan direction feature & command in addition to the switch entity features.
+pub const FAN_FEATURE_DIRECTION: &str = "direction";
+pub const FAN_CMD_DIRECTION: &str = "direction";
+
+pub(crate) fn map_fan_attributes(
+    _entity_id: &str,
+    state: &str,
+    ha_attr: Option<&mut Map<String, Value>>,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut attributes = serde_json::Map::with_capacity(2);
+    attributes.insert("state".into(), convert_ha_onoff_state(state)?);
+
+    if let Some(direction) = ha_attr
+        .and_then(|attr| attr.get("direction"))
+        .and_then(|v| v.as_str())
+    {
+        attributes.insert("direction".into(), direction.into());
+    }
+
+    Ok(attributes)
+}
+
+pub(crate) fn fan_event_to_entity_change(
+    mut data: EventData,
+) -> Result<EntityChange, ServiceError> {
+    let attributes = map_fan_attributes(
+        &data.entity_id,
+        &data.new_state.state,
+        data.new_state.attributes.as_mut(),
+    )?;
+
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Switch,
+        entity_id: data.entity_id,
+        attributes,
+    })
+}
+
+pub(crate) fn convert_fan_entity(
+    entity_id: String,
+    state: String,
+    ha_attr: &mut Map<String, Value>,
+) -> Result<AvailableIntgEntity, ServiceError> {
+    let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
+    let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
+
+    // handle features
+    let supported_features = ha_attr
+        .get("supported_features")
+        .and_then(|v| v.as_u64())
+        .unwrap_or_default() as u32;
+    // OnOff is default
+    let mut features = vec!["toggle".to_string()];
+    if supported_features & FAN_SUPPORT_DIRECTION > 0 {
+        features.push(FAN_FEATURE_DIRECTION.into());
+    }
+
+    // convert attributes
+    let attributes = Some(map_fan_attributes(&entity_id, &state, Some(ha_attr))?);
+
+    Ok(AvailableIntgEntity {
+        entity_id,
+        device_id: None, // prepared for device_id handling
+        entity_type: EntityType::Switch,
+        device_class: None,
+        name,
+        features: Some(features),
+        area: None,
+        options: None,
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case(4, true)]
+    #[case(13, true)]
+    #[case(9, false)]
+    #[case(0, false)]
+    fn direction_feature(#[case] supported_features: u32, #[case] expected: bool) {
+        let mut attr = json!({
+            "direction": "forward",
+            "friendly_name": "Ceiling fan",
+            "supported_features": supported_features
+        });
+        let entity = convert_fan_entity(
+            "fan.ceiling".into(),
+            "on".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        assert_eq!(EntityType::Switch, entity.entity_type);
+        let features = entity.features.expect("features must be set");
+        assert_eq!(
+            expected,
+            features.contains(&FAN_FEATURE_DIRECTION.to_string())
+        );
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("ON")), attributes.get("state"));
+        assert_eq!(Some(&json!("forward")), attributes.get("direction"));
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Humidifier entity specific logic.
+//!
+//! The Integration-API doesn't define a humidifier entity yet. A humidifier is exposed as a switch
+//! entity with additional humidity & mode attributes and commands.
+
+use crate::client::event::convert_ha_onoff_state;
+use crate::client::model::EventData;
+use crate::errors::ServiceError;
+use crate::util::json;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
+use uc_api::EntityType;
+
+// https://developers.home-assistant.io/docs/core/entity/humidifier#supported-features
+pub const HUMIDIFIER_SUPPORT_MODES: u32 = 1;
+
+/// Humidifier features & commands in addition to the switch entity features.
+pub const HUMIDIFIER_FEATURE_HUMIDITY: &str = "humidity";
+pub const HUMIDIFIER_FEATURE_MODE: &str = "mode";
+pub const HUMIDIFIER_CMD_HUMIDITY: &str = "humidity";
+pub const HUMIDIFIER_CMD_MODE: &str = "mode";
+
+pub(crate) fn map_humidifier_attributes(
+    _entity_id: &str,
+    state: &str,
+    ha_attr: Option<&mut Map<String, Value>>,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut attributes = serde_json::Map::with_capacity(4);
+    attributes.insert("state".into(), convert_ha_onoff_state(state)?);
+
+    if let Some(ha_attr) = ha_attr {
+        json::move_entry(ha_attr, &mut attributes, "humidity");
+        json::move_entry(ha_attr, &mut attributes, "current_humidity");
+        json::move_entry(ha_attr, &mut attributes, "mode");
+    }
+
+    Ok(attributes)
+}
+
+pub(crate) fn humidifier_event_to_entity_change(
+    mut data: EventData,
+) -> Result<EntityChange, ServiceError> {
+    let attributes = map_humidifier_attributes(
+        &data.entity_id,
+        &data.new_state.state,
+        data.new_state.attributes.as_mut(),
+    )?;
+
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Switch,
+        entity_id: data.entity_id,
+        attributes,
+    })
+}
+
+pub(crate) fn convert_humidifier_entity(
+    entity_id: String,
+    state: String,
+    ha_attr: &mut Map<String, Value>,
+) -> Result<AvailableIntgEntity, ServiceError> {
+    let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
+    let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
+
+    // handle features
+    let supported_features = ha_attr
+        .get("supported_features")
+        .and_then(|v| v.as_u64())
+        .unwrap_or_default() as u32;
+    // OnOff is default
+    let mut features = vec!["toggle".to_string(), HUMIDIFIER_FEATURE_HUMIDITY.into()];
+    if supported_features & HUMIDIFIER_SUPPORT_MODES > 0 {
+        features.push(HUMIDIFIER_FEATURE_MODE.into());
+    }
+
+    // handle options
+    let mut options = serde_json::Map::new();
+    for key in ["min_humidity", "max_humidity"] {
+        if let Some(v) = ha_attr.get(key).filter(|v| v.is_number()) {
+            options.insert(key.into(), v.clone());
+        }
+    }
+    if supported_features & HUMIDIFIER_SUPPORT_MODES > 0 {
+        if let Some(v) = ha_attr.get("available_modes").filter(|v| v.is_array()) {
+            options.insert("available_modes".into(), v.clone());
+        }
+    }
+
+    // convert attributes
+    let attributes = Some(map_humidifier_attributes(
+        &entity_id,
+        &state,
+        Some(ha_attr),
+    )?);
+
+    Ok(AvailableIntgEntity {
+        entity_id,
+        device_id: None, // prepared for device_id handling
+        entity_type: EntityType::Switch,
+        device_class: None,
+        name,
+        features: Some(features),
+        area: None,
+        options: Some(options),
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case(1, true)]
+    #[case(0, false)]
+    fn mode_feature(#[case] supported_features: u32, #[case] expected: bool) {
+        let mut attr = json!({
+            "min_humidity": 30,
+            "max_humidity": 80,
+            "available_modes": ["normal", "eco", "sleep"],
+            "current_humidity": 42,
+            "humidity": 55,
+            "mode": "eco",
+            "friendly_name": "Bedroom humidifier",
+            "supported_features": supported_features
+        });
+        let entity = convert_humidifier_entity(
+            "humidifier.bedroom".into(),
+            "on".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        assert_eq!(EntityType::Switch, entity.entity_type);
+        let features = entity.features.expect("features must be set");
+        assert!(features.contains(&HUMIDIFIER_FEATURE_HUMIDITY.to_string()));
+        assert_eq!(
+            expected,
+            features.contains(&HUMIDIFIER_FEATURE_MODE.to_string())
+        );
+        let options = entity.options.expect("options must be set");
+        assert_eq!(Some(&json!(30)), options.get("min_humidity"));
+        assert_eq!(Some(&json!(80)), options.get("max_humidity"));
+        assert_eq!(expected, options.contains_key("available_modes"));
+    }
+
+    #[test]
+    fn convert_humidifier_attributes() {
+        let mut attr = json!({
+            "current_humidity": 42,
+            "humidity": 55,
+            "mode": "eco",
+            "friendly_name": "Bedroom humidifier",
+            "supported_features": 1
+        });
+        let entity = convert_humidifier_entity(
+            "humidifier.bedroom".into(),
+            "off".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("OFF")), attributes.get("state"));
+        assert_eq!(Some(&json!(55)), attributes.get("humidity"));
+        assert_eq!(Some(&json!(42)), attributes.get("current_humidity"));
+        assert_eq!(Some(&json!("eco")), attributes.get("mode"));
+    }
+
+    #[test]
+    fn humidifier_event() {
+        let data = EventData {
+            entity_id: "humidifier.bedroom".into(),
+            new_state: serde_json::from_value(json!({
+                "state": "on",
+                "attributes": {
+                    "current_humidity": 45,
+                    "humidity": 60,
+                    "mode": "normal"
+                }
+            }))
+            .unwrap(),
+        };
+
+        let entity_change =
+            humidifier_event_to_entity_change(data).expect("Expected successful entity change");
+
+        assert_eq!(EntityType::Switch, entity_change.entity_type);
+        assert_eq!(Some(&json!("ON")), entity_change.attributes.get("state"));
+        assert_eq!(Some(&json!(60)), entity_change.attributes.get("humidity"));
+        assert_eq!(
+            Some(&json!(45)),
+            entity_change.attributes.get("current_humidity")
+        );
+        assert_eq!(Some(&json!("normal")), entity_change.attributes.get("mode"));
+    }
+}
@@ -0,0 +1,224 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Lawn mower entity specific logic.
+//!
+//! The Integration-API doesn't define a lawn mower entity yet. A lawn mower is exposed as a remote
+//! entity with simple commands and the additional lawn mower state.
+
+use crate::client::model::EventData;
+use crate::errors::ServiceError;
+use log::warn;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uc_api::intg::{AvailableIntgEntity, EntityChange, IntgRemoteFeature};
+use uc_api::EntityType;
+
+// https://developers.home-assistant.io/docs/core/entity/lawn-mower#supported-features
+pub const LAWN_MOWER_SUPPORT_START_MOWING: u32 = 1;
+pub const LAWN_MOWER_SUPPORT_PAUSE: u32 = 2;
+pub const LAWN_MOWER_SUPPORT_DOCK: u32 = 4;
+
+/// Lawn mower commands, also used as remote entity simple commands.
+pub const LAWN_MOWER_CMD_START_MOWING: &str = "START_MOWING";
+pub const LAWN_MOWER_CMD_PAUSE: &str = "PAUSE";
+pub const LAWN_MOWER_CMD_DOCK: &str = "DOCK";
+
+pub(crate) fn map_lawn_mower_attributes(
+    entity_id: &str,
+    state: &str,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut attributes = serde_json::Map::with_capacity(2);
+
+    // remote entity state: ON while the lawn mower is active
+    let remote_state = match state {
+        "unavailable" | "unknown" => state.to_uppercase(),
+        "mowing" | "returning" => "ON".into(),
+        // a mower in error state is stopped
+        "docked" | "paused" | "error" => "OFF".into(),
+        state => {
+            warn!("{} Not supported lawn mower state: {}", entity_id, state);
+            "UNKNOWN".into()
+        }
+    };
+    attributes.insert("state".into(), remote_state.into());
+    attributes.insert("lawn_mower_state".into(), state.to_uppercase().into());
+
+    Ok(attributes)
+}
+
+pub(crate) fn lawn_mower_event_to_entity_change(
+    data: EventData,
+) -> Result<EntityChange, ServiceError> {
+    let attributes = map_lawn_mower_attributes(&data.entity_id, &data.new_state.state)?;
+
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Remote,
+        entity_id: data.entity_id,
+        attributes,
+    })
+}
+
+pub(crate) fn convert_lawn_mower_entity(
+    entity_id: String,
+    state: String,
+    ha_attr: &mut Map<String, Value>,
+) -> Result<AvailableIntgEntity, ServiceError> {
+    let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
+    let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
+
+    // handle features
+    let supported_features = ha_attr
+        .get("supported_features")
+        .and_then(|v| v.as_u64())
+        .unwrap_or_default() as u32;
+    let mut features = vec![IntgRemoteFeature::SendCmd.to_string()];
+    let mut commands = Vec::with_capacity(3);
+    if supported_features & LAWN_MOWER_SUPPORT_START_MOWING > 0 {
+        commands.push(LAWN_MOWER_CMD_START_MOWING);
+    }
+    if supported_features & LAWN_MOWER_SUPPORT_PAUSE > 0 {
+        commands.push(LAWN_MOWER_CMD_PAUSE);
+    }
+    if supported_features & LAWN_MOWER_SUPPORT_DOCK > 0 {
+        commands.push(LAWN_MOWER_CMD_DOCK);
+    }
+    // on: start mowing, off: return to dock
+    if supported_features & LAWN_MOWER_SUPPORT_START_MOWING > 0
+        && supported_features & LAWN_MOWER_SUPPORT_DOCK > 0
+    {
+        features.push(IntgRemoteFeature::OnOff.to_string());
+    }
+
+    // handle options
+    let mut options = serde_json::Map::new();
+    options.insert("simple_commands".into(), commands.into());
+
+    // convert attributes
+    let attributes = Some(map_lawn_mower_attributes(&entity_id, &state)?);
+
+    Ok(AvailableIntgEntity {
+        entity_id,
+        device_id: None, // prepared for device_id handling
+        entity_type: EntityType::Remote,
+        device_class: None,
+        name,
+        features: Some(features),
+        area: None,
+        options: Some(options),
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case("mowing", "ON")]
+    #[case("returning", "ON")]
+    #[case("docked", "OFF")]
+    #[case("paused", "OFF")]
+    #[case("error", "OFF")]
+    #[case("unavailable", "UNAVAILABLE")]
+    #[case("unknown", "UNKNOWN")]
+    fn lawn_mower_states(#[case] ha_state: &str, #[case] state: &str) {
+        let attributes = map_lawn_mower_attributes("lawn_mower.test", ha_state)
+            .expect("Expected successful attribute mapping");
+
+        assert_eq!(Some(&json!(state)), attributes.get("state"));
+        assert_eq!(
+            Some(&json!(ha_state.to_uppercase())),
+            attributes.get("lawn_mower_state")
+        );
+    }
+
+    #[test]
+    fn lawn_mower_event() {
+        let data = EventData {
+            entity_id: "lawn_mower.automower".into(),
+            new_state: serde_json::from_value(json!({
+                "state": "error",
+                "attributes": {
+                    "friendly_name": "Automower",
+                    "supported_features": 7
+                }
+            }))
+            .expect("invalid test data"),
+        };
+        let result = lawn_mower_event_to_entity_change(data);
+        assert!(
+            result.is_ok(),
+            "Expected successful event mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity_change = result.unwrap();
+
+        assert_eq!(EntityType::Remote, entity_change.entity_type);
+        assert_eq!(Some(&json!("OFF")), entity_change.attributes.get("state"));
+        assert_eq!(
+            Some(&json!("ERROR")),
+            entity_change.attributes.get("lawn_mower_state")
+        );
+    }
+
+    #[test]
+    fn convert_lawn_mower() {
+        let mut attr = json!({
+            "friendly_name": "Automower",
+            // START_MOWING | PAUSE | DOCK
+            "supported_features": 7
+        });
+        let result = convert_lawn_mower_entity(
+            "lawn_mower.automower".into(),
+            "docked".into(),
+            attr.as_object_mut().unwrap(),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+
+        assert_eq!(EntityType::Remote, entity.entity_type);
+        assert_eq!(Some(&"Automower".to_string()), entity.name.get("en"));
+        let features = entity.features.expect("features must be set");
+        assert!(features.contains(&IntgRemoteFeature::SendCmd.to_string()));
+        assert!(features.contains(&IntgRemoteFeature::OnOff.to_string()));
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!(["START_MOWING", "PAUSE", "DOCK"])),
+            options.get("simple_commands")
+        );
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("OFF")), attributes.get("state"));
+        assert_eq!(Some(&json!("DOCKED")), attributes.get("lawn_mower_state"));
+    }
+
+    #[test]
+    fn convert_lawn_mower_without_dock() {
+        let mut attr = json!({
+            "friendly_name": "Automower",
+            // START_MOWING | PAUSE
+            "supported_features": 3
+        });
+        let entity = convert_lawn_mower_entity(
+            "lawn_mower.automower".into(),
+            "mowing".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        let features = entity.features.expect("features must be set");
+        assert!(!features.contains(&IntgRemoteFeature::OnOff.to_string()));
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!(["START_MOWING", "PAUSE"])),
+            options.get("simple_commands")
+        );
+    }
+}
@@ -13,6 +13,16 @@ use std::collections::HashMap;
 use uc_api::intg::AvailableIntgEntity;
 use uc_api::{intg::EntityChange, EntityType, LightFeature};
 
+/// Light effect feature & command. Not yet defined in the Integration-API `LightFeature` enum.
+pub const LIGHT_FEATURE_EFFECT: &str = "effect";
+pub const LIGHT_CMD_EFFECT: &str = "effect";
+/// Available light effects entity option.
+pub const LIGHT_OPTION_EFFECT_LIST: &str = "effect_list";
+
+/// Map the HA light state and attributes to the Integration-API light attributes.
+///
+/// The `brightness` attribute is passed through as is: both HA and the Integration-API use the
+/// 0..255 range. Only the color temperature is scaled to the 0..100 range of the remote.
 pub(crate) fn map_light_attributes(
     entity_id: &str,
     state: &str,
@@ -32,6 +42,15 @@ pub(crate) fn map_light_attributes(
             })
             .map(|(key, value)| attributes.insert(key, value));
 
+        // some integrations report "None" if no effect is active
+        if let Some(effect) = ha_attr
+            .get("effect")
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.eq_ignore_ascii_case("none"))
+        {
+            attributes.insert("effect".into(), effect.into());
+        }
+
         // Color modes in HA are quite confusing...
         match ha_attr.get("color_mode").and_then(|v| v.as_str()) {
             Some("brightness") => {
@@ -51,6 +70,26 @@ pub(crate) fn map_light_attributes(
                     let color_temp_pct =
                         color_temp_mired_to_percent(color_temp, min_mireds, max_mireds)?;
 
+                    attributes.insert(
+                        "color_temperature".into(),
+                        Value::Number(color_temp_pct.into()),
+                    );
+                } else if let Some(color_temp) =
+                    ha_attr.get("color_temp_kelvin").and_then(|v| v.as_u64())
+                {
+                    // newer HA cores only provide Kelvin values
+                    let min_kelvin = ha_attr
+                        .get("min_color_temp_kelvin")
+                        .and_then(|v| v.as_u64())
+                        .unwrap_or_default();
+                    let max_kelvin = ha_attr
+                        .get("max_color_temp_kelvin")
+                        .and_then(|v| v.as_u64())
+                        .unwrap_or_default();
+
+                    let color_temp_pct =
+                        color_temp_kelvin_to_percent(color_temp, min_kelvin, max_kelvin)?;
+
                     attributes.insert(
                         "color_temperature".into(),
                         Value::Number(color_temp_pct.into()),
@@ -139,6 +178,33 @@ fn color_temp_mired_to_percent(
     Ok(((value as u16) - min_mireds) * 100 / (max_mireds - min_mireds))
 }
 
+/// Convert a Kelvin color temperature into the 0..100 percentage of the remote.
+///
+/// The percentage has the same orientation as the mired conversion: 0 is the coldest (highest
+/// Kelvin), 100 the warmest (lowest Kelvin) color temperature. Out of range values are adjusted.
+fn color_temp_kelvin_to_percent(
+    mut value: u64,
+    min_kelvin: u64,
+    max_kelvin: u64,
+) -> Result<u16, ServiceError> {
+    if max_kelvin <= min_kelvin {
+        return Err(ServiceError::BadRequest(format!(
+            "Invalid min_color_temp_kelvin or max_color_temp_kelvin value! min_color_temp_kelvin={}, max_color_temp_kelvin={}",
+            min_kelvin, max_kelvin
+        )));
+    }
+    if value < min_kelvin || value > max_kelvin {
+        let adjusted = value.clamp(min_kelvin, max_kelvin);
+        warn!(
+            "Adjusted invalid color_temp_kelvin value {} to: {}",
+            value, adjusted
+        );
+        value = adjusted;
+    }
+
+    Ok(((max_kelvin - value) * 100 / (max_kelvin - min_kelvin)) as u16)
+}
+
 pub(crate) fn convert_light_entity(
     entity_id: String,
     state: String,
@@ -152,13 +218,13 @@ pub(crate) fn convert_light_entity(
     // OnOff is default
     light_feats.push(LightFeature::Toggle);
 
+    let mut dim = false;
+    let mut color = false;
+    let mut color_temp = false;
     if let Some(color_modes) = ha_attr
         .get("supported_color_modes")
         .and_then(|v| v.as_array())
     {
-        let mut dim = false;
-        let mut color = false;
-        let mut color_temp = false;
         for color_mode in color_modes {
             match color_mode.as_str().unwrap_or_default() {
                 "brightness" => dim = true,
@@ -173,15 +239,34 @@ pub(crate) fn convert_light_entity(
                 &_ => continue,
             };
         }
-        if dim {
-            light_feats.push(LightFeature::Dim);
-        }
-        if color {
-            light_feats.push(LightFeature::Color);
-        }
-        if color_temp {
-            light_feats.push(LightFeature::ColorTemperature);
-        }
+    }
+    // older cores report a mired range, newer cores a Kelvin range
+    if (ha_attr.contains_key("min_mireds") && ha_attr.contains_key("max_mireds"))
+        || (ha_attr.contains_key("min_color_temp_kelvin")
+            && ha_attr.contains_key("max_color_temp_kelvin"))
+    {
+        dim = true;
+        color_temp = true;
+    }
+    if dim {
+        light_feats.push(LightFeature::Dim);
+    }
+    if color {
+        light_feats.push(LightFeature::Color);
+    }
+    if color_temp {
+        light_feats.push(LightFeature::ColorTemperature);
+    }
+
+    let mut features: Vec<String> = light_feats.into_iter().map(|v| v.to_string()).collect();
+    let mut options = serde_json::Map::new();
+    if let Some(effect_list) = ha_attr
+        .get("effect_list")
+        .and_then(|v| v.as_array())
+        .filter(|v| !v.is_empty())
+    {
+        features.push(LIGHT_FEATURE_EFFECT.into());
+        options.insert(LIGHT_OPTION_EFFECT_LIST.into(), effect_list.clone().into());
     }
 
     // TODO color entity options: color_temperature_steps - do we get that from HASS? #8
@@ -195,9 +280,13 @@ pub(crate) fn convert_light_entity(
         entity_type: EntityType::Light,
         device_class: None,
         name,
-        features: Some(light_feats.into_iter().map(|v| v.to_string()).collect()),
+        features: Some(features),
         area: None,
-        options: None,
+        options: if options.is_empty() {
+            None
+        } else {
+            Some(options)
+        },
         attributes,
     })
 }
@@ -328,9 +417,14 @@ fn extract_rgb_color(
 
 #[cfg(test)]
 mod tests {
-    use crate::client::entity::light::color_temp_mired_to_percent;
+    use crate::client::entity::light::{
+        color_temp_kelvin_to_percent, color_temp_mired_to_percent, convert_light_entity,
+        map_light_attributes, LIGHT_FEATURE_EFFECT, LIGHT_OPTION_EFFECT_LIST,
+    };
     use crate::errors::ServiceError;
     use rstest::rstest;
+    use serde_json::{json, Value};
+    use uc_api::LightFeature;
 
     #[rstest]
     #[case(0, 0)]
@@ -376,4 +470,179 @@ mod tests {
 
         assert_eq!(Ok(expected), result);
     }
+
+    #[test]
+    fn convert_light_with_effects() {
+        let mut attr = json!({
+            "supported_color_modes": ["hs"],
+            "color_mode": "hs",
+            "brightness": 180,
+            "hs_color": [30.0, 50.0],
+            "effect_list": ["colorloop", "random"],
+            "effect": "colorloop",
+            "friendly_name": "LED strip",
+            "supported_features": 44
+        });
+        let result = convert_light_entity(
+            "light.led_strip".into(),
+            "on".into(),
+            attr.as_object_mut().unwrap(),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+
+        let features = entity.features.expect("features must be set");
+        assert!(features.contains(&LIGHT_FEATURE_EFFECT.to_string()));
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!(["colorloop", "random"])),
+            options.get(LIGHT_OPTION_EFFECT_LIST)
+        );
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("colorloop")), attributes.get("effect"));
+    }
+
+    #[test]
+    fn convert_light_without_effects() {
+        let mut attr = json!({
+            "supported_color_modes": ["brightness"],
+            "friendly_name": "Desk lamp"
+        });
+        let entity = convert_light_entity(
+            "light.desk".into(),
+            "off".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        let features = entity.features.expect("features must be set");
+        assert!(!features.contains(&LIGHT_FEATURE_EFFECT.to_string()));
+        assert!(entity.options.is_none(), "No options expected");
+    }
+
+    #[rstest]
+    #[case(json!("None"))]
+    #[case(json!("none"))]
+    #[case(Value::Null)]
+    fn map_light_without_active_effect_omits_effect(#[case] effect: Value) {
+        let mut attr = json!({
+            "effect_list": ["colorloop", "random"],
+            "effect": effect
+        });
+        let attributes = map_light_attributes("light.led_strip", "on", attr.as_object_mut())
+            .expect("Expected successful attribute mapping");
+
+        assert_eq!(None, attributes.get("effect"));
+    }
+
+    #[rstest]
+    #[case(6500, 0)]
+    #[case(2000, 100)]
+    #[case(4250, 50)]
+    #[case(10000, 0)]
+    #[case(1000, 100)]
+    fn color_temp_kelvin_to_percent_returns_scaled_values(
+        #[case] input: u64,
+        #[case] expected: u16,
+    ) {
+        let result = color_temp_kelvin_to_percent(input, 2000, 6500);
+        assert_eq!(Ok(expected), result);
+    }
+
+    #[rstest]
+    #[case(2000, 2000)]
+    #[case(6535, 2000)]
+    fn color_temp_kelvin_to_percent_with_invalid_min_max_returns_err(
+        #[case] min_kelvin: u64,
+        #[case] max_kelvin: u64,
+    ) {
+        let result = color_temp_kelvin_to_percent(4000, min_kelvin, max_kelvin);
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid min / max Kelvin value must return BadRequest"
+        );
+    }
+
+    #[rstest]
+    #[case(json!({ "color_mode": "color_temp", "color_temp": 325, "min_mireds": 150, "max_mireds": 500, "color_temp_kelvin": 2000, "min_color_temp_kelvin": 2000, "max_color_temp_kelvin": 6535 }), 50)]
+    #[case(json!({ "color_mode": "color_temp", "color_temp_kelvin": 6535, "min_color_temp_kelvin": 2000, "max_color_temp_kelvin": 6535 }), 0)]
+    #[case(json!({ "color_mode": "color_temp", "color_temp_kelvin": 2000, "min_color_temp_kelvin": 2000, "max_color_temp_kelvin": 6535 }), 100)]
+    fn map_light_color_temp(#[case] attr: Value, #[case] expected: u16) {
+        let mut attr = attr;
+        let attributes = map_light_attributes("light.bulb", "on", attr.as_object_mut())
+            .expect("Expected successful attribute mapping");
+
+        assert_eq!(Some(&json!(expected)), attributes.get("color_temperature"));
+    }
+
+    #[rstest]
+    #[case(json!({ "min_mireds": 153, "max_mireds": 500 }))]
+    #[case(json!({ "min_color_temp_kelvin": 2000, "max_color_temp_kelvin": 6535 }))]
+    fn convert_light_with_color_temp_range_supports_color_temperature(#[case] attr: Value) {
+        let mut attr = attr;
+        let entity = convert_light_entity(
+            "light.bulb".into(),
+            "off".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        let features = entity.features.expect("features must be set");
+        assert!(features.contains(&LightFeature::ColorTemperature.to_string()));
+    }
+
+    #[test]
+    fn convert_light_with_color_temp_and_hs_supports_both_features() {
+        let mut attr = json!({
+            "supported_color_modes": ["color_temp", "hs"],
+            "color_mode": "color_temp",
+            "color_temp_kelvin": 4250,
+            "min_color_temp_kelvin": 2000,
+            "max_color_temp_kelvin": 6500,
+            "friendly_name": "Ceiling light"
+        });
+        let entity = convert_light_entity(
+            "light.ceiling".into(),
+            "on".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        let features = entity.features.expect("features must be set");
+        assert!(features.contains(&LightFeature::Dim.to_string()));
+        assert!(features.contains(&LightFeature::Color.to_string()));
+        assert!(features.contains(&LightFeature::ColorTemperature.to_string()));
+    }
+
+    #[rstest]
+    #[case("color_temp", Some(50), None)]
+    #[case("hs", None, Some(30))]
+    fn map_light_attributes_uses_active_color_mode(
+        #[case] color_mode: &str,
+        #[case] color_temp: Option<u16>,
+        #[case] hue: Option<u16>,
+    ) {
+        // HA reports the values of all color models, independent of the active color mode
+        let mut attr = json!({
+            "supported_color_modes": ["color_temp", "hs"],
+            "color_mode": color_mode,
+            "color_temp_kelvin": 4250,
+            "min_color_temp_kelvin": 2000,
+            "max_color_temp_kelvin": 6500,
+            "hs_color": [30.0, 50.0]
+        });
+        let attributes = map_light_attributes("light.ceiling", "on", attr.as_object_mut())
+            .expect("Expected successful attribute mapping");
+
+        assert_eq!(
+            color_temp.map(|v| json!(v)).as_ref(),
+            attributes.get("color_temperature")
+        );
+        assert_eq!(hue.map(|v| json!(v)).as_ref(), attributes.get("hue"));
+        assert_eq!(hue.is_some(), attributes.contains_key("saturation"));
+    }
 }
@@ -0,0 +1,154 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Lock entity specific logic.
+//!
+//! The Integration-API doesn't define a lock entity yet. A lock is exposed as a switch entity:
+//! on = locked, off = unlocked.
+
+use crate::client::model::EventData;
+use crate::errors::ServiceError;
+use log::warn;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
+use uc_api::EntityType;
+
+/// Lock code feature, set if the lock requires a code. Not defined in the switch entity.
+pub const LOCK_FEATURE_CODE: &str = "code";
+/// Lock code format entity option: regular expression of a valid code.
+pub const LOCK_OPTION_CODE_FORMAT: &str = "code_format";
+
+pub(crate) fn map_lock_attributes(
+    entity_id: &str,
+    state: &str,
+    _ha_attr: Option<&mut Map<String, Value>>,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut attributes = serde_json::Map::with_capacity(2);
+
+    // switch entity state: target state while locking or unlocking
+    let switch_state = match state {
+        "unavailable" | "unknown" => state.to_uppercase(),
+        "locked" | "locking" => "ON".into(),
+        "unlocked" | "unlocking" | "open" | "opening" => "OFF".into(),
+        "jammed" => "UNKNOWN".into(),
+        state => {
+            warn!("{} Not supported lock state: {}", entity_id, state);
+            "UNKNOWN".into()
+        }
+    };
+    attributes.insert("state".into(), switch_state.into());
+    attributes.insert("lock_state".into(), state.to_uppercase().into());
+
+    Ok(attributes)
+}
+
+pub(crate) fn lock_event_to_entity_change(
+    mut data: EventData,
+) -> Result<EntityChange, ServiceError> {
+    let attributes = map_lock_attributes(
+        &data.entity_id,
+        &data.new_state.state,
+        data.new_state.attributes.as_mut(),
+    )?;
+
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Switch,
+        entity_id: data.entity_id,
+        attributes,
+    })
+}
+
+pub(crate) fn convert_lock_entity(
+    entity_id: String,
+    state: String,
+    ha_attr: &mut Map<String, Value>,
+) -> Result<AvailableIntgEntity, ServiceError> {
+    let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
+    let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
+
+    // OnOff is a default feature. Toggle is not supported to prevent unintentional unlocking.
+    let mut features = Vec::with_capacity(1);
+    let mut options = serde_json::Map::new();
+    if let Some(code_format) = ha_attr
+        .get("code_format")
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.is_empty())
+    {
+        features.push(LOCK_FEATURE_CODE.to_string());
+        options.insert(LOCK_OPTION_CODE_FORMAT.into(), code_format.into());
+    }
+
+    let attributes = Some(map_lock_attributes(&entity_id, &state, Some(ha_attr))?);
+
+    Ok(AvailableIntgEntity {
+        entity_id,
+        device_id: None, // prepared for device_id handling
+        entity_type: EntityType::Switch,
+        device_class: None,
+        name,
+        features: Some(features),
+        area: None,
+        options: if options.is_empty() {
+            None
+        } else {
+            Some(options)
+        },
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case("locked", "ON")]
+    #[case("locking", "ON")]
+    #[case("unlocked", "OFF")]
+    #[case("unlocking", "OFF")]
+    #[case("open", "OFF")]
+    #[case("jammed", "UNKNOWN")]
+    #[case("unavailable", "UNAVAILABLE")]
+    fn lock_states(#[case] ha_state: &str, #[case] state: &str) {
+        let attributes = map_lock_attributes("lock.front_door", ha_state, None)
+            .expect("Expected successful attribute mapping");
+
+        assert_eq!(Some(&json!(state)), attributes.get("state"));
+        assert_eq!(
+            Some(&json!(ha_state.to_uppercase())),
+            attributes.get("lock_state")
+        );
+    }
+
+    #[test]
+    fn convert_lock_with_code_format() {
+        let mut attr = json!({
+            "code_format": "^\\d{4,6}$",
+            "friendly_name": "Front door",
+            "supported_features": 0
+        });
+        let result = convert_lock_entity(
+            "lock.front_door".into(),
+            "locked".into(),
+            attr.as_object_mut().unwrap(),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+
+        assert_eq!(EntityType::Switch, entity.entity_type);
+        assert_eq!(Some(vec![LOCK_FEATURE_CODE.to_string()]), entity.features);
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!("^\\d{4,6}$")),
+            options.get(LOCK_OPTION_CODE_FORMAT)
+        );
+    }
+}
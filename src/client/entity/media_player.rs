@@ -5,11 +5,14 @@
 
 use crate::client::event::convert_ha_onoff_state;
 use crate::client::model::EventData;
+use crate::configuration::MediaActivity;
 use crate::errors::ServiceError;
 use crate::util::json;
 use log::error;
 use serde_json::{Map, Value};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use uc_api::intg::{AvailableIntgEntity, EntityChange};
 use uc_api::{EntityType, MediaPlayerDeviceClass, MediaPlayerFeature};
 use url::Url;
@@ -23,7 +26,7 @@ pub const SUPPORT_PREVIOUS_TRACK: u32 = 16;
 pub const SUPPORT_NEXT_TRACK: u32 = 32;
 pub const SUPPORT_TURN_ON: u32 = 128;
 pub const SUPPORT_TURN_OFF: u32 = 256;
-// pub const SUPPORT_PLAY_MEDIA: u32 = 512;
+pub const SUPPORT_PLAY_MEDIA: u32 = 512;
 pub const SUPPORT_VOLUME_STEP: u32 = 1024;
 pub const SUPPORT_SELECT_SOURCE: u32 = 2048;
 pub const SUPPORT_STOP: u32 = 4096;
@@ -35,6 +38,40 @@ pub const SUPPORT_SELECT_SOUND_MODE: u32 = 65536;
 pub const SUPPORT_REPEAT_SET: u32 = 262144;
 // pub const SUPPORT_GROUPING: u32 = 524288;
 
+/// Play media feature. Not yet defined in the Integration-API `MediaPlayerFeature` enum.
+pub const FEATURE_PLAY_MEDIA: &str = "play_media";
+/// Play media command. Not yet defined in the Integration-API `MediaPlayerCommand` enum.
+pub const CMD_PLAY_MEDIA: &str = "play_media";
+/// Activity feature of configured media player activities.
+pub const FEATURE_ACTIVITY: &str = "activity";
+/// Activity command, triggering a configured media player activity.
+pub const CMD_ACTIVITY: &str = "activity";
+/// Available activity names entity option.
+pub const OPTION_ACTIVITIES: &str = "activities";
+
+/// Add the configured activities of a media player to the entity features and options.
+pub(crate) fn with_media_activities(
+    entity: &mut AvailableIntgEntity,
+    activities: &[MediaActivity],
+) {
+    let names: Vec<Value> = activities
+        .iter()
+        .filter(|a| a.entity_id == entity.entity_id)
+        .map(|a| a.name.clone().into())
+        .collect();
+    if names.is_empty() {
+        return;
+    }
+    entity
+        .features
+        .get_or_insert_with(Default::default)
+        .push(FEATURE_ACTIVITY.into());
+    entity
+        .options
+        .get_or_insert_with(Default::default)
+        .insert(OPTION_ACTIVITIES.into(), names.into());
+}
+
 pub(crate) fn map_media_player_attributes(
     server: &Url,
     _entity_id: &str,
@@ -71,26 +108,8 @@ pub(crate) fn map_media_player_attributes(
         json::move_entry(ha_attr, &mut attributes, "sound_mode_list");
 
         if let Some(value) = ha_attr.get("entity_picture").and_then(|v| v.as_str()) {
-            // let's hope it's only http, https or a local path :-)
-            if value.starts_with("http") {
-                attributes.insert("media_image_url".into(), value.into());
-            } else if value.starts_with('/') {
-                // `url.set_path(value)` doesn't work since the HA path contains query params as well
-                // or we'd have to decode `%3F` -> `?` (and maybe other chars as well).
-                // Let's try the simple (and dangerous) approach first which also worked in YIO v1
-                attributes.insert(
-                    "media_image_url".into(),
-                    format!(
-                        "{}://{}:{}{}",
-                        server.scheme(),
-                        server.host_str().unwrap_or_default(),
-                        server.port_or_known_default().unwrap_or_default(),
-                        value
-                    )
-                    .into(),
-                );
-            } else {
-                error!("Unexpected entity_picture format: {value}");
+            if let Some(url) = media_image_url(server, value) {
+                attributes.insert("media_image_url".into(), url.into());
             }
         }
     }
@@ -98,6 +117,68 @@ pub(crate) fn map_media_player_attributes(
     Ok(attributes)
 }
 
+/// Create the absolute media image URL of a HA `entity_picture` attribute.
+pub(crate) fn media_image_url(server: &Url, entity_picture: &str) -> Option<String> {
+    // let's hope it's only http, https or a local path :-)
+    if entity_picture.starts_with("http") {
+        Some(entity_picture.into())
+    } else if entity_picture.starts_with('/') {
+        Some(local_image_url(server, entity_picture))
+    } else {
+        error!("Unexpected entity_picture format: {entity_picture}");
+        None
+    }
+}
+
+/// Create the absolute URL of a local HA `entity_picture` path.
+///
+/// `url.set_path(path)` doesn't work since the HA path contains query params as well, or we'd have
+/// to decode `%3F` -> `?` (and maybe other chars as well). The path is appended to the server base
+/// URL including the path prefix of a HA instance served under a sub-path.
+fn local_image_url(server: &Url, path: &str) -> String {
+    format!(
+        "{}://{}:{}{}{}",
+        server.scheme(),
+        server.host_str().unwrap_or_default(),
+        server.port_or_known_default().unwrap_or_default(),
+        server.path().trim_end_matches('/'),
+        path
+    )
+}
+
+/// Upgrade an absolute `http://` media image URL to `https://` if the HA server is accessed with
+/// https, to avoid mixed-content issues.
+pub(crate) fn upgrade_media_image_url(server: &Url, attributes: &mut Map<String, Value>) {
+    if server.scheme() != "https" {
+        return;
+    }
+    if let Some(Value::String(url)) = attributes.get_mut("media_image_url") {
+        if let Some(path) = url.strip_prefix("http://") {
+            *url = format!("https://{path}");
+        }
+    }
+}
+
+/// Point the media image URL to the media image proxy endpoint of the integration.
+///
+/// The `proxy_url` is the base URL of the integration as reachable by the remote. A hash of the
+/// original URL is added as query parameter, so that the remote notices an image change of the
+/// entity.
+pub(crate) fn proxy_media_image_url(
+    proxy_url: &str,
+    entity_id: &str,
+    attributes: &mut Map<String, Value>,
+) {
+    if let Some(Value::String(url)) = attributes.get_mut("media_image_url") {
+        let mut hasher = DefaultHasher::new();
+        url.hash(&mut hasher);
+        *url = format!(
+            "{proxy_url}/media_proxy/{entity_id}?v={:x}",
+            hasher.finish()
+        );
+    }
+}
+
 pub(crate) fn media_player_event_to_entity_change(
     server: &Url,
     mut data: EventData,
@@ -151,7 +232,8 @@ pub(crate) fn convert_media_player_entity(
         media_feats.push(MediaPlayerFeature::SelectSource);
     }
     if supported_features & SUPPORT_VOLUME_MUTE > 0 {
-        // HASS media player doesn't support mute toggle!
+        // HASS media player doesn't support mute toggle: emulated with the last known mute state
+        media_feats.push(MediaPlayerFeature::MuteToggle);
         media_feats.push(MediaPlayerFeature::Mute);
         media_feats.push(MediaPlayerFeature::Unmute);
     }
@@ -187,6 +269,11 @@ pub(crate) fn convert_media_player_entity(
     media_feats.push(MediaPlayerFeature::MediaImageUrl);
     media_feats.push(MediaPlayerFeature::MediaType);
 
+    let mut features: Vec<String> = media_feats.into_iter().map(|v| v.to_string()).collect();
+    if supported_features & SUPPORT_PLAY_MEDIA > 0 {
+        features.push(FEATURE_PLAY_MEDIA.into());
+    }
+
     /* TODO from YIO v1
     features.push("APP_NAME"); ???
      */
@@ -207,9 +294,140 @@ pub(crate) fn convert_media_player_entity(
         entity_type: EntityType::MediaPlayer,
         device_class,
         name,
-        features: Some(media_feats.into_iter().map(|v| v.to_string()).collect()),
+        features: Some(features),
         area: None,
         options: None,
         attributes,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case(
+        "https://ha.example.com",
+        "http://192.168.1.10/cover.jpg",
+        "https://192.168.1.10/cover.jpg"
+    )]
+    #[case(
+        "https://ha.example.com",
+        "https://i.scdn.co/image/ab67616d",
+        "https://i.scdn.co/image/ab67616d"
+    )]
+    #[case(
+        "http://homeassistant.local:8123",
+        "http://192.168.1.10/cover.jpg",
+        "http://192.168.1.10/cover.jpg"
+    )]
+    fn upgrade_image_url_scheme(#[case] server: &str, #[case] url: &str, #[case] expected: &str) {
+        let server = Url::parse(server).unwrap();
+        let mut attributes = Map::from_iter([("media_image_url".to_string(), json!(url))]);
+
+        upgrade_media_image_url(&server, &mut attributes);
+
+        assert_eq!(Some(&json!(expected)), attributes.get("media_image_url"));
+    }
+
+    #[rstest]
+    #[case(
+        "http://homeassistant.local:8123/",
+        "http://homeassistant.local:8123/api/media_player_proxy/media_player.tv?token=1&cache=a1b2"
+    )]
+    #[case(
+        "https://example.com/homeassistant",
+        "https://example.com:443/homeassistant/api/media_player_proxy/media_player.tv?token=1&cache=a1b2"
+    )]
+    #[case(
+        "http://192.168.1.2:8080/proxy/ha/",
+        "http://192.168.1.2:8080/proxy/ha/api/media_player_proxy/media_player.tv?token=1&cache=a1b2"
+    )]
+    fn local_entity_picture_url(#[case] server: &str, #[case] expected: &str) {
+        let server = Url::parse(server).unwrap();
+        let mut ha_attr = json!({
+            "entity_picture": "/api/media_player_proxy/media_player.tv?token=1&cache=a1b2"
+        });
+
+        let attributes = map_media_player_attributes(
+            &server,
+            "media_player.tv",
+            "playing",
+            ha_attr.as_object_mut(),
+        )
+        .expect("Expected successful attribute mapping");
+
+        assert_eq!(Some(&json!(expected)), attributes.get("media_image_url"));
+    }
+
+    #[test]
+    fn upgrade_image_url_of_local_entity_picture() {
+        let server = Url::parse("https://ha.example.com").unwrap();
+        let mut ha_attr =
+            json!({ "entity_picture": "/api/media_player_proxy/media_player.tv?token=1" });
+        let mut attributes = map_media_player_attributes(
+            &server,
+            "media_player.tv",
+            "playing",
+            ha_attr.as_object_mut(),
+        )
+        .expect("Expected successful attribute mapping");
+
+        upgrade_media_image_url(&server, &mut attributes);
+
+        assert_eq!(
+            Some(&json!(
+                "https://ha.example.com:443/api/media_player_proxy/media_player.tv?token=1"
+            )),
+            attributes.get("media_image_url")
+        );
+    }
+
+    #[test]
+    fn upgrade_without_image_url() {
+        let server = Url::parse("https://ha.example.com").unwrap();
+        let mut attributes = Map::from_iter([("state".to_string(), json!("ON"))]);
+
+        upgrade_media_image_url(&server, &mut attributes);
+
+        assert_eq!(None, attributes.get("media_image_url"));
+    }
+
+    #[test]
+    fn proxy_image_url() {
+        let proxy_url = "http://127.0.0.1:8000";
+        let mut attributes = Map::from_iter([(
+            "media_image_url".to_string(),
+            json!("http://homeassistant.local:8123/api/media_player_proxy/media_player.tv?cache=1"),
+        )]);
+        let mut changed = attributes.clone();
+        changed.insert(
+            "media_image_url".into(),
+            json!("http://homeassistant.local:8123/api/media_player_proxy/media_player.tv?cache=2"),
+        );
+
+        proxy_media_image_url(proxy_url, "media_player.tv", &mut attributes);
+        proxy_media_image_url(proxy_url, "media_player.tv", &mut changed);
+
+        let url = attributes
+            .get("media_image_url")
+            .and_then(|v| v.as_str())
+            .expect("media_image_url must be set");
+        assert!(
+            url.starts_with("http://127.0.0.1:8000/media_proxy/media_player.tv?v="),
+            "{url}"
+        );
+        assert_ne!(Some(&json!(url)), changed.get("media_image_url"));
+    }
+
+    #[test]
+    fn proxy_without_image_url() {
+        let mut attributes = Map::from_iter([("state".to_string(), json!("ON"))]);
+
+        proxy_media_image_url("http://127.0.0.1:8000", "media_player.tv", &mut attributes);
+
+        assert_eq!(None, attributes.get("media_image_url"));
+    }
+}
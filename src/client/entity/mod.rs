@@ -3,20 +3,50 @@
 
 //! Home Assistant entity helper functions.
 
+mod alarm_control_panel;
 mod button;
 mod climate;
 mod cover;
+mod fan;
+mod humidifier;
+mod lawn_mower;
 mod light;
+mod lock;
 mod media_player;
+mod number;
+mod presence;
 mod remote;
+mod scene;
+mod select;
 mod sensor;
+mod siren;
 mod switch;
+mod text;
+mod update;
+mod vacuum;
+mod water_heater;
+mod weather;
 
+pub(crate) use alarm_control_panel::*;
 pub(crate) use button::*;
 pub(crate) use climate::*;
 pub(crate) use cover::*;
+pub(crate) use fan::*;
+pub(crate) use humidifier::*;
+pub(crate) use lawn_mower::*;
 pub(crate) use light::*;
+pub(crate) use lock::*;
 pub(crate) use media_player::*;
+pub(crate) use number::*;
+pub(crate) use presence::*;
 pub(crate) use remote::*;
+pub(crate) use scene::*;
+pub(crate) use select::*;
 pub(crate) use sensor::*;
+pub(crate) use siren::*;
 pub(crate) use switch::*;
+pub(crate) use text::*;
+pub(crate) use update::*;
+pub(crate) use vacuum::*;
+pub(crate) use water_heater::*;
+pub(crate) use weather::*;
@@ -0,0 +1,198 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Number and input_number entity specific logic.
+//!
+//! The Integration-API doesn't define a number entity yet. A number is exposed as a custom sensor
+//! entity with the value range as entity options and an additional `set_value` command.
+
+use crate::client::model::EventData;
+use crate::errors::ServiceError;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
+use uc_api::{EntityType, SensorOptionField};
+
+/// Set value feature & command. Not defined in the Integration-API sensor entity.
+pub const NUMBER_FEATURE_SET_VALUE: &str = "set_value";
+pub const NUMBER_CMD_SET_VALUE: &str = "set_value";
+/// Number entity options.
+pub const NUMBER_OPTION_MIN: &str = "min";
+pub const NUMBER_OPTION_MAX: &str = "max";
+pub const NUMBER_OPTION_STEP: &str = "step";
+/// Display mode: `slider`, `box` or `auto`.
+pub const NUMBER_OPTION_MODE: &str = "mode";
+
+/// Check if the entity is a HA number or input_number entity.
+pub(crate) fn is_number_entity(entity_id: &str) -> bool {
+    entity_id.starts_with("number.") || entity_id.starts_with("input_number.")
+}
+
+pub(crate) fn map_number_attributes(
+    entity_id: &str,
+    state: &str,
+    ha_attr: Option<&mut Map<String, Value>>,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut attributes = serde_json::Map::with_capacity(3);
+
+    match state {
+        "unavailable" => {
+            attributes.insert("state".into(), "UNAVAILABLE".into());
+        }
+        _ => {
+            let value = state.parse::<f64>().map_err(|_| {
+                ServiceError::BadRequest(format!("{entity_id}: invalid number state: {state}"))
+            })?;
+            attributes.insert("state".into(), "ON".into());
+            attributes.insert("value".into(), value.into());
+        }
+    }
+
+    if let Some(ha_attr) = ha_attr {
+        if let Some(uom) = ha_attr.remove("unit_of_measurement") {
+            attributes.insert("unit".into(), uom);
+        }
+    }
+
+    Ok(attributes)
+}
+
+pub(crate) fn number_event_to_entity_change(
+    mut data: EventData,
+) -> Result<EntityChange, ServiceError> {
+    let attributes = map_number_attributes(
+        &data.entity_id,
+        &data.new_state.state,
+        data.new_state.attributes.as_mut(),
+    )?;
+
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Sensor,
+        entity_id: data.entity_id,
+        attributes,
+    })
+}
+
+pub(crate) fn convert_number_entity(
+    entity_id: String,
+    state: String,
+    ha_attr: &mut Map<String, Value>,
+) -> Result<AvailableIntgEntity, ServiceError> {
+    let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
+    let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
+
+    // handle options
+    let mut options = serde_json::Map::new();
+    for (ha_key, key) in [
+        ("min", NUMBER_OPTION_MIN),
+        ("max", NUMBER_OPTION_MAX),
+        ("step", NUMBER_OPTION_STEP),
+    ] {
+        if let Some(v) = ha_attr.get(ha_key).filter(|v| v.is_number()) {
+            options.insert(key.into(), v.clone());
+        }
+    }
+    if let Some(v) = ha_attr.get("mode").and_then(|v| v.as_str()) {
+        options.insert(NUMBER_OPTION_MODE.into(), v.into());
+    }
+    if let Some(v) = ha_attr.get("unit_of_measurement") {
+        options.insert(SensorOptionField::CustomUnit.to_string(), v.clone());
+    }
+
+    // convert attributes
+    let attributes = Some(map_number_attributes(&entity_id, &state, Some(ha_attr))?);
+
+    Ok(AvailableIntgEntity {
+        entity_id,
+        device_id: None, // prepared for device_id handling
+        entity_type: EntityType::Sensor,
+        device_class: Some("custom".into()),
+        name,
+        features: Some(vec![NUMBER_FEATURE_SET_VALUE.into()]),
+        area: None,
+        options: Some(options),
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[test]
+    fn convert_number_extracts_range_options() {
+        let mut attr = json!({
+            "min": 16.0,
+            "max": 30.0,
+            "step": 0.5,
+            "mode": "slider",
+            "unit_of_measurement": "°C",
+            "friendly_name": "Target temperature"
+        });
+        let result = convert_number_entity(
+            "input_number.target_temp".into(),
+            "21.5".into(),
+            attr.as_object_mut().unwrap(),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+
+        assert_eq!(EntityType::Sensor, entity.entity_type);
+        assert_eq!(
+            Some(vec![NUMBER_FEATURE_SET_VALUE.to_string()]),
+            entity.features
+        );
+        let options = entity.options.expect("options must be set");
+        assert_eq!(Some(&json!(16.0)), options.get(NUMBER_OPTION_MIN));
+        assert_eq!(Some(&json!(30.0)), options.get(NUMBER_OPTION_MAX));
+        assert_eq!(Some(&json!(0.5)), options.get(NUMBER_OPTION_STEP));
+        assert_eq!(Some(&json!("slider")), options.get(NUMBER_OPTION_MODE));
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!(21.5)), attributes.get("value"));
+        assert_eq!(Some(&json!("°C")), attributes.get("unit"));
+    }
+
+    #[test]
+    fn convert_number_without_range_attributes() {
+        let mut attr = json!({ "friendly_name": "Volume" });
+        let entity = convert_number_entity(
+            "number.volume".into(),
+            "10".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        let options = entity.options.expect("options must be set");
+        assert!(options.is_empty(), "Unexpected options: {options:?}");
+    }
+
+    #[rstest]
+    #[case("unknown")]
+    #[case("")]
+    #[case("on")]
+    fn map_number_with_invalid_state_returns_error(#[case] state: &str) {
+        let result = map_number_attributes("number.test", state, None);
+
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid state must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn map_number_unavailable() {
+        let attributes = map_number_attributes("number.test", "unavailable", None)
+            .expect("Expected successful attribute mapping");
+
+        assert_eq!(Some(&json!("UNAVAILABLE")), attributes.get("state"));
+        assert_eq!(None, attributes.get("value"));
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Device tracker and person entity specific logic.
+//!
+//! The Integration-API doesn't define a presence entity. A presence entity is exposed as a
+//! read-only custom sensor entity with the current zone as value and an additional `home`
+//! attribute.
+
+use crate::client::model::EventData;
+use crate::errors::ServiceError;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
+use uc_api::EntityType;
+
+/// Presence attribute if the tracked device or person is at home.
+pub const PRESENCE_ATTR_HOME: &str = "home";
+
+/// Check if the entity is a HA device_tracker or person entity.
+pub(crate) fn is_presence_entity(entity_id: &str) -> bool {
+    entity_id.starts_with("device_tracker.") || entity_id.starts_with("person.")
+}
+
+pub(crate) fn map_presence_attributes(
+    _entity_id: &str,
+    state: &str,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut attributes = serde_json::Map::with_capacity(3);
+
+    match state {
+        "unavailable" | "unknown" => {
+            attributes.insert("state".into(), state.to_uppercase().into());
+        }
+        // `home`, `not_home` or a zone name
+        _ => {
+            attributes.insert("state".into(), "ON".into());
+            attributes.insert("value".into(), state.into());
+            attributes.insert(PRESENCE_ATTR_HOME.into(), (state == "home").into());
+        }
+    }
+
+    Ok(attributes)
+}
+
+pub(crate) fn presence_event_to_entity_change(
+    data: EventData,
+) -> Result<EntityChange, ServiceError> {
+    let attributes = map_presence_attributes(&data.entity_id, &data.new_state.state)?;
+
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Sensor,
+        entity_id: data.entity_id,
+        attributes,
+    })
+}
+
+pub(crate) fn convert_presence_entity(
+    entity_id: String,
+    state: String,
+    ha_attr: &mut Map<String, Value>,
+) -> Result<AvailableIntgEntity, ServiceError> {
+    let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
+    let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
+
+    // convert attributes
+    let attributes = Some(map_presence_attributes(&entity_id, &state)?);
+
+    Ok(AvailableIntgEntity {
+        entity_id,
+        device_id: None, // prepared for device_id handling
+        entity_type: EntityType::Sensor,
+        device_class: Some("custom".into()),
+        name,
+        features: None,
+        area: None,
+        options: None,
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case("home", true)]
+    #[case("not_home", false)]
+    #[case("Office", false)]
+    fn convert_presence(#[case] state: &str, #[case] home: bool) {
+        let mut attr = json!({
+            "friendly_name": "Jane",
+            "source": "device_tracker.jane_phone"
+        });
+        let result = convert_presence_entity(
+            "person.jane".into(),
+            state.into(),
+            attr.as_object_mut().unwrap(),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+
+        assert_eq!(EntityType::Sensor, entity.entity_type);
+        assert_eq!(Some("custom".to_string()), entity.device_class);
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("ON")), attributes.get("state"));
+        assert_eq!(Some(&json!(state)), attributes.get("value"));
+        assert_eq!(Some(&json!(home)), attributes.get(PRESENCE_ATTR_HOME));
+    }
+
+    #[rstest]
+    #[case("home", true)]
+    #[case("not_home", false)]
+    #[case("Office", false)]
+    fn presence_event(#[case] state: &str, #[case] home: bool) {
+        let data = EventData {
+            entity_id: "device_tracker.jane_phone".into(),
+            new_state: serde_json::from_value(json!({
+                "state": state,
+                "attributes": { "source_type": "gps" }
+            }))
+            .expect("invalid test data"),
+        };
+        let entity_change =
+            presence_event_to_entity_change(data).expect("Expected successful event mapping");
+
+        assert_eq!(EntityType::Sensor, entity_change.entity_type);
+        assert_eq!(Some(&json!(state)), entity_change.attributes.get("value"));
+        assert_eq!(
+            Some(&json!(home)),
+            entity_change.attributes.get(PRESENCE_ATTR_HOME)
+        );
+    }
+
+    #[test]
+    fn presence_event_unavailable() {
+        let data = EventData {
+            entity_id: "person.jane".into(),
+            new_state: serde_json::from_value(json!({ "state": "unavailable" }))
+                .expect("invalid test data"),
+        };
+        let entity_change =
+            presence_event_to_entity_change(data).expect("Expected successful event mapping");
+
+        assert_eq!(
+            Some(&json!("UNAVAILABLE")),
+            entity_change.attributes.get("state")
+        );
+        assert_eq!(None, entity_change.attributes.get(PRESENCE_ATTR_HOME));
+    }
+}
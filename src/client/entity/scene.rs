@@ -0,0 +1,104 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Scene entity specific logic.
+//!
+//! A HA scene can only be activated and is exposed as a button entity. The scene state is the
+//! timestamp of the last activation and not forwarded, only the availability is.
+
+use crate::client::model::EventData;
+use crate::errors::ServiceError;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
+use uc_api::EntityType;
+
+/// Activate feature & command of a scene.
+pub const SCENE_FEATURE_ACTIVATE: &str = "activate";
+pub const SCENE_CMD_ACTIVATE: &str = "activate";
+
+pub(crate) fn map_scene_attributes(state: &str) -> Map<String, Value> {
+    let state = match state {
+        "unavailable" => "UNAVAILABLE",
+        _ => "AVAILABLE",
+    };
+    Map::from_iter([("state".to_string(), state.into())])
+}
+
+pub(crate) fn scene_event_to_entity_change(data: EventData) -> Result<EntityChange, ServiceError> {
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Button,
+        attributes: map_scene_attributes(&data.new_state.state),
+        entity_id: data.entity_id,
+    })
+}
+
+pub(crate) fn convert_scene_entity(
+    entity_id: String,
+    state: String,
+    ha_attr: &mut Map<String, Value>,
+) -> Result<AvailableIntgEntity, ServiceError> {
+    let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
+    let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
+
+    Ok(AvailableIntgEntity {
+        entity_id,
+        device_id: None, // prepared for device_id handling
+        entity_type: EntityType::Button,
+        device_class: None,
+        name,
+        features: Some(vec![SCENE_FEATURE_ACTIVATE.into()]),
+        area: None,
+        options: None,
+        attributes: Some(map_scene_attributes(&state)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[test]
+    fn convert_scene() {
+        let mut attr = json!({
+            "entity_id": ["light.kitchen", "light.office"],
+            "id": "1680000000000",
+            "friendly_name": "Movie night"
+        });
+        let result = convert_scene_entity(
+            "scene.movie_night".into(),
+            "2024-05-30T19:41:02.123456+00:00".into(),
+            attr.as_object_mut().unwrap(),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+
+        assert_eq!(EntityType::Button, entity.entity_type);
+        assert_eq!(Some(&"Movie night".to_string()), entity.name.get("en"));
+        assert_eq!(
+            Some(vec![SCENE_FEATURE_ACTIVATE.to_string()]),
+            entity.features
+        );
+        assert_eq!(None, entity.options);
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("AVAILABLE")), attributes.get("state"));
+        assert_eq!(1, attributes.len(), "Only state attribute expected");
+    }
+
+    #[rstest]
+    #[case("2024-05-30T19:41:02.123456+00:00", "AVAILABLE")]
+    #[case("unknown", "AVAILABLE")]
+    #[case("unavailable", "UNAVAILABLE")]
+    fn scene_states(#[case] ha_state: &str, #[case] state: &str) {
+        let attributes = map_scene_attributes(ha_state);
+
+        assert_eq!(Some(&json!(state)), attributes.get("state"));
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Select and input_select entity specific logic.
+//!
+//! The Integration-API doesn't define a select entity yet. A select is exposed as a custom sensor
+//! entity with the current option as value, the available options as entity option and an
+//! additional `select_option` command.
+
+use crate::client::model::EventData;
+use crate::errors::ServiceError;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
+use uc_api::EntityType;
+
+/// Select option feature & command. Not defined in the Integration-API sensor entity.
+pub const SELECT_FEATURE_SELECT_OPTION: &str = "select_option";
+pub const SELECT_CMD_SELECT_OPTION: &str = "select_option";
+/// Available options entity option.
+pub const SELECT_OPTION_OPTIONS: &str = "options";
+
+/// Check if the entity is a HA select or input_select entity.
+pub(crate) fn is_select_entity(entity_id: &str) -> bool {
+    entity_id.starts_with("select.") || entity_id.starts_with("input_select.")
+}
+
+pub(crate) fn map_select_attributes(
+    _entity_id: &str,
+    state: &str,
+    _ha_attr: Option<&mut Map<String, Value>>,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut attributes = serde_json::Map::with_capacity(2);
+
+    match state {
+        "unavailable" | "unknown" => {
+            attributes.insert("state".into(), state.to_uppercase().into());
+        }
+        _ => {
+            attributes.insert("state".into(), "ON".into());
+            attributes.insert("value".into(), state.into());
+        }
+    }
+
+    Ok(attributes)
+}
+
+pub(crate) fn select_event_to_entity_change(
+    mut data: EventData,
+) -> Result<EntityChange, ServiceError> {
+    let attributes = map_select_attributes(
+        &data.entity_id,
+        &data.new_state.state,
+        data.new_state.attributes.as_mut(),
+    )?;
+
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Sensor,
+        entity_id: data.entity_id,
+        attributes,
+    })
+}
+
+pub(crate) fn convert_select_entity(
+    entity_id: String,
+    state: String,
+    ha_attr: &mut Map<String, Value>,
+) -> Result<AvailableIntgEntity, ServiceError> {
+    let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
+    let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
+
+    // handle options
+    let mut options = serde_json::Map::new();
+    if let Some(v) = ha_attr.get("options").filter(|v| v.is_array()) {
+        options.insert(SELECT_OPTION_OPTIONS.into(), v.clone());
+    }
+
+    // convert attributes
+    let attributes = Some(map_select_attributes(&entity_id, &state, Some(ha_attr))?);
+
+    Ok(AvailableIntgEntity {
+        entity_id,
+        device_id: None, // prepared for device_id handling
+        entity_type: EntityType::Sensor,
+        device_class: Some("custom".into()),
+        name,
+        features: Some(vec![SELECT_FEATURE_SELECT_OPTION.into()]),
+        area: None,
+        options: Some(options),
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn convert_select() {
+        let mut attr = json!({
+            "options": ["Normal", "Eco", "Turbo"],
+            "friendly_name": "Washing program"
+        });
+        let result = convert_select_entity(
+            "input_select.washing_program".into(),
+            "Eco".into(),
+            attr.as_object_mut().unwrap(),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+
+        assert_eq!(EntityType::Sensor, entity.entity_type);
+        assert_eq!(
+            Some(vec![SELECT_FEATURE_SELECT_OPTION.to_string()]),
+            entity.features
+        );
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!(["Normal", "Eco", "Turbo"])),
+            options.get(SELECT_OPTION_OPTIONS)
+        );
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("ON")), attributes.get("state"));
+        assert_eq!(Some(&json!("Eco")), attributes.get("value"));
+    }
+
+    #[test]
+    fn select_event_unavailable() {
+        let data = EventData {
+            entity_id: "select.fan_speed".into(),
+            new_state: serde_json::from_value(json!({
+                "state": "unavailable",
+                "attributes": { "options": ["low", "high"] }
+            }))
+            .expect("invalid test data"),
+        };
+        let entity_change =
+            select_event_to_entity_change(data).expect("Expected successful event mapping");
+
+        assert_eq!(
+            Some(&json!("UNAVAILABLE")),
+            entity_change.attributes.get("state")
+        );
+        assert_eq!(None, entity_change.attributes.get("value"));
+    }
+}
@@ -5,31 +5,84 @@
 
 use crate::client::event::convert_ha_onoff_state;
 use crate::client::model::EventData;
+use crate::configuration::SensorSettings;
 use crate::errors::ServiceError;
 use serde_json::{Map, Value};
 use std::collections::HashMap;
 use uc_api::intg::AvailableIntgEntity;
 use uc_api::{intg::EntityChange, EntityType, SensorOptionField};
 
+/// Alert severity hint attribute of a binary sensor with a problem or safety device class.
+pub const ATTR_ALERT_SEVERITY: &str = "alert_severity";
+/// Value type hint attribute for sensor values requiring special rendering.
+pub const ATTR_VALUE_TYPE: &str = "value_type";
+/// Value type of an ISO 8601 datetime sensor value, e.g. to render relative times.
+pub const VALUE_TYPE_TIMESTAMP: &str = "timestamp";
+
+/// HA sensor device classes supported by the Integration-API sensor entity.
+const SENSOR_DEVICE_CLASSES: [&str; 7] = [
+    "battery",
+    "current",
+    "energy",
+    "humidity",
+    "power",
+    "temperature",
+    "voltage",
+];
+
 pub(crate) fn map_sensor_attributes(
     _entity_id: &str,
     state: &str,
     ha_attr: Option<&mut Map<String, Value>>,
 ) -> Result<Map<String, Value>, ServiceError> {
     let mut attributes = serde_json::Map::with_capacity(2);
-    attributes.insert("value".into(), state.into());
+    let value = match ha_attr.as_deref() {
+        Some(ha_attr) if is_numeric_sensor(ha_attr) => numeric_value(state),
+        _ => None,
+    };
+    attributes.insert("value".into(), value.unwrap_or_else(|| state.into()));
 
     if let Some(ha_attr) = ha_attr {
         if let Some(uom) = ha_attr.remove("unit_of_measurement") {
             attributes.insert("unit".into(), uom);
         }
-        // TODO check and handle attributes.device_class? E.g. checking for supported sensors.
-        // Currently supported: "battery" | "current" | "energy" | "humidity" | "power" | "temperature" | "voltage"
+        // the ISO 8601 datetime value is forwarded as is
+        if ha_attr.get("device_class").and_then(|v| v.as_str()) == Some("timestamp")
+            && !matches!(state, "unavailable" | "unknown")
+        {
+            attributes.insert(ATTR_VALUE_TYPE.into(), VALUE_TYPE_TIMESTAMP.into());
+        }
     }
 
     Ok(attributes)
 }
 
+/// Check if the HA sensor has a numeric state.
+///
+/// A numeric sensor has a `state_class` or a unit of measurement, but isn't an enum, timestamp or
+/// date sensor. Other sensors are text sensors, even if the state looks like a number.
+fn is_numeric_sensor(ha_attr: &Map<String, Value>) -> bool {
+    let has_attr = |key: &str| ha_attr.get(key).is_some_and(|v| !v.is_null());
+    let device_class = ha_attr.get("device_class").and_then(|v| v.as_str());
+
+    !matches!(device_class, Some("enum" | "timestamp" | "date"))
+        && (has_attr("state_class") || has_attr("unit_of_measurement"))
+}
+
+/// Parse a numeric sensor state as JSON number.
+///
+/// returns: None for non-numeric states, e.g. `unavailable` or `unknown`.
+fn numeric_value(state: &str) -> Option<Value> {
+    if let Ok(value) = state.parse::<i64>() {
+        return Some(value.into());
+    }
+    state
+        .parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+        .map(Value::Number)
+}
+
 pub(crate) fn sensor_event_to_entity_change(
     mut data: EventData,
 ) -> Result<EntityChange, ServiceError> {
@@ -49,12 +102,24 @@ pub(crate) fn sensor_event_to_entity_change(
 
 pub(crate) fn binary_sensor_event_to_entity_change(
     data: EventData,
+    settings: &SensorSettings,
 ) -> Result<EntityChange, ServiceError> {
-    let mut attributes = serde_json::Map::with_capacity(3);
+    let mut attributes = serde_json::Map::with_capacity(4);
     let state = convert_ha_onoff_state(&data.new_state.state)?;
+    let device_class = data
+        .new_state
+        .attributes
+        .as_ref()
+        .and_then(|a| a.get("device_class"))
+        .and_then(|v| v.as_str());
 
     // TODO decide on how to handle the special binary sensor #13
     attributes.insert("value".into(), (Some("ON") == state.as_str()).into());
+    if settings.alert_severity {
+        if let Some(severity) = alert_severity(device_class, &data.new_state.state) {
+            attributes.insert(ATTR_ALERT_SEVERITY.into(), severity.into());
+        }
+    }
     attributes.insert("state".into(), state);
     attributes.insert("unit".into(), "boolean".into());
 
@@ -70,14 +135,19 @@ pub(crate) fn convert_sensor_entity(
     entity_id: String,
     state: String,
     ha_attr: &mut Map<String, Value>,
+    settings: &SensorSettings,
 ) -> Result<AvailableIntgEntity, ServiceError> {
     let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
     let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
     let mut options = serde_json::Map::new();
     let device_class = ha_attr.get("device_class").and_then(|v| v.as_str());
+    let severity = if settings.alert_severity && entity_id.starts_with("binary_sensor.") {
+        alert_severity(device_class, &state)
+    } else {
+        None
+    };
     let device_class = match device_class {
-        Some("battery") | Some("current") | Some("energy") | Some("humidity") | Some("power")
-        | Some("temperature") | Some("voltage") => device_class.map(|v| v.into()),
+        Some(v) if SENSOR_DEVICE_CLASSES.contains(&v) => Some(v.into()),
         // Map non-supported device classes to a custom sensor and use device class as label
         v => {
             if let Some(v) = v {
@@ -96,7 +166,10 @@ pub(crate) fn convert_sensor_entity(
     };
 
     // convert attributes
-    let attributes = Some(map_sensor_attributes(&entity_id, &state, Some(ha_attr))?);
+    let mut attributes = map_sensor_attributes(&entity_id, &state, Some(ha_attr))?;
+    if let Some(severity) = severity {
+        attributes.insert(ATTR_ALERT_SEVERITY.into(), severity.into());
+    }
 
     Ok(AvailableIntgEntity {
         entity_id,
@@ -106,14 +179,370 @@ pub(crate) fn convert_sensor_entity(
         name,
         features: None,
         area: None,
-        options: None,
-        attributes,
+        options: if options.is_empty() {
+            None
+        } else {
+            Some(options)
+        },
+        attributes: Some(attributes),
     })
 }
 
+/// Get the alert severity hint of a binary sensor with a problem or safety related device class.
+///
+/// The hint allows the remote to prioritize the display of an active alert. An inactive alert
+/// returns `NONE` to clear a previous alert, other device classes don't provide a hint.
+fn alert_severity(device_class: Option<&str>, state: &str) -> Option<&'static str> {
+    let severity = match device_class? {
+        "gas" | "smoke" | "safety" => "CRITICAL",
+        "problem" => "WARNING",
+        _ => return None,
+    };
+
+    match state {
+        "on" => Some(severity),
+        _ => Some("NONE"),
+    }
+}
+
 fn device_class_to_label(class: &str) -> Option<String> {
     let name = class.replace('_', " ");
     let mut c = name.chars();
     c.next()
         .map(|f| f.to_uppercase().collect::<String>() + c.as_str())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    const ALERT_SEVERITY: SensorSettings = SensorSettings {
+        alert_severity: true,
+    };
+
+    #[rstest]
+    #[case("on", true, "CRITICAL")]
+    #[case("off", false, "NONE")]
+    fn smoke_binary_sensor_event_has_alert_hint(
+        #[case] state: &str,
+        #[case] value: bool,
+        #[case] severity: &str,
+    ) {
+        let entity_change = map_binary_sensor_event(
+            json!({
+                "state": state,
+                "attributes": {
+                    "device_class": "smoke",
+                    "friendly_name": "Kitchen smoke detector"
+                }
+            }),
+            &ALERT_SEVERITY,
+        );
+
+        assert_eq!(Some(&json!(value)), entity_change.attributes.get("value"));
+        assert_eq!(
+            Some(&json!(severity)),
+            entity_change.attributes.get(ATTR_ALERT_SEVERITY)
+        );
+    }
+
+    #[test]
+    fn binary_sensor_event_has_no_alert_hint_if_disabled() {
+        let entity_change = map_binary_sensor_event(
+            json!({
+                "state": "on",
+                "attributes": { "device_class": "smoke" }
+            }),
+            &SensorSettings::default(),
+        );
+
+        assert_eq!(Some(&json!(true)), entity_change.attributes.get("value"));
+        assert_eq!(None, entity_change.attributes.get(ATTR_ALERT_SEVERITY));
+    }
+
+    #[rstest]
+    #[case(Some("gas"), "CRITICAL")]
+    #[case(Some("safety"), "CRITICAL")]
+    #[case(Some("problem"), "WARNING")]
+    fn alert_device_classes_have_alert_hint(
+        #[case] device_class: Option<&str>,
+        #[case] severity: &str,
+    ) {
+        assert_eq!(Some(severity), alert_severity(device_class, "on"));
+    }
+
+    #[rstest]
+    #[case(None)]
+    #[case(Some("door"))]
+    #[case(Some("motion"))]
+    fn other_device_classes_have_no_alert_hint(#[case] device_class: Option<&str>) {
+        assert_eq!(None, alert_severity(device_class, "on"));
+    }
+
+    #[test]
+    fn convert_smoke_binary_sensor_has_alert_hint() {
+        let mut attr = json!({
+            "device_class": "smoke",
+            "friendly_name": "Kitchen smoke detector"
+        });
+        let result = convert_sensor_entity(
+            "binary_sensor.kitchen_smoke".into(),
+            "on".into(),
+            attr.as_object_mut().unwrap(),
+            &ALERT_SEVERITY,
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let attributes = result.unwrap().attributes.expect("attributes must be set");
+        assert_eq!(
+            Some(&json!("CRITICAL")),
+            attributes.get(ATTR_ALERT_SEVERITY)
+        );
+    }
+
+    #[test]
+    fn convert_binary_sensor_has_no_alert_hint_if_disabled() {
+        let mut attr = json!({ "device_class": "smoke" });
+        let attributes = convert_sensor_entity(
+            "binary_sensor.kitchen_smoke".into(),
+            "on".into(),
+            attr.as_object_mut().unwrap(),
+            &SensorSettings::default(),
+        )
+        .expect("Expected successful entity conversion")
+        .attributes
+        .expect("attributes must be set");
+
+        assert_eq!(None, attributes.get(ATTR_ALERT_SEVERITY));
+    }
+
+    #[test]
+    fn convert_sensor_with_safety_class_has_no_alert_hint() {
+        let mut attr = json!({ "device_class": "gas", "unit_of_measurement": "m³" });
+        let result = convert_sensor_entity(
+            "sensor.gas_consumption".into(),
+            "42".into(),
+            attr.as_object_mut().unwrap(),
+            &ALERT_SEVERITY,
+        );
+        let attributes = result
+            .expect("Expected successful entity conversion")
+            .attributes
+            .expect("attributes must be set");
+        assert_eq!(None, attributes.get(ATTR_ALERT_SEVERITY));
+    }
+
+    #[test]
+    fn timestamp_sensor_event_has_value_type_hint() {
+        let data = EventData {
+            entity_id: "sensor.last_boot".into(),
+            new_state: serde_json::from_value(json!({
+                "state": "2024-03-12T07:45:12+00:00",
+                "attributes": {
+                    "device_class": "timestamp",
+                    "friendly_name": "Last boot"
+                }
+            }))
+            .expect("invalid test data"),
+        };
+        let entity_change =
+            sensor_event_to_entity_change(data).expect("Expected successful event mapping");
+
+        assert_eq!(
+            Some(&json!("2024-03-12T07:45:12+00:00")),
+            entity_change.attributes.get("value")
+        );
+        assert_eq!(
+            Some(&json!(VALUE_TYPE_TIMESTAMP)),
+            entity_change.attributes.get(ATTR_VALUE_TYPE)
+        );
+    }
+
+    #[rstest]
+    #[case("unknown", Some("timestamp"))]
+    #[case("2024-03-12T07:45:12+00:00", None)]
+    #[case("21.5", Some("temperature"))]
+    fn sensor_without_timestamp_value_has_no_value_type_hint(
+        #[case] state: &str,
+        #[case] device_class: Option<&str>,
+    ) {
+        let mut attr = json!({ "device_class": device_class });
+        let attributes = map_sensor_attributes("sensor.test", state, attr.as_object_mut())
+            .expect("Expected successful attribute mapping");
+
+        assert_eq!(None, attributes.get(ATTR_VALUE_TYPE));
+    }
+
+    #[test]
+    fn convert_timestamp_sensor() {
+        let mut attr = json!({
+            "device_class": "timestamp",
+            "friendly_name": "Next alarm"
+        });
+        let entity = convert_sensor_entity(
+            "sensor.next_alarm".into(),
+            "2024-03-13T06:30:00+00:00".into(),
+            attr.as_object_mut().unwrap(),
+            &Default::default(),
+        )
+        .expect("Expected successful entity conversion");
+
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(
+            Some(&json!("2024-03-13T06:30:00+00:00")),
+            attributes.get("value")
+        );
+        assert_eq!(
+            Some(&json!(VALUE_TYPE_TIMESTAMP)),
+            attributes.get(ATTR_VALUE_TYPE)
+        );
+    }
+
+    #[rstest]
+    #[case("temperature", "°C", "21.5", json!(21.5))]
+    #[case("humidity", "%", "45", json!(45))]
+    #[case("battery", "%", "87", json!(87))]
+    #[case("power", "W", "1250.3", json!(1250.3))]
+    #[case("energy", "kWh", "3456.78", json!(3456.78))]
+    #[case("voltage", "V", "230.1", json!(230.1))]
+    #[case("current", "A", "5.4", json!(5.4))]
+    fn convert_sensor_with_supported_device_class(
+        #[case] device_class: &str,
+        #[case] unit: &str,
+        #[case] state: &str,
+        #[case] value: Value,
+    ) {
+        let mut attr = json!({
+            "state_class": "measurement",
+            "unit_of_measurement": unit,
+            "device_class": device_class,
+            "friendly_name": "Test sensor"
+        });
+        let result = convert_sensor_entity(
+            "sensor.test".into(),
+            state.into(),
+            attr.as_object_mut().unwrap(),
+            &Default::default(),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+
+        assert_eq!(Some(device_class.to_string()), entity.device_class);
+        assert_eq!(None, entity.options);
+        assert_eq!(
+            Some(json!({ "value": value, "unit": unit })),
+            entity.attributes.map(Value::Object)
+        );
+    }
+
+    #[rstest]
+    #[case(Some("illuminance"), Some("lx"), Some("Illuminance"))]
+    #[case(
+        Some("atmospheric_pressure"),
+        Some("hPa"),
+        Some("Atmospheric pressure")
+    )]
+    #[case(Some("some_future_class"), None, Some("Some future class"))]
+    #[case(None, Some("ppm"), None)]
+    #[case(None, None, None)]
+    fn convert_sensor_with_other_device_class_is_custom(
+        #[case] device_class: Option<&str>,
+        #[case] unit: Option<&str>,
+        #[case] label: Option<&str>,
+    ) {
+        let mut attr = serde_json::Map::new();
+        if let Some(unit) = unit {
+            attr.insert("unit_of_measurement".into(), unit.into());
+        }
+        if let Some(device_class) = device_class {
+            attr.insert("device_class".into(), device_class.into());
+        }
+        let entity = convert_sensor_entity(
+            "sensor.test".into(),
+            "42".into(),
+            &mut attr,
+            &Default::default(),
+        )
+        .expect("Expected successful entity conversion");
+
+        assert_eq!(Some("custom".to_string()), entity.device_class);
+        let options = entity.options.unwrap_or_default();
+        assert_eq!(
+            label.map(|v| json!(v)).as_ref(),
+            options.get(&SensorOptionField::CustomLabel.to_string())
+        );
+        assert_eq!(
+            unit.map(|v| json!(v)).as_ref(),
+            options.get(&SensorOptionField::CustomUnit.to_string())
+        );
+        let attributes = entity.attributes.expect("attributes must be set");
+        let value = if unit.is_some() {
+            json!(42)
+        } else {
+            json!("42")
+        };
+        assert_eq!(Some(&value), attributes.get("value"));
+        assert_eq!(unit.map(|v| json!(v)).as_ref(), attributes.get("unit"));
+    }
+
+    #[rstest]
+    #[case(json!({ "state_class": "measurement", "unit_of_measurement": "°C" }), "21.5", json!(21.5))]
+    #[case(json!({ "state_class": "total_increasing", "unit_of_measurement": "kWh" }), "1024", json!(1024))]
+    #[case(json!({ "state_class": "measurement" }), "-3", json!(-3))]
+    #[case(json!({ "unit_of_measurement": "W" }), "0.5", json!(0.5))]
+    #[case(json!({ "state_class": "measurement", "unit_of_measurement": "°C" }), "unknown", json!("unknown"))]
+    #[case(json!({ "state_class": "measurement", "unit_of_measurement": "°C" }), "unavailable", json!("unavailable"))]
+    #[case(json!({ "device_class": "enum", "options": ["1", "2"] }), "1", json!("1"))]
+    #[case(json!({ "device_class": "enum", "options": ["idle", "mowing"] }), "mowing", json!("mowing"))]
+    #[case(json!({ "friendly_name": "Postal code" }), "01234", json!("01234"))]
+    #[case(json!({ "state_class": "measurement" }), "NaN", json!("NaN"))]
+    fn sensor_event_value(#[case] attributes: Value, #[case] state: &str, #[case] value: Value) {
+        let data = EventData {
+            entity_id: "sensor.test".into(),
+            new_state: serde_json::from_value(json!({
+                "state": state,
+                "attributes": attributes
+            }))
+            .expect("invalid test data"),
+        };
+        let entity_change =
+            sensor_event_to_entity_change(data).expect("Expected successful event mapping");
+
+        assert_eq!(Some(&value), entity_change.attributes.get("value"));
+    }
+
+    #[test]
+    fn numeric_sensor_event_has_unit() {
+        let mut attr = json!({ "state_class": "measurement", "unit_of_measurement": "ppm" });
+        let attributes = map_sensor_attributes("sensor.co2", "612", attr.as_object_mut())
+            .expect("Expected successful attribute mapping");
+
+        assert_eq!(
+            Some(json!({ "value": 612, "unit": "ppm" })),
+            Some(Value::Object(attributes))
+        );
+    }
+
+    fn map_binary_sensor_event(new_state: Value, settings: &SensorSettings) -> EntityChange {
+        let data = EventData {
+            entity_id: "binary_sensor.test".into(),
+            new_state: serde_json::from_value(new_state).expect("invalid test data"),
+        };
+        let result = binary_sensor_event_to_entity_change(data, settings);
+        assert!(
+            result.is_ok(),
+            "Expected successful event mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        result.unwrap()
+    }
+}
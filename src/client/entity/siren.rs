@@ -0,0 +1,163 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Siren entity specific logic.
+//!
+//! The Integration-API doesn't define a siren entity yet. A siren is exposed as a switch entity
+//! with optional tone, volume and duration parameters for the `on` command.
+
+use crate::client::event::convert_ha_onoff_state;
+use crate::client::model::EventData;
+use crate::errors::ServiceError;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
+use uc_api::EntityType;
+
+// https://developers.home-assistant.io/docs/core/entity/siren#supported-features
+pub const SIREN_SUPPORT_TONES: u32 = 4;
+pub const SIREN_SUPPORT_VOLUME_SET: u32 = 8;
+pub const SIREN_SUPPORT_DURATION: u32 = 16;
+/* not yet used constants
+pub const SIREN_SUPPORT_TURN_ON: u32 = 1;
+pub const SIREN_SUPPORT_TURN_OFF: u32 = 2;
+*/
+
+/// Siren features in addition to the switch entity features.
+pub const SIREN_FEATURE_TONE: &str = "tone";
+pub const SIREN_FEATURE_VOLUME: &str = "volume";
+pub const SIREN_FEATURE_DURATION: &str = "duration";
+/// Available tones entity option.
+pub const SIREN_OPTION_TONES: &str = "available_tones";
+
+pub(crate) fn map_siren_attributes(
+    _entity_id: &str,
+    state: &str,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut attributes = serde_json::Map::with_capacity(1);
+    attributes.insert("state".into(), convert_ha_onoff_state(state)?);
+
+    Ok(attributes)
+}
+
+pub(crate) fn siren_event_to_entity_change(data: EventData) -> Result<EntityChange, ServiceError> {
+    let attributes = map_siren_attributes(&data.entity_id, &data.new_state.state)?;
+
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Switch,
+        entity_id: data.entity_id,
+        attributes,
+    })
+}
+
+pub(crate) fn convert_siren_entity(
+    entity_id: String,
+    state: String,
+    ha_attr: &mut Map<String, Value>,
+) -> Result<AvailableIntgEntity, ServiceError> {
+    let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
+    let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
+
+    // handle features
+    let supported_features = ha_attr
+        .get("supported_features")
+        .and_then(|v| v.as_u64())
+        .unwrap_or_default() as u32;
+    // OnOff is default
+    let mut features = vec!["toggle".to_string()];
+    let mut options = serde_json::Map::new();
+    if supported_features & SIREN_SUPPORT_TONES > 0 {
+        // list of tones or a map of tone id -> name
+        if let Some(tones) = ha_attr
+            .get("available_tones")
+            .filter(|v| v.is_array() || v.is_object())
+        {
+            features.push(SIREN_FEATURE_TONE.into());
+            options.insert(SIREN_OPTION_TONES.into(), tones.clone());
+        }
+    }
+    if supported_features & SIREN_SUPPORT_VOLUME_SET > 0 {
+        features.push(SIREN_FEATURE_VOLUME.into());
+    }
+    if supported_features & SIREN_SUPPORT_DURATION > 0 {
+        features.push(SIREN_FEATURE_DURATION.into());
+    }
+
+    // convert attributes
+    let attributes = Some(map_siren_attributes(&entity_id, &state)?);
+
+    Ok(AvailableIntgEntity {
+        entity_id,
+        device_id: None, // prepared for device_id handling
+        entity_type: EntityType::Switch,
+        device_class: None,
+        name,
+        features: Some(features),
+        area: None,
+        options: if options.is_empty() {
+            None
+        } else {
+            Some(options)
+        },
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case(3, false)]
+    #[case(7, true)]
+    #[case(31, true)]
+    fn tone_feature(#[case] supported_features: u32, #[case] expected: bool) {
+        let mut attr = json!({
+            "available_tones": ["ding", "alarm", "doorbell"],
+            "friendly_name": "Hallway siren",
+            "supported_features": supported_features
+        });
+        let entity = convert_siren_entity(
+            "siren.hallway".into(),
+            "off".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        assert_eq!(EntityType::Switch, entity.entity_type);
+        let features = entity.features.expect("features must be set");
+        assert_eq!(expected, features.contains(&SIREN_FEATURE_TONE.to_string()));
+        assert_eq!(
+            expected,
+            entity.options.is_some_and(
+                |o| o.get(SIREN_OPTION_TONES) == Some(&json!(["ding", "alarm", "doorbell"]))
+            )
+        );
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("OFF")), attributes.get("state"));
+    }
+
+    #[test]
+    fn volume_and_duration_features() {
+        let mut attr = json!({
+            "friendly_name": "Hallway siren",
+            // TURN_ON | TURN_OFF | VOLUME_SET | DURATION
+            "supported_features": 27
+        });
+        let entity = convert_siren_entity(
+            "siren.hallway".into(),
+            "on".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        let features = entity.features.expect("features must be set");
+        assert!(features.contains(&SIREN_FEATURE_VOLUME.to_string()));
+        assert!(features.contains(&SIREN_FEATURE_DURATION.to_string()));
+        assert!(!features.contains(&SIREN_FEATURE_TONE.to_string()));
+        assert!(entity.options.is_none());
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Text and input_text entity specific logic.
+//!
+//! The Integration-API doesn't define a text entity yet. A text is exposed as a custom sensor
+//! entity with the text as value, the length and pattern constraints as entity options and an
+//! additional `set_value` command.
+
+use crate::client::model::EventData;
+use crate::errors::ServiceError;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
+use uc_api::EntityType;
+
+/// Set value feature & command. Not defined in the Integration-API sensor entity.
+pub const TEXT_FEATURE_SET_VALUE: &str = "set_value";
+pub const TEXT_CMD_SET_VALUE: &str = "set_value";
+/// Text entity options: min and max length, regex pattern.
+pub const TEXT_OPTION_MIN: &str = "min";
+pub const TEXT_OPTION_MAX: &str = "max";
+pub const TEXT_OPTION_PATTERN: &str = "pattern";
+/// Display mode: `text` or `password`.
+pub const TEXT_OPTION_MODE: &str = "mode";
+
+/// Check if the entity is a HA text or input_text entity.
+pub(crate) fn is_text_entity(entity_id: &str) -> bool {
+    entity_id.starts_with("text.") || entity_id.starts_with("input_text.")
+}
+
+pub(crate) fn map_text_attributes(
+    _entity_id: &str,
+    state: &str,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut attributes = serde_json::Map::with_capacity(2);
+
+    match state {
+        "unavailable" | "unknown" => {
+            attributes.insert("state".into(), state.to_uppercase().into());
+        }
+        _ => {
+            attributes.insert("state".into(), "ON".into());
+            attributes.insert("value".into(), state.into());
+        }
+    }
+
+    Ok(attributes)
+}
+
+pub(crate) fn text_event_to_entity_change(data: EventData) -> Result<EntityChange, ServiceError> {
+    let attributes = map_text_attributes(&data.entity_id, &data.new_state.state)?;
+
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Sensor,
+        entity_id: data.entity_id,
+        attributes,
+    })
+}
+
+pub(crate) fn convert_text_entity(
+    entity_id: String,
+    state: String,
+    ha_attr: &mut Map<String, Value>,
+) -> Result<AvailableIntgEntity, ServiceError> {
+    let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
+    let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
+
+    // handle options
+    let mut options = serde_json::Map::new();
+    for (ha_key, key) in [("min", TEXT_OPTION_MIN), ("max", TEXT_OPTION_MAX)] {
+        if let Some(v) = ha_attr.get(ha_key).filter(|v| v.is_u64()) {
+            options.insert(key.into(), v.clone());
+        }
+    }
+    for (ha_key, key) in [("pattern", TEXT_OPTION_PATTERN), ("mode", TEXT_OPTION_MODE)] {
+        if let Some(v) = ha_attr.get(ha_key).and_then(|v| v.as_str()) {
+            options.insert(key.into(), v.into());
+        }
+    }
+
+    // convert attributes
+    let attributes = Some(map_text_attributes(&entity_id, &state)?);
+
+    Ok(AvailableIntgEntity {
+        entity_id,
+        device_id: None, // prepared for device_id handling
+        entity_type: EntityType::Sensor,
+        device_class: Some("custom".into()),
+        name,
+        features: Some(vec![TEXT_FEATURE_SET_VALUE.into()]),
+        area: None,
+        options: Some(options),
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn convert_text() {
+        let mut attr = json!({
+            "min": 0,
+            "max": 100,
+            "pattern": null,
+            "mode": "text",
+            "editable": true,
+            "friendly_name": "Welcome message"
+        });
+        let result = convert_text_entity(
+            "input_text.welcome_message".into(),
+            "Hello world".into(),
+            attr.as_object_mut().unwrap(),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+
+        assert_eq!(EntityType::Sensor, entity.entity_type);
+        assert_eq!(
+            Some(vec![TEXT_FEATURE_SET_VALUE.to_string()]),
+            entity.features
+        );
+        assert_eq!(
+            Some(json!({ "min": 0, "max": 100, "mode": "text" })),
+            entity.options.map(Value::Object)
+        );
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("ON")), attributes.get("state"));
+        assert_eq!(Some(&json!("Hello world")), attributes.get("value"));
+    }
+
+    #[test]
+    fn convert_text_with_pattern() {
+        let mut attr = json!({
+            "min": 4,
+            "max": 8,
+            "pattern": "[0-9]*",
+            "mode": "password",
+            "friendly_name": "Door code"
+        });
+        let entity = convert_text_entity(
+            "text.door_code".into(),
+            "1234".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        assert_eq!(
+            Some(json!({ "min": 4, "max": 8, "pattern": "[0-9]*", "mode": "password" })),
+            entity.options.map(Value::Object)
+        );
+    }
+
+    #[test]
+    fn text_event_with_empty_value() {
+        let data = EventData {
+            entity_id: "input_text.welcome_message".into(),
+            new_state: serde_json::from_value(json!({
+                "state": "",
+                "attributes": { "min": 0, "max": 100 }
+            }))
+            .expect("invalid test data"),
+        };
+        let entity_change =
+            text_event_to_entity_change(data).expect("Expected successful event mapping");
+
+        assert_eq!(Some(&json!("ON")), entity_change.attributes.get("state"));
+        assert_eq!(Some(&json!("")), entity_change.attributes.get("value"));
+    }
+}
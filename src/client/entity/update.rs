@@ -0,0 +1,287 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Update entity specific logic.
+//!
+//! The Integration-API doesn't define an update entity. An update is exposed as a custom sensor
+//! entity with a boolean value if an update is available, the version information and release
+//! notes as additional attributes and an optional `install` command.
+
+use crate::client::event::convert_ha_onoff_state;
+use crate::client::model::EventData;
+use crate::errors::ServiceError;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
+use uc_api::EntityType;
+
+// https://developers.home-assistant.io/docs/core/entity/update#supported-features
+pub const UPDATE_SUPPORT_INSTALL: u32 = 1;
+/* not yet used constants
+pub const UPDATE_SUPPORT_SPECIFIC_VERSION: u32 = 2;
+pub const UPDATE_SUPPORT_PROGRESS: u32 = 4;
+pub const UPDATE_SUPPORT_BACKUP: u32 = 8;
+pub const UPDATE_SUPPORT_RELEASE_NOTES: u32 = 16;
+*/
+
+/// Install feature & command. Not defined in the Integration-API sensor entity.
+pub const UPDATE_FEATURE_INSTALL: &str = "install";
+pub const UPDATE_CMD_INSTALL: &str = "install";
+
+/// HA update attributes forwarded as is, if present.
+///
+/// The release summary and URL allow the remote to show what an update contains.
+const UPDATE_ATTRIBUTES: [&str; 6] = [
+    "installed_version",
+    "latest_version",
+    "skipped_version",
+    "in_progress",
+    "release_summary",
+    "release_url",
+];
+
+/// Check if the entity is a HA update entity.
+pub(crate) fn is_update_entity(entity_id: &str) -> bool {
+    entity_id.starts_with("update.")
+}
+
+pub(crate) fn map_update_attributes(
+    _entity_id: &str,
+    state: &str,
+    ha_attr: Option<&mut Map<String, Value>>,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut attributes = serde_json::Map::with_capacity(8);
+    let state = convert_ha_onoff_state(state)?;
+
+    // update available
+    attributes.insert("value".into(), (Some("ON") == state.as_str()).into());
+    attributes.insert("state".into(), state);
+
+    if let Some(ha_attr) = ha_attr {
+        for key in UPDATE_ATTRIBUTES {
+            if let Some(value) = ha_attr.get(key).filter(|v| !v.is_null()) {
+                attributes.insert(key.into(), value.clone());
+            }
+        }
+    }
+
+    Ok(attributes)
+}
+
+pub(crate) fn update_event_to_entity_change(
+    mut data: EventData,
+) -> Result<EntityChange, ServiceError> {
+    let attributes = map_update_attributes(
+        &data.entity_id,
+        &data.new_state.state,
+        data.new_state.attributes.as_mut(),
+    )?;
+
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Sensor,
+        entity_id: data.entity_id,
+        attributes,
+    })
+}
+
+pub(crate) fn convert_update_entity(
+    entity_id: String,
+    state: String,
+    ha_attr: &mut Map<String, Value>,
+) -> Result<AvailableIntgEntity, ServiceError> {
+    let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
+    let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
+
+    // handle features
+    let supported_features = ha_attr
+        .get("supported_features")
+        .and_then(|v| v.as_u64())
+        .unwrap_or_default() as u32;
+    let mut features = Vec::with_capacity(1);
+    if supported_features & UPDATE_SUPPORT_INSTALL > 0 {
+        features.push(UPDATE_FEATURE_INSTALL.to_string());
+    }
+
+    // convert attributes
+    let attributes = Some(map_update_attributes(&entity_id, &state, Some(ha_attr))?);
+
+    Ok(AvailableIntgEntity {
+        entity_id,
+        device_id: None, // prepared for device_id handling
+        entity_type: EntityType::Sensor,
+        device_class: Some("custom".into()),
+        name,
+        features: Some(features),
+        area: None,
+        options: None,
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case("on", "2024.5.1", true)]
+    #[case("off", "2024.4.4", false)]
+    fn convert_update(#[case] state: &str, #[case] latest_version: &str, #[case] value: bool) {
+        let mut attr = json!({
+            "auto_update": false,
+            "installed_version": "2024.4.4",
+            "in_progress": false,
+            "latest_version": latest_version,
+            "release_summary": null,
+            "release_url": "https://www.home-assistant.io/blog/",
+            "skipped_version": null,
+            "title": "Home Assistant Core",
+            "friendly_name": "Home Assistant Core Update",
+            "supported_features": 11
+        });
+        let result = convert_update_entity(
+            "update.home_assistant_core_update".into(),
+            state.into(),
+            attr.as_object_mut().unwrap(),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+
+        assert_eq!(EntityType::Sensor, entity.entity_type);
+        assert_eq!(
+            Some(vec![UPDATE_FEATURE_INSTALL.to_string()]),
+            entity.features
+        );
+        assert_eq!(
+            Some(json!({
+                "state": state.to_uppercase(),
+                "value": value,
+                "installed_version": "2024.4.4",
+                "latest_version": latest_version,
+                "in_progress": false,
+                "release_url": "https://www.home-assistant.io/blog/"
+            })),
+            entity.attributes.map(Value::Object)
+        );
+    }
+
+    #[test]
+    fn convert_update_with_release_notes() {
+        let mut attr = json!({
+            "installed_version": "2024.5.4",
+            "latest_version": "2024.6.0",
+            "release_summary": "Sections dashboard, collapsible blocks and more!",
+            "release_url": "https://www.home-assistant.io/blog/2024/06/05/release-20246/",
+            "supported_features": 1
+        });
+        let entity = convert_update_entity(
+            "update.home_assistant_core_update".into(),
+            "on".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(
+            Some(&json!("Sections dashboard, collapsible blocks and more!")),
+            attributes.get("release_summary")
+        );
+        assert_eq!(
+            Some(&json!(
+                "https://www.home-assistant.io/blog/2024/06/05/release-20246/"
+            )),
+            attributes.get("release_url")
+        );
+    }
+
+    #[test]
+    fn update_event_without_release_notes() {
+        let data = EventData {
+            entity_id: "update.zigbee_bridge".into(),
+            new_state: serde_json::from_value(json!({
+                "state": "off",
+                "attributes": {
+                    "installed_version": "1.2.0",
+                    "latest_version": "1.2.0",
+                    "release_summary": null,
+                    "release_url": null
+                }
+            }))
+            .expect("invalid test data"),
+        };
+        let entity_change =
+            update_event_to_entity_change(data).expect("Expected successful event mapping");
+
+        assert_eq!(None, entity_change.attributes.get("release_summary"));
+        assert_eq!(None, entity_change.attributes.get("release_url"));
+    }
+
+    #[test]
+    fn update_without_install_support() {
+        let mut attr = json!({
+            "installed_version": "1.0.0",
+            "latest_version": "1.0.0",
+            "supported_features": 0
+        });
+        let entity = convert_update_entity(
+            "update.zigbee_bridge".into(),
+            "off".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        assert_eq!(Some(vec![]), entity.features);
+    }
+
+    #[test]
+    fn update_event_with_skipped_version_and_progress() {
+        let data = EventData {
+            entity_id: "update.zigbee_bridge".into(),
+            new_state: serde_json::from_value(json!({
+                "state": "on",
+                "attributes": {
+                    "installed_version": "1.0.0",
+                    "latest_version": "1.2.0",
+                    "skipped_version": "1.1.0",
+                    "in_progress": 42
+                }
+            }))
+            .expect("invalid test data"),
+        };
+        let entity_change =
+            update_event_to_entity_change(data).expect("Expected successful event mapping");
+
+        assert_eq!(Some(&json!(true)), entity_change.attributes.get("value"));
+        assert_eq!(
+            Some(&json!("1.1.0")),
+            entity_change.attributes.get("skipped_version")
+        );
+        assert_eq!(
+            Some(&json!(42)),
+            entity_change.attributes.get("in_progress")
+        );
+    }
+
+    #[test]
+    fn update_event_unavailable() {
+        let data = EventData {
+            entity_id: "update.zigbee_bridge".into(),
+            new_state: serde_json::from_value(json!({ "state": "unavailable" }))
+                .expect("invalid test data"),
+        };
+        let entity_change =
+            update_event_to_entity_change(data).expect("Expected successful event mapping");
+
+        assert_eq!(
+            Some(&json!("UNAVAILABLE")),
+            entity_change.attributes.get("state")
+        );
+        assert_eq!(Some(&json!(false)), entity_change.attributes.get("value"));
+    }
+}
@@ -0,0 +1,257 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Vacuum entity specific logic.
+//!
+//! The Integration-API doesn't define a vacuum entity yet. A vacuum is exposed as a remote entity
+//! with simple commands and additional vacuum attributes.
+
+use crate::client::model::EventData;
+use crate::errors::ServiceError;
+use crate::util::json;
+use log::warn;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uc_api::intg::{AvailableIntgEntity, EntityChange, IntgRemoteFeature};
+use uc_api::EntityType;
+
+// https://developers.home-assistant.io/docs/core/entity/vacuum#supported-features
+pub const VACUUM_SUPPORT_PAUSE: u32 = 4;
+pub const VACUUM_SUPPORT_STOP: u32 = 8;
+pub const VACUUM_SUPPORT_RETURN_HOME: u32 = 16;
+pub const VACUUM_SUPPORT_FAN_SPEED: u32 = 32;
+pub const VACUUM_SUPPORT_BATTERY: u32 = 64;
+pub const VACUUM_SUPPORT_START: u32 = 8192;
+/* not yet used constants
+pub const VACUUM_SUPPORT_TURN_ON: u32 = 1;
+pub const VACUUM_SUPPORT_TURN_OFF: u32 = 2;
+pub const VACUUM_SUPPORT_STATUS: u32 = 128;
+pub const VACUUM_SUPPORT_SEND_COMMAND: u32 = 256;
+pub const VACUUM_SUPPORT_LOCATE: u32 = 512;
+pub const VACUUM_SUPPORT_CLEAN_SPOT: u32 = 1024;
+pub const VACUUM_SUPPORT_MAP: u32 = 2048;
+pub const VACUUM_SUPPORT_STATE: u32 = 4096;
+*/
+
+/// Vacuum commands, also used as remote entity simple commands.
+pub const VACUUM_CMD_START: &str = "START";
+pub const VACUUM_CMD_STOP: &str = "STOP";
+pub const VACUUM_CMD_PAUSE: &str = "PAUSE";
+pub const VACUUM_CMD_RETURN_HOME: &str = "RETURN_HOME";
+/// Vacuum features in addition to the remote entity features.
+pub const VACUUM_FEATURE_FAN_SPEED: &str = "fan_speed";
+pub const VACUUM_FEATURE_BATTERY: &str = "battery";
+
+pub(crate) fn map_vacuum_attributes(
+    entity_id: &str,
+    state: &str,
+    ha_attr: Option<&mut Map<String, Value>>,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut attributes = serde_json::Map::with_capacity(4);
+
+    // remote entity state: ON while the vacuum is active
+    let remote_state = match state {
+        "unavailable" | "unknown" => state.to_uppercase(),
+        "cleaning" | "returning" => "ON".into(),
+        "docked" | "paused" | "idle" | "error" => "OFF".into(),
+        state => {
+            warn!("{} Not supported vacuum state: {}", entity_id, state);
+            "UNKNOWN".into()
+        }
+    };
+    attributes.insert("state".into(), remote_state.into());
+    attributes.insert("vacuum_state".into(), state.to_uppercase().into());
+
+    if let Some(ha_attr) = ha_attr {
+        json::move_entry(ha_attr, &mut attributes, "battery_level");
+        json::move_entry(ha_attr, &mut attributes, "fan_speed");
+    }
+
+    Ok(attributes)
+}
+
+pub(crate) fn vacuum_event_to_entity_change(
+    mut data: EventData,
+) -> Result<EntityChange, ServiceError> {
+    let attributes = map_vacuum_attributes(
+        &data.entity_id,
+        &data.new_state.state,
+        data.new_state.attributes.as_mut(),
+    )?;
+
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Remote,
+        entity_id: data.entity_id,
+        attributes,
+    })
+}
+
+pub(crate) fn convert_vacuum_entity(
+    entity_id: String,
+    state: String,
+    ha_attr: &mut Map<String, Value>,
+) -> Result<AvailableIntgEntity, ServiceError> {
+    let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
+    let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
+
+    // handle features
+    let supported_features = ha_attr
+        .get("supported_features")
+        .and_then(|v| v.as_u64())
+        .unwrap_or_default() as u32;
+    let mut features = vec![IntgRemoteFeature::SendCmd.to_string()];
+    let mut commands = Vec::with_capacity(4);
+    if supported_features & VACUUM_SUPPORT_START > 0 {
+        commands.push(VACUUM_CMD_START);
+    }
+    if supported_features & VACUUM_SUPPORT_STOP > 0 {
+        commands.push(VACUUM_CMD_STOP);
+    }
+    if supported_features & VACUUM_SUPPORT_PAUSE > 0 {
+        commands.push(VACUUM_CMD_PAUSE);
+    }
+    if supported_features & VACUUM_SUPPORT_RETURN_HOME > 0 {
+        commands.push(VACUUM_CMD_RETURN_HOME);
+    }
+    // on: start cleaning, off: return to base
+    if supported_features & VACUUM_SUPPORT_START > 0
+        && supported_features & VACUUM_SUPPORT_RETURN_HOME > 0
+    {
+        features.push(IntgRemoteFeature::OnOff.to_string());
+    }
+    if supported_features & VACUUM_SUPPORT_FAN_SPEED > 0 {
+        features.push(VACUUM_FEATURE_FAN_SPEED.into());
+    }
+    if supported_features & VACUUM_SUPPORT_BATTERY > 0 {
+        features.push(VACUUM_FEATURE_BATTERY.into());
+    }
+
+    // handle options
+    let mut options = serde_json::Map::new();
+    options.insert("simple_commands".into(), commands.into());
+    if supported_features & VACUUM_SUPPORT_FAN_SPEED > 0 {
+        if let Some(v) = ha_attr.get("fan_speed_list").filter(|v| v.is_array()) {
+            options.insert("fan_speeds".into(), v.clone());
+        }
+    }
+
+    // convert attributes
+    let attributes = Some(map_vacuum_attributes(&entity_id, &state, Some(ha_attr))?);
+
+    Ok(AvailableIntgEntity {
+        entity_id,
+        device_id: None, // prepared for device_id handling
+        entity_type: EntityType::Remote,
+        device_class: None,
+        name,
+        features: Some(features),
+        area: None,
+        options: Some(options),
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case("cleaning", "ON")]
+    #[case("returning", "ON")]
+    #[case("docked", "OFF")]
+    #[case("paused", "OFF")]
+    #[case("idle", "OFF")]
+    #[case("error", "OFF")]
+    #[case("unavailable", "UNAVAILABLE")]
+    fn vacuum_states(#[case] ha_state: &str, #[case] state: &str) {
+        let attributes = map_vacuum_attributes("vacuum.test", ha_state, None)
+            .expect("Expected successful attribute mapping");
+
+        assert_eq!(Some(&json!(state)), attributes.get("state"));
+        assert_eq!(
+            Some(&json!(ha_state.to_uppercase())),
+            attributes.get("vacuum_state")
+        );
+    }
+
+    #[test]
+    fn vacuum_event() {
+        let data = EventData {
+            entity_id: "vacuum.roborock".into(),
+            new_state: serde_json::from_value(json!({
+                "state": "cleaning",
+                "attributes": {
+                    "fan_speed_list": ["Silent", "Standard", "Turbo"],
+                    "battery_level": 87,
+                    "fan_speed": "Standard",
+                    "friendly_name": "Roborock",
+                    "supported_features": 12412
+                }
+            }))
+            .expect("invalid test data"),
+        };
+        let result = vacuum_event_to_entity_change(data);
+        assert!(
+            result.is_ok(),
+            "Expected successful event mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity_change = result.unwrap();
+
+        assert_eq!(EntityType::Remote, entity_change.entity_type);
+        assert_eq!(Some(&json!("ON")), entity_change.attributes.get("state"));
+        assert_eq!(
+            Some(&json!(87)),
+            entity_change.attributes.get("battery_level")
+        );
+        assert_eq!(
+            Some(&json!("Standard")),
+            entity_change.attributes.get("fan_speed")
+        );
+    }
+
+    #[test]
+    fn convert_vacuum() {
+        let mut attr = json!({
+            "fan_speed_list": ["Silent", "Standard", "Turbo"],
+            "battery_level": 100,
+            "fan_speed": "Silent",
+            "friendly_name": "Roborock",
+            // START | STOP | PAUSE | RETURN_HOME | FAN_SPEED | BATTERY | STATUS
+            "supported_features": 8444
+        });
+        let result = convert_vacuum_entity(
+            "vacuum.roborock".into(),
+            "docked".into(),
+            attr.as_object_mut().unwrap(),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+
+        assert_eq!(EntityType::Remote, entity.entity_type);
+        let features = entity.features.expect("features must be set");
+        assert!(features.contains(&IntgRemoteFeature::OnOff.to_string()));
+        assert!(features.contains(&VACUUM_FEATURE_FAN_SPEED.to_string()));
+        assert!(features.contains(&VACUUM_FEATURE_BATTERY.to_string()));
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!(["START", "STOP", "PAUSE", "RETURN_HOME"])),
+            options.get("simple_commands")
+        );
+        assert_eq!(
+            Some(&json!(["Silent", "Standard", "Turbo"])),
+            options.get("fan_speeds")
+        );
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("OFF")), attributes.get("state"));
+        assert_eq!(Some(&json!("DOCKED")), attributes.get("vacuum_state"));
+        assert_eq!(Some(&json!(100)), attributes.get("battery_level"));
+    }
+}
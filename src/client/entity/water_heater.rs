@@ -0,0 +1,289 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Water heater entity specific logic.
+//!
+//! The Integration-API doesn't define a water heater entity yet. A water heater is exposed as a
+//! climate entity with the operation mode as additional attribute and command.
+
+use crate::client::entity::convert_temperature_unit;
+use crate::client::model::EventData;
+use crate::errors::ServiceError;
+use crate::util::json;
+use crate::util::json::{is_float_value, number_value};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
+use uc_api::{ClimateFeature, ClimateOptionField, EntityType};
+
+// https://developers.home-assistant.io/docs/core/entity/water-heater#supported-features
+pub const WATER_HEATER_SUPPORT_TARGET_TEMPERATURE: u32 = 1;
+pub const WATER_HEATER_SUPPORT_OPERATION_MODE: u32 = 2;
+pub const WATER_HEATER_SUPPORT_ON_OFF: u32 = 8;
+/* not yet used constants
+pub const WATER_HEATER_SUPPORT_AWAY_MODE: u32 = 4;
+*/
+
+/// Operation mode feature & command in addition to the climate entity features.
+pub const WATER_HEATER_FEATURE_OPERATION_MODE: &str = "operation_mode";
+pub const WATER_HEATER_CMD_OPERATION_MODE: &str = "operation_mode";
+/// Available operation modes entity option.
+pub const WATER_HEATER_OPTION_OPERATION_MODES: &str = "operation_modes";
+
+pub(crate) fn map_water_heater_attributes(
+    _entity_id: &str,
+    state: &str,
+    ha_attr: Option<&mut Map<String, Value>>,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut attributes = serde_json::Map::with_capacity(4);
+
+    // the state is the current operation mode: every mode except off is heating
+    let climate_state = match state {
+        "unavailable" | "unknown" | "off" => state.to_uppercase(),
+        _ => "HEAT".into(),
+    };
+    attributes.insert("state".into(), climate_state.into());
+
+    if let Some(ha_attr) = ha_attr {
+        json::move_entry(ha_attr, &mut attributes, "current_temperature");
+        json::move_value(
+            ha_attr,
+            &mut attributes,
+            "temperature",
+            "target_temperature",
+        );
+        // operation modes are device specific and passed through as is
+        json::move_entry(ha_attr, &mut attributes, "operation_mode");
+    }
+
+    Ok(attributes)
+}
+
+pub(crate) fn water_heater_event_to_entity_change(
+    mut data: EventData,
+) -> Result<EntityChange, ServiceError> {
+    let attributes = map_water_heater_attributes(
+        &data.entity_id,
+        &data.new_state.state,
+        data.new_state.attributes.as_mut(),
+    )?;
+
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Climate,
+        entity_id: data.entity_id,
+        attributes,
+    })
+}
+
+/// Convert a HA water heater entity.
+///
+/// The optional `temperature_unit` is used if the entity doesn't provide a `temperature_unit`
+/// attribute, like for climate entities.
+pub(crate) fn convert_water_heater_entity(
+    entity_id: String,
+    state: String,
+    ha_attr: &mut Map<String, Value>,
+    temperature_unit: Option<&str>,
+) -> Result<AvailableIntgEntity, ServiceError> {
+    let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
+    let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
+
+    // handle features
+    let supported_features = ha_attr
+        .get("supported_features")
+        .and_then(|v| v.as_u64())
+        .unwrap_or_default() as u32;
+    let mut climate_feats = vec![ClimateFeature::Heat];
+    if supported_features & WATER_HEATER_SUPPORT_ON_OFF > 0 {
+        climate_feats.push(ClimateFeature::OnOff);
+    }
+    if supported_features & WATER_HEATER_SUPPORT_TARGET_TEMPERATURE > 0 {
+        climate_feats.push(ClimateFeature::TargetTemperature);
+    }
+    if is_float_value(ha_attr, "current_temperature") {
+        climate_feats.push(ClimateFeature::CurrentTemperature);
+    }
+    let mut features: Vec<String> = climate_feats.into_iter().map(|v| v.to_string()).collect();
+
+    let operation_modes = if supported_features & WATER_HEATER_SUPPORT_OPERATION_MODE > 0 {
+        ha_attr
+            .get("operation_list")
+            .filter(|v| v.is_array())
+            .cloned()
+    } else {
+        None
+    };
+    if operation_modes.is_some() {
+        features.push(WATER_HEATER_FEATURE_OPERATION_MODE.into());
+    }
+
+    // handle options
+    let mut options = serde_json::Map::new();
+    if let Some(v) = number_value(ha_attr, "min_temp") {
+        options.insert(ClimateOptionField::MinTemperature.to_string(), v);
+    }
+    if let Some(v) = number_value(ha_attr, "max_temp") {
+        options.insert(ClimateOptionField::MaxTemperature.to_string(), v);
+    }
+    if let Some(v) = number_value(ha_attr, "target_temp_step") {
+        options.insert(ClimateOptionField::TargetTemperatureStep.to_string(), v);
+    }
+    if let Some(v) = ha_attr
+        .get("temperature_unit")
+        .and_then(|v| v.as_str())
+        .or(temperature_unit)
+    {
+        options.insert(
+            ClimateOptionField::TemperatureUnit.to_string(),
+            convert_temperature_unit(v).into(),
+        );
+    }
+    if let Some(v) = operation_modes {
+        options.insert(WATER_HEATER_OPTION_OPERATION_MODES.into(), v);
+    }
+
+    // convert attributes
+    let attributes = Some(map_water_heater_attributes(
+        &entity_id,
+        &state,
+        Some(ha_attr),
+    )?);
+
+    Ok(AvailableIntgEntity {
+        entity_id,
+        device_id: None, // prepared for device_id handling
+        entity_type: EntityType::Climate,
+        device_class: None,
+        name,
+        features: Some(features),
+        area: None,
+        options: if options.is_empty() {
+            None
+        } else {
+            Some(options)
+        },
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn water_heater_attributes(supported_features: u32) -> Value {
+        json!({
+            "min_temp": 43.3,
+            "max_temp": 60.0,
+            "operation_list": ["eco", "electric", "performance", "off"],
+            "current_temperature": 48.5,
+            "temperature": 55.0,
+            "operation_mode": "eco",
+            "friendly_name": "Boiler",
+            "supported_features": supported_features
+        })
+    }
+
+    #[rstest]
+    #[case(0, false)]
+    #[case(1, false)]
+    #[case(2, true)]
+    #[case(3, true)]
+    #[case(15, true)]
+    fn operation_mode_feature(#[case] supported_features: u32, #[case] expected: bool) {
+        let mut attr = water_heater_attributes(supported_features);
+        let entity = convert_water_heater_entity(
+            "water_heater.boiler".into(),
+            "eco".into(),
+            attr.as_object_mut().unwrap(),
+            None,
+        )
+        .expect("Expected successful entity conversion");
+
+        assert_eq!(EntityType::Climate, entity.entity_type);
+        let features = entity.features.expect("features must be set");
+        assert_eq!(
+            expected,
+            features.contains(&WATER_HEATER_FEATURE_OPERATION_MODE.to_string())
+        );
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            expected,
+            options.contains_key(WATER_HEATER_OPTION_OPERATION_MODES)
+        );
+    }
+
+    #[rstest]
+    #[case(1, ClimateFeature::TargetTemperature, true)]
+    #[case(2, ClimateFeature::TargetTemperature, false)]
+    #[case(8, ClimateFeature::OnOff, true)]
+    #[case(3, ClimateFeature::OnOff, false)]
+    fn climate_features(
+        #[case] supported_features: u32,
+        #[case] feature: ClimateFeature,
+        #[case] expected: bool,
+    ) {
+        let mut attr = water_heater_attributes(supported_features);
+        let entity = convert_water_heater_entity(
+            "water_heater.boiler".into(),
+            "eco".into(),
+            attr.as_object_mut().unwrap(),
+            None,
+        )
+        .expect("Expected successful entity conversion");
+
+        let features = entity.features.expect("features must be set");
+        assert!(features.contains(&ClimateFeature::Heat.to_string()));
+        assert!(features.contains(&ClimateFeature::CurrentTemperature.to_string()));
+        assert_eq!(expected, features.contains(&feature.to_string()));
+    }
+
+    #[test]
+    fn convert_water_heater() {
+        let mut attr = water_heater_attributes(3);
+        let entity = convert_water_heater_entity(
+            "water_heater.boiler".into(),
+            "eco".into(),
+            attr.as_object_mut().unwrap(),
+            Some("°C"),
+        )
+        .expect("Expected successful entity conversion");
+
+        let options = entity.options.expect("options must be set");
+        assert_eq!(
+            Some(&json!(43.3)),
+            options.get(&ClimateOptionField::MinTemperature.to_string())
+        );
+        assert_eq!(
+            Some(&json!(60.0)),
+            options.get(&ClimateOptionField::MaxTemperature.to_string())
+        );
+        assert_eq!(
+            Some(&json!("CELSIUS")),
+            options.get(&ClimateOptionField::TemperatureUnit.to_string())
+        );
+        assert_eq!(
+            Some(&json!(["eco", "electric", "performance", "off"])),
+            options.get(WATER_HEATER_OPTION_OPERATION_MODES)
+        );
+        let attributes = entity.attributes.expect("attributes must be set");
+        assert_eq!(Some(&json!("HEAT")), attributes.get("state"));
+        assert_eq!(Some(&json!(48.5)), attributes.get("current_temperature"));
+        assert_eq!(Some(&json!(55.0)), attributes.get("target_temperature"));
+        assert_eq!(Some(&json!("eco")), attributes.get("operation_mode"));
+    }
+
+    #[rstest]
+    #[case("eco", "HEAT")]
+    #[case("performance", "HEAT")]
+    #[case("off", "OFF")]
+    #[case("unavailable", "UNAVAILABLE")]
+    fn water_heater_states(#[case] ha_state: &str, #[case] state: &str) {
+        let attributes = map_water_heater_attributes("water_heater.boiler", ha_state, None)
+            .expect("Expected successful attribute mapping");
+
+        assert_eq!(Some(&json!(state)), attributes.get("state"));
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Weather entity specific logic.
+//!
+//! The Integration-API doesn't define a weather entity. A weather entity is exposed as a read-only
+//! custom sensor entity with the current condition as value and the current numeric weather
+//! values as additional attributes.
+
+use crate::client::model::EventData;
+use crate::errors::ServiceError;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
+use uc_api::EntityType;
+
+/// Numeric HA weather attributes with their unit attribute, forwarded as is.
+const WEATHER_ATTRIBUTES: [(&str, Option<&str>); 4] = [
+    ("temperature", Some("temperature_unit")),
+    ("humidity", None),
+    ("pressure", Some("pressure_unit")),
+    ("wind_speed", Some("wind_speed_unit")),
+];
+
+pub(crate) fn map_weather_attributes(
+    _entity_id: &str,
+    state: &str,
+    ha_attr: Option<&mut Map<String, Value>>,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut attributes = serde_json::Map::with_capacity(10);
+
+    match state {
+        "unavailable" | "unknown" => {
+            attributes.insert("state".into(), state.to_uppercase().into());
+            return Ok(attributes);
+        }
+        // condition, e.g. `sunny`, `rainy`, `partlycloudy`
+        _ => {
+            attributes.insert("state".into(), "ON".into());
+            attributes.insert("value".into(), state.into());
+        }
+    }
+
+    if let Some(ha_attr) = ha_attr {
+        for (key, unit_key) in WEATHER_ATTRIBUTES {
+            if let Some(value) = ha_attr.get(key).filter(|v| v.is_number()) {
+                attributes.insert(key.into(), value.clone());
+                if let Some(unit) = unit_key.and_then(|k| ha_attr.get(k)) {
+                    attributes.insert(format!("{key}_unit"), unit.clone());
+                }
+            }
+        }
+        // Extension point: the legacy `forecast` attribute is ignored for now. Newer HA cores
+        // only provide the forecast with the `weather.get_forecasts` service.
+    }
+
+    Ok(attributes)
+}
+
+pub(crate) fn weather_event_to_entity_change(
+    mut data: EventData,
+) -> Result<EntityChange, ServiceError> {
+    let attributes = map_weather_attributes(
+        &data.entity_id,
+        &data.new_state.state,
+        data.new_state.attributes.as_mut(),
+    )?;
+
+    Ok(EntityChange {
+        device_id: None,
+        entity_type: EntityType::Sensor,
+        entity_id: data.entity_id,
+        attributes,
+    })
+}
+
+pub(crate) fn convert_weather_entity(
+    entity_id: String,
+    state: String,
+    ha_attr: &mut Map<String, Value>,
+) -> Result<AvailableIntgEntity, ServiceError> {
+    let friendly_name = ha_attr.get("friendly_name").and_then(|v| v.as_str());
+    let name = HashMap::from([("en".into(), friendly_name.unwrap_or(&entity_id).into())]);
+
+    // convert attributes
+    let attributes = Some(map_weather_attributes(&entity_id, &state, Some(ha_attr))?);
+
+    Ok(AvailableIntgEntity {
+        entity_id,
+        device_id: None, // prepared for device_id handling
+        entity_type: EntityType::Sensor,
+        device_class: Some("custom".into()),
+        name,
+        features: None,
+        area: None,
+        options: None,
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ha_weather_attributes() -> Value {
+        json!({
+            "temperature": 18.4,
+            "apparent_temperature": 17.1,
+            "dew_point": 9.2,
+            "temperature_unit": "°C",
+            "humidity": 55,
+            "cloud_coverage": 40.6,
+            "uv_index": 3.1,
+            "pressure": 1016.3,
+            "pressure_unit": "hPa",
+            "wind_bearing": 250.5,
+            "wind_speed": 14.8,
+            "wind_speed_unit": "km/h",
+            "visibility_unit": "km",
+            "precipitation_unit": "mm",
+            "forecast": [
+                { "condition": "rainy", "datetime": "2024-05-02T10:00:00+00:00", "temperature": 16.0 }
+            ],
+            "attribution": "Weather forecast from met.no, delivered by the Norwegian Meteorological Institute.",
+            "friendly_name": "Forecast Home",
+            "supported_features": 3
+        })
+    }
+
+    #[test]
+    fn convert_weather() {
+        let mut attr = ha_weather_attributes();
+        let result = convert_weather_entity(
+            "weather.forecast_home".into(),
+            "partlycloudy".into(),
+            attr.as_object_mut().unwrap(),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful entity conversion but got: {:?}",
+            result.unwrap_err()
+        );
+        let entity = result.unwrap();
+
+        assert_eq!(EntityType::Sensor, entity.entity_type);
+        assert_eq!(Some("custom".to_string()), entity.device_class);
+        assert_eq!(Some(&"Forecast Home".to_string()), entity.name.get("en"));
+        assert_eq!(
+            Some(json!({
+                "state": "ON",
+                "value": "partlycloudy",
+                "temperature": 18.4,
+                "temperature_unit": "°C",
+                "humidity": 55,
+                "pressure": 1016.3,
+                "pressure_unit": "hPa",
+                "wind_speed": 14.8,
+                "wind_speed_unit": "km/h"
+            })),
+            entity.attributes.map(Value::Object)
+        );
+    }
+
+    #[test]
+    fn weather_event_without_values() {
+        let data = EventData {
+            entity_id: "weather.forecast_home".into(),
+            new_state: serde_json::from_value(json!({
+                "state": "rainy",
+                "attributes": { "temperature": null, "temperature_unit": "°C" }
+            }))
+            .expect("invalid test data"),
+        };
+        let entity_change =
+            weather_event_to_entity_change(data).expect("Expected successful event mapping");
+
+        assert_eq!(
+            json!({ "state": "ON", "value": "rainy" }),
+            Value::Object(entity_change.attributes)
+        );
+    }
+
+    #[test]
+    fn weather_event_unavailable() {
+        let mut attr = ha_weather_attributes();
+        let attributes =
+            map_weather_attributes("weather.forecast_home", "unavailable", attr.as_object_mut())
+                .expect("Expected successful attribute mapping");
+
+        assert_eq!(json!({ "state": "UNAVAILABLE" }), Value::Object(attributes));
+    }
+}
@@ -0,0 +1,240 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Entity name handling.
+//!
+//! The remote shows a blank entity if the name map is empty or only contains empty names, e.g.
+//! for HA entities with an empty `friendly_name`.
+//!
+//! The entity conversion sets the name for the default language `en` only. The name is also set
+//! for the configured HA language, so the remote can show it as localized label.
+
+use crate::configuration::EntityNameFallback;
+use crate::util::{language_map, DEFAULT_LANGUAGE};
+use uc_api::intg::AvailableIntgEntity;
+
+/// Make sure that the entity has at least one non-empty name.
+///
+/// Empty names are removed. If no name is left, a fallback name is set for the default language.
+pub(crate) fn ensure_entity_name(entity: &mut AvailableIntgEntity, fallback: EntityNameFallback) {
+    entity.name.retain(|_, name| !name.trim().is_empty());
+    if entity.name.is_empty() {
+        entity.name.insert(
+            DEFAULT_LANGUAGE.into(),
+            fallback_name(&entity.entity_id, fallback),
+        );
+    }
+}
+
+/// Set the default language name of the entity for the HA configuration language as well.
+///
+/// # Arguments
+///
+/// * `entity`: converted entity with a name for the default language.
+/// * `language`: HA configuration language, e.g. `de`. The name is not changed if not set.
+pub(crate) fn localize_entity_name(entity: &mut AvailableIntgEntity, language: Option<&str>) {
+    if language.is_none() {
+        return;
+    }
+    if let Some(name) = entity.name.get(DEFAULT_LANGUAGE).cloned() {
+        entity.name.extend(language_map(&name, language));
+    }
+}
+
+/// Create the fallback name of an entity.
+fn fallback_name(entity_id: &str, fallback: EntityNameFallback) -> String {
+    match fallback {
+        EntityNameFallback::EntityId => entity_id.to_string(),
+        EntityNameFallback::ObjectId => {
+            let object_id = entity_id
+                .split_once('.')
+                .map(|(_, id)| id)
+                .unwrap_or(entity_id);
+            let name = object_id
+                .split('_')
+                .filter(|part| !part.is_empty())
+                .map(capitalize)
+                .collect::<Vec<_>>()
+                .join(" ");
+            if name.is_empty() {
+                entity_id.to_string()
+            } else {
+                name
+            }
+        }
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::entity::*;
+    use crate::errors::ServiceError;
+    use rstest::rstest;
+    use serde_json::{json, Map, Value};
+    use url::Url;
+
+    /// Convert an entity of every supported domain with the given HA attributes.
+    fn convert_all_entities(
+        attr: &Map<String, Value>,
+    ) -> Vec<Result<AvailableIntgEntity, ServiceError>> {
+        let server = Url::parse("http://homeassistant.local:8123").unwrap();
+        let convert: Vec<(
+            &str,
+            &str,
+            fn(String, String, &mut Map<String, Value>) -> Result<_, _>,
+        )> = vec![
+            (
+                "alarm_control_panel.home",
+                "disarmed",
+                convert_alarm_control_panel_entity,
+            ),
+            ("button.restart", "unknown", convert_button_entity),
+            ("cover.garage", "closed", convert_cover_entity),
+            ("fan.ceiling", "off", convert_fan_entity),
+            ("humidifier.bedroom", "off", convert_humidifier_entity),
+            ("lawn_mower.automower", "docked", convert_lawn_mower_entity),
+            ("light.kitchen", "on", convert_light_entity),
+            ("lock.front_door", "locked", convert_lock_entity),
+            ("number.volume", "10", convert_number_entity),
+            ("remote.tv", "on", convert_remote_entity),
+            ("scene.movie", "unknown", convert_scene_entity),
+            ("select.mode", "eco", convert_select_entity),
+            ("siren.hallway", "off", convert_siren_entity),
+            ("switch.plug", "off", convert_switch_entity),
+            ("vacuum.robot", "docked", convert_vacuum_entity),
+        ];
+
+        let mut entities = Vec::with_capacity(convert.len() + 4);
+        for (entity_id, state, convert_fn) in convert {
+            entities.push(convert_fn(
+                entity_id.into(),
+                state.into(),
+                &mut attr.clone(),
+            ));
+        }
+        entities.push(convert_sensor_entity(
+            "sensor.power".into(),
+            "12".into(),
+            &mut attr.clone(),
+            &Default::default(),
+        ));
+        entities.push(convert_climate_entity(
+            "climate.living_room".into(),
+            "heat".into(),
+            &mut attr.clone(),
+            None,
+        ));
+        entities.push(convert_water_heater_entity(
+            "water_heater.boiler".into(),
+            "eco".into(),
+            &mut attr.clone(),
+            None,
+        ));
+        entities.push(convert_media_player_entity(
+            &server,
+            "media_player.tv".into(),
+            "off".into(),
+            &mut attr.clone(),
+        ));
+        entities
+    }
+
+    #[rstest]
+    #[case(json!({}))]
+    #[case(json!({ "friendly_name": "" }))]
+    #[case(json!({ "friendly_name": "  " }))]
+    #[case(json!({ "friendly_name": null }))]
+    fn every_converted_entity_has_a_name(
+        #[case] attr: Value,
+        #[values(EntityNameFallback::EntityId, EntityNameFallback::ObjectId)]
+        fallback: EntityNameFallback,
+    ) {
+        for result in convert_all_entities(attr.as_object().unwrap()) {
+            let mut entity = result.expect("Expected successful entity conversion");
+            ensure_entity_name(&mut entity, fallback);
+
+            assert!(
+                entity.name.values().any(|name| !name.trim().is_empty()),
+                "Entity {} without a name: {:?}",
+                entity.entity_id,
+                entity.name
+            );
+        }
+    }
+
+    #[rstest]
+    #[case("light.living_room", EntityNameFallback::EntityId, "light.living_room")]
+    #[case("light.living_room", EntityNameFallback::ObjectId, "Living Room")]
+    #[case("sensor.power__2", EntityNameFallback::ObjectId, "Power 2")]
+    #[case("sensor._", EntityNameFallback::ObjectId, "sensor._")]
+    fn fallback_names(
+        #[case] entity_id: &str,
+        #[case] fallback: EntityNameFallback,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(expected, fallback_name(entity_id, fallback));
+    }
+
+    #[test]
+    fn name_is_localized_with_ha_language() {
+        let mut attr = json!({ "friendly_name": "Wohnzimmer" });
+        let mut entity = convert_light_entity(
+            "light.living_room".into(),
+            "on".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        localize_entity_name(&mut entity, Some("de"));
+
+        assert_eq!(2, entity.name.len());
+        assert_eq!(
+            Some(&"Wohnzimmer".to_string()),
+            entity.name.get(DEFAULT_LANGUAGE)
+        );
+        assert_eq!(Some(&"Wohnzimmer".to_string()), entity.name.get("de"));
+    }
+
+    #[test]
+    fn name_without_ha_language_is_not_localized() {
+        let mut attr = json!({ "friendly_name": "Living Room" });
+        let mut entity = convert_light_entity(
+            "light.living_room".into(),
+            "on".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        localize_entity_name(&mut entity, None);
+
+        assert_eq!(1, entity.name.len());
+    }
+
+    #[test]
+    fn existing_name_is_kept() {
+        let mut attr = json!({ "friendly_name": "Kitchen" });
+        let mut entity = convert_switch_entity(
+            "switch.kitchen".into(),
+            "on".into(),
+            attr.as_object_mut().unwrap(),
+        )
+        .expect("Expected successful entity conversion");
+
+        ensure_entity_name(&mut entity, EntityNameFallback::ObjectId);
+
+        assert_eq!(1, entity.name.len());
+        assert_eq!(
+            Some(&"Kitchen".to_string()),
+            entity.name.get(DEFAULT_LANGUAGE)
+        );
+    }
+}
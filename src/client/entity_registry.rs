@@ -0,0 +1,190 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Home Assistant entity registry handling with the `config/entity_registry/list` request.
+//!
+//! Entities hidden or disabled in the HA entity registry are excluded from the available entities,
+//! unless configured otherwise. The area and device assignments of the entities are used for the
+//! entity area.
+
+use crate::client::HomeAssistantClient;
+use actix::Context;
+use log::{error, info};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+
+impl HomeAssistantClient {
+    /// Request the HA entity registry for hidden & disabled entities and entity areas.
+    ///
+    /// The result is handled in [`Self::handle_entity_registry_result`].
+    pub(crate) fn send_entity_registry_list(&mut self, ctx: &mut Context<HomeAssistantClient>) {
+        let id = self.new_msg_id();
+        self.entity_registry_id = Some(id);
+        if let Err(e) = self.send_json(
+            json!({"id": id, "type": "config/entity_registry/list"}),
+            ctx,
+        ) {
+            error!(
+                "[{}] Error sending config/entity_registry/list to HA: {:?}",
+                self.id, e
+            );
+        }
+    }
+
+    /// Cache the hidden & disabled entity ids and the entity areas of the HA entity registry.
+    pub(crate) fn handle_entity_registry_result(&mut self, result: Option<&Value>) {
+        self.hidden_entities = hidden_entity_ids(result);
+        self.entity_areas = entity_area_ids(result);
+        self.entity_devices = entity_device_ids(result);
+        if !self.settings.include_hidden_entities {
+            info!(
+                "[{}] Excluding {} hidden or disabled entities",
+                self.id,
+                self.hidden_entities.len()
+            );
+        }
+    }
+
+    /// Check if the entity is excluded from the available entities.
+    pub(crate) fn is_hidden_entity(&self, entity_id: &str) -> bool {
+        is_excluded(
+            entity_id,
+            &self.hidden_entities,
+            self.settings.include_hidden_entities,
+        )
+    }
+}
+
+/// Get the ids of hidden or disabled entities from an entity registry list result.
+fn hidden_entity_ids(result: Option<&Value>) -> HashSet<String> {
+    result
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|entry| {
+            ["hidden_by", "disabled_by"]
+                .iter()
+                .any(|key| entry.get(*key).is_some_and(|v| !v.is_null()))
+        })
+        .filter_map(|entry| entry.get("entity_id").and_then(|v| v.as_str()))
+        .map(|v| v.to_string())
+        .collect()
+}
+
+/// Get the entity id to area id mapping from an entity registry list result.
+///
+/// Entities without an assigned area are skipped.
+fn entity_area_ids(result: Option<&Value>) -> HashMap<String, String> {
+    result
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let entity_id = entry.get("entity_id").and_then(|v| v.as_str())?;
+            let area_id = entry.get("area_id").and_then(|v| v.as_str())?;
+            Some((entity_id.to_string(), area_id.to_string()))
+        })
+        .collect()
+}
+
+/// Get the entity id to device id mapping from an entity registry list result.
+///
+/// Entities without a device are skipped.
+fn entity_device_ids(result: Option<&Value>) -> HashMap<String, String> {
+    result
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let entity_id = entry.get("entity_id").and_then(|v| v.as_str())?;
+            let device_id = entry.get("device_id").and_then(|v| v.as_str())?;
+            Some((entity_id.to_string(), device_id.to_string()))
+        })
+        .collect()
+}
+
+fn is_excluded(entity_id: &str, hidden_entities: &HashSet<String>, include_hidden: bool) -> bool {
+    !include_hidden && hidden_entities.contains(entity_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> Value {
+        json!([
+            {
+                "entity_id": "light.kitchen",
+                "area_id": "kitchen",
+                "device_id": "d1",
+                "disabled_by": null,
+                "hidden_by": null
+            },
+            {
+                "entity_id": "sensor.kitchen_power",
+                "area_id": null,
+                "device_id": "d1",
+                "disabled_by": null,
+                "hidden_by": "integration"
+            },
+            {
+                "entity_id": "switch.old_plug",
+                "disabled_by": "user",
+                "hidden_by": null
+            },
+            {
+                "entity_id": "sensor.uptime"
+            }
+        ])
+    }
+
+    #[test]
+    fn hidden_and_disabled_entities_are_collected() {
+        let result = hidden_entity_ids(Some(&registry()));
+
+        assert_eq!(2, result.len());
+        assert!(result.contains("sensor.kitchen_power"));
+        assert!(result.contains("switch.old_plug"));
+    }
+
+    #[test]
+    fn invalid_registry_result_has_no_hidden_entities() {
+        assert!(hidden_entity_ids(None).is_empty());
+        assert!(hidden_entity_ids(Some(&json!({ "code": "unauthorized" }))).is_empty());
+    }
+
+    #[test]
+    fn entity_areas_are_collected() {
+        let result = entity_area_ids(Some(&registry()));
+
+        assert_eq!(1, result.len());
+        assert_eq!(Some(&"kitchen".to_string()), result.get("light.kitchen"));
+    }
+
+    #[test]
+    fn entity_devices_are_collected() {
+        let result = entity_device_ids(Some(&registry()));
+
+        assert_eq!(2, result.len());
+        assert_eq!(Some(&"d1".to_string()), result.get("light.kitchen"));
+        assert_eq!(Some(&"d1".to_string()), result.get("sensor.kitchen_power"));
+    }
+
+    #[test]
+    fn hidden_entities_are_excluded_by_default() {
+        let hidden = hidden_entity_ids(Some(&registry()));
+
+        assert!(is_excluded("sensor.kitchen_power", &hidden, false));
+        assert!(is_excluded("switch.old_plug", &hidden, false));
+        assert!(!is_excluded("light.kitchen", &hidden, false));
+        assert!(!is_excluded("sensor.uptime", &hidden, false));
+    }
+
+    #[test]
+    fn hidden_entities_are_included_if_configured() {
+        let hidden = hidden_entity_ids(Some(&registry()));
+
+        assert!(!is_excluded("sensor.kitchen_power", &hidden, true));
+        assert!(!is_excluded("switch.old_plug", &hidden, true));
+    }
+}
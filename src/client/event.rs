@@ -6,14 +6,37 @@
 //! See <https://developers.home-assistant.io/docs/api/websocket/#subscribe-to-events> for further
 //! information.
 
+use crate::client::attribute_entities::attribute_entity_changes;
+use crate::client::climate_setpoint::setpoint_entity_changes;
 use crate::client::entity::*;
-use crate::client::messages::EntityEvent;
+use crate::client::messages::{EntityAvailability, EntityEvent};
 use crate::client::model::Event;
 use crate::client::HomeAssistantClient;
+use crate::controller::entity_namespace::remote_entity_id;
 use crate::errors::ServiceError;
-use log::debug;
+use actix::{AsyncContext, Context};
+use log::{debug, error};
+use std::time::Instant;
+use uc_api::intg::EntityChange;
+use uc_api::EntityType;
 
 impl HomeAssistantClient {
+    /// Handle the given `state_changed` events in order.
+    pub(crate) fn handle_events(
+        &mut self,
+        events: Vec<Event>,
+        ctx: &mut Context<HomeAssistantClient>,
+    ) {
+        for event in events {
+            if let Err(e) = self.handle_event(event, ctx) {
+                error!(
+                    "[{}] Error handling HA state_changed event: {:?}",
+                    self.id, e
+                );
+            }
+        }
+    }
+
     /// Whenever an `event` message is received from HA, this method is called to handle it.  
     /// The event conversion is delegated to entity type specific functions for the supported entity
     /// types.  
@@ -24,42 +47,192 @@ impl HomeAssistantClient {
     /// # Arguments
     ///
     /// * `event`: Transformed `.event` json object containing only the required data.
+    /// * `ctx`: Actor execution context to send debounced `unavailable` entity changes.
     ///
     /// returns: Result<(), ServiceError>
-    pub(crate) fn handle_event(&mut self, event: Event) -> Result<(), ServiceError> {
+    pub(crate) fn handle_event(
+        &mut self,
+        event: Event,
+        ctx: &mut Context<HomeAssistantClient>,
+    ) -> Result<(), ServiceError> {
         let entity_type = match event.data.entity_id.split_once('.') {
             None => return Err(ServiceError::BadRequest("Invalid entity_id format".into())),
             Some((l, _)) => l,
         };
 
-        if event.data.entity_id.is_empty() || event.data.new_state.state.is_empty() {
+        // an empty state is a valid text value
+        if event.data.entity_id.is_empty()
+            || (event.data.new_state.state.is_empty() && !is_text_entity(&event.data.entity_id))
+        {
             return Err(ServiceError::BadRequest(format!(
                 "Missing data in state_changed event: {:?}",
                 event.data
             )));
         }
 
-        let entity_change = match entity_type {
+        let entity_id = event.data.entity_id.clone();
+        let new_state = event.data.new_state.clone();
+        let mut entity_change = match entity_type {
             "light" => light_event_to_entity_change(event.data),
             "switch" | "input_boolean" => switch_event_to_entity_change(event.data),
-            "button" | "input_button" | "script" => {
-                // the button & script entity is stateless and the remote doesn't need to be notified when the button was pressed externally
+            "lock" => lock_event_to_entity_change(event.data),
+            "fan" => fan_event_to_entity_change(event.data),
+            "humidifier" => humidifier_event_to_entity_change(event.data),
+            "siren" => siren_event_to_entity_change(event.data),
+            // the state of a button is the last pressed timestamp
+            "button" | "input_button" => button_event_to_entity_change(event.data),
+            "script" => {
+                // the script entity is stateless and the remote doesn't need to be notified when the script was run externally
                 return Ok(());
             }
+            // only the availability of a scene is forwarded, the state is the last activation
+            "scene" => scene_event_to_entity_change(event.data),
             "cover" => cover_event_to_entity_change(event.data),
             "sensor" => sensor_event_to_entity_change(event.data),
-            "binary_sensor" => binary_sensor_event_to_entity_change(event.data),
+            "binary_sensor" => {
+                binary_sensor_event_to_entity_change(event.data, &self.settings.sensor)
+            }
             "climate" => climate_event_to_entity_change(event.data),
+            "water_heater" => water_heater_event_to_entity_change(event.data),
             "media_player" => media_player_event_to_entity_change(&self.server, event.data),
             "remote" => remote_event_to_entity_change(event.data),
+            "vacuum" => vacuum_event_to_entity_change(event.data),
+            "lawn_mower" => lawn_mower_event_to_entity_change(event.data),
+            "alarm_control_panel" => alarm_control_panel_event_to_entity_change(event.data),
+            "number" | "input_number" => {
+                if new_state.state == "unknown" {
+                    debug!("[{}] Ignoring unknown number state: {entity_id}", self.id);
+                    return Ok(());
+                }
+                number_event_to_entity_change(event.data)
+            }
+            "select" | "input_select" => select_event_to_entity_change(event.data),
+            "device_tracker" | "person" => presence_event_to_entity_change(event.data),
+            "weather" => weather_event_to_entity_change(event.data),
+            "update" => update_event_to_entity_change(event.data),
+            "text" | "input_text" => text_event_to_entity_change(event.data),
             &_ => {
                 debug!("[{}] Unsupported entity: {}", self.id, entity_type);
                 return Ok(()); // it's not really an error, so it's ok ;-)
             }
         }?;
 
+        if entity_change.entity_type == EntityType::MediaPlayer
+            && self.settings.media_player.https_image_url
+        {
+            upgrade_media_image_url(&self.server, &mut entity_change.attributes);
+        }
+        if entity_change.entity_type == EntityType::MediaPlayer
+            && self.settings.media_player.image_proxy
+        {
+            proxy_media_image_url(
+                &self.settings.media_player.image_proxy_url,
+                &remote_entity_id(&self.device_id, &entity_id),
+                &mut entity_change.attributes,
+            );
+        }
+        if entity_change.entity_type == EntityType::Climate {
+            if let Some(conversion) = self.temperature_conversion(new_state.attributes.as_ref()) {
+                conversion.convert_attributes(&mut entity_change.attributes);
+            }
+        }
+
+        let mut attribute_changes = attribute_entity_changes(
+            &entity_id,
+            &new_state.state,
+            new_state.attributes.as_ref(),
+            &self.settings.attribute_entities,
+        );
+        if is_number_entity(&entity_id) {
+            attribute_changes.extend(setpoint_entity_changes(
+                &entity_id,
+                &new_state.state,
+                &self.settings.climate_setpoint_numbers,
+            ));
+        }
+        let unavailable = new_state.state == "unavailable";
+        let context_id = new_state.context.as_ref().map(|c| c.id.clone());
+        let previous_available = self
+            .entity_states
+            .insert(entity_id.clone(), new_state)
+            .map(|s| s.state != "unavailable");
+
+        if !self.event_filter.is_forwarded(&entity_id) {
+            debug!("[{}] Events disabled for entity: {entity_id}", self.id);
+            return Ok(());
+        }
+        if self
+            .echo_filter
+            .is_echo(context_id.as_deref(), Instant::now())
+        {
+            debug!(
+                "[{}] Suppressing echo of own service call: {entity_id}",
+                self.id
+            );
+            return Ok(());
+        }
+
+        for entity_change in attribute_changes {
+            self.send_entity_change(entity_change)?;
+        }
+
+        match self
+            .unavailable_debounce
+            .filter(entity_change, unavailable, Instant::now())
+        {
+            Some(entity_change) => {
+                self.send_availability_change(&entity_change, !unavailable, previous_available);
+                self.send_entity_change(entity_change)
+            }
+            None => {
+                debug!("[{}] Debouncing unavailable state: {entity_id}", self.id);
+                ctx.run_later(self.unavailable_debounce.delay(), move |act, _| {
+                    if let Some(entity_change) = act.unavailable_debounce.take_pending(&entity_id) {
+                        act.send_availability_change(&entity_change, false, previous_available);
+                        if let Err(e) = act.send_entity_change(entity_change) {
+                            error!("[{}] Error sending debounced entity change: {e:?}", act.id);
+                        }
+                    }
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Send a dedicated `entity_available` event to the controller if the availability of the
+    /// forwarded entity change flipped.
+    fn send_availability_change(
+        &mut self,
+        entity_change: &EntityChange,
+        available: bool,
+        previous_available: Option<bool>,
+    ) {
+        let available = match self.availability_events.changed(
+            &entity_change.entity_id,
+            available,
+            previous_available,
+        ) {
+            Some(available) => available,
+            None => return,
+        };
+        if let Err(e) = self.controller_actor.try_send(EntityAvailability {
+            client_id: self.id.clone(),
+            device_id: self.device_id.clone(),
+            entity_id: entity_change.entity_id.clone(),
+            entity_type: entity_change.entity_type.clone(),
+            available,
+        }) {
+            error!("[{}] Error sending entity availability: {e:?}", self.id);
+        }
+    }
+
+    pub(crate) fn send_entity_change(
+        &self,
+        entity_change: EntityChange,
+    ) -> Result<(), ServiceError> {
         self.controller_actor.try_send(EntityEvent {
             client_id: self.id.clone(),
+            device_id: self.device_id.clone(),
             entity_change,
         })?;
 
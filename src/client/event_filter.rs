@@ -0,0 +1,74 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Filter for HA entity state change events.
+
+use std::collections::HashSet;
+
+/// Suppress forwarding state change events of configured entities, e.g. a noisy power sensor.
+///
+/// The filter only applies to events sent to the remote: the entity state is still cached and the
+/// entity can be controlled.
+#[derive(Debug, Default)]
+pub(crate) struct EventFilter {
+    disabled_entities: HashSet<String>,
+}
+
+impl EventFilter {
+    pub fn new(disabled_entities: HashSet<String>) -> Self {
+        Self { disabled_entities }
+    }
+
+    /// Check if state change events of the given entity are forwarded to the remote.
+    pub fn is_forwarded(&self, entity_id: &str) -> bool {
+        !self.disabled_entities.contains(entity_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::entity_command_to_service;
+    use serde_json::json;
+    use uc_api::intg::EntityCommand;
+
+    fn filter() -> EventFilter {
+        EventFilter::new(HashSet::from(["switch.washing_machine_plug".to_string()]))
+    }
+
+    #[test]
+    fn disabled_entity_is_not_forwarded() {
+        assert!(!filter().is_forwarded("switch.washing_machine_plug"));
+    }
+
+    #[test]
+    fn other_entities_are_forwarded() {
+        let filter = filter();
+
+        assert!(filter.is_forwarded("switch.fan"));
+        assert!(filter.is_forwarded("sensor.washing_machine_plug_power"));
+        assert!(EventFilter::default().is_forwarded("switch.washing_machine_plug"));
+    }
+
+    #[test]
+    fn disabled_entity_is_still_commandable() {
+        let filter = filter();
+        let cmd: EntityCommand = serde_json::from_value(json!({
+            "cmd_id": "off",
+            "entity_id": "switch.washing_machine_plug",
+            "entity_type": "switch"
+        }))
+        .expect("invalid test data");
+
+        assert!(!filter.is_forwarded(&cmd.entity_id));
+        let result = entity_command_to_service(&cmd, None, &Default::default());
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (domain, service, _) = result.unwrap();
+        assert_eq!("switch", domain);
+        assert_eq!("turn_off", service);
+    }
+}
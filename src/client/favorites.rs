@@ -0,0 +1,119 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Favorite entities: always subscribed and listed first in entity responses.
+
+use std::collections::HashSet;
+use uc_api::intg::AvailableIntgEntity;
+
+/// Add the favorite entities to the subscribed entities.
+///
+/// # Arguments
+///
+/// * `subscriptions`: subscribed entity ids and domains of the remote.
+/// * `favorites`: configured favorite entity ids.
+///
+/// returns: subscriptions including all favorites.
+pub(crate) fn with_favorites(
+    subscriptions: &HashSet<String>,
+    favorites: &[String],
+) -> HashSet<String> {
+    subscriptions
+        .iter()
+        .chain(favorites.iter())
+        .cloned()
+        .collect()
+}
+
+/// Sort the entities by the order of the favorites list.
+///
+/// Favorite entities are moved to the front, all other entities keep their relative order.
+pub(crate) fn sort_by_favorites(entities: &mut [AvailableIntgEntity], favorites: &[String]) {
+    if favorites.is_empty() {
+        return;
+    }
+    entities.sort_by_key(|entity| {
+        favorites
+            .iter()
+            .position(|id| id == &entity.entity_id)
+            .unwrap_or(favorites.len())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use uc_api::EntityType;
+
+    fn entity(entity_id: &str) -> AvailableIntgEntity {
+        AvailableIntgEntity {
+            entity_id: entity_id.into(),
+            device_id: None,
+            entity_type: EntityType::Light,
+            device_class: None,
+            name: HashMap::from([("en".into(), entity_id.into())]),
+            features: None,
+            area: None,
+            options: None,
+            attributes: None,
+        }
+    }
+
+    fn entity_ids(entities: &[AvailableIntgEntity]) -> Vec<&str> {
+        entities.iter().map(|e| e.entity_id.as_str()).collect()
+    }
+
+    #[test]
+    fn favorites_are_included_in_initial_subscriptions() {
+        let result = with_favorites(
+            &HashSet::new(),
+            &["light.kitchen".into(), "switch.fan".into()],
+        );
+
+        assert_eq!(
+            HashSet::from(["light.kitchen".to_string(), "switch.fan".to_string()]),
+            result
+        );
+    }
+
+    #[test]
+    fn favorites_are_added_to_subscriptions() {
+        let subscriptions =
+            HashSet::from(["light.office".to_string(), "light.kitchen".to_string()]);
+        let result = with_favorites(&subscriptions, &["light.kitchen".into()]);
+
+        assert_eq!(subscriptions, result);
+    }
+
+    #[test]
+    fn favorites_are_sorted_first() {
+        let mut entities = vec![
+            entity("light.office"),
+            entity("light.kitchen"),
+            entity("switch.fan"),
+            entity("light.hall"),
+        ];
+        sort_by_favorites(
+            &mut entities,
+            &[
+                "switch.fan".into(),
+                "light.unknown".into(),
+                "light.kitchen".into(),
+            ],
+        );
+
+        assert_eq!(
+            vec!["switch.fan", "light.kitchen", "light.office", "light.hall"],
+            entity_ids(&entities)
+        );
+    }
+
+    #[test]
+    fn without_favorites_order_is_kept() {
+        let mut entities = vec![entity("light.office"), entity("light.kitchen")];
+        sort_by_favorites(&mut entities, &[]);
+
+        assert_eq!(vec!["light.office", "light.kitchen"], entity_ids(&entities));
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Home Assistant system configuration handling with the `get_config` request.
+
+use crate::client::temperature::{TemperatureConversion, TemperatureUnit};
+use crate::client::HomeAssistantClient;
+use crate::configuration::TemperatureUnitSource;
+use actix::Context;
+use log::{error, info};
+use serde_json::{json, Map, Value};
+
+impl HomeAssistantClient {
+    /// Request the HA system configuration. The result is handled in [`Self::handle_get_config_result`].
+    pub(crate) fn send_get_config(&mut self, ctx: &mut Context<HomeAssistantClient>) {
+        let id = self.new_msg_id();
+        self.get_config_id = Some(id);
+        if let Err(e) = self.send_json(json!({"id": id, "type": "get_config"}), ctx) {
+            error!("[{}] Error sending get_config to HA: {:?}", self.id, e);
+        }
+    }
+
+    /// Cache the required values of the HA system configuration.
+    pub(crate) fn handle_get_config_result(&mut self, result: Option<&Value>) {
+        self.temperature_unit = result
+            .and_then(|v| v.pointer("/unit_system/temperature"))
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string());
+        self.language = result
+            .and_then(|v| v.get("language"))
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string());
+        info!(
+            "[{}] HA temperature unit: {}, language: {}",
+            self.id,
+            self.temperature_unit.as_deref().unwrap_or("unknown"),
+            self.language.as_deref().unwrap_or("unknown")
+        );
+    }
+
+    /// Get the temperature unit for climate entities without a `temperature_unit` attribute.
+    ///
+    /// Returns `None` if the temperature unit of the remote should be used.
+    pub(crate) fn climate_temperature_unit(&self) -> Option<&str> {
+        match self.temperature_unit_source {
+            TemperatureUnitSource::Ha => self.temperature_unit.as_deref(),
+            TemperatureUnitSource::Remote => None,
+            TemperatureUnitSource::Celsius => Some(TemperatureUnit::Celsius.ha_unit()),
+            TemperatureUnitSource::Fahrenheit => Some(TemperatureUnit::Fahrenheit.ha_unit()),
+        }
+    }
+
+    /// Get the temperature conversion from the HA unit of an entity to the forced temperature
+    /// unit.
+    ///
+    /// Returns `None` if no temperature unit is forced, or if the entity already uses it.
+    pub(crate) fn temperature_conversion(
+        &self,
+        ha_attr: Option<&Map<String, Value>>,
+    ) -> Option<TemperatureConversion> {
+        let to = match self.temperature_unit_source {
+            TemperatureUnitSource::Celsius => TemperatureUnit::Celsius,
+            TemperatureUnitSource::Fahrenheit => TemperatureUnit::Fahrenheit,
+            TemperatureUnitSource::Ha | TemperatureUnitSource::Remote => return None,
+        };
+        let from = ha_attr
+            .and_then(|attr| attr.get("temperature_unit"))
+            .and_then(|v| v.as_str())
+            .or(self.temperature_unit.as_deref())
+            .and_then(TemperatureUnit::from_ha_unit)?;
+
+        TemperatureConversion::new(from, to)
+    }
+}
@@ -6,12 +6,13 @@
 use crate::client::messages::GetAvailableEntities;
 use crate::client::HomeAssistantClient;
 use crate::errors::ServiceError;
-use actix::Handler;
+use crate::util::return_fut_err;
+use actix::{fut, Handler, ResponseFuture};
 use log::debug;
 use serde_json::json;
 
 impl Handler<GetAvailableEntities> for HomeAssistantClient {
-    type Result = Result<(), ServiceError>;
+    type Result = ResponseFuture<Result<(), ServiceError>>;
 
     fn handle(&mut self, msg: GetAvailableEntities, ctx: &mut Self::Context) -> Self::Result {
         debug!("[{}] GetAvailableEntities from {}", self.id, msg.remote_id);
@@ -22,7 +23,7 @@ impl Handler<GetAvailableEntities> for HomeAssistantClient {
         // Try to subscribe again to custom events if not already done when
         // GetAvailableEntities command is received from the remote
         self.send_uc_info_command(ctx);
-        if self.uc_ha_component {
+        let result = if self.uc_ha_component {
             // Retrieve the states of available entities (including subscribed entities)
             // Available entities are defined on HA component side and should include
             // subscribed entities but sent anyway just in case some are missing
@@ -34,7 +35,7 @@ impl Handler<GetAvailableEntities> for HomeAssistantClient {
                 json!(
                     {"id": id, "type": "unfoldedcircle/entities/states",
                     "data": {
-                        "entity_ids": self.subscribed_entities,
+                        "entity_ids": self.expanded_subscribed_entities(),
                         "client_id": self.remote_id
                     }}
                 ),
@@ -42,12 +43,23 @@ impl Handler<GetAvailableEntities> for HomeAssistantClient {
             )
         } else {
             debug!("[{}] Get standard states from {} ", self.id, self.remote_id);
+            self.entity_list_id = Some(id);
             self.send_json(
                 json!(
                     {"id": id, "type": "get_states"}
                 ),
                 ctx,
             )
+        };
+
+        match result {
+            Ok(_) => {
+                let result = self.entity_request_result(id);
+                Box::pin(async move { result.await.map_err(ServiceError::from) })
+            }
+            Err(e) => {
+                return_fut_err!(e);
+            }
         }
     }
 }
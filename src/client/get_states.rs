@@ -3,40 +3,58 @@
 
 //! Actix actor handler implementation for the `GetStates` message
 
+use std::collections::HashSet;
 use std::str::FromStr;
 
+use crate::client::assumed_state::with_assumed_state;
+use crate::client::attribute_entities::attribute_entities;
+use crate::client::climate_setpoint::with_climate_setpoint;
 use crate::client::entity::*;
+use crate::client::entity_name::{ensure_entity_name, localize_entity_name};
+use crate::client::favorites::{sort_by_favorites, with_favorites};
 use crate::client::messages::GetStates;
+use crate::client::model::EventState;
+use crate::client::subscribed_entities::{expand_subscriptions, uc_states_request};
 use crate::client::HomeAssistantClient;
+use crate::controller::entity_namespace::remote_entity_id;
 use crate::errors::ServiceError;
-use actix::Handler;
+use crate::util::return_fut_err;
+use actix::{fut, Handler, ResponseFuture};
 use log::{debug, error, info, warn};
 use serde_json::{json, Value};
 use uc_api::intg::AvailableIntgEntity;
 use uc_api::EntityType;
 
 impl Handler<GetStates> for HomeAssistantClient {
-    type Result = Result<(), ServiceError>;
+    type Result = ResponseFuture<Result<(), ServiceError>>;
 
     fn handle(&mut self, msg: GetStates, ctx: &mut Self::Context) -> Self::Result {
         debug!("[{}] GetStates from '{}'", self.id, msg.remote_id);
         self.remote_id = msg.remote_id;
-        let entity_ids = msg.entity_ids;
+        let entity_ids = expand_subscriptions(
+            &with_favorites(&msg.entity_ids, &self.settings.favorite_entities),
+            self.entity_states.keys(),
+        );
         let id = self.new_msg_id();
         // Use the same message id for get states and get available entities (same result format)
         self.entity_states_id = Some(id);
         // Try to subsscribe again to custom events if not already done when GetStates command
         // is received from the remote
         self.send_uc_info_command(ctx);
-        // If UC HA component available, get states only on given (subscribed) entities
-        if self.uc_ha_component {
+        // If UC HA component available, get states only on given (subscribed) entities.
+        // Domain subscriptions require all HA entities, e.g. for the first request after connecting.
+        let result = if uc_states_request(
+            self.uc_ha_component,
+            &msg.entity_ids,
+            self.entity_list_loaded,
+        ) {
             self.send_json(
                 json!(
                     {
                         "id": id,
                         "type": "unfoldedcircle/entities/states",
                         "data": {
-                            "entity_ids": entity_ids.clone(),
+                            "entity_ids": entity_ids,
                             "client_id": self.remote_id
                         }
                     }
@@ -44,21 +62,40 @@ impl Handler<GetStates> for HomeAssistantClient {
                 ctx,
             )
         } else {
+            self.entity_list_id = Some(id);
             self.send_json(
                 json!(
                     {"id": id, "type": "get_states"}
                 ),
                 ctx,
             )
+        };
+
+        match result {
+            Ok(_) => {
+                let result = self.entity_request_result(id);
+                Box::pin(async move { result.await.map_err(ServiceError::from) })
+            }
+            Err(e) => {
+                return_fut_err!(e);
+            }
         }
     }
 }
 
 impl HomeAssistantClient {
+    /// Convert the HA entity states of a `get_states` result to available entities.
+    ///
+    /// # Arguments
+    ///
+    /// * `result`: `result` field of the HA response message. Must be an array of entity states.
+    ///
+    /// returns: converted entities, favorite entities first. Non-supported entities are skipped.
     pub(crate) fn handle_get_states_result(
         &mut self,
-        entities: Vec<Value>,
+        result: Option<Value>,
     ) -> Result<Vec<AvailableIntgEntity>, ServiceError> {
+        let entities = entity_states_array(result)?;
         let mut available = Vec::with_capacity(32);
 
         for mut entity in entities {
@@ -66,6 +103,13 @@ impl HomeAssistantClient {
                 .get("entity_id")
                 .and_then(|v| v.as_str())
                 .unwrap_or_default();
+            if self.is_hidden_entity(entity_id) {
+                debug!(
+                    "[{}] Filtering hidden or disabled entity: {entity_id}",
+                    self.id
+                );
+                continue;
+            }
             let entity_id = entity_id.to_string();
             let error_id = entity_id.to_string();
             let entity_type = match entity_id.split_once('.') {
@@ -76,6 +120,19 @@ impl HomeAssistantClient {
                     );
                     continue; // best effort
                 }
+                Some((domain, _))
+                    if !is_exposed_domain(
+                        domain,
+                        &self.settings.entity_domains,
+                        &self.settings.exclude_domains,
+                    ) =>
+                {
+                    debug!(
+                        "[{}] Filtering excluded domain entity: {entity_id}",
+                        self.id
+                    );
+                    continue;
+                }
                 // map different entity type names
                 Some((domain, _)) => match domain {
                     "input_boolean" => "switch",
@@ -83,6 +140,20 @@ impl HomeAssistantClient {
                     "input_button" => "button",
                     "script" => "button",
                     "scene" => "button",
+                    "vacuum" => "remote",
+                    "lawn_mower" => "remote",
+                    "alarm_control_panel" => "remote",
+                    "lock" => "switch",
+                    "fan" => "switch",
+                    "humidifier" => "switch",
+                    "siren" => "switch",
+                    "water_heater" => "climate",
+                    "number" | "input_number" => "sensor",
+                    "select" | "input_select" => "sensor",
+                    "device_tracker" | "person" => "sensor",
+                    "weather" => "sensor",
+                    "update" => "sensor",
+                    "text" | "input_text" => "sensor",
                     v => v,
                 },
             };
@@ -111,17 +182,83 @@ impl HomeAssistantClient {
                 Some(o) => o,
             };
 
+            let ha_state = EventState {
+                state: state.clone(),
+                attributes: Some(attr.clone()),
+                context: None,
+            };
             let avail_entity = match entity_type {
+                EntityType::Button if entity_id.starts_with("scene.") => {
+                    convert_scene_entity(entity_id, state, attr)
+                }
                 EntityType::Button => convert_button_entity(entity_id, state, attr),
+                EntityType::Switch if entity_id.starts_with("lock.") => {
+                    convert_lock_entity(entity_id, state, attr)
+                }
+                EntityType::Switch if entity_id.starts_with("fan.") => {
+                    convert_fan_entity(entity_id, state, attr)
+                }
+                EntityType::Switch if entity_id.starts_with("siren.") => {
+                    convert_siren_entity(entity_id, state, attr)
+                }
+                EntityType::Switch if entity_id.starts_with("humidifier.") => {
+                    convert_humidifier_entity(entity_id, state, attr)
+                }
                 EntityType::Switch => convert_switch_entity(entity_id, state, attr),
-                EntityType::Climate => convert_climate_entity(entity_id, state, attr),
+                EntityType::Climate if entity_id.starts_with("water_heater.") => {
+                    convert_water_heater_entity(
+                        entity_id,
+                        state,
+                        attr,
+                        self.climate_temperature_unit(),
+                    )
+                }
+                EntityType::Climate => {
+                    convert_climate_entity(entity_id, state, attr, self.climate_temperature_unit())
+                }
                 EntityType::Cover => convert_cover_entity(entity_id, state, attr),
                 EntityType::Light => convert_light_entity(entity_id, state, attr),
                 EntityType::MediaPlayer => {
                     convert_media_player_entity(&self.server, entity_id, state, attr)
                 }
+                EntityType::Remote if entity_id.starts_with("vacuum.") => {
+                    convert_vacuum_entity(entity_id, state, attr)
+                }
+                EntityType::Remote if entity_id.starts_with("lawn_mower.") => {
+                    convert_lawn_mower_entity(entity_id, state, attr)
+                }
+                EntityType::Remote if entity_id.starts_with("alarm_control_panel.") => {
+                    convert_alarm_control_panel_entity(entity_id, state, attr)
+                }
                 EntityType::Remote => convert_remote_entity(entity_id, state, attr),
-                EntityType::Sensor => convert_sensor_entity(entity_id, state, attr),
+                EntityType::Sensor if is_number_entity(&entity_id) => {
+                    if state == "unknown" {
+                        debug!(
+                            "[{}] Ignoring number entity with unknown state: {entity_id}",
+                            self.id
+                        );
+                        continue;
+                    }
+                    convert_number_entity(entity_id, state, attr)
+                }
+                EntityType::Sensor if is_select_entity(&entity_id) => {
+                    convert_select_entity(entity_id, state, attr)
+                }
+                EntityType::Sensor if is_presence_entity(&entity_id) => {
+                    convert_presence_entity(entity_id, state, attr)
+                }
+                EntityType::Sensor if is_text_entity(&entity_id) => {
+                    convert_text_entity(entity_id, state, attr)
+                }
+                EntityType::Sensor if is_update_entity(&entity_id) => {
+                    convert_update_entity(entity_id, state, attr)
+                }
+                EntityType::Sensor if entity_id.starts_with("weather.") => {
+                    convert_weather_entity(entity_id, state, attr)
+                }
+                EntityType::Sensor => {
+                    convert_sensor_entity(entity_id, state, attr, &self.settings.sensor)
+                }
                 EntityType::IrEmitter => {
                     // no related HA entity
                     continue;
@@ -134,7 +271,46 @@ impl HomeAssistantClient {
             };
 
             match avail_entity {
-                Ok(entity) => available.push(entity),
+                Ok(mut entity) => {
+                    entity.area = self.entity_area(&entity.entity_id);
+                    ensure_entity_name(&mut entity, self.settings.name_fallback);
+                    localize_entity_name(&mut entity, self.language.as_deref());
+                    with_assumed_state(&mut entity, &ha_state);
+                    if entity.entity_type == EntityType::MediaPlayer {
+                        with_media_activities(&mut entity, &self.settings.media_player.activities);
+                        if self.settings.media_player.https_image_url {
+                            if let Some(attributes) = entity.attributes.as_mut() {
+                                upgrade_media_image_url(&self.server, attributes);
+                            }
+                        }
+                        if self.settings.media_player.image_proxy {
+                            if let Some(attributes) = entity.attributes.as_mut() {
+                                proxy_media_image_url(
+                                    &self.settings.media_player.image_proxy_url,
+                                    &remote_entity_id(&self.device_id, &entity.entity_id),
+                                    attributes,
+                                );
+                            }
+                        }
+                    }
+                    if entity.entity_type == EntityType::Climate {
+                        if let Some(conversion) =
+                            self.temperature_conversion(ha_state.attributes.as_ref())
+                        {
+                            conversion.convert_entity(&mut entity);
+                        }
+                    }
+                    let derived = attribute_entities(
+                        &entity,
+                        &ha_state.state,
+                        ha_state.attributes.as_ref(),
+                        &self.settings.attribute_entities,
+                    );
+                    self.entity_states
+                        .insert(entity.entity_id.clone(), ha_state);
+                    available.push(entity);
+                    available.extend(derived);
+                }
                 Err(e) => warn!(
                     "[{}] Could not convert HASS entity {error_id}: {e:?}",
                     self.id
@@ -142,6 +318,95 @@ impl HomeAssistantClient {
             }
         }
 
+        // setpoint numbers might be listed after their climate entity
+        for entity in available.iter_mut() {
+            with_climate_setpoint(
+                entity,
+                &self.entity_states,
+                &self.settings.climate_setpoint_numbers,
+            );
+        }
+        sort_by_favorites(&mut available, &self.settings.favorite_entities);
         Ok(available)
     }
 }
+
+/// Check if entities of a HA domain are exposed to the remote.
+///
+/// A domain must be in the allow-list, if the list is not empty, and must not be in the
+/// deny-list.
+fn is_exposed_domain(domain: &str, allow: &HashSet<String>, deny: &HashSet<String>) -> bool {
+    (allow.is_empty() || allow.contains(domain)) && !deny.contains(domain)
+}
+
+/// Get the entity states array of a `get_states` result.
+///
+/// An error is returned if the result is not an array, e.g. an error object.
+fn entity_states_array(result: Option<Value>) -> Result<Vec<Value>, ServiceError> {
+    match result {
+        Some(Value::Array(entities)) => Ok(entities),
+        Some(v) => Err(ServiceError::BadRequest(format!(
+            "Invalid get_states result, expected an array but got: {v}"
+        ))),
+        None => Err(ServiceError::BadRequest("Missing get_states result".into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn domains(domains: &[&str]) -> HashSet<String> {
+        domains.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[rstest]
+    #[case("light", &[], &[], true)]
+    #[case("light", &["light", "switch"], &[], true)]
+    #[case("sensor", &["light", "switch"], &[], false)]
+    #[case("sensor", &[], &["sensor"], false)]
+    #[case("light", &[], &["sensor"], true)]
+    #[case("light", &["light"], &["light"], false)]
+    fn exposed_domains(
+        #[case] domain: &str,
+        #[case] allow: &[&str],
+        #[case] deny: &[&str],
+        #[case] expected: bool,
+    ) {
+        assert_eq!(
+            expected,
+            is_exposed_domain(domain, &domains(allow), &domains(deny))
+        );
+    }
+
+    #[test]
+    fn entity_states_array_returns_entities() {
+        let result = entity_states_array(Some(json!([
+            { "entity_id": "light.kitchen", "state": "on", "attributes": {} },
+            { "entity_id": "switch.fan", "state": "off", "attributes": {} }
+        ])));
+
+        assert!(
+            result.is_ok(),
+            "Expected successful result but got: {:?}",
+            result.unwrap_err()
+        );
+        assert_eq!(2, result.unwrap().len());
+    }
+
+    #[rstest]
+    #[case(Some(json!({ "code": "unknown_error", "message": "Unknown error" })))]
+    #[case(Some(json!("light.kitchen")))]
+    #[case(Some(Value::Null))]
+    #[case(None)]
+    fn entity_states_array_with_non_array_result_returns_error(#[case] result: Option<Value>) {
+        let result = entity_states_array(result);
+
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Non-array result must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+}
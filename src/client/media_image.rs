@@ -0,0 +1,113 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Actix actor handler implementation for the `GetMediaImageSource` message of the media image
+//! proxy.
+
+use crate::client::entity::media_image_url;
+use crate::client::messages::{GetMediaImageSource, MediaImageSource};
+use crate::client::model::EventState;
+use crate::client::HomeAssistantClient;
+use crate::errors::ServiceError;
+use actix::Handler;
+use url::Url;
+
+impl Handler<GetMediaImageSource> for HomeAssistantClient {
+    type Result = Result<MediaImageSource, ServiceError>;
+
+    fn handle(&mut self, msg: GetMediaImageSource, _ctx: &mut Self::Context) -> Self::Result {
+        let state = self
+            .entity_states
+            .get(&msg.entity_id)
+            .ok_or_else(|| ServiceError::NotFound(format!("Unknown entity: {}", msg.entity_id)))?;
+
+        media_image_source(&self.server, state, &self.access_token).ok_or_else(|| {
+            ServiceError::NotFound(format!("No media image for entity: {}", msg.entity_id))
+        })
+    }
+}
+
+/// Get the media image source of the last known HA entity state.
+///
+/// The access token is only included for images served by the HA server and never sent to an
+/// external image host.
+fn media_image_source(
+    server: &Url,
+    state: &EventState,
+    access_token: &str,
+) -> Option<MediaImageSource> {
+    let entity_picture = state
+        .attributes
+        .as_ref()
+        .and_then(|a| a.get("entity_picture"))
+        .and_then(|v| v.as_str())?;
+    let url = media_image_url(server, entity_picture)?;
+    let access_token = if entity_picture.starts_with('/') {
+        Some(access_token.to_string())
+    } else {
+        None
+    };
+
+    Some(MediaImageSource { url, access_token })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn state(entity_picture: Option<&str>) -> EventState {
+        let mut state = json!({ "state": "playing", "attributes": { "friendly_name": "TV" } });
+        if let Some(entity_picture) = entity_picture {
+            state["attributes"]["entity_picture"] = json!(entity_picture);
+        }
+        serde_json::from_value(state).expect("invalid test data")
+    }
+
+    #[test]
+    fn local_image_includes_access_token() {
+        let server = Url::parse("http://homeassistant.local:8123/").unwrap();
+
+        let source = media_image_source(
+            &server,
+            &state(Some("/api/media_player_proxy/media_player.tv?token=1")),
+            "secret",
+        );
+
+        assert_eq!(
+            Some(MediaImageSource {
+                url:
+                    "http://homeassistant.local:8123/api/media_player_proxy/media_player.tv?token=1"
+                        .into(),
+                access_token: Some("secret".into())
+            }),
+            source
+        );
+    }
+
+    #[test]
+    fn external_image_without_access_token() {
+        let server = Url::parse("http://homeassistant.local:8123/").unwrap();
+
+        let source = media_image_source(
+            &server,
+            &state(Some("https://i.scdn.co/image/ab67616d")),
+            "secret",
+        );
+
+        assert_eq!(
+            Some(MediaImageSource {
+                url: "https://i.scdn.co/image/ab67616d".into(),
+                access_token: None
+            }),
+            source
+        );
+    }
+
+    #[test]
+    fn missing_entity_picture() {
+        let server = Url::parse("http://homeassistant.local:8123/").unwrap();
+
+        assert_eq!(None, media_image_source(&server, &state(None), "secret"));
+    }
+}
@@ -8,10 +8,13 @@ use awc::ws::CloseCode;
 use std::collections::HashSet;
 
 use uc_api::intg::{AvailableIntgEntity, EntityChange, EntityCommand};
+use uc_api::EntityType;
 
 use crate::errors::ServiceError;
 
-/// Call a service in Home Assistant
+/// Call a service in Home Assistant.
+///
+/// The result is returned when Home Assistant confirms the service call, or the request timed out.
 #[derive(Message)]
 #[rtype(result = "Result<(), ServiceError>")]
 pub struct CallService {
@@ -19,7 +22,10 @@ pub struct CallService {
     pub command: EntityCommand,
 }
 
-/// Fetch all states from Home Assistant
+/// Fetch all states from Home Assistant.
+///
+/// The result is returned when Home Assistant responded, or the request timed out. The entities
+/// are sent asynchronously with [`AvailableEntities`].
 #[derive(Message)]
 #[rtype(result = "Result<(), ServiceError>")]
 pub struct GetStates {
@@ -27,7 +33,9 @@ pub struct GetStates {
     pub entity_ids: HashSet<String>,
 }
 
-/// Get available entities from Home Assistant
+/// Get available entities from Home Assistant.
+///
+/// The result is returned when Home Assistant responded, or the request timed out.
 #[derive(Message)]
 #[rtype(result = "Result<(), ServiceError>")]
 pub struct GetAvailableEntities {
@@ -40,6 +48,8 @@ pub struct GetAvailableEntities {
 #[allow(dead_code)] // client_id not used
 pub struct AvailableEntities {
     pub client_id: String,
+    /// Device identifier of the HA server connection
+    pub device_id: String,
     pub entities: Vec<AvailableIntgEntity>,
 }
 
@@ -49,6 +59,8 @@ pub struct AvailableEntities {
 pub struct SetAvailableEntities {
     #[allow(dead_code)]
     pub client_id: String,
+    /// Device identifier of the HA server connection
+    pub device_id: String,
     pub entities: Vec<AvailableIntgEntity>,
 }
 
@@ -72,6 +84,8 @@ pub enum ConnectionState {
 #[rtype(result = "()")]
 pub struct ConnectionEvent {
     pub client_id: String,
+    /// Device identifier of the HA server connection
+    pub device_id: String,
     pub state: ConnectionState,
 }
 
@@ -81,9 +95,37 @@ pub struct ConnectionEvent {
 #[allow(dead_code)] // client_id not used
 pub struct EntityEvent {
     pub client_id: String,
+    /// Device identifier of the HA server connection
+    pub device_id: String,
     pub entity_change: EntityChange,
 }
 
+/// Confirmation of an executed service call of an entity command
+#[derive(Message)]
+#[rtype(result = "()")]
+#[allow(dead_code)] // client_id not used
+pub struct ServiceCallConfirmation {
+    pub client_id: String,
+    /// Device identifier of the HA server connection
+    pub device_id: String,
+    pub entity_id: String,
+    pub entity_type: EntityType,
+    pub cmd_id: String,
+}
+
+/// Availability flip of an entity
+#[derive(Message)]
+#[rtype(result = "()")]
+#[allow(dead_code)] // client_id not used
+pub struct EntityAvailability {
+    pub client_id: String,
+    /// Device identifier of the HA server connection
+    pub device_id: String,
+    pub entity_id: String,
+    pub entity_type: EntityType,
+    pub available: bool,
+}
+
 /// Set remote id from remote to client
 #[derive(Message)]
 #[rtype(result = "Result<(), ServiceError>")]
@@ -91,6 +133,21 @@ pub struct SetRemoteId {
     pub remote_id: String,
 }
 
+/// Get the source of the media image of a media player entity for the media image proxy.
+#[derive(Message)]
+#[rtype(result = "Result<MediaImageSource, ServiceError>")]
+pub struct GetMediaImageSource {
+    pub entity_id: String,
+}
+
+/// Media image URL of a media player entity.
+#[derive(Debug, PartialEq)]
+pub struct MediaImageSource {
+    pub url: String,
+    /// HA access token, only set for images served by the HA server.
+    pub access_token: Option<String>,
+}
+
 /// HA client request: disconnect and close the session.
 // Used internally by the client and from Controller
 #[derive(Message)]
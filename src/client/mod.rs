@@ -3,15 +3,28 @@
 
 //! Home Assistant client WebSocket API implementation with Actix actors.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::env;
+use std::future::Future;
 use std::time::{Duration, Instant};
 
+use crate::client::availability::AvailabilityEvents;
+use crate::client::debounce::UnavailableDebounce;
+use crate::client::echo_filter::EchoFilter;
+use crate::client::event_filter::EventFilter;
 use crate::client::messages::{
-    AvailableEntities, ConnectionEvent, ConnectionState, SetAvailableEntities,
+    AvailableEntities, ConnectionEvent, ConnectionState, ServiceCallConfirmation,
+    SetAvailableEntities,
+};
+use crate::client::model::{Event, EventState};
+use crate::client::pending_requests::{
+    wait_for_result, PendingRequests, RequestError, RequestResult,
+};
+use crate::client::service_confirmation::{PendingCommand, ServiceConfirmation};
+use crate::client::uc_info_retry::{UcInfoAction, UcInfoRetry};
+use crate::configuration::{
+    HeartbeatSettings, HomeAssistantSettings, TemperatureUnitSource, ENV_HASS_MSG_TRACING,
 };
-use crate::client::model::Event;
-use crate::configuration::{HeartbeatSettings, ENV_HASS_MSG_TRACING};
 use crate::errors::ServiceError;
 use crate::Controller;
 use crate::APP_VERSION;
@@ -20,36 +33,61 @@ use actix::{Actor, ActorContext, Addr, AsyncContext, Context, SpawnHandle};
 use actix_codec::Framed;
 use awc::{ws, BoxedSocket};
 use bytes::Bytes;
+use futures::channel::oneshot;
 use futures::stream::{SplitSink, SplitStream};
 use log::{debug, error, info, warn};
 use messages::Close;
+use msg_id::MsgIds;
 use serde::de::Error;
 use serde_json::{json, Value};
 use std::sync::atomic::{AtomicU32, Ordering};
 use url::Url;
 
 mod actor;
+mod area_registry;
+mod assumed_state;
+mod attribute_entities;
+mod availability;
+mod climate_setpoint;
 mod close_handler;
+mod command_retry;
+mod debounce;
+mod device_registry;
+mod echo_filter;
 mod entity;
+mod entity_name;
+mod entity_registry;
 mod event;
+mod event_filter;
+mod favorites;
+mod get_config;
 mod get_entities;
 mod get_states;
+mod media_image;
 pub mod messages;
 mod model;
+mod msg_id;
+mod pending_requests;
 mod service;
+mod service_confirmation;
 mod set_remote_id;
 mod streamhandler;
 mod subscribed_entities;
+mod temperature;
+mod uc_info_retry;
+pub mod verify;
 
 static CLIENT_SEQ: AtomicU32 = AtomicU32::new(1);
 
 pub struct HomeAssistantClient {
     /// Unique HA client id
     id: String,
+    /// Device identifier of the HA server connection
+    device_id: String,
     /// Base server address for media image access (e.g. <http://hassio.local:8123>)
     server: Url,
-    /// HA request message id
-    ws_id: u32,
+    /// HA request message ids
+    msg_ids: MsgIds,
     access_token: String,
     /// True if custom HA component is detected and will use optimized workflows
     uc_ha_component: bool,
@@ -69,7 +107,14 @@ pub struct HomeAssistantClient {
     subscribe_uc_events_id: Option<u32>,
     /// request id of the last `unfoldedcircle/event/configure/subscribe` request. This id will be used in the result and event messages.
     subscribe_configure_id: Option<u32>,
+    /// request id of the `subscribe_events` request for `call_service` events.
+    subscribe_call_service_id: Option<u32>,
     entity_states_id: Option<u32>,
+    /// request id of the last `get_states` request for all HA entities.
+    entity_list_id: Option<u32>,
+    /// All HA entities have been loaded with `get_states` on this connection, required to expand
+    /// domain subscriptions.
+    entity_list_loaded: bool,
     sink: SinkWrite<ws::Message, SplitSink<Framed<BoxedSocket, ws::Codec>, ws::Message>>,
     controller_actor: Addr<Controller>,
     /// Last heart beat timestamp.
@@ -83,20 +128,62 @@ pub struct HomeAssistantClient {
     subscribed_entities: HashSet<String>,
     authenticated: bool,
     remote_id: String,
+    /// Last known HA state of all converted entities.
+    ///
+    /// Used to map command values back to the exact HA representation.
+    entity_states: HashMap<String, EventState>,
+    /// Request id of the `get_config` request
+    get_config_id: Option<u32>,
+    /// Temperature unit of the HA unit system, retrieved with `get_config`
+    temperature_unit: Option<String>,
+    /// Language of the HA configuration, retrieved with `get_config`
+    language: Option<String>,
+    /// Request id of the `config/entity_registry/list` request
+    entity_registry_id: Option<u32>,
+    /// Hidden or disabled entities of the HA entity registry
+    hidden_entities: HashSet<String>,
+    /// Entity id to area id mapping of the HA entity registry
+    entity_areas: HashMap<String, String>,
+    /// Entity id to device id mapping of the HA entity registry
+    entity_devices: HashMap<String, String>,
+    /// Request id of the `config/device_registry/list` request
+    device_registry_id: Option<u32>,
+    /// Device id to area id mapping of the HA device registry
+    device_areas: HashMap<String, String>,
+    /// Request id of the `config/area_registry/list` request
+    area_registry_id: Option<u32>,
+    /// Area id to area name mapping of the HA area registry
+    area_names: HashMap<String, String>,
+    temperature_unit_source: TemperatureUnitSource,
+    unavailable_debounce: UnavailableDebounce,
+    /// Entities whose state change events are not forwarded
+    event_filter: EventFilter,
+    /// State change events caused by own service calls
+    echo_filter: EchoFilter,
+    /// Confirmation of own service calls with `call_service` events
+    service_confirmation: ServiceConfirmation,
+    /// Availability flips of forwarded entity changes
+    availability_events: AvailabilityEvents,
+    /// Requests waiting for the HA result message
+    pending_requests: PendingRequests,
+    /// Retries of the UC HA component info request before falling back to standard events
+    uc_info_retry: UcInfoRetry,
+    settings: HomeAssistantSettings,
 }
 
 impl HomeAssistantClient {
     pub fn start(
+        device_id: String,
         url: Url,
         controller_actor: Addr<Controller>,
         access_token: String,
+        remote_id: String,
         sink: SplitSink<Framed<BoxedSocket, ws::Codec>, ws::Message>,
         stream: SplitStream<Framed<BoxedSocket, ws::Codec>>,
-        heartbeat: HeartbeatSettings,
+        settings: &HomeAssistantSettings,
     ) -> Addr<Self> {
         HomeAssistantClient::create(|ctx| {
             ctx.add_stream(stream);
-            let scheme = url.scheme();
             let host = url.host_str().unwrap_or(url.as_str());
             let port = url.port_or_known_default().unwrap_or_default();
             let msg_tracing = env::var(ENV_HASS_MSG_TRACING).unwrap_or_default();
@@ -107,42 +194,98 @@ impl HomeAssistantClient {
                     port,
                     CLIENT_SEQ.fetch_add(1, Ordering::SeqCst)
                 ),
-                server: {
-                    let mut server = url.clone();
-                    server
-                        .set_scheme(if scheme == "wss" { "https" } else { "http" })
-                        .expect("invalid scheme");
-                    server.set_path("");
-                    server
-                },
-                ws_id: 0,
+                device_id,
+                server: server_base_url(&url),
+                msg_ids: MsgIds::new(),
                 access_token,
                 subscribed_events: false,
                 subscribe_standard_events_id: None,
                 subscribe_uc_events_id: None,
                 entity_states_id: None,
+                entity_list_id: None,
+                entity_list_loaded: false,
                 subscribe_configure_id: None,
+                subscribe_call_service_id: None,
                 sink: SinkWrite::new(sink, ctx),
                 controller_actor,
                 last_hb: Instant::now(),
-                heartbeat,
+                heartbeat: settings.heartbeat,
                 msg_tracing_in: msg_tracing == "all" || msg_tracing == "in",
                 msg_tracing_out: msg_tracing == "all" || msg_tracing == "out",
                 uc_ha_component: false,
                 uc_ha_component_info_id: None,
                 subscribed_entities: HashSet::new(),
                 authenticated: false,
-                remote_id: "".to_string(),
+                remote_id,
                 uc_ha_component_check_interval: Duration::from_secs(5),
                 uc_ha_component_check_duration: None, // check forever
                 uc_ha_comp_check_handle: None,
+                entity_states: HashMap::new(),
+                get_config_id: None,
+                temperature_unit: None,
+                language: None,
+                entity_registry_id: None,
+                hidden_entities: Default::default(),
+                entity_areas: Default::default(),
+                entity_devices: Default::default(),
+                device_registry_id: None,
+                device_areas: Default::default(),
+                area_registry_id: None,
+                area_names: Default::default(),
+                temperature_unit_source: settings.climate_temperature_unit,
+                unavailable_debounce: UnavailableDebounce::new(settings.unavailable_debounce),
+                event_filter: EventFilter::new(settings.disabled_event_entities.clone()),
+                echo_filter: EchoFilter::new(settings.suppress_echo_events),
+                service_confirmation: ServiceConfirmation::new(settings.confirm_service_calls),
+                availability_events: AvailabilityEvents::new(settings.entity_available_events),
+                pending_requests: Default::default(),
+                uc_info_retry: UcInfoRetry::new(settings.uc_component_retries),
+                settings: settings.clone(),
             }
         })
     }
 
     fn new_msg_id(&mut self) -> u32 {
-        self.ws_id += 1;
-        self.ws_id
+        self.msg_ids.next()
+    }
+
+    /// Wait for the HA result message of a sent entity command service call.
+    ///
+    /// The request counts towards the max concurrent commands limit until the result is received.
+    ///
+    /// returns: future resolving to the request result, or a `Timeout` error if HA doesn't respond
+    /// within the configured request timeout.
+    fn request_result(&mut self, id: u32) -> impl Future<Output = RequestResult> + 'static {
+        let request_timeout = Duration::from_secs(self.settings.request_timeout as u64);
+        let result = self.pending_requests.track_command(id);
+        self.wait_for_request(id, result, request_timeout)
+    }
+
+    /// Wait for the HA result message of an entity state request.
+    ///
+    /// Loading the entity states takes much longer than other requests on a large HA installation
+    /// and uses the separate entity request timeout.
+    fn entity_request_result(&mut self, id: u32) -> impl Future<Output = RequestResult> + 'static {
+        let request_timeout = Duration::from_secs(self.settings.entity_request_timeout as u64);
+        let result = self.pending_requests.track_request(id);
+        self.wait_for_request(id, result, request_timeout)
+    }
+
+    fn wait_for_request(
+        &self,
+        id: u32,
+        result: oneshot::Receiver<RequestResult>,
+        request_timeout: Duration,
+    ) -> impl Future<Output = RequestResult> + 'static {
+        let client_id = self.id.clone();
+
+        async move {
+            let result = wait_for_result(result, request_timeout).await;
+            if let Err(RequestError::Timeout(timeout)) = &result {
+                warn!("[{client_id}] Request {id} aborted: no result within {timeout:?}");
+            }
+            result
+        }
     }
 
     fn heartbeat(&self, ctx: &mut Context<Self>) {
@@ -205,6 +348,19 @@ impl HomeAssistantClient {
         {
             "event" => {
                 // debug!("[{}] Event received {}", self.id, text);
+                if Some(id) == self.subscribe_call_service_id {
+                    let context_id = object_msg
+                        .get("event")
+                        .and_then(|v| v.pointer("/context/id"))
+                        .and_then(|v| v.as_str());
+                    if let Some(command) = self
+                        .service_confirmation
+                        .handle_event(context_id, Instant::now())
+                    {
+                        self.send_service_confirmation(command);
+                    }
+                    return;
+                }
                 // TODO should we only check Event.event_type == "state_changed"? The id check worked well though in YIO v1
                 if Some(id) != self.subscribe_standard_events_id
                     && Some(id) != self.subscribe_uc_events_id
@@ -221,6 +377,10 @@ impl HomeAssistantClient {
                         "[{}] Received request from HA for configuring subscribed entities",
                         self.id
                     );
+                    // entity areas might have changed as well, refresh them for the next requests
+                    self.send_entity_registry_list(ctx);
+                    self.send_device_registry_list(ctx);
+                    self.send_area_registry_list(ctx);
                     if let Some(entities) =
                         object_msg.get_mut("event").and_then(|v| v.as_object_mut())
                     {
@@ -234,28 +394,25 @@ impl HomeAssistantClient {
                         //  Obviously the research of entities in the remote's form on HA integration page should be dynamic
                         //  after each keypress/filter applied, a request should be done to the client then to HA to
                         //  get corresponding results
-                        if let Some(entities) =
-                            entities.get_mut("data").and_then(|v| v.as_array_mut())
-                        {
-                            debug!("[{}] {}", self.id, "Sending new entities to subscribe to");
-                            // this looks ugly! Is there a better way to get ownership of the array?
-                            let entities: Vec<Value> =
-                                entities.iter_mut().map(|v| v.take()).collect();
-                            match self.handle_get_states_result(entities) {
-                                Ok(entities) => {
-                                    if let Err(e) =
-                                        self.controller_actor.try_send(SetAvailableEntities {
-                                            client_id: self.id.clone(),
-                                            entities,
-                                        })
-                                    {
-                                        error!("[{}] Error handling HA set available entities result: {:?}", self.id, e);
-                                    }
-                                }
-                                Err(e) => {
+                        debug!("[{}] {}", self.id, "Sending new entities to subscribe to");
+                        match self.handle_get_states_result(entities.remove("data")) {
+                            Ok(entities) => {
+                                if let Err(e) =
+                                    self.controller_actor.try_send(SetAvailableEntities {
+                                        client_id: self.id.clone(),
+                                        device_id: self.device_id.clone(),
+                                        entities,
+                                    })
+                                {
                                     error!("[{}] Error handling HA set available entities result: {:?}", self.id, e);
                                 }
                             }
+                            Err(e) => {
+                                error!(
+                                    "[{}] Error handling HA set available entities result: {:?}",
+                                    self.id, e
+                                );
+                            }
                         }
                     }
                     return;
@@ -267,12 +424,8 @@ impl HomeAssistantClient {
                     object_msg.remove("event").unwrap_or(Value::Null),
                 );
                 if let Ok(event) = event {
-                    if let Err(e) = self.handle_event(event) {
-                        error!(
-                            "[{}] Error handling HA state_changed event: {:?}",
-                            self.id, e
-                        );
-                    }
+                    let events = self.echo_filter.filter_event(event, Instant::now());
+                    self.handle_events(events, ctx);
                 }
             }
             // result messages : sent by HA in response of a previous request, including :
@@ -281,18 +434,48 @@ impl HomeAssistantClient {
             //   with subscribe_events
             // - Request for all entity states (id=entity_states_id) with get_states
             "result" => {
+                if !self.msg_ids.is_current(id) {
+                    warn!(
+                        "[{}] Ignoring stale result of unknown request id {id}",
+                        self.id
+                    );
+                    return;
+                }
                 let success = object_msg
                     .get("success")
                     .and_then(|v| v.as_bool())
                     .unwrap_or_default();
+                if self
+                    .pending_requests
+                    .handle_result(id, success, object_msg.get("error"))
+                {
+                    debug!("[{}] Request {id} result: {success}", self.id);
+                }
+                if let Some(command) = self.service_confirmation.handle_result(
+                    id,
+                    success,
+                    object_msg.get("result"),
+                    Instant::now(),
+                ) {
+                    self.send_service_confirmation(command);
+                }
                 if Some(id) == self.uc_ha_component_info_id {
                     debug!(
                         "[{}] Received HA response for unfoldedcircle/info custom event ({})",
                         self.id, success
                     );
                     // If the unfoldedcircle/info message type is unknown, the UC HA component is not
-                    // installed then we switch back to standard HA events
-                    if !success {
+                    // installed or not yet loaded after a HA restart. Retry with the next component
+                    // check, then switch back to standard HA events
+                    let action = self.uc_info_retry.handle_result(success);
+                    if action == UcInfoAction::Retry {
+                        debug!(
+                            "[{}] UC HA component not yet available, retrying with next check",
+                            self.id
+                        );
+                        return;
+                    }
+                    if action == UcInfoAction::Unavailable {
                         if !self.subscribed_events {
                             self.subscribe_standard_events(ctx);
                         }
@@ -300,6 +483,7 @@ impl HomeAssistantClient {
                     } else {
                         self.controller_actor.do_send(ConnectionEvent {
                             client_id: self.id.clone(),
+                            device_id: self.device_id.clone(),
                             state: ConnectionState::Connected,
                         });
                     }
@@ -341,6 +525,7 @@ impl HomeAssistantClient {
                     } else {
                         self.controller_actor.do_send(ConnectionEvent {
                             client_id: self.id.clone(),
+                            device_id: self.device_id.clone(),
                             state: ConnectionState::Connected,
                         });
                     }
@@ -350,40 +535,93 @@ impl HomeAssistantClient {
                         debug!("[{}] Subscribed to state changes", self.id);
                         self.controller_actor.do_send(ConnectionEvent {
                             client_id: self.id.clone(),
+                            device_id: self.device_id.clone(),
                             state: ConnectionState::Connected,
                         });
                     } else {
                         ctx.notify(Close::invalid());
                     }
+                } else if Some(id) == self.subscribe_call_service_id {
+                    if !success {
+                        warn!(
+                            "[{}] Subscribing to call_service events failed, service calls are not confirmed",
+                            self.id
+                        );
+                        self.subscribe_call_service_id = None;
+                    }
+                } else if Some(id) == self.get_config_id {
+                    self.get_config_id = None;
+                    if success {
+                        self.handle_get_config_result(object_msg.get("result"));
+                    } else {
+                        warn!("[{}] get_config request failed", self.id);
+                    }
+                } else if Some(id) == self.entity_registry_id {
+                    self.entity_registry_id = None;
+                    if success {
+                        self.handle_entity_registry_result(object_msg.get("result"));
+                    } else {
+                        warn!(
+                            "[{}] config/entity_registry/list request failed, hidden entities are not excluded",
+                            self.id
+                        );
+                    }
+                } else if Some(id) == self.device_registry_id {
+                    self.device_registry_id = None;
+                    if success {
+                        self.handle_device_registry_result(object_msg.get("result"));
+                    } else {
+                        warn!(
+                            "[{}] config/device_registry/list request failed, device areas are not used",
+                            self.id
+                        );
+                    }
+                } else if Some(id) == self.area_registry_id {
+                    self.area_registry_id = None;
+                    if success {
+                        self.handle_area_registry_result(object_msg.get("result"));
+                    } else {
+                        warn!(
+                            "[{}] config/area_registry/list request failed, entity areas are not set",
+                            self.id
+                        );
+                    }
+                } else if let Some(events) =
+                    self.echo_filter
+                        .handle_result(id, object_msg.get("result"), Instant::now())
+                {
+                    if !success {
+                        warn!("[{}] call_service request {id} failed", self.id);
+                    }
+                    // state changes received before the result
+                    self.handle_events(events, ctx);
                 } else if Some(id) == self.entity_states_id {
                     if !success {
                         error!("[{}] get_states request failed", self.id);
                         ctx.notify(Close::invalid());
+                        return;
                     }
 
-                    if let Some(entities) =
-                        object_msg.get_mut("result").and_then(|v| v.as_array_mut())
-                    {
-                        // this looks ugly! Is there a better way to get ownership of the array?
-                        let entities: Vec<Value> = entities.iter_mut().map(|v| v.take()).collect();
-                        match self.handle_get_states_result(entities) {
-                            Ok(entities) => {
-                                if let Err(e) = self.controller_actor.try_send(AvailableEntities {
-                                    client_id: self.id.clone(),
-                                    entities,
-                                }) {
-                                    error!(
-                                        "[{}] Error handling HA get_states result: {:?}",
-                                        self.id, e
-                                    );
-                                }
+                    match self.handle_get_states_result(object_msg.remove("result")) {
+                        Ok(entities) => {
+                            if self.entity_list_id == Some(id) {
+                                self.entity_list_id = None;
+                                self.entity_list_loaded = true;
                             }
-                            Err(e) => {
+                            if let Err(e) = self.controller_actor.try_send(AvailableEntities {
+                                client_id: self.id.clone(),
+                                device_id: self.device_id.clone(),
+                                entities,
+                            }) {
                                 error!(
                                     "[{}] Error handling HA get_states result: {:?}",
                                     self.id, e
                                 );
                             }
+                            self.refresh_domain_subscriptions(ctx);
+                        }
+                        Err(e) => {
+                            error!("[{}] Error handling HA get_states result: {:?}", self.id, e);
                         }
                     }
                 }
@@ -408,11 +646,14 @@ impl HomeAssistantClient {
                 );
                 self.controller_actor.do_send(ConnectionEvent {
                     client_id: self.id.clone(),
+                    device_id: self.device_id.clone(),
                     state: ConnectionState::AuthenticationFailed,
                 });
             }
             "auth_ok" => {
                 self.authenticated = true;
+                // entities might flap between unavailable and available after a HA restart
+                self.unavailable_debounce.start(Instant::now());
                 info!(
                     "[{}] Authentication OK. HA version: {}",
                     self.id,
@@ -422,6 +663,18 @@ impl HomeAssistantClient {
                         .unwrap_or_default()
                 );
 
+                // HA system configuration is required for the entity conversion
+                self.send_get_config(ctx);
+                // hidden & disabled entities are excluded from the available entities, and
+                // entity areas are set from the area registry
+                self.send_entity_registry_list(ctx);
+                self.send_device_registry_list(ctx);
+                self.send_area_registry_list(ctx);
+                // own service calls are confirmed with call_service events
+                if self.settings.confirm_service_calls {
+                    self.subscribe_call_service_events(ctx);
+                }
+
                 // Instead of subscribing to standard events which sends events from all entities
                 // we check after the UC HA component then fall back to standard HA events
                 // However the custom messages won't be available right after HA restart so
@@ -429,6 +682,7 @@ impl HomeAssistantClient {
                 // Note : this check should be done right after authentication EXCEPT that
                 // if auth occurs right after HA reboots, custom events won't be available yet
                 // We will have to check after custom events later if unavailable
+                self.uc_info_retry.reset();
                 self.send_uc_info_command(ctx);
                 // Store start time of HA so that we check regularly after custom events
                 let ha_start_time = Instant::now();
@@ -570,6 +824,46 @@ impl HomeAssistantClient {
         }
     }
 
+    /// Subscribe to HA `call_service` events to confirm the service calls of the integration.
+    fn subscribe_call_service_events(&mut self, ctx: &mut Context<HomeAssistantClient>) {
+        let id = self.new_msg_id();
+        self.subscribe_call_service_id = Some(id);
+        if let Err(e) = self.send_json(
+            json!({
+              "id": id,
+              "type": "subscribe_events",
+              "event_type": "call_service"
+            }),
+            ctx,
+        ) {
+            error!(
+                "[{}] Error sending call_service subscribe_events to HA: {:?}",
+                self.id, e
+            );
+            self.subscribe_call_service_id = None;
+        }
+    }
+
+    /// Notify the controller about a confirmed service call of an entity command.
+    fn send_service_confirmation(&self, command: PendingCommand) {
+        debug!(
+            "[{}] Confirmed {} command '{}'",
+            self.id, command.entity_id, command.cmd_id
+        );
+        if let Err(e) = self.controller_actor.try_send(ServiceCallConfirmation {
+            client_id: self.id.clone(),
+            device_id: self.device_id.clone(),
+            entity_id: command.entity_id,
+            entity_type: command.entity_type,
+            cmd_id: command.cmd_id,
+        }) {
+            error!(
+                "[{}] Error sending service call confirmation: {e:?}",
+                self.id
+            );
+        }
+    }
+
     /// Subscribe to configuration events handled by UC HA component
     /// This event is raised when the entities list to subscribe to change from HA side
     fn subscribe_uc_configuration(&mut self, ctx: &mut Context<HomeAssistantClient>) {
@@ -577,18 +871,9 @@ impl HomeAssistantClient {
         if self.subscribe_configure_id.is_some() {
             return;
         }
-        self.subscribe_configure_id = Some(self.new_msg_id());
-        if let Err(e) = self.send_json(
-            json!({
-                "id": self.subscribe_configure_id,
-                "type": "unfoldedcircle/event/configure/subscribe",
-                "data": {
-                    "client_id": self.remote_id,
-                    "version": Some(APP_VERSION.to_string())
-                }
-            }),
-            ctx,
-        ) {
+        let id = self.new_msg_id();
+        self.subscribe_configure_id = Some(id);
+        if let Err(e) = self.send_json(uc_configure_subscribe_msg(id, &self.remote_id), ctx) {
             error!(
                 "[{}] Error sending unfoldedcircle/event/configure/subscribe to HA: {:?}",
                 self.id, e
@@ -629,22 +914,15 @@ impl HomeAssistantClient {
         if self.subscribe_uc_events_id.is_some() {
             return;
         }
-        self.subscribe_uc_events_id = Some(self.new_msg_id());
+        let id = self.new_msg_id();
+        self.subscribe_uc_events_id = Some(id);
         debug!(
             "[{}] Subscribe to unfoldedcircle/event/entities/subscribe events with remote id '{}'",
             self.id, self.remote_id
         );
-        if let Err(e) = self.send_json(
-            json!({
-                "id": self.subscribe_uc_events_id.unwrap(),
-                "type": "unfoldedcircle/event/entities/subscribe",
-                "data": {
-                    "entities": self.subscribed_entities,
-                    "client_id": self.remote_id
-                }
-            }),
-            ctx,
-        ) {
+        let msg =
+            uc_entities_subscribe_msg(id, &self.expanded_subscribed_entities(), &self.remote_id);
+        if let Err(e) = self.send_json(msg, ctx) {
             error!(
                 "[{}] Error sending unfoldedcircle/event/entities/subscribe to HA: {:?}",
                 self.id, e
@@ -725,6 +1003,53 @@ impl HomeAssistantClient {
     }
 }
 
+/// HA WebSocket API path, relative to the HA base URL.
+const HA_WEBSOCKET_PATH: &str = "/api/websocket";
+
+/// Derive the HA base URL from the WebSocket URL.
+///
+/// The path prefix of a HA instance served under a sub-path behind a reverse proxy is kept, e.g.
+/// `wss://example.com/homeassistant/api/websocket` -> `https://example.com/homeassistant`.
+fn server_base_url(ws_url: &Url) -> Url {
+    let mut server = ws_url.clone();
+    server
+        .set_scheme(if ws_url.scheme() == "wss" {
+            "https"
+        } else {
+            "http"
+        })
+        .expect("invalid scheme");
+    let path = ws_url.path().trim_end_matches('/');
+    server.set_path(path.strip_suffix(HA_WEBSOCKET_PATH).unwrap_or_default());
+    server.set_query(None);
+    server.set_fragment(None);
+    server
+}
+
+/// Create the UC HA component `unfoldedcircle/event/configure/subscribe` request message.
+fn uc_configure_subscribe_msg(id: u32, client_id: &str) -> Value {
+    json!({
+        "id": id,
+        "type": "unfoldedcircle/event/configure/subscribe",
+        "data": {
+            "client_id": client_id,
+            "version": APP_VERSION
+        }
+    })
+}
+
+/// Create the UC HA component `unfoldedcircle/event/entities/subscribe` request message.
+fn uc_entities_subscribe_msg(id: u32, entities: &HashSet<String>, client_id: &str) -> Value {
+    json!({
+        "id": id,
+        "type": "unfoldedcircle/event/entities/subscribe",
+        "data": {
+            "entities": entities,
+            "client_id": client_id
+        }
+    })
+}
+
 pub fn json_object_from_text_msg(id: &str, txt: &[u8]) -> Result<Value, serde_json::Error> {
     let msg: Value = match serde_json::from_slice(txt) {
         Ok(v) => v,
@@ -741,3 +1066,60 @@ pub fn json_object_from_text_msg(id: &str, txt: &[u8]) -> Result<Value, serde_js
 
     Ok(msg)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(
+        "ws://homeassistant.local:8123/api/websocket",
+        "http://homeassistant.local:8123/"
+    )]
+    #[case("wss://ha.example.com/api/websocket", "https://ha.example.com/")]
+    #[case(
+        "wss://example.com/homeassistant/api/websocket",
+        "https://example.com/homeassistant"
+    )]
+    #[case(
+        "ws://192.168.1.2:8080/proxy/ha/api/websocket/?access_token=secret",
+        "http://192.168.1.2:8080/proxy/ha"
+    )]
+    #[case(
+        "ws://homeassistant.local:8123/websocket",
+        "http://homeassistant.local:8123/"
+    )]
+    fn server_base_url_keeps_path_prefix(#[case] ws_url: &str, #[case] expected: &str) {
+        let ws_url = Url::parse(ws_url).unwrap();
+
+        assert_eq!(expected, server_base_url(&ws_url).as_str());
+    }
+
+    #[test]
+    fn uc_configure_subscribe_msg_contains_client_id() {
+        let msg = uc_configure_subscribe_msg(3, "RemoteTwo-AABBCCDDEEFF");
+
+        assert_eq!(Some(&json!(3)), msg.get("id"));
+        assert_eq!(
+            Some(&json!("RemoteTwo-AABBCCDDEEFF")),
+            msg.pointer("/data/client_id")
+        );
+    }
+
+    #[test]
+    fn uc_entities_subscribe_msg_contains_client_id() {
+        let entities = HashSet::from(["light.kitchen".to_string()]);
+        let msg = uc_entities_subscribe_msg(4, &entities, "RemoteTwo-AABBCCDDEEFF");
+
+        assert_eq!(Some(&json!(4)), msg.get("id"));
+        assert_eq!(
+            Some(&json!("RemoteTwo-AABBCCDDEEFF")),
+            msg.pointer("/data/client_id")
+        );
+        assert_eq!(
+            Some(&json!(["light.kitchen"])),
+            msg.pointer("/data/entities")
+        );
+    }
+}
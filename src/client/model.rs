@@ -34,8 +34,16 @@ pub(crate) struct EventData {
     pub new_state: EventState,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub(crate) struct EventState {
     pub state: String,
     pub attributes: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Context of the state change
+    #[serde(default)]
+    pub context: Option<EventContext>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct EventContext {
+    pub id: String,
 }
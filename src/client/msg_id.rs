@@ -0,0 +1,83 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Home Assistant WebSocket request message ids.
+//!
+//! The ids are not reset on reconnect, they keep increasing for the lifetime of the process. A
+//! late `result` message of a request sent on a previous connection can't be mistaken for the
+//! result of a new request with the same id.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Last issued request message id of all HA connections.
+static LAST_MSG_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Request message ids of a HA connection.
+#[derive(Debug)]
+pub(crate) struct MsgIds {
+    /// First id of the connection
+    first: u32,
+    /// Last issued id of the connection
+    last: u32,
+}
+
+impl MsgIds {
+    /// Start the message ids of a new connection after the last issued id.
+    pub fn new() -> Self {
+        let last = LAST_MSG_ID.load(Ordering::SeqCst);
+        Self {
+            first: last.wrapping_add(1),
+            last,
+        }
+    }
+
+    /// Get a new request message id.
+    pub fn next(&mut self) -> u32 {
+        self.last = LAST_MSG_ID.fetch_add(1, Ordering::SeqCst).wrapping_add(1);
+        self.last
+    }
+
+    /// Check if the id of a `result` message was issued for the current connection.
+    pub fn is_current(&self, id: u32) -> bool {
+        id >= self.first && id <= self.last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_are_increasing_over_reconnects() {
+        let mut old_connection = MsgIds::new();
+        let first = old_connection.next();
+        let second = old_connection.next();
+        assert!(second > first);
+
+        let mut new_connection = MsgIds::new();
+        let id = new_connection.next();
+        assert!(id > second);
+    }
+
+    #[test]
+    fn stale_result_is_rejected_after_reconnect() {
+        let mut old_connection = MsgIds::new();
+        let stale_id = old_connection.next();
+        assert!(old_connection.is_current(stale_id));
+
+        let mut new_connection = MsgIds::new();
+        assert!(!new_connection.is_current(stale_id));
+        let id = new_connection.next();
+        assert!(new_connection.is_current(id));
+        assert!(!new_connection.is_current(stale_id));
+    }
+
+    #[test]
+    fn unknown_ids_are_rejected() {
+        let mut ids = MsgIds::new();
+        let id = ids.next();
+
+        assert!(!ids.is_current(0));
+        assert!(!ids.is_current(id + 1));
+    }
+}
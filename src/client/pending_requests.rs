@@ -0,0 +1,438 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Correlate HA requests with the `result` response messages.
+//!
+//! The result of a request is forwarded to the waiting caller with a oneshot channel. The caller
+//! waits for the result with [`wait_for_result`], which aborts the request if HA doesn't respond
+//! within the request timeout.
+//!
+//! Requests are correlated by their message id, multiple requests can be in-flight concurrently on
+//! the same HA connection and resolve independently of the order they were sent.
+
+use crate::errors::ServiceError;
+use actix::clock::timeout;
+use futures::channel::oneshot;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Result of a HA request.
+pub(crate) type RequestResult = Result<(), RequestError>;
+
+/// Failed HA request.
+#[derive(Debug, PartialEq)]
+pub(crate) enum RequestError {
+    /// Error result message from HA.
+    Ha { code: String, message: String },
+    /// No result message received within the request timeout.
+    Timeout(Duration),
+    /// Connection closed before the result message was received.
+    NotConnected,
+}
+
+impl RequestError {
+    /// Check if the request failed with a transient HA error, e.g. a device is momentarily
+    /// unreachable.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, RequestError::Ha { code, .. } if code == "home_assistant_error")
+    }
+}
+
+/// Convert a failed HA request.
+///
+/// See <https://developers.home-assistant.io/docs/api/websocket/#error-handling> for HA error codes.
+impl From<RequestError> for ServiceError {
+    fn from(error: RequestError) -> Self {
+        match error {
+            RequestError::Ha { code, message } => match code.as_str() {
+                "not_found" => ServiceError::NotFound(message),
+                "invalid_format" | "service_validation_error" => ServiceError::BadRequest(message),
+                _ => ServiceError::InternalServerError(message),
+            },
+            RequestError::Timeout(timeout) => ServiceError::ServiceUnavailable(format!(
+                "No response from Home Assistant within {}s",
+                timeout.as_secs_f32()
+            )),
+            RequestError::NotConnected => ServiceError::NotConnected,
+        }
+    }
+}
+
+/// Pending HA requests waiting for the result message.
+#[derive(Default)]
+pub(crate) struct PendingRequests {
+    /// Result channel by request id
+    pending: HashMap<u32, oneshot::Sender<RequestResult>>,
+    /// Request ids of the pending `call_service` requests of entity commands
+    commands: HashSet<u32>,
+}
+
+impl PendingRequests {
+    /// Track a sent request.
+    ///
+    /// returns: the receiver of the request result.
+    pub fn track_request(&mut self, id: u32) -> oneshot::Receiver<RequestResult> {
+        // remove timed out requests
+        self.pending.retain(|_, tx| !tx.is_canceled());
+        let (tx, rx) = oneshot::channel();
+        self.pending.insert(id, tx);
+        rx
+    }
+
+    /// Track a sent `call_service` request of an entity command.
+    ///
+    /// Other than [`Self::track_request`], the request is included in [`Self::commands_in_flight`].
+    ///
+    /// returns: the receiver of the request result.
+    pub fn track_command(&mut self, id: u32) -> oneshot::Receiver<RequestResult> {
+        let rx = self.track_request(id);
+        self.commands.insert(id);
+        rx
+    }
+
+    /// Number of in-flight requests still waiting for their result.
+    pub fn in_flight(&mut self) -> usize {
+        self.pending.retain(|_, tx| !tx.is_canceled());
+        self.pending.len()
+    }
+
+    /// Number of in-flight entity commands still waiting for their result.
+    ///
+    /// Other requests, e.g. loading the entity states, are not included.
+    pub fn commands_in_flight(&mut self) -> usize {
+        self.pending.retain(|_, tx| !tx.is_canceled());
+        let pending = &self.pending;
+        self.commands.retain(|id| pending.contains_key(id));
+        self.commands.len()
+    }
+
+    /// Handle a result message and notify the waiting caller.
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: request id of the result message.
+    /// * `success`: `success` field of the result message.
+    /// * `error`: `error` field of the result message with HA error code and message.
+    ///
+    /// returns: true if the result belongs to a pending request.
+    pub fn handle_result(&mut self, id: u32, success: bool, error: Option<&Value>) -> bool {
+        self.commands.remove(&id);
+        let tx = match self.pending.remove(&id) {
+            Some(tx) => tx,
+            None => return false,
+        };
+        let result = if success {
+            Ok(())
+        } else {
+            Err(request_error(error))
+        };
+        // caller might have timed out in the meantime
+        let _ = tx.send(result);
+        true
+    }
+
+    /// Abort all pending requests, e.g. when the connection is closed.
+    pub fn clear(&mut self) {
+        self.commands.clear();
+        for (_, tx) in self.pending.drain() {
+            let _ = tx.send(Err(RequestError::NotConnected));
+        }
+    }
+}
+
+/// Wait for the result of a tracked request.
+///
+/// # Arguments
+///
+/// * `result`: receiver of the request result from [`PendingRequests::track_request`].
+/// * `request_timeout`: max time to wait for the HA result message.
+///
+/// returns: the request result, `Timeout` if HA didn't respond in time, or `NotConnected` if the
+/// connection was closed.
+pub(crate) async fn wait_for_result(
+    result: oneshot::Receiver<RequestResult>,
+    request_timeout: Duration,
+) -> RequestResult {
+    match timeout(request_timeout, result).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err(RequestError::NotConnected),
+        Err(_) => Err(RequestError::Timeout(request_timeout)),
+    }
+}
+
+/// Convert the `error` object of a failed HA result message.
+fn request_error(error: Option<&Value>) -> RequestError {
+    let field = |key: &str| {
+        error
+            .and_then(|e| e.get(key))
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+    };
+
+    RequestError::Ha {
+        code: field("code").unwrap_or_default(),
+        message: field("message").unwrap_or_else(|| "Request failed".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::messages::GetStates;
+    use crate::client::HomeAssistantClient;
+    use crate::configuration::{get_driver_metadata, HomeAssistantSettings, Settings};
+    use crate::Controller;
+    use actix::{Actor, Handler, Message, StreamHandler};
+    use actix_web::{web, App, Error, HttpRequest, HttpResponse, HttpServer};
+    use actix_web_actors::ws;
+    use futures::StreamExt;
+    use rstest::rstest;
+    use serde_json::json;
+    use std::net::TcpListener;
+    use url::Url;
+
+    #[test]
+    fn successful_result_notifies_caller() {
+        let mut requests = PendingRequests::default();
+        let mut rx = requests.track_request(7);
+
+        assert!(requests.handle_result(7, true, None));
+
+        assert_eq!(Ok(Some(Ok(()))), rx.try_recv());
+    }
+
+    #[rstest]
+    #[case("not_found", ServiceError::NotFound("Service light.foo not found.".into()))]
+    #[case(
+        "service_validation_error",
+        ServiceError::BadRequest("Service light.foo not found.".into())
+    )]
+    #[case(
+        "home_assistant_error",
+        ServiceError::InternalServerError("Service light.foo not found.".into())
+    )]
+    fn failed_result_forwards_ha_error(#[case] code: &str, #[case] expected: ServiceError) {
+        let mut requests = PendingRequests::default();
+        let mut rx = requests.track_request(7);
+        let error = json!({ "code": code, "message": "Service light.foo not found." });
+
+        assert!(requests.handle_result(7, false, Some(&error)));
+
+        let result = rx.try_recv().expect("result must be sent");
+        assert_eq!(
+            Some(Err(expected)),
+            result.map(|r| r.map_err(ServiceError::from))
+        );
+    }
+
+    #[rstest]
+    #[case("home_assistant_error", true)]
+    #[case("service_validation_error", false)]
+    #[case("not_found", false)]
+    #[case("unknown_error", false)]
+    fn transient_error(#[case] code: &str, #[case] transient: bool) {
+        let error = request_error(Some(&json!({ "code": code, "message": "error" })));
+
+        assert_eq!(transient, error.is_transient());
+    }
+
+    #[actix::test]
+    async fn concurrent_requests_resolve_independently() {
+        let mut requests = PendingRequests::default();
+        let rx1 = requests.track_request(1);
+        let rx2 = requests.track_request(2);
+        let rx3 = requests.track_request(3);
+        assert_eq!(3, requests.in_flight());
+        let error = json!({ "code": "not_found", "message": "Service light.foo not found." });
+
+        let waiting = futures::future::join3(
+            wait_for_result(rx1, Duration::from_millis(200)),
+            wait_for_result(rx2, Duration::from_millis(200)),
+            wait_for_result(rx3, Duration::from_millis(50)),
+        );
+        // HA responds out of order, request 3 is never answered
+        let responding = async {
+            actix::clock::sleep(Duration::from_millis(10)).await;
+            assert!(requests.handle_result(2, false, Some(&error)));
+            actix::clock::sleep(Duration::from_millis(10)).await;
+            assert!(requests.handle_result(1, true, None));
+        };
+        let ((result1, result2, result3), _) = futures::join!(waiting, responding);
+
+        assert_eq!(Ok(()), result1);
+        assert_eq!(
+            Err(ServiceError::NotFound(
+                "Service light.foo not found.".into()
+            )),
+            result2.map_err(ServiceError::from)
+        );
+        assert_eq!(
+            Err(RequestError::Timeout(Duration::from_millis(50))),
+            result3
+        );
+        assert_eq!(0, requests.in_flight());
+    }
+
+    #[test]
+    fn in_flight_ignores_aborted_requests() {
+        let mut requests = PendingRequests::default();
+        let _rx1 = requests.track_request(1);
+        let rx2 = requests.track_request(2);
+        assert_eq!(2, requests.in_flight());
+
+        drop(rx2);
+
+        assert_eq!(1, requests.in_flight());
+    }
+
+    #[test]
+    fn commands_in_flight_ignores_other_requests() {
+        let mut requests = PendingRequests::default();
+        let _states = requests.track_request(1);
+        let _cmd1 = requests.track_command(2);
+        let cmd2 = requests.track_command(3);
+        let _cmd3 = requests.track_command(4);
+        assert_eq!(4, requests.in_flight());
+        assert_eq!(3, requests.commands_in_flight());
+
+        requests.handle_result(2, true, None);
+        drop(cmd2);
+
+        assert_eq!(2, requests.in_flight());
+        assert_eq!(1, requests.commands_in_flight());
+    }
+
+    #[test]
+    fn unknown_request_id_is_ignored() {
+        let mut requests = PendingRequests::default();
+        let mut rx = requests.track_request(7);
+
+        assert!(!requests.handle_result(8, false, None));
+
+        assert_eq!(Ok(None), rx.try_recv());
+    }
+
+    #[test]
+    fn clear_aborts_all_pending_requests() {
+        let mut requests = PendingRequests::default();
+        let mut rx1 = requests.track_request(1);
+        let mut rx2 = requests.track_request(2);
+
+        requests.clear();
+
+        assert_eq!(Ok(Some(Err(RequestError::NotConnected))), rx1.try_recv());
+        assert_eq!(Ok(Some(Err(RequestError::NotConnected))), rx2.try_recv());
+    }
+
+    #[actix::test]
+    async fn result_within_timeout() {
+        let mut requests = PendingRequests::default();
+        let rx = requests.track_request(7);
+        requests.handle_result(7, true, None);
+
+        let result = wait_for_result(rx, Duration::from_millis(50)).await;
+
+        assert_eq!(Ok(()), result);
+    }
+
+    /// Number of in-flight requests of a running HA client.
+    #[derive(Message)]
+    #[rtype(result = "usize")]
+    struct InFlightRequests;
+
+    impl Handler<InFlightRequests> for HomeAssistantClient {
+        type Result = usize;
+
+        fn handle(&mut self, _msg: InFlightRequests, _ctx: &mut Self::Context) -> Self::Result {
+            self.pending_requests.in_flight()
+        }
+    }
+
+    /// HA WebSocket connection which accepts requests, but never responds.
+    struct SilentHa;
+
+    impl Actor for SilentHa {
+        type Context = ws::WebsocketContext<Self>;
+    }
+
+    impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for SilentHa {
+        fn handle(
+            &mut self,
+            _msg: Result<ws::Message, ws::ProtocolError>,
+            _ctx: &mut Self::Context,
+        ) {
+        }
+    }
+
+    async fn silent_ha(req: HttpRequest, stream: web::Payload) -> Result<HttpResponse, Error> {
+        ws::start(SilentHa, &req, stream)
+    }
+
+    #[actix::test]
+    async fn request_without_response_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("test listener");
+        let port = listener.local_addr().expect("listener address").port();
+        let server =
+            HttpServer::new(|| App::new().route("/api/websocket", web::get().to(silent_ha)))
+                .workers(1)
+                .listen(listener)
+                .expect("test server")
+                .run();
+        let server_handle = server.handle();
+        actix::spawn(server);
+
+        let url = Url::parse(&format!("ws://127.0.0.1:{port}/api/websocket")).unwrap();
+        let (_, framed) = awc::Client::new()
+            .ws(url.as_str())
+            .connect()
+            .await
+            .expect("WebSocket connection to test server");
+        let (sink, stream) = framed.split();
+        let mut settings = HomeAssistantSettings::default();
+        settings.entity_request_timeout = 1;
+        let metadata = get_driver_metadata().expect("driver metadata");
+        let controller = Controller::new(Settings::default(), metadata).start();
+        let client = HomeAssistantClient::start(
+            "main".into(),
+            url,
+            controller,
+            "token".into(),
+            "remote".into(),
+            sink,
+            stream,
+            &settings,
+        );
+
+        let result = client
+            .send(GetStates {
+                remote_id: "remote".into(),
+                entity_ids: Default::default(),
+            })
+            .await
+            .expect("HA client must be running");
+
+        assert!(
+            matches!(result, Err(ServiceError::ServiceUnavailable(_))),
+            "Expected request timeout, but got: {result:?}"
+        );
+        let in_flight = client
+            .send(InFlightRequests)
+            .await
+            .expect("HA client must be running");
+        assert_eq!(0, in_flight, "timed out request must be removed");
+
+        server_handle.stop(false).await;
+    }
+
+    #[actix::test]
+    async fn closed_connection_aborts_waiting_caller() {
+        let mut requests = PendingRequests::default();
+        let rx = requests.track_request(7);
+        drop(requests);
+
+        let result = wait_for_result(rx, Duration::from_millis(50)).await;
+
+        assert_eq!(Err(RequestError::NotConnected), result);
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Alarm control panel entity specific HA service call logic.
+//!
+//! Alarm control panels are exposed as remote entities: the remote entity simple commands are
+//! mapped to the arm & disarm services.
+
+use crate::client::entity::{
+    ALARM_CMD_ARM_AWAY, ALARM_CMD_ARM_HOME, ALARM_CMD_ARM_NIGHT, ALARM_CMD_DISARM,
+};
+use crate::client::model::EventState;
+use crate::client::service::{cmd_from_str, get_required_params};
+use crate::errors::ServiceError;
+use serde_json::{json, Value};
+use uc_api::intg::{EntityCommand, IntgRemoteCommand};
+
+pub(crate) fn handle_alarm_control_panel(
+    msg: &EntityCommand,
+    ha_state: Option<&EventState>,
+) -> Result<(String, Option<Value>), ServiceError> {
+    let cmd: IntgRemoteCommand = cmd_from_str(&msg.cmd_id)?;
+
+    if !matches!(cmd, IntgRemoteCommand::SendCmd) {
+        return Err(ServiceError::BadRequest(format!(
+            "Command not supported for alarm control panel: {}",
+            msg.cmd_id
+        )));
+    }
+
+    let params = get_required_params(msg)?;
+    let command = params
+        .get("command")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let (service, arming) = match command {
+        ALARM_CMD_ARM_HOME => ("alarm_arm_home", true),
+        ALARM_CMD_ARM_AWAY => ("alarm_arm_away", true),
+        ALARM_CMD_ARM_NIGHT => ("alarm_arm_night", true),
+        ALARM_CMD_DISARM => ("alarm_disarm", false),
+        _ => {
+            return Err(ServiceError::BadRequest(format!(
+                "Invalid or missing params.command attribute: {command}"
+            )))
+        }
+    };
+
+    let code = params
+        .get("code")
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.is_empty());
+
+    if code.is_none() && is_code_required(ha_state, arming) {
+        return Err(ServiceError::BadRequest(
+            "Missing params.code attribute: alarm control panel requires a code".into(),
+        ));
+    }
+
+    Ok((service.into(), code.map(|code| json!({ "code": code }))))
+}
+
+/// Check if the alarm control panel requires a code for the service call.
+///
+/// A code is required if the entity has a `code_format`. Arming only requires a code if
+/// `code_arm_required` is set, which is the HA default.
+fn is_code_required(ha_state: Option<&EventState>, arming: bool) -> bool {
+    let attr = match ha_state.and_then(|s| s.attributes.as_ref()) {
+        None => return false,
+        Some(attr) => attr,
+    };
+    if !attr.get("code_format").is_some_and(|v| v.is_string()) {
+        return false;
+    }
+
+    !arming
+        || attr
+            .get("code_arm_required")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("ARM_HOME", "alarm_arm_home")]
+    #[case("ARM_AWAY", "alarm_arm_away")]
+    #[case("ARM_NIGHT", "alarm_arm_night")]
+    #[case("DISARM", "alarm_disarm")]
+    fn send_cmd_without_code(#[case] command: &str, #[case] service: &str) {
+        let result = handle_alarm_control_panel(
+            &new_entity_command(
+                "remote",
+                "alarm_control_panel.home",
+                "send_cmd",
+                Some(json!({ "command": command })),
+            ),
+            Some(&ha_state(json!({ "code_format": null }))),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!(service, cmd);
+        assert!(data.is_none(), "no cmd data allowed");
+    }
+
+    #[test]
+    fn send_cmd_with_code() {
+        let result = handle_alarm_control_panel(
+            &new_entity_command(
+                "remote",
+                "alarm_control_panel.home",
+                "send_cmd",
+                Some(json!({ "command": "DISARM", "code": "1234" })),
+            ),
+            Some(&ha_state(json!({ "code_format": "number" }))),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("alarm_disarm", cmd);
+        assert_eq!(Some(json!({ "code": "1234" })), data);
+    }
+
+    #[test]
+    fn arming_without_code_if_not_required() {
+        let result = handle_alarm_control_panel(
+            &new_entity_command(
+                "remote",
+                "alarm_control_panel.home",
+                "send_cmd",
+                Some(json!({ "command": "ARM_AWAY" })),
+            ),
+            Some(&ha_state(
+                json!({ "code_format": "number", "code_arm_required": false }),
+            )),
+        );
+        assert_eq!(
+            Some(("alarm_arm_away".to_string(), None)),
+            result.ok(),
+            "Arming must not require a code"
+        );
+    }
+
+    #[rstest]
+    #[case("ARM_HOME", json!({ "code_format": "number" }))]
+    #[case("ARM_AWAY", json!({ "code_format": "text", "code_arm_required": true }))]
+    #[case("DISARM", json!({ "code_format": "number", "code_arm_required": false }))]
+    fn missing_code_returns_bad_request(#[case] command: &str, #[case] attributes: Value) {
+        let result = handle_alarm_control_panel(
+            &new_entity_command(
+                "remote",
+                "alarm_control_panel.home",
+                "send_cmd",
+                Some(json!({ "command": command, "code": "" })),
+            ),
+            Some(&ha_state(attributes)),
+        );
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Missing code must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[rstest]
+    #[case("send_cmd", Some(json!({ "command": "TRIGGER" })))]
+    #[case("send_cmd", None)]
+    #[case("on", None)]
+    #[case("toggle", None)]
+    fn invalid_cmd_returns_bad_request(#[case] cmd_id: &str, #[case] params: Option<Value>) {
+        let result = handle_alarm_control_panel(
+            &new_entity_command("remote", "alarm_control_panel.home", cmd_id, params),
+            None,
+        );
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid command must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    fn ha_state(attributes: Value) -> EventState {
+        serde_json::from_value(json!({
+            "state": "disarmed",
+            "attributes": attributes
+        }))
+        .expect("invalid test data")
+    }
+}
@@ -12,12 +12,11 @@ use uc_api::ButtonCommand;
 pub(crate) fn handle_button(msg: &EntityCommand) -> Result<(String, Option<Value>), ServiceError> {
     let cmd: ButtonCommand = cmd_from_str(&msg.cmd_id)?;
 
-    let entity: Vec<&str> = msg.entity_id.split('.').collect();
-
-    let service_call: &str = match entity[0] {
-        "script" => entity[1],
-        "scene" => "turn_on",
-        &_ => "press",
+    // a script is started with `script.turn_on` and the script as target entity
+    let service_call = if msg.entity_id.starts_with("script.") {
+        "turn_on"
+    } else {
+        "press"
     };
 
     let result = match cmd {
@@ -26,3 +25,26 @@ pub(crate) fn handle_button(msg: &EntityCommand) -> Result<(String, Option<Value
 
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("button.restart", "press")]
+    #[case("input_button.doorbell", "press")]
+    #[case("script.movie_mode", "turn_on")]
+    fn push_cmd(#[case] entity_id: &str, #[case] service: &str) {
+        let result = handle_button(&new_entity_command("button", entity_id, "push", None));
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!(service, cmd);
+        assert!(data.is_none(), "no cmd data allowed");
+    }
+}
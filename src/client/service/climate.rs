@@ -3,6 +3,8 @@
 
 //! Climate entity specific HA service call logic.
 
+use crate::client::entity::normalize_mode;
+use crate::client::model::EventState;
 use crate::client::service::{cmd_from_str, get_required_params};
 use crate::errors::ServiceError;
 use crate::util::json::copy_entry;
@@ -10,7 +12,22 @@ use serde_json::{json, Map, Value};
 use uc_api::intg::EntityCommand;
 use uc_api::ClimateCommand;
 
-pub(crate) fn handle_climate(msg: &EntityCommand) -> Result<(String, Option<Value>), ServiceError> {
+/// Map a climate entity command to a HA service call.
+///
+/// The last known HA state of the entity is used to map upper-cased mode values back to the
+/// exact values advertised by the entity.
+pub(crate) fn handle_climate(
+    msg: &EntityCommand,
+    ha_state: Option<&EventState>,
+) -> Result<(String, Option<Value>), ServiceError> {
+    // commands not (yet) defined in the Integration-API ClimateCommand enum
+    match msg.cmd_id.as_str() {
+        "preset_mode" => return set_preset_mode(msg),
+        "fan_mode" => return set_fan_mode(msg, ha_state),
+        "swing_mode" => return set_swing_mode(msg, ha_state),
+        _ => {}
+    }
+
     let cmd: ClimateCommand = cmd_from_str(&msg.cmd_id)?;
 
     let result = match cmd {
@@ -62,9 +79,79 @@ pub(crate) fn handle_climate(msg: &EntityCommand) -> Result<(String, Option<Valu
     Ok(result)
 }
 
+fn set_preset_mode(msg: &EntityCommand) -> Result<(String, Option<Value>), ServiceError> {
+    let params = get_required_params(msg)?;
+    match params.get("preset_mode").and_then(|v| v.as_str()) {
+        Some(preset) if !preset.is_empty() => Ok((
+            "set_preset_mode".into(),
+            Some(json!({ "preset_mode": preset })),
+        )),
+        _ => Err(ServiceError::BadRequest(
+            "Invalid or missing params.preset_mode attribute".into(),
+        )),
+    }
+}
+
+fn set_fan_mode(
+    msg: &EntityCommand,
+    ha_state: Option<&EventState>,
+) -> Result<(String, Option<Value>), ServiceError> {
+    let params = get_required_params(msg)?;
+    match params.get("fan_mode").and_then(|v| v.as_str()) {
+        Some(mode) if !mode.is_empty() => {
+            let fan_mode = ha_mode_value(ha_state, "fan_modes", mode);
+            Ok(("set_fan_mode".into(), Some(json!({ "fan_mode": fan_mode }))))
+        }
+        _ => Err(ServiceError::BadRequest(
+            "Invalid or missing params.fan_mode attribute".into(),
+        )),
+    }
+}
+
+fn set_swing_mode(
+    msg: &EntityCommand,
+    ha_state: Option<&EventState>,
+) -> Result<(String, Option<Value>), ServiceError> {
+    let params = get_required_params(msg)?;
+    match params.get("swing_mode").and_then(|v| v.as_str()) {
+        Some(mode) if !mode.is_empty() => {
+            let swing_mode = ha_mode_value(ha_state, "swing_modes", mode);
+            Ok((
+                "set_swing_mode".into(),
+                Some(json!({ "swing_mode": swing_mode })),
+            ))
+        }
+        _ => Err(ServiceError::BadRequest(
+            "Invalid or missing params.swing_mode attribute".into(),
+        )),
+    }
+}
+
+/// Find the HA value of a normalized mode in the list attribute advertised by the entity.
+///
+/// The comparison is case-insensitive, so that an unexpected casing from HA still round-trips.
+/// Falls back to the lower-cased mode if the entity state or the mode is not known, matching the
+/// common HA modes like `on`, `auto`, `low`, `medium` and `high`.
+fn ha_mode_value(ha_state: Option<&EventState>, modes_attr: &str, mode: &str) -> String {
+    ha_state
+        .and_then(|s| s.attributes.as_ref())
+        .and_then(|attr| attr.get(modes_attr))
+        .and_then(|v| v.as_array())
+        .and_then(|modes| {
+            modes
+                .iter()
+                .filter_map(|v| v.as_str())
+                .find(|v| normalize_mode(v) == normalize_mode(mode))
+        })
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| mode.trim().to_lowercase())
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::client::model::EventState;
     use crate::client::service::climate::handle_climate;
+    use crate::errors::ServiceError;
     use rstest::rstest;
     use serde_json::{json, Value};
     use uc_api::intg::EntityCommand;
@@ -133,9 +220,203 @@ mod tests {
         assert_eq!(Some(&json!(22.5)), data.get("temperature"));
     }
 
+    #[test]
+    fn set_preset_mode() {
+        let msg_data = json!({
+            "cmd_id": "preset_mode",
+            "entity_id": "climate.bathroom_floor_heating_mode",
+            "entity_type": "climate",
+            "params": {
+              "preset_mode": "Energy heat"
+            }
+        });
+        let (cmd, data) = map_msg_data(msg_data);
+        assert_eq!("set_preset_mode", cmd);
+        assert_eq!(Some(json!({ "preset_mode": "Energy heat" })), data);
+    }
+
+    #[rstest]
+    #[case(json!({}))]
+    #[case(json!({ "preset_mode": "" }))]
+    #[case(json!({ "preset_mode": 1 }))]
+    fn set_preset_mode_with_invalid_param_returns_bad_request(#[case] params: Value) {
+        let msg_data = json!({
+            "cmd_id": "preset_mode",
+            "entity_id": "climate.bathroom_floor_heating_mode",
+            "entity_type": "climate",
+            "params": params
+        });
+        let cmd: EntityCommand = serde_json::from_value(msg_data).expect("invalid test data");
+        let result = handle_climate(&cmd, None);
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid value must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[rstest]
+    #[case("AUTO", "auto")]
+    #[case("HIGH", "High")]
+    #[case("Low", "low")]
+    #[case("QUIET", "quiet")]
+    fn set_fan_mode_uses_advertised_ha_value(#[case] uc_mode: &str, #[case] ha_mode: &str) {
+        let msg_data = json!({
+            "cmd_id": "fan_mode",
+            "entity_id": "climate.living_room_ac",
+            "entity_type": "climate",
+            "params": {
+              "fan_mode": uc_mode
+            }
+        });
+        let ha_state: EventState = serde_json::from_value(json!({
+            "state": "cool",
+            "attributes": {
+                "fan_modes": ["auto", "low", "High"],
+                "fan_mode": "auto"
+            }
+        }))
+        .expect("invalid test data");
+        let cmd: EntityCommand = serde_json::from_value(msg_data).expect("invalid test data");
+        let result = handle_climate(&cmd, Some(&ha_state));
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("set_fan_mode", cmd);
+        assert_eq!(Some(json!({ "fan_mode": ha_mode })), data);
+    }
+
+    #[rstest]
+    #[case("ON", "On")]
+    #[case("AUTO", "AUTO")]
+    #[case("LOW", "low")]
+    #[case("MEDIUM", "Medium")]
+    #[case("HIGH", "hIgh")]
+    #[case(" medium ", "Medium")]
+    fn set_fan_mode_round_trips_mixed_case_ha_modes(#[case] uc_mode: &str, #[case] ha_mode: &str) {
+        let msg_data = json!({
+            "cmd_id": "fan_mode",
+            "entity_id": "climate.living_room_ac",
+            "entity_type": "climate",
+            "params": {
+              "fan_mode": uc_mode
+            }
+        });
+        let ha_state: EventState = serde_json::from_value(json!({
+            "state": "cool",
+            "attributes": {
+                "fan_modes": ["On", "AUTO", "low", "Medium", "hIgh"],
+                "fan_mode": "Medium"
+            }
+        }))
+        .expect("invalid test data");
+        let cmd: EntityCommand = serde_json::from_value(msg_data).expect("invalid test data");
+        let result = handle_climate(&cmd, Some(&ha_state));
+
+        assert_eq!(
+            Ok((
+                "set_fan_mode".to_string(),
+                Some(json!({ "fan_mode": ha_mode }))
+            )),
+            result
+        );
+    }
+
+    #[test]
+    fn set_fan_mode_without_entity_state_lower_cases_mode() {
+        let msg_data = json!({
+            "cmd_id": "fan_mode",
+            "entity_id": "climate.living_room_ac",
+            "entity_type": "climate",
+            "params": {
+              "fan_mode": "AUTO"
+            }
+        });
+        let (cmd, data) = map_msg_data(msg_data);
+        assert_eq!("set_fan_mode", cmd);
+        assert_eq!(Some(json!({ "fan_mode": "auto" })), data);
+    }
+
+    #[rstest]
+    #[case(json!({}))]
+    #[case(json!({ "fan_mode": "" }))]
+    #[case(json!({ "fan_mode": true }))]
+    fn set_fan_mode_with_invalid_param_returns_bad_request(#[case] params: Value) {
+        let msg_data = json!({
+            "cmd_id": "fan_mode",
+            "entity_id": "climate.living_room_ac",
+            "entity_type": "climate",
+            "params": params
+        });
+        let cmd: EntityCommand = serde_json::from_value(msg_data).expect("invalid test data");
+        let result = handle_climate(&cmd, None);
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid value must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[rstest]
+    #[case("3D AUTO", "3D Auto")]
+    #[case("3d auto", "3D Auto")]
+    #[case("VERTICAL", "vertical")]
+    #[case("HORIZONTAL", "horizontal")]
+    fn set_swing_mode_uses_advertised_ha_value(#[case] uc_mode: &str, #[case] ha_mode: &str) {
+        let msg_data = json!({
+            "cmd_id": "swing_mode",
+            "entity_id": "climate.living_room_ac",
+            "entity_type": "climate",
+            "params": {
+              "swing_mode": uc_mode
+            }
+        });
+        let ha_state: EventState = serde_json::from_value(json!({
+            "state": "cool",
+            "attributes": {
+                "swing_modes": ["off", "3D Auto", "vertical"],
+                "swing_mode": "off"
+            }
+        }))
+        .expect("invalid test data");
+        let cmd: EntityCommand = serde_json::from_value(msg_data).expect("invalid test data");
+        let result = handle_climate(&cmd, Some(&ha_state));
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("set_swing_mode", cmd);
+        assert_eq!(Some(json!({ "swing_mode": ha_mode })), data);
+    }
+
+    #[rstest]
+    #[case(json!({}))]
+    #[case(json!({ "swing_mode": "" }))]
+    #[case(json!({ "swing_mode": 3 }))]
+    fn set_swing_mode_with_invalid_param_returns_bad_request(#[case] params: Value) {
+        let msg_data = json!({
+            "cmd_id": "swing_mode",
+            "entity_id": "climate.living_room_ac",
+            "entity_type": "climate",
+            "params": params
+        });
+        let cmd: EntityCommand = serde_json::from_value(msg_data).expect("invalid test data");
+        let result = handle_climate(&cmd, None);
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid value must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
     fn map_msg_data(msg_data: Value) -> (String, Option<Value>) {
         let cmd: EntityCommand = serde_json::from_value(msg_data).expect("invalid test data");
-        let result = handle_climate(&cmd);
+        let result = handle_climate(&cmd, None);
         assert!(
             result.is_ok(),
             "Expected successful cmd mapping but got: {:?}",
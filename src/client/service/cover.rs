@@ -3,13 +3,25 @@
 
 //! Cover entity specific HA service call logic.
 
-use crate::client::service::cmd_from_str;
+use crate::client::entity::{
+    COVER_CMD_TILT, COVER_CMD_TILT_DOWN, COVER_CMD_TILT_STOP, COVER_CMD_TILT_UP,
+};
+use crate::client::service::{cmd_from_str, get_required_params};
 use crate::errors::ServiceError;
-use serde_json::{Map, Value};
+use serde_json::{json, Map, Value};
 use uc_api::intg::EntityCommand;
 use uc_api::CoverCommand;
 
 pub(crate) fn handle_cover(msg: &EntityCommand) -> Result<(String, Option<Value>), ServiceError> {
+    // tilt commands not defined in the Integration-API CoverCommand enum
+    match msg.cmd_id.as_str() {
+        COVER_CMD_TILT => return set_tilt_position(msg),
+        COVER_CMD_TILT_UP => return Ok(("open_cover_tilt".into(), None)),
+        COVER_CMD_TILT_DOWN => return Ok(("close_cover_tilt".into(), None)),
+        COVER_CMD_TILT_STOP => return Ok(("stop_cover_tilt".into(), None)),
+        _ => {}
+    }
+
     let cmd: CoverCommand = cmd_from_str(&msg.cmd_id)?;
 
     let result = match cmd {
@@ -24,8 +36,85 @@ pub(crate) fn handle_cover(msg: &EntityCommand) -> Result<(String, Option<Value>
                 }
             }
             ("set_cover_position".into(), Some(data.into()))
-        } // TODO implement tilt command #6
+        }
     };
 
     Ok(result)
 }
+
+fn set_tilt_position(msg: &EntityCommand) -> Result<(String, Option<Value>), ServiceError> {
+    let params = get_required_params(msg)?;
+    match params.get("tilt_position").and_then(|v| v.as_u64()) {
+        Some(pos @ 0..=100) => Ok((
+            "set_cover_tilt_position".into(),
+            Some(json!({ "tilt_position": pos })),
+        )),
+        _ => Err(ServiceError::BadRequest(
+            "Invalid or missing params.tilt_position attribute".into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("tilt_up", "open_cover_tilt")]
+    #[case("tilt_down", "close_cover_tilt")]
+    #[case("tilt_stop", "stop_cover_tilt")]
+    fn tilt_cmd(#[case] cmd_id: &str, #[case] service: &str) {
+        let result = handle_cover(&new_entity_command(
+            "cover",
+            "cover.living_room",
+            cmd_id,
+            None,
+        ));
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!(service, cmd);
+        assert!(data.is_none(), "no cmd data allowed");
+    }
+
+    #[test]
+    fn tilt_position_cmd() {
+        let result = handle_cover(&new_entity_command(
+            "cover",
+            "cover.living_room",
+            "tilt",
+            Some(json!({ "tilt_position": 30 })),
+        ));
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("set_cover_tilt_position", cmd);
+        assert_eq!(Some(json!({ "tilt_position": 30 })), data);
+    }
+
+    #[rstest]
+    #[case(Some(json!({ "tilt_position": 101 })))]
+    #[case(Some(json!({ "position": 30 })))]
+    #[case(None)]
+    fn tilt_position_cmd_with_invalid_params_returns_bad_request(#[case] params: Option<Value>) {
+        let result = handle_cover(&new_entity_command(
+            "cover",
+            "cover.living_room",
+            "tilt",
+            params,
+        ));
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid tilt position must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+}
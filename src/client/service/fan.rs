@@ -0,0 +1,97 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Fan entity specific HA service call logic.
+//!
+//! Fans are exposed as switch entities with an additional direction command.
+
+use crate::client::entity::FAN_CMD_DIRECTION;
+use crate::client::service::{cmd_from_str, get_required_params};
+use crate::errors::ServiceError;
+use serde_json::{json, Value};
+use uc_api::intg::EntityCommand;
+use uc_api::SwitchCommand;
+
+pub(crate) fn handle_fan(msg: &EntityCommand) -> Result<(String, Option<Value>), ServiceError> {
+    // fan specific command not defined in the Integration-API SwitchCommand enum
+    if msg.cmd_id == FAN_CMD_DIRECTION {
+        return set_direction(msg);
+    }
+
+    let cmd: SwitchCommand = cmd_from_str(&msg.cmd_id)?;
+
+    let result = match cmd {
+        SwitchCommand::On => ("turn_on".into(), None),
+        SwitchCommand::Off => ("turn_off".into(), None),
+        SwitchCommand::Toggle => ("toggle".into(), None),
+    };
+
+    Ok(result)
+}
+
+fn set_direction(msg: &EntityCommand) -> Result<(String, Option<Value>), ServiceError> {
+    let params = get_required_params(msg)?;
+    match params.get("direction").and_then(|v| v.as_str()) {
+        Some(direction @ ("forward" | "reverse")) => Ok((
+            "set_direction".into(),
+            Some(json!({ "direction": direction })),
+        )),
+        _ => Err(ServiceError::BadRequest(
+            "Invalid or missing params.direction attribute. Valid: forward, reverse".into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("forward")]
+    #[case("reverse")]
+    fn direction_cmd(#[case] direction: &str) {
+        let result = handle_fan(&new_entity_command(
+            "switch",
+            "fan.ceiling",
+            "direction",
+            Some(json!({ "direction": direction })),
+        ));
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("set_direction", cmd);
+        assert_eq!(Some(json!({ "direction": direction })), data);
+    }
+
+    #[rstest]
+    #[case(Some(json!({ "direction": "left" })))]
+    #[case(Some(json!({ "direction": "FORWARD" })))]
+    #[case(None)]
+    fn direction_cmd_with_invalid_params_returns_bad_request(#[case] params: Option<Value>) {
+        let result = handle_fan(&new_entity_command(
+            "switch",
+            "fan.ceiling",
+            "direction",
+            params,
+        ));
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid direction must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[rstest]
+    #[case("on", "turn_on")]
+    #[case("off", "turn_off")]
+    #[case("toggle", "toggle")]
+    fn on_off_toggle(#[case] cmd_id: &str, #[case] service: &str) {
+        let result = handle_fan(&new_entity_command("switch", "fan.ceiling", cmd_id, None));
+        assert_eq!(Some(service.to_string()), result.ok().map(|(cmd, _)| cmd));
+    }
+}
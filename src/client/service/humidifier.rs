@@ -0,0 +1,201 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Humidifier entity specific HA service call logic.
+//!
+//! Humidifiers are exposed as switch entities with additional humidity and mode commands.
+
+use crate::client::entity::{HUMIDIFIER_CMD_HUMIDITY, HUMIDIFIER_CMD_MODE};
+use crate::client::model::EventState;
+use crate::client::service::{cmd_from_str, get_required_params, validate_list_value};
+use crate::errors::ServiceError;
+use serde_json::{json, Value};
+use uc_api::intg::EntityCommand;
+use uc_api::SwitchCommand;
+
+pub(crate) fn handle_humidifier(
+    msg: &EntityCommand,
+    ha_state: Option<&EventState>,
+) -> Result<(String, Option<Value>), ServiceError> {
+    // humidifier specific commands not defined in the Integration-API SwitchCommand enum
+    if msg.cmd_id == HUMIDIFIER_CMD_HUMIDITY {
+        return set_humidity(msg, ha_state);
+    }
+    if msg.cmd_id == HUMIDIFIER_CMD_MODE {
+        return set_mode(msg, ha_state);
+    }
+
+    let cmd: SwitchCommand = cmd_from_str(&msg.cmd_id)?;
+
+    let result = match cmd {
+        SwitchCommand::On => ("turn_on".into(), None),
+        SwitchCommand::Off => ("turn_off".into(), None),
+        SwitchCommand::Toggle => ("toggle".into(), None),
+    };
+
+    Ok(result)
+}
+
+fn set_humidity(
+    msg: &EntityCommand,
+    ha_state: Option<&EventState>,
+) -> Result<(String, Option<Value>), ServiceError> {
+    let params = get_required_params(msg)?;
+    let humidity = match params.get("humidity").and_then(|v| v.as_u64()) {
+        Some(humidity @ 0..=100) => humidity,
+        _ => {
+            return Err(ServiceError::BadRequest(
+                "Invalid or missing params.humidity attribute".into(),
+            ))
+        }
+    };
+
+    let attr = ha_state.and_then(|s| s.attributes.as_ref());
+    let min = attr
+        .and_then(|a| a.get("min_humidity"))
+        .and_then(|v| v.as_f64());
+    let max = attr
+        .and_then(|a| a.get("max_humidity"))
+        .and_then(|v| v.as_f64());
+    if min.is_some_and(|min| (humidity as f64) < min)
+        || max.is_some_and(|max| (humidity as f64) > max)
+    {
+        return Err(ServiceError::BadRequest(format!(
+            "Humidity {humidity} is out of range: {}..{}",
+            min.unwrap_or(0.0),
+            max.unwrap_or(100.0)
+        )));
+    }
+
+    Ok(("set_humidity".into(), Some(json!({ "humidity": humidity }))))
+}
+
+fn set_mode(
+    msg: &EntityCommand,
+    ha_state: Option<&EventState>,
+) -> Result<(String, Option<Value>), ServiceError> {
+    let params = get_required_params(msg)?;
+    let mode = match params.get("mode").and_then(|v| v.as_str()) {
+        Some(mode) if !mode.is_empty() => mode,
+        _ => {
+            return Err(ServiceError::BadRequest(
+                "Invalid or missing params.mode attribute".into(),
+            ))
+        }
+    };
+
+    // validate against the available modes, if known
+    validate_list_value(ha_state, "available_modes", "mode", mode)?;
+
+    Ok(("set_mode".into(), Some(json!({ "mode": mode }))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("on", "turn_on")]
+    #[case("off", "turn_off")]
+    #[case("toggle", "toggle")]
+    fn on_off_toggle(#[case] cmd_id: &str, #[case] service: &str) {
+        let result = handle_humidifier(
+            &new_entity_command("switch", "humidifier.bedroom", cmd_id, None),
+            None,
+        );
+        assert_eq!(Some(service.to_string()), result.ok().map(|(cmd, _)| cmd));
+    }
+
+    #[rstest]
+    #[case(30)]
+    #[case(55)]
+    #[case(80)]
+    fn humidity_cmd(#[case] humidity: u64) {
+        let result = handle_humidifier(
+            &new_entity_command(
+                "switch",
+                "humidifier.bedroom",
+                "humidity",
+                Some(json!({ "humidity": humidity })),
+            ),
+            Some(&ha_state()),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("set_humidity", cmd);
+        assert_eq!(Some(json!({ "humidity": humidity })), data);
+    }
+
+    #[rstest]
+    #[case(Some(json!({ "humidity": 29 })))]
+    #[case(Some(json!({ "humidity": 81 })))]
+    #[case(Some(json!({ "humidity": -1 })))]
+    #[case(Some(json!({ "humidity": "50" })))]
+    #[case(None)]
+    fn humidity_cmd_with_invalid_params_returns_bad_request(#[case] params: Option<Value>) {
+        let result = handle_humidifier(
+            &new_entity_command("switch", "humidifier.bedroom", "humidity", params),
+            Some(&ha_state()),
+        );
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid humidity must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn mode_cmd() {
+        let result = handle_humidifier(
+            &new_entity_command(
+                "switch",
+                "humidifier.bedroom",
+                "mode",
+                Some(json!({ "mode": "eco" })),
+            ),
+            Some(&ha_state()),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("set_mode", cmd);
+        assert_eq!(Some(json!({ "mode": "eco" })), data);
+    }
+
+    #[rstest]
+    #[case(Some(json!({ "mode": "turbo" })))]
+    #[case(Some(json!({ "mode": "" })))]
+    #[case(None)]
+    fn mode_cmd_with_invalid_params_returns_bad_request(#[case] params: Option<Value>) {
+        let result = handle_humidifier(
+            &new_entity_command("switch", "humidifier.bedroom", "mode", params),
+            Some(&ha_state()),
+        );
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid mode must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    fn ha_state() -> EventState {
+        serde_json::from_value(json!({
+            "state": "on",
+            "attributes": {
+                "min_humidity": 30,
+                "max_humidity": 80,
+                "available_modes": ["normal", "eco", "sleep"]
+            }
+        }))
+        .expect("invalid test data")
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Lawn mower entity specific HA service call logic.
+//!
+//! Lawn mowers are exposed as remote entities: the remote entity commands are mapped to lawn mower
+//! services.
+
+use crate::client::entity::{
+    LAWN_MOWER_CMD_DOCK, LAWN_MOWER_CMD_PAUSE, LAWN_MOWER_CMD_START_MOWING,
+};
+use crate::client::service::{cmd_from_str, get_required_params};
+use crate::errors::ServiceError;
+use serde_json::Value;
+use uc_api::intg::{EntityCommand, IntgRemoteCommand};
+
+pub(crate) fn handle_lawn_mower(
+    msg: &EntityCommand,
+) -> Result<(String, Option<Value>), ServiceError> {
+    let cmd: IntgRemoteCommand = cmd_from_str(&msg.cmd_id)?;
+
+    let result = match cmd {
+        IntgRemoteCommand::On => ("start_mowing".into(), None),
+        IntgRemoteCommand::Off => ("dock".into(), None),
+        IntgRemoteCommand::SendCmd => {
+            let params = get_required_params(msg)?;
+            let command = params
+                .get("command")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            let service = match command {
+                LAWN_MOWER_CMD_START_MOWING => "start_mowing",
+                LAWN_MOWER_CMD_PAUSE => "pause",
+                LAWN_MOWER_CMD_DOCK => "dock",
+                _ => {
+                    return Err(ServiceError::BadRequest(format!(
+                        "Invalid or missing params.command attribute: {command}"
+                    )))
+                }
+            };
+            (service.into(), None)
+        }
+        IntgRemoteCommand::Toggle | IntgRemoteCommand::SendCmdSequence => {
+            return Err(ServiceError::BadRequest(format!(
+                "Command not supported for lawn mower: {}",
+                msg.cmd_id
+            )))
+        }
+    };
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case("START_MOWING", "start_mowing")]
+    #[case("PAUSE", "pause")]
+    #[case("DOCK", "dock")]
+    fn send_cmd(#[case] command: &str, #[case] service: &str) {
+        let result = handle_lawn_mower(&new_entity_command(
+            "remote",
+            "lawn_mower.automower",
+            "send_cmd",
+            Some(json!({ "command": command })),
+        ));
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!(service, cmd);
+        assert!(data.is_none(), "no cmd data allowed");
+    }
+
+    #[rstest]
+    #[case("on", "start_mowing")]
+    #[case("off", "dock")]
+    fn on_off(#[case] cmd_id: &str, #[case] service: &str) {
+        let result = handle_lawn_mower(&new_entity_command(
+            "remote",
+            "lawn_mower.automower",
+            cmd_id,
+            None,
+        ));
+        assert_eq!(Some(service.to_string()), result.ok().map(|(cmd, _)| cmd));
+    }
+
+    #[rstest]
+    #[case("send_cmd", Some(json!({ "command": "RETURN_HOME" })))]
+    #[case("send_cmd", Some(json!({ "command": "start_mowing" })))]
+    #[case("send_cmd", None)]
+    #[case("toggle", None)]
+    fn invalid_cmd_returns_bad_request(#[case] cmd_id: &str, #[case] params: Option<Value>) {
+        let result = handle_lawn_mower(&new_entity_command(
+            "remote",
+            "lawn_mower.automower",
+            cmd_id,
+            params,
+        ));
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid command must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+}
@@ -3,23 +3,38 @@
 
 //! Light entity specific HA service call logic.
 
-use crate::client::service::cmd_from_str;
+use crate::client::entity::LIGHT_CMD_EFFECT;
+use crate::client::service::{cmd_from_str, get_required_params};
 use crate::errors::ServiceError;
 use serde_json::{json, Map, Value};
 use uc_api::intg::EntityCommand;
 use uc_api::LightCommand;
 
 pub(crate) fn handle_light(msg: &EntityCommand) -> Result<(String, Option<Value>), ServiceError> {
+    // light specific command not defined in the Integration-API LightCommand enum
+    if msg.cmd_id == LIGHT_CMD_EFFECT {
+        return set_effect(msg);
+    }
+
     let cmd: LightCommand = cmd_from_str(&msg.cmd_id)?;
 
     let result = match cmd {
         LightCommand::On => {
             let mut data = Map::new();
             if let Some(params) = msg.params.as_ref() {
+                // the Integration-API brightness uses the same 0..255 range as HA
                 if let Some(brightness @ 0..=255) =
                     params.get("brightness").and_then(|v| v.as_u64())
                 {
                     data.insert("brightness".into(), Value::Number(brightness.into()));
+                } else if let Some(brightness_pct @ 0..=100) =
+                    params.get("brightness_pct").and_then(|v| v.as_u64())
+                {
+                    // brightness is always sent and reported in the 0..255 range
+                    data.insert(
+                        "brightness".into(),
+                        Value::Number(brightness_percent_to_255(brightness_pct).into()),
+                    );
                 }
                 if let Some(color_temp_pct) =
                     params.get("color_temperature").and_then(|v| v.as_u64())
@@ -38,16 +53,52 @@ pub(crate) fn handle_light(msg: &EntityCommand) -> Result<(String, Option<Value>
                         data.insert("hs_color".into(), json!([hue, saturation * 100 / 255]));
                     }
                 }
+                if let Some(transition) = transition_secs(params) {
+                    data.insert("transition".into(), transition.into());
+                }
             }
             ("turn_on".into(), Some(Value::Object(data)))
         }
-        LightCommand::Off => ("turn_off".into(), None),
+        LightCommand::Off => {
+            let data = msg
+                .params
+                .as_ref()
+                .and_then(transition_secs)
+                .map(|transition| json!({ "transition": transition }));
+            ("turn_off".into(), data)
+        }
         LightCommand::Toggle => ("Toggle".into(), None),
     };
 
     Ok(result)
 }
 
+fn set_effect(msg: &EntityCommand) -> Result<(String, Option<Value>), ServiceError> {
+    let params = get_required_params(msg)?;
+    match params.get("effect").and_then(|v| v.as_str()) {
+        Some(effect) if !effect.is_empty() => {
+            Ok(("turn_on".into(), Some(json!({ "effect": effect }))))
+        }
+        _ => Err(ServiceError::BadRequest(
+            "Invalid or missing params.effect attribute".into(),
+        )),
+    }
+}
+
+/// Convert a brightness percentage 0..100 into the HA brightness range 0..255.
+fn brightness_percent_to_255(value: u64) -> u64 {
+    (value * 255 + 50) / 100
+}
+
+/// Get the optional transition time in seconds from the `transition` command parameter in
+/// milliseconds.
+fn transition_secs(params: &Map<String, Value>) -> Option<f64> {
+    params
+        .get("transition")
+        .and_then(|v| v.as_u64())
+        .map(|ms| ms as f64 / 1000.0)
+}
+
 fn color_temp_percent_to_mired(
     value: u64,
     min_mireds: u16,
@@ -71,9 +122,153 @@ fn color_temp_percent_to_mired(
 
 #[cfg(test)]
 mod tests {
-    use crate::client::service::light::color_temp_percent_to_mired;
+    use crate::client::entity::map_light_attributes;
+    use crate::client::service::light::{
+        brightness_percent_to_255, color_temp_percent_to_mired, handle_light,
+    };
+    use crate::client::service::new_entity_command;
     use crate::errors::ServiceError;
     use rstest::rstest;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn effect_cmd_returns_turn_on_with_effect() {
+        let result = handle_light(&new_entity_command(
+            "light",
+            "light.led_strip",
+            "effect",
+            Some(json!({ "effect": "colorloop" })),
+        ));
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("turn_on", cmd);
+        assert_eq!(Some(json!({ "effect": "colorloop" })), data);
+    }
+
+    #[rstest]
+    #[case(Some(json!({ "effect": "" })))]
+    #[case(Some(json!({ "effect": 1 })))]
+    #[case(None)]
+    fn effect_cmd_with_invalid_params_returns_bad_request(#[case] params: Option<Value>) {
+        let result = handle_light(&new_entity_command(
+            "light",
+            "light.led_strip",
+            "effect",
+            params,
+        ));
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid effect must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[rstest]
+    #[case(Some(json!({ "brightness": 128, "transition": 1500 })), json!({ "brightness": 128, "transition": 1.5 }))]
+    #[case(Some(json!({ "hue": 180, "saturation": 255, "transition": 250 })), json!({ "hs_color": [180, 100], "transition": 0.25 }))]
+    #[case(Some(json!({ "transition": 2000 })), json!({ "transition": 2.0 }))]
+    #[case(Some(json!({ "brightness": 128 })), json!({ "brightness": 128 }))]
+    #[case(None, json!({}))]
+    fn on_cmd_with_transition(#[case] params: Option<Value>, #[case] expected: Value) {
+        let result = handle_light(&new_entity_command(
+            "light",
+            "light.led_strip",
+            "on",
+            params,
+        ));
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("turn_on", cmd);
+        assert_eq!(Some(expected), data);
+    }
+
+    #[rstest]
+    #[case(Some(json!({ "transition": 500 })), Some(json!({ "transition": 0.5 })))]
+    #[case(Some(json!({ "transition": "slow" })), None)]
+    #[case(None, None)]
+    fn off_cmd_with_transition(#[case] params: Option<Value>, #[case] expected: Option<Value>) {
+        let result = handle_light(&new_entity_command(
+            "light",
+            "light.led_strip",
+            "off",
+            params,
+        ));
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("turn_off", cmd);
+        assert_eq!(expected, data);
+    }
+
+    #[rstest]
+    #[case(0, 0)]
+    #[case(1, 3)]
+    #[case(50, 128)]
+    #[case(99, 252)]
+    #[case(100, 255)]
+    fn brightness_percent_to_255_returns_scaled_values(#[case] input: u64, #[case] expected: u64) {
+        assert_eq!(expected, brightness_percent_to_255(input));
+    }
+
+    #[rstest]
+    #[case(json!({ "brightness_pct": 50 }), json!({ "brightness": 128 }))]
+    #[case(json!({ "brightness_pct": 100 }), json!({ "brightness": 255 }))]
+    #[case(json!({ "brightness_pct": 101 }), json!({}))]
+    #[case(json!({ "brightness": 10, "brightness_pct": 100 }), json!({ "brightness": 10 }))]
+    fn on_cmd_with_brightness_pct(#[case] params: Value, #[case] expected: Value) {
+        let result = handle_light(&new_entity_command(
+            "light",
+            "light.led_strip",
+            "on",
+            Some(params),
+        ));
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("turn_on", cmd);
+        assert_eq!(Some(expected), data);
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(1)]
+    #[case(128)]
+    #[case(255)]
+    fn brightness_round_trip_keeps_ha_range(#[case] brightness: u64) {
+        let mut attr = json!({ "color_mode": "brightness", "brightness": brightness });
+        let attributes = map_light_attributes("light.led_strip", "on", attr.as_object_mut())
+            .expect("Expected successful attribute mapping");
+        let reported = attributes.get("brightness").cloned();
+        assert_eq!(Some(json!(brightness)), reported);
+
+        let result = handle_light(&new_entity_command(
+            "light",
+            "light.led_strip",
+            "on",
+            Some(json!({ "brightness": reported })),
+        ));
+        assert_eq!(
+            Some((
+                "turn_on".to_string(),
+                Some(json!({ "brightness": brightness }))
+            )),
+            result.ok()
+        );
+    }
 
     #[test]
     fn color_temp_percent_to_mired_with_invalid_input_returns_err() {
@@ -0,0 +1,170 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Lock entity specific HA service call logic.
+//!
+//! Locks are exposed as switch entities: on = lock, off = unlock.
+
+use crate::client::model::EventState;
+use crate::client::service::cmd_from_str;
+use crate::errors::ServiceError;
+use log::warn;
+use regex::Regex;
+use serde_json::{json, Value};
+use uc_api::intg::EntityCommand;
+use uc_api::SwitchCommand;
+
+pub(crate) fn handle_lock(
+    msg: &EntityCommand,
+    ha_state: Option<&EventState>,
+) -> Result<(String, Option<Value>), ServiceError> {
+    let cmd: SwitchCommand = cmd_from_str(&msg.cmd_id)?;
+
+    let service = match cmd {
+        SwitchCommand::On => "lock",
+        SwitchCommand::Off => "unlock",
+        SwitchCommand::Toggle => {
+            return Err(ServiceError::BadRequest(
+                "Toggle is not supported for locks".into(),
+            ))
+        }
+    };
+
+    let code = msg
+        .params
+        .as_ref()
+        .and_then(|p| p.get("code"))
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.is_empty());
+    let code_format = ha_state
+        .and_then(|s| s.attributes.as_ref())
+        .and_then(|attr| attr.get("code_format"))
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.is_empty());
+
+    match (code_format, code) {
+        (Some(_), None) => Err(ServiceError::BadRequest(
+            "Missing params.code attribute: lock requires a code".into(),
+        )),
+        (Some(code_format), Some(code)) => {
+            validate_code(code_format, code)?;
+            Ok((service.into(), Some(json!({ "code": code }))))
+        }
+        (None, Some(code)) => Ok((service.into(), Some(json!({ "code": code })))),
+        (None, None) => Ok((service.into(), None)),
+    }
+}
+
+/// Validate a lock code against the HA `code_format` regular expression.
+///
+/// The code must match at the beginning like HA does with `re.match`. An invalid regular
+/// expression is not validated and left to HA.
+fn validate_code(code_format: &str, code: &str) -> Result<(), ServiceError> {
+    let regex = match Regex::new(&format!("^(?:{code_format})")) {
+        Ok(regex) => regex,
+        Err(e) => {
+            warn!("Not validating lock code, invalid code_format '{code_format}': {e}");
+            return Ok(());
+        }
+    };
+
+    if regex.is_match(code) {
+        Ok(())
+    } else {
+        Err(ServiceError::BadRequest(format!(
+            "Invalid code: doesn't match the required code format {code_format}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+
+    fn ha_state(code_format: Option<&str>) -> EventState {
+        serde_json::from_value(json!({
+            "state": "locked",
+            "attributes": match code_format {
+                Some(code_format) => json!({ "code_format": code_format }),
+                None => json!({}),
+            }
+        }))
+        .expect("invalid test data")
+    }
+
+    #[rstest]
+    #[case("on", "lock")]
+    #[case("off", "unlock")]
+    fn lock_with_valid_code(#[case] cmd_id: &str, #[case] service: &str) {
+        let ha_state = ha_state(Some("^\\d{4}$"));
+        let result = handle_lock(
+            &new_entity_command(
+                "switch",
+                "lock.front_door",
+                cmd_id,
+                Some(json!({ "code": "1234" })),
+            ),
+            Some(&ha_state),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!(service, cmd);
+        assert_eq!(Some(json!({ "code": "1234" })), data);
+    }
+
+    #[rstest]
+    #[case(Some(json!({ "code": "12345" })))]
+    #[case(Some(json!({ "code": "12a4" })))]
+    #[case(Some(json!({ "code": "" })))]
+    #[case(None)]
+    fn lock_with_invalid_code_returns_bad_request(#[case] params: Option<Value>) {
+        let ha_state = ha_state(Some("^\\d{4}$"));
+        let result = handle_lock(
+            &new_entity_command("switch", "lock.front_door", "off", params),
+            Some(&ha_state),
+        );
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid code must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn code_format_is_matched_at_start() {
+        assert!(validate_code("\\d{4}", "1234").is_ok());
+        assert!(validate_code("\\d{4}", "x1234").is_err());
+    }
+
+    #[test]
+    fn lock_without_code_format() {
+        let ha_state = ha_state(None);
+        let result = handle_lock(
+            &new_entity_command("switch", "lock.front_door", "on", None),
+            Some(&ha_state),
+        );
+
+        let (cmd, data) = result.expect("Expected successful cmd mapping");
+        assert_eq!("lock", cmd);
+        assert!(data.is_none(), "no cmd data expected");
+    }
+
+    #[test]
+    fn toggle_returns_bad_request() {
+        let result = handle_lock(
+            &new_entity_command("switch", "lock.front_door", "toggle", None),
+            None,
+        );
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Toggle must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+}
@@ -3,35 +3,54 @@
 
 //! Media player entity specific HA service call logic.
 
-use crate::client::service::{cmd_from_str, get_required_params};
+use crate::client::entity::{
+    CMD_ACTIVITY, CMD_PLAY_MEDIA, SUPPORT_NEXT_TRACK, SUPPORT_PAUSE, SUPPORT_PLAY,
+    SUPPORT_PREVIOUS_TRACK, SUPPORT_STOP, SUPPORT_TURN_OFF,
+};
+use crate::client::model::EventState;
+use crate::client::service::{
+    cmd_from_str, get_required_params, unknown_value, validate_list_value,
+};
+use crate::configuration::{MediaActivity, MediaPlayerOffMode, MediaPlayerSettings};
 use crate::errors::ServiceError;
+use log::info;
 use serde_json::{json, Map, Value};
 use uc_api::intg::EntityCommand;
 use uc_api::MediaPlayerCommand;
 
-pub fn handle_media_player(msg: &EntityCommand) -> Result<(String, Option<Value>), ServiceError> {
+pub fn handle_media_player(
+    msg: &EntityCommand,
+    ha_state: Option<&EventState>,
+    settings: &MediaPlayerSettings,
+) -> Result<(String, Option<Value>), ServiceError> {
+    // media player specific command not defined in the Integration-API MediaPlayerCommand enum
+    if msg.cmd_id == CMD_PLAY_MEDIA {
+        return play_media(msg);
+    }
+
     let cmd: MediaPlayerCommand = cmd_from_str(&msg.cmd_id)?;
 
     let result = match cmd {
         MediaPlayerCommand::On => ("turn_on".into(), None),
-        MediaPlayerCommand::Off => ("turn_off".into(), None),
+        MediaPlayerCommand::Off => (off_service(ha_state, settings.off_mode).into(), None),
         MediaPlayerCommand::Toggle => ("toggle".into(), None),
-        MediaPlayerCommand::PlayPause => ("media_play_pause".into(), None),
-        MediaPlayerCommand::Stop => ("media_stop".into(), None),
-        MediaPlayerCommand::Previous => ("media_previous_track".into(), None),
-        MediaPlayerCommand::Next => ("media_next_track".into(), None),
+        MediaPlayerCommand::PlayPause => (play_pause_service(ha_state).into(), None),
+        MediaPlayerCommand::Stop => (stop_service(ha_state)?.into(), None),
+        MediaPlayerCommand::Previous => (
+            supported_service(ha_state, SUPPORT_PREVIOUS_TRACK, "media_previous_track")?.into(),
+            None,
+        ),
+        MediaPlayerCommand::Next => (
+            supported_service(ha_state, SUPPORT_NEXT_TRACK, "media_next_track")?.into(),
+            None,
+        ),
         MediaPlayerCommand::Seek => {
-            let mut data = Map::new();
             let params = get_required_params(msg)?;
-            // TODO test and verify seeking! Docs says: platform dependent...
-            if let Some(value) = params.get("media_position").and_then(|v| v.as_u64()) {
-                data.insert("seek_position".into(), value.into());
-            } else {
-                return Err(ServiceError::BadRequest(
-                    "Invalid or missing params.media_position attribute".into(),
-                ));
-            }
-            ("media_seek".into(), Some(data.into()))
+            let position = seek_position(params.get("media_position"), ha_state)?;
+            (
+                "media_seek".into(),
+                Some(json!({ "seek_position": position })),
+            )
         }
         MediaPlayerCommand::Volume => {
             let mut data = Map::new();
@@ -45,13 +64,34 @@ pub fn handle_media_player(msg: &EntityCommand) -> Result<(String, Option<Value>
             }
             ("volume_set".into(), Some(data.into()))
         }
-        MediaPlayerCommand::VolumeUp => ("volume_up".into(), None),
-        MediaPlayerCommand::VolumeDown => ("volume_down".into(), None),
-        MediaPlayerCommand::FastForward
-        | MediaPlayerCommand::Rewind
-        | MediaPlayerCommand::MuteToggle => {
+        MediaPlayerCommand::VolumeUp => volume_step(ha_state, settings.volume_step as i32)
+            .unwrap_or_else(|| ("volume_up".into(), None)),
+        MediaPlayerCommand::VolumeDown => volume_step(ha_state, -(settings.volume_step as i32))
+            .unwrap_or_else(|| ("volume_down".into(), None)),
+        MediaPlayerCommand::FastForward | MediaPlayerCommand::Rewind => {
             return Err(ServiceError::BadRequest("Not supported".into()))
         }
+        MediaPlayerCommand::MuteToggle => {
+            // not supported by HA: invert the last known mute state
+            let muted = match ha_state
+                .and_then(|s| s.attributes.as_ref())
+                .and_then(|attr| attr.get("is_volume_muted"))
+                .and_then(|v| v.as_bool())
+            {
+                Some(muted) => muted,
+                None => {
+                    info!(
+                        "{}: unknown mute state, muting media player for mute toggle",
+                        msg.entity_id
+                    );
+                    false
+                }
+            };
+            (
+                "volume_mute".into(),
+                Some(json!({ "is_volume_muted": !muted })),
+            )
+        }
         MediaPlayerCommand::Mute => (
             "volume_mute".into(),
             Some(json!({ "is_volume_muted": true })),
@@ -64,7 +104,7 @@ pub fn handle_media_player(msg: &EntityCommand) -> Result<(String, Option<Value>
             let mut data = Map::new();
             let params = get_required_params(msg)?;
             if let Some(repeat) = params.get("repeat").and_then(|v| v.as_str()) {
-                data.insert("repeat".into(), repeat.to_lowercase().into());
+                data.insert("repeat".into(), repeat_mode(repeat)?.into());
             } else {
                 return Err(ServiceError::BadRequest(
                     "Invalid or missing params.repeat attribute".into(),
@@ -127,6 +167,7 @@ pub fn handle_media_player(msg: &EntityCommand) -> Result<(String, Option<Value>
             let mut data = Map::new();
             let params = get_required_params(msg)?;
             if let Some(source) = params.get("source").and_then(|v| v.as_str()) {
+                validate_list_value(ha_state, "source_list", "source", source)?;
                 data.insert("source".into(), source.into());
             } else {
                 return Err(ServiceError::BadRequest(
@@ -139,6 +180,7 @@ pub fn handle_media_player(msg: &EntityCommand) -> Result<(String, Option<Value>
             let mut data = Map::new();
             let params = get_required_params(msg)?;
             if let Some(mode) = params.get("mode").and_then(|v| v.as_str()) {
+                validate_list_value(ha_state, "sound_mode_list", "sound mode", mode)?;
                 data.insert("sound_mode".into(), mode.into());
             } else {
                 return Err(ServiceError::BadRequest(
@@ -152,28 +194,240 @@ pub fn handle_media_player(msg: &EntityCommand) -> Result<(String, Option<Value>
     Ok(result)
 }
 
+/// Get the seek position in seconds, clamped to the `media_duration` of the last known state.
+fn seek_position(
+    position: Option<&Value>,
+    ha_state: Option<&EventState>,
+) -> Result<Value, ServiceError> {
+    let position = match position.and_then(|v| v.as_f64()) {
+        Some(position) if position.is_finite() && position >= 0.0 => position,
+        _ => {
+            return Err(ServiceError::BadRequest(
+                "Invalid or missing params.media_position attribute".into(),
+            ))
+        }
+    };
+    let duration = ha_state
+        .and_then(|s| s.attributes.as_ref())
+        .and_then(|attr| attr.get("media_duration"))
+        .and_then(|v| v.as_f64())
+        .filter(|v| v.is_finite() && *v > 0.0);
+
+    match duration {
+        Some(duration) if position > duration => Ok(number_value(duration)),
+        _ => Ok(number_value(position)),
+    }
+}
+
+/// Convert a number to a JSON integer if it has no fractional part.
+fn number_value(value: f64) -> Value {
+    if value.fract() == 0.0 && value <= u64::MAX as f64 {
+        (value as u64).into()
+    } else {
+        value.into()
+    }
+}
+
+/// Map the repeat mode of the remote to the HA `repeat_set` mode.
+fn repeat_mode(repeat: &str) -> Result<&'static str, ServiceError> {
+    match repeat.to_uppercase().as_str() {
+        "OFF" => Ok("off"),
+        "ALL" => Ok("all"),
+        "ONE" => Ok("one"),
+        _ => Err(ServiceError::BadRequest(format!(
+            "Invalid repeat mode: {repeat}. Valid modes: OFF, ALL, ONE"
+        ))),
+    }
+}
+
+/// Get the HA service for the off command.
+///
+/// The standby mode requires a media player supporting stop. If the supported features are not
+/// known, stop is assumed to be supported.
+fn off_service(ha_state: Option<&EventState>, off_mode: MediaPlayerOffMode) -> &'static str {
+    if off_mode == MediaPlayerOffMode::TurnOff {
+        return "turn_off";
+    }
+
+    match supported_features(ha_state) {
+        Some(features) if features & SUPPORT_STOP == 0 && features & SUPPORT_TURN_OFF > 0 => {
+            "turn_off"
+        }
+        _ => "media_stop",
+    }
+}
+
+/// Get the HA service for the play/pause command based on the current playback state.
+///
+/// A playing media player is paused, or stopped if it doesn't support pause. Otherwise playback
+/// is started. The HA `media_play_pause` service is used if the state is not known.
+fn play_pause_service(ha_state: Option<&EventState>) -> &'static str {
+    let features = supported_features(ha_state);
+    let supports = |feature: u32| features.map(|f| f & feature > 0).unwrap_or(true);
+
+    match ha_state.map(|s| s.state.as_str()) {
+        Some("playing") if supports(SUPPORT_PAUSE) => "media_pause",
+        Some("playing") if supports(SUPPORT_STOP) => "media_stop",
+        Some("paused" | "idle" | "on" | "standby" | "buffering") if supports(SUPPORT_PLAY) => {
+            "media_play"
+        }
+        _ => "media_play_pause",
+    }
+}
+
+/// Get the HA service for the stop command.
+///
+/// Media players without stop support are paused instead. If the supported features are not
+/// known, stop is assumed to be supported.
+fn stop_service(ha_state: Option<&EventState>) -> Result<&'static str, ServiceError> {
+    match supported_features(ha_state) {
+        Some(features) if features & SUPPORT_STOP > 0 => Ok("media_stop"),
+        Some(features) if features & SUPPORT_PAUSE > 0 => Ok("media_pause"),
+        Some(_) => Err(ServiceError::BadRequest(
+            "Media player doesn't support stop or pause".into(),
+        )),
+        None => Ok("media_stop"),
+    }
+}
+
+/// Check if the media player supports a service.
+///
+/// If the supported features are not known, the service is assumed to be supported.
+fn supported_service(
+    ha_state: Option<&EventState>,
+    feature: u32,
+    service: &'static str,
+) -> Result<&'static str, ServiceError> {
+    match supported_features(ha_state) {
+        Some(features) if features & feature == 0 => Err(ServiceError::BadRequest(format!(
+            "Media player doesn't support {service}"
+        ))),
+        _ => Ok(service),
+    }
+}
+
+/// Get the HA `supported_features` bitmask of the last known entity state.
+fn supported_features(ha_state: Option<&EventState>) -> Option<u32> {
+    ha_state
+        .and_then(|s| s.attributes.as_ref())
+        .and_then(|attr| attr.get("supported_features"))
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32)
+}
+
+/// Calculate an explicit `volume_set` service call for a volume up or down command.
+///
+/// # Arguments
+///
+/// * `ha_state`: last known HA state with the current `volume_level` attribute.
+/// * `step`: volume change in percent. 0 = use HA `volume_up` & `volume_down` services.
+///
+/// returns: `None` if disabled or the current volume is not known.
+fn volume_step(ha_state: Option<&EventState>, step: i32) -> Option<(String, Option<Value>)> {
+    if step == 0 {
+        return None;
+    }
+    let volume_level = ha_state
+        .and_then(|s| s.attributes.as_ref())
+        .and_then(|attr| attr.get("volume_level"))
+        .and_then(|v| v.as_f64())?;
+
+    // calculate in percent to avoid floating point artifacts like 0.30000000000000004
+    let volume = ((volume_level * 100.0).round() as i32 + step).clamp(0, 100);
+    Some((
+        "volume_set".into(),
+        Some(json!({ "volume_level": volume as f64 / 100.0 })),
+    ))
+}
+
+/// Map an activity command to the HA service calls of the configured media player activity.
+///
+/// The media player is turned on first, if configured, followed by the input source and sound
+/// mode selection.
+pub(crate) fn handle_activity(
+    msg: &EntityCommand,
+    settings: &MediaPlayerSettings,
+) -> Result<Vec<(String, Option<Value>)>, ServiceError> {
+    if msg.cmd_id != CMD_ACTIVITY {
+        return Err(ServiceError::BadRequest(format!(
+            "Invalid cmd_id: {}",
+            msg.cmd_id
+        )));
+    }
+    let params = get_required_params(msg)?;
+    let name = match params.get("activity").and_then(|v| v.as_str()) {
+        Some(name) if !name.is_empty() => name,
+        _ => {
+            return Err(ServiceError::BadRequest(
+                "Invalid or missing params.activity attribute".into(),
+            ))
+        }
+    };
+    let activities: Vec<&MediaActivity> = settings
+        .activities
+        .iter()
+        .filter(|a| a.entity_id == msg.entity_id)
+        .collect();
+    let activity = activities.iter().find(|a| a.name == name).ok_or_else(|| {
+        unknown_value("activity", name, activities.iter().map(|a| a.name.as_str()))
+    })?;
+
+    let mut calls = Vec::with_capacity(3);
+    if activity.turn_on {
+        calls.push(("turn_on".into(), None));
+    }
+    if let Some(source) = activity.source.as_ref() {
+        calls.push(("select_source".into(), Some(json!({ "source": source }))));
+    }
+    if let Some(sound_mode) = activity.sound_mode.as_ref() {
+        calls.push((
+            "select_sound_mode".into(),
+            Some(json!({ "sound_mode": sound_mode })),
+        ));
+    }
+    if calls.is_empty() {
+        return Err(ServiceError::BadRequest(format!(
+            "Activity '{name}' doesn't define any action"
+        )));
+    }
+
+    Ok(calls)
+}
+
+fn play_media(msg: &EntityCommand) -> Result<(String, Option<Value>), ServiceError> {
+    let params = get_required_params(msg)?;
+    let mut data = Map::new();
+    for field in ["media_content_id", "media_content_type"] {
+        match params.get(field).and_then(|v| v.as_str()) {
+            Some(value) if !value.is_empty() => {
+                data.insert(field.into(), value.into());
+            }
+            _ => {
+                return Err(ServiceError::BadRequest(format!(
+                    "Invalid or missing params.{field} attribute"
+                )))
+            }
+        }
+    }
+    // optional: play, next, add, replace
+    if let Some(enqueue) = params.get("enqueue").and_then(|v| v.as_str()) {
+        data.insert("enqueue".into(), enqueue.to_lowercase().into());
+    }
+
+    Ok(("play_media".into(), Some(data.into())))
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::client::service::media_player::handle_media_player;
+    use crate::client::entity::map_media_player_attributes;
+    use crate::client::model::EventState;
+    use crate::client::service::media_player::{handle_activity, handle_media_player};
+    use crate::client::service::new_entity_command;
+    use crate::configuration::{MediaActivity, MediaPlayerOffMode, MediaPlayerSettings};
     use crate::errors::ServiceError;
     use rstest::rstest;
     use serde_json::{json, Map, Value};
-    use uc_api::intg::EntityCommand;
-    use uc_api::EntityType;
-
-    fn new_entity_command(cmd_id: impl Into<String>, params: Value) -> EntityCommand {
-        EntityCommand {
-            device_id: None,
-            entity_type: EntityType::MediaPlayer,
-            entity_id: "test".into(),
-            cmd_id: cmd_id.into(),
-            params: if params.is_object() {
-                Some(params.as_object().unwrap().clone())
-            } else {
-                None
-            },
-        }
-    }
+    use url::Url;
 
     #[rstest]
     #[case(json!(0), json!(0.0))] // TODO find a safer way to compare floats, this might blow any time
@@ -181,8 +435,13 @@ mod tests {
     #[case(json!(50), json!(0.5))]
     #[case(json!(100), json!(1.0))]
     fn volume_cmd_returns_proper_request(#[case] volume: Value, #[case] output: Value) {
-        let cmd = new_entity_command("volume", json!({ "volume": volume }));
-        let result = handle_media_player(&cmd);
+        let cmd = new_entity_command(
+            "media_player",
+            "test",
+            "volume",
+            Some(json!({ "volume": volume })),
+        );
+        let result = handle_media_player(&cmd, None, &Default::default());
 
         assert!(
             result.is_ok(),
@@ -204,8 +463,13 @@ mod tests {
     #[case(json!(true))]
     #[case(json!(false))]
     fn volume_cmd_with_invalid_volume_param_returns_bad_request(#[case] volume: Value) {
-        let cmd = new_entity_command("volume", json!({ "volume": volume }));
-        let result = handle_media_player(&cmd);
+        let cmd = new_entity_command(
+            "media_player",
+            "test",
+            "volume",
+            Some(json!({ "volume": volume })),
+        );
+        let result = handle_media_player(&cmd, None, &Default::default());
 
         assert!(
             matches!(result, Err(ServiceError::BadRequest(_))),
@@ -214,12 +478,89 @@ mod tests {
         );
     }
 
+    #[test]
+    fn volume_cmd_round_trips_with_attribute_mapping() {
+        let server = Url::parse("http://homeassistant.local:8123").unwrap();
+        for volume in 0..=100 {
+            let cmd = new_entity_command(
+                "media_player",
+                "test",
+                "volume",
+                Some(json!({ "volume": volume })),
+            );
+            let (_, data) = handle_media_player(&cmd, None, &Default::default())
+                .expect("Valid volume must return Ok");
+            let mut ha_attr = data.expect("Param object missing");
+
+            let attributes = map_media_player_attributes(
+                &server,
+                "media_player.test",
+                "on",
+                ha_attr.as_object_mut(),
+            )
+            .expect("Expected successful attribute mapping");
+
+            assert_eq!(Some(&json!(volume)), attributes.get("volume"));
+        }
+    }
+
     #[rstest]
     #[case(Value::Null)]
     #[case(Value::Object(Map::new()))]
     fn volume_cmd_with_invalid_param_object_returns_bad_request(#[case] params: Value) {
-        let cmd = new_entity_command("volume", params);
-        let result = handle_media_player(&cmd);
+        let cmd = new_entity_command("media_player", "test", "volume", Some(params));
+        let result = handle_media_player(&cmd, None, &Default::default());
+
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid value must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[rstest]
+    #[case(json!(0), None, json!(0))]
+    #[case(json!(42), None, json!(42))]
+    #[case(json!(42.5), None, json!(42.5))]
+    #[case(json!(120), Some(json!(300)), json!(120))]
+    #[case(json!(301), Some(json!(300)), json!(300))]
+    #[case(json!(500), Some(json!(212.5)), json!(212.5))]
+    #[case(json!(42), Some(json!(0)), json!(42))]
+    fn seek_cmd_returns_proper_request(
+        #[case] position: Value,
+        #[case] duration: Option<Value>,
+        #[case] output: Value,
+    ) {
+        let ha_state: EventState = serde_json::from_value(json!({
+            "state": "playing",
+            "attributes": { "media_duration": duration, "media_position": 10 }
+        }))
+        .expect("invalid test data");
+        let cmd = new_entity_command(
+            "media_player",
+            "test",
+            "seek",
+            Some(json!({ "media_position": position })),
+        );
+        let result = handle_media_player(&cmd, Some(&ha_state), &Default::default());
+
+        assert_eq!(
+            Ok((
+                "media_seek".into(),
+                Some(json!({ "seek_position": output }))
+            )),
+            result
+        );
+    }
+
+    #[rstest]
+    #[case(json!({ "media_position": -1 }))]
+    #[case(json!({ "media_position": -0.5 }))]
+    #[case(json!({ "media_position": "42" }))]
+    #[case(json!({}))]
+    fn seek_cmd_with_invalid_position_returns_bad_request(#[case] params: Value) {
+        let cmd = new_entity_command("media_player", "test", "seek", Some(params));
+        let result = handle_media_player(&cmd, None, &Default::default());
 
         assert!(
             matches!(result, Err(ServiceError::BadRequest(_))),
@@ -227,4 +568,559 @@ mod tests {
             result
         );
     }
+
+    #[rstest]
+    #[case("OFF", "off")]
+    #[case("ALL", "all")]
+    #[case("ONE", "one")]
+    #[case("one", "one")]
+    fn repeat_cmd_returns_proper_request(#[case] repeat: &str, #[case] output: &str) {
+        let cmd = new_entity_command(
+            "media_player",
+            "test",
+            "repeat",
+            Some(json!({ "repeat": repeat })),
+        );
+        let result = handle_media_player(&cmd, None, &Default::default());
+
+        assert_eq!(
+            Ok(("repeat_set".into(), Some(json!({ "repeat": output })))),
+            result
+        );
+    }
+
+    #[rstest]
+    #[case(json!({ "repeat": "SHUFFLE" }))]
+    #[case(json!({ "repeat": "" }))]
+    #[case(json!({ "repeat": true }))]
+    #[case(json!({}))]
+    fn repeat_cmd_with_invalid_mode_returns_bad_request(#[case] params: Value) {
+        let cmd = new_entity_command("media_player", "test", "repeat", Some(params));
+        let result = handle_media_player(&cmd, None, &Default::default());
+
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid value must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[rstest]
+    #[case(true)]
+    #[case(false)]
+    fn shuffle_cmd_returns_proper_request(#[case] shuffle: bool) {
+        let cmd = new_entity_command(
+            "media_player",
+            "test",
+            "shuffle",
+            Some(json!({ "shuffle": shuffle })),
+        );
+        let result = handle_media_player(&cmd, None, &Default::default());
+
+        assert_eq!(
+            Ok(("shuffle_set".into(), Some(json!({ "shuffle": shuffle })))),
+            result
+        );
+    }
+
+    #[rstest]
+    #[case(json!({ "shuffle": "true" }))]
+    #[case(json!({ "shuffle": 1 }))]
+    #[case(json!({}))]
+    fn shuffle_cmd_with_invalid_param_returns_bad_request(#[case] params: Value) {
+        let cmd = new_entity_command("media_player", "test", "shuffle", Some(params));
+        let result = handle_media_player(&cmd, None, &Default::default());
+
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid value must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn play_media_cmd_returns_proper_request() {
+        let cmd = new_entity_command(
+            "media_player",
+            "test",
+            "play_media",
+            Some(json!({
+                "media_content_id": "https://example.com/radio.mp3",
+                "media_content_type": "music"
+            })),
+        );
+        let result = handle_media_player(&cmd, None, &Default::default());
+
+        assert!(
+            result.is_ok(),
+            "Valid value must return Ok, but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, param) = result.unwrap();
+        assert_eq!("play_media", &cmd);
+        assert_eq!(
+            Some(json!({
+                "media_content_id": "https://example.com/radio.mp3",
+                "media_content_type": "music"
+            })),
+            param
+        );
+    }
+
+    #[test]
+    fn play_media_cmd_with_enqueue_returns_proper_request() {
+        let cmd = new_entity_command(
+            "media_player",
+            "test",
+            "play_media",
+            Some(json!({
+                "media_content_id": "spotify:playlist:37i9dQZF1DXcBWIGoYBM5M",
+                "media_content_type": "playlist",
+                "enqueue": "ADD"
+            })),
+        );
+        let result = handle_media_player(&cmd, None, &Default::default());
+
+        let (_, param) = result.expect("Valid value must return Ok");
+        assert_eq!(Some(&json!("add")), param.unwrap().get("enqueue"));
+    }
+
+    #[rstest]
+    #[case(Value::Null)]
+    #[case(json!({ "media_content_id": "https://example.com/radio.mp3" }))]
+    #[case(json!({ "media_content_type": "music" }))]
+    #[case(json!({ "media_content_id": "", "media_content_type": "music" }))]
+    #[case(json!({ "media_content_id": 1, "media_content_type": "music" }))]
+    fn play_media_cmd_with_missing_params_returns_bad_request(#[case] params: Value) {
+        let cmd = new_entity_command("media_player", "test", "play_media", Some(params));
+        let result = handle_media_player(&cmd, None, &Default::default());
+
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid value must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    fn activity_settings() -> MediaPlayerSettings {
+        MediaPlayerSettings {
+            activities: vec![
+                MediaActivity {
+                    name: "Movie".into(),
+                    entity_id: "test".into(),
+                    turn_on: true,
+                    source: Some("HDMI 1".into()),
+                    sound_mode: Some("Dolby Surround".into()),
+                },
+                MediaActivity {
+                    name: "Music".into(),
+                    entity_id: "test".into(),
+                    turn_on: false,
+                    source: None,
+                    sound_mode: Some("Stereo".into()),
+                },
+                MediaActivity {
+                    name: "TV".into(),
+                    entity_id: "media_player.other".into(),
+                    turn_on: true,
+                    source: Some("HDMI 2".into()),
+                    sound_mode: None,
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn activity_cmd_returns_service_call_sequence() {
+        let cmd = new_entity_command(
+            "media_player",
+            "test",
+            "activity",
+            Some(json!({ "activity": "Movie" })),
+        );
+        let result = handle_activity(&cmd, &activity_settings());
+
+        assert!(
+            result.is_ok(),
+            "Valid activity must return Ok, but got: {:?}",
+            result.unwrap_err()
+        );
+        assert_eq!(
+            vec![
+                ("turn_on".to_string(), None),
+                (
+                    "select_source".to_string(),
+                    Some(json!({ "source": "HDMI 1" }))
+                ),
+                (
+                    "select_sound_mode".to_string(),
+                    Some(json!({ "sound_mode": "Dolby Surround" }))
+                ),
+            ],
+            result.unwrap()
+        );
+    }
+
+    #[test]
+    fn activity_cmd_without_turn_on() {
+        let cmd = new_entity_command(
+            "media_player",
+            "test",
+            "activity",
+            Some(json!({ "activity": "Music" })),
+        );
+        let result = handle_activity(&cmd, &activity_settings());
+
+        assert_eq!(
+            Some(vec![(
+                "select_sound_mode".to_string(),
+                Some(json!({ "sound_mode": "Stereo" }))
+            )]),
+            result.ok()
+        );
+    }
+
+    #[rstest]
+    #[case(json!({ "activity": "TV" }))]
+    #[case(json!({ "activity": "Party" }))]
+    #[case(json!({ "activity": "" }))]
+    #[case(Value::Null)]
+    fn activity_cmd_with_unknown_activity_returns_bad_request(#[case] params: Value) {
+        let cmd = new_entity_command("media_player", "test", "activity", Some(params));
+        let result = handle_activity(&cmd, &activity_settings());
+
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Unknown activity must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    fn receiver_state() -> EventState {
+        serde_json::from_value(json!({
+            "state": "on",
+            "attributes": {
+                "source_list": ["HDMI 1", "HDMI 2", "Tuner"],
+                "sound_mode_list": ["Stereo", "Movie", "Music"]
+            }
+        }))
+        .expect("invalid test data")
+    }
+
+    #[rstest]
+    #[case("select_source", json!({ "source": "HDMI 2" }), "select_source", json!({ "source": "HDMI 2" }))]
+    #[case("select_sound_mode", json!({ "mode": "Movie" }), "select_sound_mode", json!({ "sound_mode": "Movie" }))]
+    fn select_cmd_with_known_value_returns_proper_request(
+        #[case] cmd_id: &str,
+        #[case] params: Value,
+        #[case] service: &str,
+        #[case] output: Value,
+    ) {
+        let ha_state = receiver_state();
+        let cmd = new_entity_command("media_player", "test", cmd_id, Some(params));
+        let result = handle_media_player(&cmd, Some(&ha_state), &Default::default());
+
+        assert!(
+            result.is_ok(),
+            "Valid value must return Ok, but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, param) = result.unwrap();
+        assert_eq!(service, &cmd);
+        assert_eq!(Some(output), param);
+    }
+
+    #[rstest]
+    #[case("select_source", json!({ "source": "HDMI 3" }))]
+    #[case("select_source", json!({ "source": "hdmi 1" }))]
+    #[case("select_sound_mode", json!({ "mode": "Dolby Atmos" }))]
+    fn select_cmd_with_unknown_value_returns_bad_request(
+        #[case] cmd_id: &str,
+        #[case] params: Value,
+    ) {
+        let ha_state = receiver_state();
+        let cmd = new_entity_command("media_player", "test", cmd_id, Some(params));
+        let result = handle_media_player(&cmd, Some(&ha_state), &Default::default());
+
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Unknown value must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn select_source_cmd_without_known_source_list_passes_source() {
+        let cmd = new_entity_command(
+            "media_player",
+            "test",
+            "select_source",
+            Some(json!({ "source": "HDMI 3" })),
+        );
+        let result = handle_media_player(&cmd, None, &Default::default());
+
+        let (cmd, param) = result.expect("Source must be passed without known source list");
+        assert_eq!("select_source", &cmd);
+        assert_eq!(Some(json!({ "source": "HDMI 3" })), param);
+    }
+
+    #[rstest]
+    #[case(Some(false), true)]
+    #[case(Some(true), false)]
+    #[case(None, true)]
+    fn mute_toggle_cmd_inverts_last_known_mute_state(
+        #[case] muted: Option<bool>,
+        #[case] output: bool,
+    ) {
+        let ha_state: EventState = serde_json::from_value(json!({
+            "state": "playing",
+            "attributes": match muted {
+                Some(muted) => json!({ "is_volume_muted": muted }),
+                None => json!({}),
+            }
+        }))
+        .expect("invalid test data");
+        let cmd = new_entity_command("media_player", "test", "mute_toggle", None);
+        let result = handle_media_player(&cmd, Some(&ha_state), &Default::default());
+
+        let (cmd, param) = result.expect("Mute toggle must return Ok");
+        assert_eq!("volume_mute", &cmd);
+        assert_eq!(Some(json!({ "is_volume_muted": output })), param);
+    }
+
+    #[test]
+    fn mute_toggle_cmd_without_state_mutes() {
+        let cmd = new_entity_command("media_player", "test", "mute_toggle", None);
+        let result = handle_media_player(&cmd, None, &Default::default());
+
+        let (_, param) = result.expect("Mute toggle must return Ok");
+        assert_eq!(Some(json!({ "is_volume_muted": true })), param);
+    }
+
+    fn volume_state(volume_level: Option<f64>) -> EventState {
+        serde_json::from_value(json!({
+            "state": "playing",
+            "attributes": match volume_level {
+                Some(volume_level) => json!({ "volume_level": volume_level }),
+                None => json!({}),
+            }
+        }))
+        .expect("invalid test data")
+    }
+
+    #[rstest]
+    #[case("volume_up", "volume_up")]
+    #[case("volume_down", "volume_down")]
+    fn volume_up_down_cmd_without_step_uses_ha_service(
+        #[case] cmd_id: &str,
+        #[case] service: &str,
+    ) {
+        let ha_state = volume_state(Some(0.5));
+        let cmd = new_entity_command("media_player", "test", cmd_id, None);
+        let result = handle_media_player(&cmd, Some(&ha_state), &Default::default());
+
+        let (cmd, param) = result.expect("Volume command must return Ok");
+        assert_eq!(service, &cmd);
+        assert!(param.is_none(), "no service data allowed");
+    }
+
+    #[rstest]
+    #[case("volume_up", 0.3, json!(0.35))]
+    #[case("volume_down", 0.3, json!(0.25))]
+    #[case("volume_up", 0.98, json!(1.0))]
+    #[case("volume_down", 0.02, json!(0.0))]
+    fn volume_up_down_cmd_with_step_sets_volume(
+        #[case] cmd_id: &str,
+        #[case] volume_level: f64,
+        #[case] output: Value,
+    ) {
+        let ha_state = volume_state(Some(volume_level));
+        let settings = MediaPlayerSettings {
+            volume_step: 5,
+            ..Default::default()
+        };
+        let cmd = new_entity_command("media_player", "test", cmd_id, None);
+        let result = handle_media_player(&cmd, Some(&ha_state), &settings);
+
+        let (cmd, param) = result.expect("Volume command must return Ok");
+        assert_eq!("volume_set", &cmd);
+        assert_eq!(Some(json!({ "volume_level": output })), param);
+    }
+
+    #[test]
+    fn volume_up_cmd_with_step_and_unknown_volume_uses_ha_service() {
+        let ha_state = volume_state(None);
+        let settings = MediaPlayerSettings {
+            volume_step: 5,
+            ..Default::default()
+        };
+        let cmd = new_entity_command("media_player", "test", "volume_up", None);
+        let result = handle_media_player(&cmd, Some(&ha_state), &settings);
+
+        let (cmd, _) = result.expect("Volume command must return Ok");
+        assert_eq!("volume_up", &cmd);
+    }
+
+    fn features_state(supported_features: u32) -> EventState {
+        serde_json::from_value(json!({
+            "state": "playing",
+            "attributes": { "supported_features": supported_features }
+        }))
+        .expect("invalid test data")
+    }
+
+    #[rstest]
+    #[case(MediaPlayerOffMode::TurnOff, 4096 | 256, "turn_off")]
+    #[case(MediaPlayerOffMode::TurnOff, 256, "turn_off")]
+    #[case(MediaPlayerOffMode::Standby, 4096 | 256, "media_stop")]
+    #[case(MediaPlayerOffMode::Standby, 4096, "media_stop")]
+    #[case(MediaPlayerOffMode::Standby, 256, "turn_off")]
+    fn off_cmd_mapping(
+        #[case] off_mode: MediaPlayerOffMode,
+        #[case] supported_features: u32,
+        #[case] service: &str,
+    ) {
+        let ha_state = features_state(supported_features);
+        let settings = MediaPlayerSettings {
+            off_mode,
+            ..Default::default()
+        };
+        let cmd = new_entity_command("media_player", "test", "off", None);
+        let result = handle_media_player(&cmd, Some(&ha_state), &settings);
+
+        let (cmd, param) = result.expect("Off command must return Ok");
+        assert_eq!(service, &cmd);
+        assert!(param.is_none(), "no service data allowed");
+    }
+
+    #[test]
+    fn off_cmd_in_standby_mode_without_state_stops_playback() {
+        let settings = MediaPlayerSettings {
+            off_mode: MediaPlayerOffMode::Standby,
+            ..Default::default()
+        };
+        let cmd = new_entity_command("media_player", "test", "off", None);
+        let result = handle_media_player(&cmd, None, &settings);
+
+        let (cmd, _) = result.expect("Off command must return Ok");
+        assert_eq!("media_stop", &cmd);
+    }
+
+    fn playback_state(state: &str, supported_features: u32) -> EventState {
+        serde_json::from_value(json!({
+            "state": state,
+            "attributes": { "supported_features": supported_features }
+        }))
+        .expect("invalid test data")
+    }
+
+    #[rstest]
+    // PAUSE | PLAY | STOP
+    #[case("playing", 1 | 16384 | 4096, "media_pause")]
+    #[case("playing", 16384 | 4096, "media_stop")]
+    #[case("playing", 16384, "media_play_pause")]
+    #[case("paused", 1 | 16384 | 4096, "media_play")]
+    #[case("idle", 1 | 16384, "media_play")]
+    #[case("paused", 1, "media_play_pause")]
+    #[case("off", 1 | 16384 | 4096, "media_play_pause")]
+    fn play_pause_cmd_mapping(
+        #[case] state: &str,
+        #[case] supported_features: u32,
+        #[case] service: &str,
+    ) {
+        let ha_state = playback_state(state, supported_features);
+        let cmd = new_entity_command("media_player", "test", "play_pause", None);
+        let result = handle_media_player(&cmd, Some(&ha_state), &Default::default());
+
+        let (cmd, param) = result.expect("Play/pause command must return Ok");
+        assert_eq!(service, &cmd);
+        assert!(param.is_none(), "no service data allowed");
+    }
+
+    #[test]
+    fn play_pause_cmd_without_state_toggles_playback() {
+        let cmd = new_entity_command("media_player", "test", "play_pause", None);
+        let result = handle_media_player(&cmd, None, &Default::default());
+
+        let (cmd, _) = result.expect("Play/pause command must return Ok");
+        assert_eq!("media_play_pause", &cmd);
+    }
+
+    #[rstest]
+    #[case(1 | 4096, "media_stop")]
+    #[case(4096, "media_stop")]
+    #[case(1 | 16384, "media_pause")]
+    fn stop_cmd_mapping(#[case] supported_features: u32, #[case] service: &str) {
+        let ha_state = playback_state("playing", supported_features);
+        let cmd = new_entity_command("media_player", "test", "stop", None);
+        let result = handle_media_player(&cmd, Some(&ha_state), &Default::default());
+
+        let (cmd, param) = result.expect("Stop command must return Ok");
+        assert_eq!(service, &cmd);
+        assert!(param.is_none(), "no service data allowed");
+    }
+
+    #[test]
+    fn stop_cmd_without_stop_and_pause_support_returns_bad_request() {
+        let ha_state = playback_state("playing", 16384);
+        let cmd = new_entity_command("media_player", "test", "stop", None);
+        let result = handle_media_player(&cmd, Some(&ha_state), &Default::default());
+
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Unsupported stop must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[rstest]
+    #[case("next", 32, "media_next_track")]
+    #[case("next", 16 | 32, "media_next_track")]
+    #[case("previous", 16, "media_previous_track")]
+    #[case("previous", 16 | 32, "media_previous_track")]
+    fn next_previous_cmd_mapping(
+        #[case] cmd_id: &str,
+        #[case] supported_features: u32,
+        #[case] service: &str,
+    ) {
+        let ha_state = playback_state("playing", supported_features);
+        let cmd = new_entity_command("media_player", "test", cmd_id, None);
+        let result = handle_media_player(&cmd, Some(&ha_state), &Default::default());
+
+        assert_eq!(Ok((service.to_string(), None)), result);
+    }
+
+    #[rstest]
+    #[case("next")]
+    #[case("previous")]
+    fn next_previous_cmd_without_known_features(#[case] cmd_id: &str) {
+        let cmd = new_entity_command("media_player", "test", cmd_id, None);
+        let result = handle_media_player(&cmd, None, &Default::default());
+
+        assert!(
+            result.is_ok(),
+            "Unknown features must not reject the command"
+        );
+    }
+
+    #[rstest]
+    #[case("next", 0)]
+    #[case("next", 16 | 1 | 16384)]
+    #[case("previous", 0)]
+    #[case("previous", 32 | 1 | 16384)]
+    fn next_previous_cmd_without_feature_support_returns_bad_request(
+        #[case] cmd_id: &str,
+        #[case] supported_features: u32,
+    ) {
+        let ha_state = playback_state("playing", supported_features);
+        let cmd = new_entity_command("media_player", "test", cmd_id, None);
+        let result = handle_media_player(&cmd, Some(&ha_state), &Default::default());
+
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Unsupported command must return BadRequest, but got: {:?}",
+            result
+        );
+    }
 }
@@ -7,29 +7,50 @@
 //! See <https://developers.home-assistant.io/docs/api/websocket/#calling-a-service> for further
 //! information.
 
+use crate::client::assumed_state::optimistic_entity_change;
+use crate::client::command_retry::retry_transient;
+use crate::client::entity::CMD_ACTIVITY;
 use crate::client::messages::CallService;
-use crate::client::model::{CallServiceMsg, Target};
+use crate::client::model::{CallServiceMsg, EventState, Target};
+use crate::client::pending_requests::{RequestError, RequestResult};
 use crate::client::HomeAssistantClient;
+use crate::configuration::HomeAssistantSettings;
 use crate::errors::ServiceError;
-use actix::Handler;
-use log::info;
+use crate::util::return_fut_err;
+use actix::{fut, Addr, AsyncContext, Handler, Message, ResponseFuture};
+use log::{error, info};
 use serde_json::{Map, Value};
-use uc_api::intg::EntityCommand;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use uc_api::intg::{EntityChange, EntityCommand};
 use uc_api::EntityType;
 
+mod alarm_control_panel;
 mod button;
 mod climate;
 mod cover;
+mod fan;
+mod humidifier;
+mod lawn_mower;
 mod light;
+mod lock;
 mod media_player;
+mod number;
 mod remote;
+mod scene;
+mod select;
+mod siren;
 mod switch;
+mod text;
+mod update;
+mod vacuum;
+mod water_heater;
 
 impl Handler<CallService> for HomeAssistantClient {
-    type Result = Result<(), ServiceError>;
+    type Result = ResponseFuture<Result<(), ServiceError>>;
 
-    /// Convert a R2 `EntityCommand` to a HA `call_service` request and send it as WebSocket text
-    /// message.  
+    /// Convert a R2 `EntityCommand` to HA `call_service` requests and send them as WebSocket text
+    /// messages.  
     /// The conversion of the entity logic is delegated to entity specific functions in this crate.
     ///
     /// # Arguments
@@ -37,55 +58,356 @@ impl Handler<CallService> for HomeAssistantClient {
     /// * `msg`: Actor message containing the R2 `EntityCommand` structure.
     /// * `ctx`: Actor execution context
     ///
-    /// returns: Result<(), ServiceError>
+    /// returns: Future resolving to the HA service call result. Some services take a long time to
+    /// respond, the result is awaited for the configured request timeout. A command failing with a
+    /// transient HA error is retried once if enabled.
     fn handle(&mut self, msg: CallService, ctx: &mut Self::Context) -> Self::Result {
-        // map Remote Two command name & parameters to HA service name and service_data payload
-        let (service, service_data) = match msg.command.entity_type {
-            EntityType::Button => button::handle_button(&msg.command),
-            EntityType::Switch => switch::handle_switch(&msg.command),
-            EntityType::Climate => climate::handle_climate(&msg.command),
-            EntityType::Cover => cover::handle_cover(&msg.command),
-            EntityType::Light => light::handle_light(&msg.command),
-            EntityType::MediaPlayer => media_player::handle_media_player(&msg.command),
-            EntityType::Remote => remote::handle_remote(&msg.command),
-            EntityType::Sensor => Err(ServiceError::BadRequest(
-                "Sensor doesn't support sending commands to! Ignoring call".to_string(),
-            )),
-            EntityType::Activity | EntityType::Macro => Err(ServiceError::BadRequest(format!(
-                "{} is an internal remote-core entity",
-                msg.command.entity_type
-            ))),
-            EntityType::IrEmitter => Err(ServiceError::BadRequest(
-                "IR-emitter not supported! Ignoring call".to_string(),
-            )),
-        }?;
-        info!(
-            "[{}] Calling {} service '{service}'",
-            self.id, msg.command.entity_id
-        );
+        let command = msg.command.clone();
+        let calls = match self.prepare_service_calls(msg) {
+            Ok(calls) => calls,
+            Err(e) => {
+                return_fut_err!(e);
+            }
+        };
+        let retry_delay = Duration::from_millis(self.settings.command_retry_delay_ms as u64);
+        let addr = ctx.address();
+
+        Box::pin(async move {
+            let result = send_service_calls(addr.clone(), calls, &command).await;
+            retry_transient(result, retry_delay, || async move {
+                addr.send(RetryCallService { command }).await?
+            })
+            .await
+        })
+    }
+}
+
+/// Resend the service calls of a failed entity command, without further retries.
+#[derive(Message)]
+#[rtype(result = "Result<(), ServiceError>")]
+struct RetryCallService {
+    command: EntityCommand,
+}
+
+impl Handler<RetryCallService> for HomeAssistantClient {
+    type Result = ResponseFuture<Result<(), ServiceError>>;
 
-        let domain = match msg.command.entity_id.split_once('.') {
-            None => return Err(ServiceError::BadRequest("Invalid entity_id format".into())),
-            Some((l, _)) => l.to_string(),
+    fn handle(&mut self, msg: RetryCallService, ctx: &mut Self::Context) -> Self::Result {
+        let command = msg.command.clone();
+        let msg = CallService {
+            command: msg.command,
         };
+        match self.prepare_service_calls(msg) {
+            Ok(calls) => {
+                let addr = ctx.address();
+                Box::pin(async move {
+                    send_service_calls(addr, calls, &command)
+                        .await
+                        .map_err(ServiceError::from)
+                })
+            }
+            Err(e) => {
+                return_fut_err!(e);
+            }
+        }
+    }
+}
+
+/// Send a single HA service call of an entity command.
+#[derive(Message)]
+#[rtype(result = "RequestResult")]
+struct SendServiceCall {
+    call: ServiceCall,
+    /// Entity command to confirm with the result of the last service call.
+    confirm: Option<EntityCommand>,
+    /// Optimistic entity change to send with the first service call.
+    entity_change: Option<EntityChange>,
+}
+
+impl Handler<SendServiceCall> for HomeAssistantClient {
+    type Result = ResponseFuture<RequestResult>;
+
+    fn handle(&mut self, msg: SendServiceCall, ctx: &mut Self::Context) -> Self::Result {
+        let call = msg.call;
+        info!(
+            "[{}] Calling {} service '{}'",
+            self.id, call.entity_id, call.service
+        );
+        let id = self.new_msg_id();
+        self.echo_filter
+            .track_request(id, &call.entity_id, Instant::now());
+        if let Some(command) = &msg.confirm {
+            self.service_confirmation
+                .track_request(id, command, Instant::now());
+        }
+        let result = self.request_result(id);
 
         let call_srv_msg = CallServiceMsg {
-            id: self.new_msg_id(),
+            id,
             msg_type: "call_service".to_string(),
-            domain,
-            service,
-            service_data,
+            domain: call.domain,
+            service: call.service,
+            service_data: call.service_data,
             target: Target {
-                entity_id: msg.command.entity_id,
+                entity_id: call.entity_id,
             },
         };
+        let sent = serde_json::to_value(call_srv_msg)
+            .map_err(ServiceError::from)
+            .and_then(|msg| self.send_json(msg, ctx));
+        if let Err(e) = sent {
+            error!("[{}] Error sending service call: {e:?}", self.id);
+            return Box::pin(fut::result(Err(RequestError::NotConnected)));
+        }
+        if let Some(entity_change) = msg.entity_change {
+            if let Err(e) = self.send_entity_change(entity_change) {
+                error!(
+                    "[{}] Error sending optimistic entity change: {e:?}",
+                    self.id
+                );
+            }
+        }
 
-        let msg = serde_json::to_value(call_srv_msg)?;
-        self.send_json(msg, ctx)
+        Box::pin(result)
+    }
+}
 
-        // TODO wait for HA response message? If the service call fails we'll get a result back with "success: false"
-        // However, some services take a long time to respond! E.g. Sonos might take 10 seconds if there's an issue with the network.
+/// Send the service calls of an entity command one after the other.
+///
+/// Each service call waits for the HA result of the previous call, e.g. a media player activity
+/// only selects the source after the device has been turned on.
+///
+/// returns: the result of the last service call, or the error of the first failed call. The
+/// following calls are not sent after a failed call.
+async fn send_service_calls(
+    addr: Addr<HomeAssistantClient>,
+    calls: PreparedServiceCalls,
+    command: &EntityCommand,
+) -> RequestResult {
+    let PreparedServiceCalls {
+        calls,
+        mut entity_change,
+    } = calls;
+    let last_call = calls.len().saturating_sub(1);
+    for (index, call) in calls.into_iter().enumerate() {
+        let msg = SendServiceCall {
+            call,
+            // a command with multiple service calls is confirmed with the last call
+            confirm: (index == last_call).then(|| command.clone()),
+            entity_change: entity_change.take(),
+        };
+        addr.send(msg)
+            .await
+            .map_err(|_| RequestError::NotConnected)??;
     }
+    Ok(())
+}
+
+/// Service calls of an entity command, ready to be sent.
+struct PreparedServiceCalls {
+    calls: Vec<ServiceCall>,
+    /// Optimistic entity change of an assumed state entity.
+    entity_change: Option<EntityChange>,
+}
+
+impl HomeAssistantClient {
+    /// Convert an entity command to the HA service calls to send.
+    ///
+    /// returns: the service calls of the command, at least one call.
+    fn prepare_service_calls(
+        &mut self,
+        mut msg: CallService,
+    ) -> Result<PreparedServiceCalls, ServiceError> {
+        check_concurrent_commands(
+            self.pending_requests.commands_in_flight(),
+            self.settings.max_concurrent_commands,
+        )?;
+        if msg.command.entity_type == EntityType::Climate {
+            let ha_attr = self
+                .entity_states
+                .get(&msg.command.entity_id)
+                .and_then(|s| s.attributes.as_ref());
+            if let Some(conversion) = self.temperature_conversion(ha_attr) {
+                conversion.reverse().convert_command(&mut msg.command);
+            }
+        }
+
+        let calls = entity_command_to_services(&msg.command, &self.entity_states, &self.settings)?;
+        if calls.is_empty() {
+            return Err(ServiceError::InternalServerError(
+                "No service call for command".into(),
+            ));
+        }
+
+        let entity_change = if self.settings.optimistic_assumed_state {
+            optimistic_entity_change(&msg.command, self.entity_states.get(&msg.command.entity_id))
+        } else {
+            None
+        };
+
+        Ok(PreparedServiceCalls {
+            calls,
+            entity_change,
+        })
+    }
+}
+
+/// Check if another command may be sent while other commands are still waiting for their result.
+///
+/// # Arguments
+///
+/// * `in_flight`: number of entity commands waiting for their result.
+/// * `max`: maximum number of concurrent commands. 0 = unlimited.
+fn check_concurrent_commands(in_flight: usize, max: u16) -> Result<(), ServiceError> {
+    if max > 0 && in_flight >= max as usize {
+        return Err(ServiceError::ServiceUnavailable(format!(
+            "Too many concurrent commands: {in_flight} waiting for Home Assistant"
+        )));
+    }
+    Ok(())
+}
+
+/// HA service call of an entity command.
+#[derive(Debug, PartialEq)]
+pub(crate) struct ServiceCall {
+    pub domain: String,
+    pub service: String,
+    pub service_data: Option<Value>,
+    /// Target entity of the service call.
+    pub entity_id: String,
+}
+
+impl ServiceCall {
+    fn new(
+        (domain, service, service_data): (String, String, Option<Value>),
+        entity_id: &str,
+    ) -> Self {
+        Self {
+            domain,
+            service,
+            service_data,
+            entity_id: entity_id.to_string(),
+        }
+    }
+}
+
+/// Translate a R2 `EntityCommand` to one or more HA service calls.
+///
+/// Media player activities are mapped to a sequence of service calls, all other commands to a
+/// single service call with [`entity_command_to_service`]. The source selection of a media player
+/// with an associated select entity is routed to the select entity, and the target temperature of
+/// a climate entity with an associated setpoint number to the number entity.
+///
+/// # Arguments
+///
+/// * `command`: R2 entity command.
+/// * `entity_states`: last known HA states of all entities.
+/// * `settings`: HA settings with entity command options.
+pub(crate) fn entity_command_to_services(
+    command: &EntityCommand,
+    entity_states: &HashMap<String, EventState>,
+    settings: &HomeAssistantSettings,
+) -> Result<Vec<ServiceCall>, ServiceError> {
+    if command.entity_type == EntityType::MediaPlayer && command.cmd_id == CMD_ACTIVITY {
+        let calls = media_player::handle_activity(command, &settings.media_player)?;
+        return Ok(calls
+            .into_iter()
+            .map(|(service, service_data)| {
+                ServiceCall::new(
+                    ("media_player".to_string(), service, service_data),
+                    &command.entity_id,
+                )
+            })
+            .collect());
+    }
+    if command.entity_type == EntityType::MediaPlayer && command.cmd_id == "select_source" {
+        if let Some(select_id) = settings.media_player.source_selects.get(&command.entity_id) {
+            let call =
+                select::handle_media_source(command, select_id, entity_states.get(select_id))?;
+            return Ok(vec![ServiceCall::new(call, select_id)]);
+        }
+    }
+    if command.entity_type == EntityType::Climate && command.cmd_id == "target_temperature" {
+        if let Some(number_id) = settings.climate_setpoint_numbers.get(&command.entity_id) {
+            let call =
+                number::handle_climate_setpoint(command, number_id, entity_states.get(number_id))?;
+            return Ok(vec![ServiceCall::new(call, number_id)]);
+        }
+    }
+
+    let call = entity_command_to_service(command, entity_states.get(&command.entity_id), settings)?;
+    Ok(vec![ServiceCall::new(call, &command.entity_id)])
+}
+
+/// Translate a R2 `EntityCommand` to a HA service call.
+///
+/// # Arguments
+///
+/// * `command`: R2 entity command.
+/// * `ha_state`: last known HA state of the entity, if available.
+/// * `settings`: HA settings with entity command options.
+///
+/// returns: HA service domain, service name and optional service_data payload.
+pub(crate) fn entity_command_to_service(
+    command: &EntityCommand,
+    ha_state: Option<&EventState>,
+    settings: &HomeAssistantSettings,
+) -> Result<(String, String, Option<Value>), ServiceError> {
+    let domain = match command.entity_id.split_once('.') {
+        None => return Err(ServiceError::BadRequest("Invalid entity_id format".into())),
+        Some((l, _)) => l.to_string(),
+    };
+
+    // map Remote Two command name & parameters to HA service name and service_data payload
+    let (service, service_data) = match command.entity_type {
+        // HA domains without a dedicated entity type in the Integration-API
+        EntityType::Remote if domain == "vacuum" => vacuum::handle_vacuum(command),
+        EntityType::Remote if domain == "lawn_mower" => lawn_mower::handle_lawn_mower(command),
+        EntityType::Remote if domain == "alarm_control_panel" => {
+            alarm_control_panel::handle_alarm_control_panel(command, ha_state)
+        }
+        EntityType::Sensor if domain == "number" || domain == "input_number" => {
+            number::handle_number(command, ha_state)
+        }
+        EntityType::Sensor if domain == "select" || domain == "input_select" => {
+            select::handle_select(command, ha_state)
+        }
+        EntityType::Sensor if domain == "text" || domain == "input_text" => {
+            text::handle_text(command, ha_state)
+        }
+        EntityType::Sensor if domain == "update" => update::handle_update(command),
+        EntityType::Button if domain == "scene" => scene::handle_scene(command),
+        EntityType::Button => button::handle_button(command),
+        EntityType::Switch if domain == "lock" => lock::handle_lock(command, ha_state),
+        EntityType::Switch if domain == "fan" => fan::handle_fan(command),
+        EntityType::Switch if domain == "siren" => siren::handle_siren(command, ha_state),
+        EntityType::Switch if domain == "humidifier" => {
+            humidifier::handle_humidifier(command, ha_state)
+        }
+        EntityType::Switch => switch::handle_switch(command),
+        EntityType::Climate if domain == "water_heater" => {
+            water_heater::handle_water_heater(command, ha_state)
+        }
+        EntityType::Climate => climate::handle_climate(command, ha_state),
+        EntityType::Cover => cover::handle_cover(command),
+        EntityType::Light => light::handle_light(command),
+        EntityType::MediaPlayer => {
+            media_player::handle_media_player(command, ha_state, &settings.media_player)
+        }
+        EntityType::Remote => remote::handle_remote(command),
+        EntityType::Sensor => Err(ServiceError::BadRequest(
+            "Sensor doesn't support sending commands to! Ignoring call".to_string(),
+        )),
+        EntityType::Activity | EntityType::Macro => Err(ServiceError::BadRequest(format!(
+            "{} is an internal remote-core entity",
+            command.entity_type
+        ))),
+        EntityType::IrEmitter => Err(ServiceError::BadRequest(
+            "IR-emitter not supported! Ignoring call".to_string(),
+        )),
+    }?;
+
+    Ok((domain, service, service_data))
 }
 
 pub fn cmd_from_str<T: std::str::FromStr + strum::VariantNames>(
@@ -109,3 +431,431 @@ fn get_required_params(cmd: &EntityCommand) -> Result<&Map<String, Value>, Servi
         Err(ServiceError::BadRequest("Missing params object".into()))
     }
 }
+
+/// Validate a selection value against the list attribute of the last known HA entity state.
+///
+/// The value is passed through if the list is not known, e.g. if no entity state has been
+/// received yet.
+///
+/// # Arguments
+///
+/// * `ha_state`: last known HA entity state.
+/// * `list_attr`: HA attribute with the available values, e.g. `source_list`.
+/// * `name`: name of the value in the error message.
+/// * `value`: value to validate.
+fn validate_list_value(
+    ha_state: Option<&EventState>,
+    list_attr: &str,
+    name: &str,
+    value: &str,
+) -> Result<(), ServiceError> {
+    let list = match ha_state
+        .and_then(|s| s.attributes.as_ref())
+        .and_then(|attr| attr.get(list_attr))
+        .and_then(|v| v.as_array())
+    {
+        None => return Ok(()),
+        Some(list) => list,
+    };
+
+    if list.iter().any(|v| v.as_str() == Some(value)) {
+        Ok(())
+    } else {
+        Err(unknown_value(
+            name,
+            value,
+            list.iter().filter_map(|v| v.as_str()),
+        ))
+    }
+}
+
+/// Create the BadRequest error of an unknown value with the list of available values.
+fn unknown_value<'a>(
+    name: &str,
+    value: &str,
+    available: impl IntoIterator<Item = &'a str>,
+) -> ServiceError {
+    ServiceError::BadRequest(format!(
+        "Unknown {name} '{value}'. Available: {}",
+        available.into_iter().collect::<Vec<_>>().join(", ")
+    ))
+}
+
+/// Create an entity command for unit tests.
+///
+/// # Arguments
+///
+/// * `entity_type`: Integration-API entity type, e.g. `light`.
+/// * `entity_id`: entity identifier.
+/// * `cmd_id`: command identifier.
+/// * `params`: optional command parameters.
+#[cfg(test)]
+pub(crate) fn new_entity_command(
+    entity_type: &str,
+    entity_id: &str,
+    cmd_id: &str,
+    params: Option<Value>,
+) -> EntityCommand {
+    let mut msg_data = serde_json::json!({
+        "cmd_id": cmd_id,
+        "entity_id": entity_id,
+        "entity_type": entity_type
+    });
+    if let Some(params) = params {
+        msg_data["params"] = params;
+    }
+    serde_json::from_value(msg_data).expect("invalid test data")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::{get_driver_metadata, MediaActivity, Settings};
+    use crate::Controller;
+    use actix::{Actor, StreamHandler};
+    use actix_web::{web, App, HttpRequest, HttpServer};
+    use actix_web_actors::ws;
+    use futures::StreamExt;
+    use rstest::rstest;
+    use serde_json::json;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use url::Url;
+
+    #[test]
+    fn script_push_calls_script_turn_on() {
+        let cmd: EntityCommand = serde_json::from_value(json!({
+            "cmd_id": "push",
+            "entity_id": "script.movie_mode",
+            "entity_type": "button"
+        }))
+        .expect("invalid test data");
+
+        let result = entity_command_to_service(&cmd, None, &Default::default());
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (domain, service, service_data) = result.unwrap();
+        assert_eq!("script", domain);
+        assert_eq!("turn_on", service);
+        assert!(service_data.is_none(), "no service data allowed");
+    }
+
+    #[rstest]
+    #[case("on", "turn_on")]
+    #[case("off", "turn_off")]
+    #[case("toggle", "toggle")]
+    fn input_boolean_calls_input_boolean_service(#[case] cmd_id: &str, #[case] ha_service: &str) {
+        let cmd: EntityCommand = serde_json::from_value(json!({
+            "cmd_id": cmd_id,
+            "entity_id": "input_boolean.guest_mode",
+            "entity_type": "switch"
+        }))
+        .expect("invalid test data");
+
+        let result = entity_command_to_service(&cmd, None, &Default::default());
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (domain, service, service_data) = result.unwrap();
+        assert_eq!("input_boolean", domain);
+        assert_eq!(ha_service, service);
+        assert!(service_data.is_none(), "no service data allowed");
+    }
+
+    #[rstest]
+    #[case("media_player.receiver", "select", "select_option", json!({ "option": "Phono" }), "select.receiver_input")]
+    #[case("media_player.tv", "media_player", "select_source", json!({ "source": "Phono" }), "media_player.tv")]
+    fn media_player_source_routed_to_select(
+        #[case] entity_id: &str,
+        #[case] domain: &str,
+        #[case] service: &str,
+        #[case] service_data: Value,
+        #[case] target: &str,
+    ) {
+        let mut settings = HomeAssistantSettings::default();
+        settings.media_player.source_selects = HashMap::from([(
+            "media_player.receiver".to_string(),
+            "select.receiver_input".to_string(),
+        )]);
+        let entity_states = HashMap::from([(
+            "select.receiver_input".to_string(),
+            serde_json::from_value(json!({
+                "state": "TV",
+                "attributes": { "options": ["TV", "Phono"] }
+            }))
+            .expect("invalid test data"),
+        )]);
+        let cmd: EntityCommand = serde_json::from_value(json!({
+            "cmd_id": "select_source",
+            "entity_id": entity_id,
+            "entity_type": "media_player",
+            "params": { "source": "Phono" }
+        }))
+        .expect("invalid test data");
+
+        let result = entity_command_to_services(&cmd, &entity_states, &settings);
+
+        assert_eq!(
+            Ok(vec![ServiceCall {
+                domain: domain.into(),
+                service: service.into(),
+                service_data: Some(service_data),
+                entity_id: target.into(),
+            }]),
+            result
+        );
+    }
+
+    #[rstest]
+    #[case("climate.floor_heating", "number", "set_value", json!({ "value": 21.5 }), "number.floor_heating_setpoint")]
+    #[case("climate.living_room", "climate", "set_temperature", json!({ "temperature": 21.5 }), "climate.living_room")]
+    fn climate_target_temperature_routed_to_setpoint_number(
+        #[case] entity_id: &str,
+        #[case] domain: &str,
+        #[case] service: &str,
+        #[case] service_data: Value,
+        #[case] target: &str,
+    ) {
+        let mut settings = HomeAssistantSettings::default();
+        settings.climate_setpoint_numbers = HashMap::from([(
+            "climate.floor_heating".to_string(),
+            "number.floor_heating_setpoint".to_string(),
+        )]);
+        let entity_states = HashMap::from([(
+            "number.floor_heating_setpoint".to_string(),
+            serde_json::from_value(json!({
+                "state": "20",
+                "attributes": { "min": 5, "max": 30, "step": 0.5 }
+            }))
+            .expect("invalid test data"),
+        )]);
+        let cmd: EntityCommand = serde_json::from_value(json!({
+            "cmd_id": "target_temperature",
+            "entity_id": entity_id,
+            "entity_type": "climate",
+            "params": { "temperature": 21.5 }
+        }))
+        .expect("invalid test data");
+
+        let result = entity_command_to_services(&cmd, &entity_states, &settings);
+
+        assert_eq!(
+            Ok(vec![ServiceCall {
+                domain: domain.into(),
+                service: service.into(),
+                service_data: Some(service_data),
+                entity_id: target.into(),
+            }]),
+            result
+        );
+    }
+
+    #[rstest]
+    #[case(0, 0, true)]
+    #[case(100, 0, true)]
+    #[case(2, 3, true)]
+    #[case(3, 3, false)]
+    #[case(4, 3, false)]
+    fn concurrent_commands(#[case] in_flight: usize, #[case] max: u16, #[case] allowed: bool) {
+        let result = check_concurrent_commands(in_flight, max);
+
+        if allowed {
+            assert_eq!(Ok(()), result);
+        } else {
+            assert!(matches!(result, Err(ServiceError::ServiceUnavailable(_))));
+        }
+    }
+
+    fn state_with_list(list_attr: &str, list: Value) -> EventState {
+        serde_json::from_value(json!({
+            "state": "a",
+            "attributes": { list_attr: list }
+        }))
+        .expect("invalid test data")
+    }
+
+    #[rstest]
+    #[case("a")]
+    #[case("b")]
+    fn list_value_is_valid(#[case] value: &str) {
+        let state = state_with_list("options", json!(["a", "b"]));
+
+        assert_eq!(
+            Ok(()),
+            validate_list_value(Some(&state), "options", "option", value)
+        );
+    }
+
+    #[test]
+    fn unknown_list_value_is_rejected_with_available_values() {
+        let state = state_with_list("options", json!(["a", "b"]));
+
+        assert_eq!(
+            Err(ServiceError::BadRequest(
+                "Unknown option 'c'. Available: a, b".into()
+            )),
+            validate_list_value(Some(&state), "options", "option", "c")
+        );
+    }
+
+    #[rstest]
+    #[case(None)]
+    #[case(Some(state_with_list("other_list", json!(["a"]))))]
+    #[case(Some(state_with_list("options", json!("a"))))]
+    fn value_is_passed_through_without_known_list(#[case] state: Option<EventState>) {
+        assert_eq!(
+            Ok(()),
+            validate_list_value(state.as_ref(), "options", "option", "c")
+        );
+    }
+
+    /// Service calls received by the fake HA server: service name and number of results sent
+    /// before the call was received.
+    type ReceivedCalls = Arc<Mutex<Vec<(String, usize)>>>;
+
+    /// HA WebSocket connection answering service calls, optionally failing a service.
+    struct FakeHa {
+        calls: ReceivedCalls,
+        failing_service: Option<&'static str>,
+        results: usize,
+    }
+
+    impl Actor for FakeHa {
+        type Context = ws::WebsocketContext<Self>;
+    }
+
+    impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for FakeHa {
+        fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+            let msg: Value = match msg {
+                Ok(ws::Message::Text(text)) => match serde_json::from_str(&text) {
+                    Ok(msg) => msg,
+                    Err(_) => return,
+                },
+                _ => return,
+            };
+            if msg.get("type").and_then(|v| v.as_str()) != Some("call_service") {
+                return;
+            }
+            let service = msg["service"].as_str().unwrap_or_default().to_string();
+            let result = if self.failing_service == Some(service.as_str()) {
+                json!({
+                    "id": msg["id"],
+                    "type": "result",
+                    "success": false,
+                    "error": { "code": "not_found", "message": "Service not found." }
+                })
+            } else {
+                json!({ "id": msg["id"], "type": "result", "success": true, "result": null })
+            };
+            self.calls.lock().unwrap().push((service, self.results));
+            self.results += 1;
+            ctx.text(result.to_string());
+        }
+    }
+
+    /// Send a media player activity command to a HA client connected to a fake HA server.
+    ///
+    /// returns: the command result and the service calls received by HA.
+    async fn call_activity(
+        failing_service: Option<&'static str>,
+    ) -> (Result<(), ServiceError>, Vec<(String, usize)>) {
+        let calls = ReceivedCalls::default();
+        let listener = TcpListener::bind("127.0.0.1:0").expect("test listener");
+        let port = listener.local_addr().expect("listener address").port();
+        let ha_calls = calls.clone();
+        let server = HttpServer::new(move || {
+            let ha_calls = ha_calls.clone();
+            App::new().route(
+                "/api/websocket",
+                web::get().to(move |req: HttpRequest, stream: web::Payload| {
+                    let ha = FakeHa {
+                        calls: ha_calls.clone(),
+                        failing_service,
+                        results: 0,
+                    };
+                    async move { ws::start(ha, &req, stream) }
+                }),
+            )
+        })
+        .workers(1)
+        .listen(listener)
+        .expect("test server")
+        .run();
+        let server_handle = server.handle();
+        actix::spawn(server);
+
+        let url = Url::parse(&format!("ws://127.0.0.1:{port}/api/websocket")).unwrap();
+        let (_, framed) = awc::Client::new()
+            .ws(url.as_str())
+            .connect()
+            .await
+            .expect("WebSocket connection to test server");
+        let (sink, stream) = framed.split();
+        let mut settings = HomeAssistantSettings::default();
+        settings.media_player.activities = vec![MediaActivity {
+            name: "Movie".into(),
+            entity_id: "media_player.tv".into(),
+            turn_on: true,
+            source: Some("HDMI 1".into()),
+            sound_mode: Some("Dolby Surround".into()),
+        }];
+        let metadata = get_driver_metadata().expect("driver metadata");
+        let controller = Controller::new(Settings::default(), metadata).start();
+        let client = HomeAssistantClient::start(
+            "main".into(),
+            url,
+            controller,
+            "token".into(),
+            "remote".into(),
+            sink,
+            stream,
+            &settings,
+        );
+
+        let command = new_entity_command(
+            "media_player",
+            "media_player.tv",
+            CMD_ACTIVITY,
+            Some(json!({ "activity": "Movie" })),
+        );
+        let result = client
+            .send(CallService { command })
+            .await
+            .expect("HA client must be running");
+
+        server_handle.stop(false).await;
+        let calls = calls.lock().unwrap().clone();
+        (result, calls)
+    }
+
+    #[actix::test]
+    async fn activity_service_calls_wait_for_previous_result() {
+        let (result, calls) = call_activity(None).await;
+
+        assert_eq!(Ok(()), result);
+        assert_eq!(
+            vec![
+                ("turn_on".to_string(), 0),
+                ("select_source".to_string(), 1),
+                ("select_sound_mode".to_string(), 2)
+            ],
+            calls
+        );
+    }
+
+    #[actix::test]
+    async fn activity_stops_at_first_failed_service_call() {
+        let (result, calls) = call_activity(Some("turn_on")).await;
+
+        assert_eq!(
+            Err(ServiceError::NotFound("Service not found.".into())),
+            result
+        );
+        assert_eq!(vec![("turn_on".to_string(), 0)], calls);
+    }
+}
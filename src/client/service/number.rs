@@ -0,0 +1,336 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Number and input_number entity specific HA service call logic.
+
+use crate::client::entity::NUMBER_CMD_SET_VALUE;
+use crate::client::model::EventState;
+use crate::client::service::get_required_params;
+use crate::errors::ServiceError;
+use serde_json::{json, Value};
+use uc_api::intg::EntityCommand;
+
+/// Max number of decimal places of a number step.
+const MAX_STEP_DECIMALS: i32 = 6;
+
+pub(crate) fn handle_number(
+    msg: &EntityCommand,
+    ha_state: Option<&EventState>,
+) -> Result<(String, Option<Value>), ServiceError> {
+    if msg.cmd_id != NUMBER_CMD_SET_VALUE {
+        return Err(ServiceError::BadRequest(format!(
+            "Invalid cmd_id: {}. Valid commands: {NUMBER_CMD_SET_VALUE}",
+            msg.cmd_id
+        )));
+    }
+
+    let params = get_required_params(msg)?;
+    match params.get("value").and_then(|v| v.as_f64()) {
+        Some(value) => {
+            let value = adjust_value(value, &NumberRange::from(ha_state))?;
+            Ok(("set_value".into(), Some(json!({ "value": value }))))
+        }
+        None => Err(ServiceError::BadRequest(
+            "Invalid or missing params.value attribute".into(),
+        )),
+    }
+}
+
+/// Set the associated setpoint number of a climate entity.
+///
+/// # Arguments
+///
+/// * `msg`: `target_temperature` command of the climate entity.
+/// * `number_id`: associated number or input_number entity with the target temperature.
+/// * `ha_state`: last known HA state of the number entity, if available.
+///
+/// returns: HA service domain, service name and service_data payload for the number entity.
+pub(crate) fn handle_climate_setpoint(
+    msg: &EntityCommand,
+    number_id: &str,
+    ha_state: Option<&EventState>,
+) -> Result<(String, String, Option<Value>), ServiceError> {
+    let domain = match number_id.split_once('.') {
+        Some((domain @ ("number" | "input_number"), _)) => domain,
+        _ => {
+            return Err(ServiceError::BadRequest(format!(
+                "Invalid setpoint number entity: {number_id}"
+            )))
+        }
+    };
+    let params = get_required_params(msg)?;
+    match params.get("temperature").and_then(|v| v.as_f64()) {
+        Some(temperature) => {
+            let value = adjust_value(temperature, &NumberRange::from(ha_state))?;
+            Ok((
+                domain.to_string(),
+                "set_value".into(),
+                Some(json!({ "value": value })),
+            ))
+        }
+        None => Err(ServiceError::BadRequest(
+            "Invalid or missing params.temperature attribute".into(),
+        )),
+    }
+}
+
+/// Value range of a number entity from the `min`, `max` and `step` attributes.
+#[derive(Debug, Default)]
+struct NumberRange {
+    min: Option<f64>,
+    max: Option<f64>,
+    step: Option<f64>,
+}
+
+impl From<Option<&EventState>> for NumberRange {
+    fn from(ha_state: Option<&EventState>) -> Self {
+        let attr = ha_state.and_then(|s| s.attributes.as_ref());
+        let value = |key: &str| attr.and_then(|a| a.get(key)).and_then(|v| v.as_f64());
+        Self {
+            min: value("min"),
+            max: value("max"),
+            step: value("step").filter(|step| *step > 0.0),
+        }
+    }
+}
+
+/// Validate a number value against the entity range and round it to the step size.
+///
+/// Values outside of `min` and `max` are rejected. The value is rounded to the nearest step,
+/// starting from `min`, and clamped to the range if rounding exceeds it.
+fn adjust_value(value: f64, range: &NumberRange) -> Result<f64, ServiceError> {
+    if range.min.is_some_and(|min| value < min) || range.max.is_some_and(|max| value > max) {
+        return Err(ServiceError::BadRequest(format!(
+            "Value {value} is out of range: {}..{}",
+            range.min.map(|v| v.to_string()).unwrap_or_default(),
+            range.max.map(|v| v.to_string()).unwrap_or_default()
+        )));
+    }
+
+    let mut value = value;
+    if let Some(step) = range.step {
+        let offset = range.min.unwrap_or_default();
+        value = offset + ((value - offset) / step).round() * step;
+        // avoid floating point artifacts like 21.299999999999997
+        let factor = 10_f64.powi(step_decimals(step));
+        value = (value * factor).round() / factor;
+    }
+    if let Some(max) = range.max {
+        value = value.min(max);
+    }
+    if let Some(min) = range.min {
+        value = value.max(min);
+    }
+
+    Ok(value)
+}
+
+/// Get the number of decimal places of a step size.
+fn step_decimals(step: f64) -> i32 {
+    (0..MAX_STEP_DECIMALS)
+        .find(|decimals| {
+            let scaled = step * 10_f64.powi(*decimals);
+            (scaled - scaled.round()).abs() < 1e-9
+        })
+        .unwrap_or(MAX_STEP_DECIMALS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(json!(21.5), json!({ "value": 21.5 }))]
+    #[case(json!(10), json!({ "value": 10.0 }))]
+    #[case(json!(-5), json!({ "value": -5.0 }))]
+    fn set_value(#[case] value: Value, #[case] output: Value) {
+        let result = handle_number(
+            &new_entity_command(
+                "sensor",
+                "input_number.target_temp",
+                "set_value",
+                Some(json!({ "value": value })),
+            ),
+            None,
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("set_value", cmd);
+        assert_eq!(Some(output), data);
+    }
+
+    #[rstest]
+    #[case("set_value", Some(json!({ "value": "high" })))]
+    #[case("set_value", Some(json!({})))]
+    #[case("set_value", None)]
+    #[case("on", None)]
+    fn invalid_cmd_returns_bad_request(#[case] cmd_id: &str, #[case] params: Option<Value>) {
+        let result = handle_number(
+            &new_entity_command("sensor", "input_number.target_temp", cmd_id, params),
+            None,
+        );
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid command must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[rstest]
+    #[case(json!(21.34), json!({ "value": 21.5 }))]
+    #[case(json!(21.2), json!({ "value": 21.0 }))]
+    #[case(json!(5), json!({ "value": 5.0 }))]
+    #[case(json!(30), json!({ "value": 30.0 }))]
+    fn set_value_is_rounded_to_step(#[case] value: Value, #[case] output: Value) {
+        let result = handle_number(
+            &new_entity_command(
+                "sensor",
+                "input_number.target_temp",
+                "set_value",
+                Some(json!({ "value": value })),
+            ),
+            Some(&ha_state(5.0, 30.0, 0.5)),
+        );
+        assert_eq!(Some(("set_value".to_string(), Some(output))), result.ok());
+    }
+
+    #[test]
+    fn set_value_rounding_avoids_float_artifacts() {
+        let result = handle_number(
+            &new_entity_command(
+                "sensor",
+                "input_number.target_temp",
+                "set_value",
+                Some(json!({ "value": 0.3 })),
+            ),
+            Some(&ha_state(0.0, 1.0, 0.1)),
+        );
+        assert_eq!(
+            Some(("set_value".to_string(), Some(json!({ "value": 0.3 })))),
+            result.ok()
+        );
+    }
+
+    #[test]
+    fn set_value_rounded_above_max_is_clamped() {
+        // steps from min: 0, 4, 8, 12 > max
+        let result = handle_number(
+            &new_entity_command(
+                "sensor",
+                "input_number.target_temp",
+                "set_value",
+                Some(json!({ "value": 10.0 })),
+            ),
+            Some(&ha_state(0.0, 10.0, 4.0)),
+        );
+        assert_eq!(
+            Some(("set_value".to_string(), Some(json!({ "value": 10.0 })))),
+            result.ok()
+        );
+    }
+
+    #[rstest]
+    #[case(json!(4.9))]
+    #[case(json!(30.5))]
+    #[case(json!(-10))]
+    fn set_value_out_of_range_returns_bad_request(#[case] value: Value) {
+        let result = handle_number(
+            &new_entity_command(
+                "sensor",
+                "input_number.target_temp",
+                "set_value",
+                Some(json!({ "value": value })),
+            ),
+            Some(&ha_state(5.0, 30.0, 0.5)),
+        );
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Out of range value must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn standalone_setpoint_number_control() {
+        let msg: EntityCommand = serde_json::from_value(json!({
+            "cmd_id": "set_value",
+            "entity_id": "number.floor_heating_setpoint",
+            "entity_type": "sensor",
+            "params": { "value": 21.3 }
+        }))
+        .expect("invalid test data");
+
+        let result = handle_number(&msg, Some(&ha_state(5.0, 30.0, 0.5)));
+
+        assert_eq!(
+            Ok(("set_value".to_string(), Some(json!({ "value": 21.5 })))),
+            result
+        );
+    }
+
+    #[rstest]
+    #[case("number.floor_heating_setpoint", json!(21.3), "number", json!({ "value": 21.5 }))]
+    #[case("input_number.target_temp", json!(22), "input_number", json!({ "value": 22.0 }))]
+    fn climate_setpoint(
+        #[case] number_id: &str,
+        #[case] temperature: Value,
+        #[case] domain: &str,
+        #[case] output: Value,
+    ) {
+        let result = handle_climate_setpoint(
+            &new_entity_command(
+                "climate",
+                "climate.floor_heating",
+                "target_temperature",
+                Some(json!({ "temperature": temperature })),
+            ),
+            number_id,
+            Some(&ha_state(5.0, 30.0, 0.5)),
+        );
+
+        assert_eq!(
+            Ok((domain.to_string(), "set_value".to_string(), Some(output))),
+            result
+        );
+    }
+
+    #[rstest]
+    #[case("number.floor_heating_setpoint", Some(json!({ "temperature": 35 })))]
+    #[case("number.floor_heating_setpoint", Some(json!({ "temperature": "warm" })))]
+    #[case("number.floor_heating_setpoint", None)]
+    #[case("sensor.floor_temperature", Some(json!({ "temperature": 21 })))]
+    fn invalid_climate_setpoint_returns_bad_request(
+        #[case] number_id: &str,
+        #[case] params: Option<Value>,
+    ) {
+        let result = handle_climate_setpoint(
+            &new_entity_command(
+                "climate",
+                "climate.floor_heating",
+                "target_temperature",
+                params,
+            ),
+            number_id,
+            Some(&ha_state(5.0, 30.0, 0.5)),
+        );
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid command must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    fn ha_state(min: f64, max: f64, step: f64) -> EventState {
+        serde_json::from_value(json!({
+            "state": "10",
+            "attributes": { "min": min, "max": max, "step": step }
+        }))
+        .expect("invalid test data")
+    }
+}
@@ -53,5 +53,86 @@ fn create_command(msg: &EntityCommand, cmd: &str) -> Result<(String, Option<Valu
     {
         data.insert("hold_secs".into(), value.into());
     }
+    // optional device of the remote, e.g. an activity device of a Harmony hub
+    if let Some(value) = params.get("device") {
+        match value.as_str().map(str::trim) {
+            Some(device) if !device.is_empty() => {
+                data.insert("device".into(), device.into());
+            }
+            _ => {
+                return Err(ServiceError::BadRequest(
+                    "Invalid params.device attribute".into(),
+                ))
+            }
+        }
+    }
     Ok(("send_command".into(), Some(data.into())))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case("send_cmd", json!({ "command": "VOLUME_UP", "device": "Denon AV Receiver" }), json!({ "command": "VOLUME_UP", "device": "Denon AV Receiver" }))]
+    #[case("send_cmd", json!({ "command": "VOLUME_UP", "device": " TV ", "repeat": 2 }), json!({ "command": "VOLUME_UP", "device": "TV", "num_repeats": 2 }))]
+    #[case("send_cmd_sequence", json!({ "sequence": ["1", "2"], "device": "TV" }), json!({ "command": ["1", "2"], "device": "TV" }))]
+    fn send_command_with_device(
+        #[case] cmd_id: &str,
+        #[case] params: Value,
+        #[case] output: Value,
+    ) {
+        let result = handle_remote(&new_entity_command(
+            "remote",
+            "remote.harmony_hub",
+            cmd_id,
+            Some(params),
+        ));
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("send_command", cmd);
+        assert_eq!(Some(output), data);
+    }
+
+    #[test]
+    fn send_command_without_device() {
+        let result = handle_remote(&new_entity_command(
+            "remote",
+            "remote.harmony_hub",
+            "send_cmd",
+            Some(json!({ "command": "POWER" })),
+        ));
+        assert_eq!(
+            Some((
+                "send_command".to_string(),
+                Some(json!({ "command": "POWER" }))
+            )),
+            result.ok()
+        );
+    }
+
+    #[rstest]
+    #[case(json!({ "command": "POWER", "device": "" }))]
+    #[case(json!({ "command": "POWER", "device": 1 }))]
+    #[case(json!({ "command": "POWER", "device": null }))]
+    fn send_command_with_invalid_device_returns_bad_request(#[case] params: Value) {
+        let result = handle_remote(&new_entity_command(
+            "remote",
+            "remote.harmony_hub",
+            "send_cmd",
+            Some(params),
+        ));
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid device must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+}
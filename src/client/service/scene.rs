@@ -0,0 +1,67 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Scene entity specific HA service call logic.
+
+use crate::client::entity::SCENE_CMD_ACTIVATE;
+use crate::client::service::cmd_from_str;
+use crate::errors::ServiceError;
+use serde_json::Value;
+use uc_api::intg::EntityCommand;
+use uc_api::ButtonCommand;
+
+pub(crate) fn handle_scene(msg: &EntityCommand) -> Result<(String, Option<Value>), ServiceError> {
+    if msg.cmd_id == SCENE_CMD_ACTIVATE {
+        return Ok(("turn_on".into(), None));
+    }
+
+    // a scene is exposed as button entity: also accept the button push command
+    let cmd: ButtonCommand = cmd_from_str(&msg.cmd_id)?;
+    let result = match cmd {
+        ButtonCommand::Push => ("turn_on".into(), None),
+    };
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("activate")]
+    #[case("push")]
+    fn activate_scene(#[case] cmd_id: &str) {
+        let result = handle_scene(&new_entity_command(
+            "button",
+            "scene.movie_night",
+            cmd_id,
+            None,
+        ));
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("turn_on", cmd);
+        assert!(data.is_none(), "no cmd data allowed");
+    }
+
+    #[test]
+    fn invalid_cmd_returns_bad_request() {
+        let result = handle_scene(&new_entity_command(
+            "button",
+            "scene.movie_night",
+            "off",
+            None,
+        ));
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid command must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+}
@@ -0,0 +1,227 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Select and input_select entity specific HA service call logic.
+
+use crate::client::entity::SELECT_CMD_SELECT_OPTION;
+use crate::client::model::EventState;
+use crate::client::service::{get_required_params, validate_list_value};
+use crate::errors::ServiceError;
+use serde_json::{json, Value};
+use uc_api::intg::EntityCommand;
+
+pub(crate) fn handle_select(
+    msg: &EntityCommand,
+    ha_state: Option<&EventState>,
+) -> Result<(String, Option<Value>), ServiceError> {
+    if msg.cmd_id != SELECT_CMD_SELECT_OPTION {
+        return Err(ServiceError::BadRequest(format!(
+            "Invalid cmd_id: {}. Valid commands: {SELECT_CMD_SELECT_OPTION}",
+            msg.cmd_id
+        )));
+    }
+
+    let params = get_required_params(msg)?;
+    let option = match params.get("option").and_then(|v| v.as_str()) {
+        Some(option) if !option.is_empty() => option,
+        _ => {
+            return Err(ServiceError::BadRequest(
+                "Invalid or missing params.option attribute".into(),
+            ))
+        }
+    };
+
+    select_option(option, ha_state)
+}
+
+/// Route the source selection of a media player to the associated select entity.
+///
+/// # Arguments
+///
+/// * `msg`: `select_source` command of the media player.
+/// * `select_id`: associated select or input_select entity of the media player inputs.
+/// * `ha_state`: last known HA state of the select entity, if available.
+///
+/// returns: HA service domain, service name and service_data payload for the select entity.
+pub(crate) fn handle_media_source(
+    msg: &EntityCommand,
+    select_id: &str,
+    ha_state: Option<&EventState>,
+) -> Result<(String, String, Option<Value>), ServiceError> {
+    let domain = match select_id.split_once('.') {
+        Some((domain @ ("select" | "input_select"), _)) => domain,
+        _ => {
+            return Err(ServiceError::BadRequest(format!(
+                "Invalid source select entity: {select_id}"
+            )))
+        }
+    };
+    let params = get_required_params(msg)?;
+    let source = match params.get("source").and_then(|v| v.as_str()) {
+        Some(source) if !source.is_empty() => source,
+        _ => {
+            return Err(ServiceError::BadRequest(
+                "Invalid or missing params.source attribute".into(),
+            ))
+        }
+    };
+
+    let (service, service_data) = select_option(source, ha_state)?;
+    Ok((domain.to_string(), service, service_data))
+}
+
+/// Create the `select_option` service call of an option.
+fn select_option(
+    option: &str,
+    ha_state: Option<&EventState>,
+) -> Result<(String, Option<Value>), ServiceError> {
+    // validate against the advertised options of the last known entity state
+    validate_list_value(ha_state, "options", "option", option)?;
+
+    Ok(("select_option".into(), Some(json!({ "option": option }))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+
+    fn ha_state() -> EventState {
+        serde_json::from_value(json!({
+            "state": "Normal",
+            "attributes": { "options": ["Normal", "Eco", "Turbo"] }
+        }))
+        .expect("invalid test data")
+    }
+
+    #[test]
+    fn select_option() {
+        let ha_state = ha_state();
+        let result = handle_select(
+            &new_entity_command(
+                "sensor",
+                "input_select.washing_program",
+                "select_option",
+                Some(json!({ "option": "Eco" })),
+            ),
+            Some(&ha_state),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("select_option", cmd);
+        assert_eq!(Some(json!({ "option": "Eco" })), data);
+    }
+
+    #[test]
+    fn select_option_without_known_options() {
+        let result = handle_select(
+            &new_entity_command(
+                "sensor",
+                "input_select.washing_program",
+                "select_option",
+                Some(json!({ "option": "Eco" })),
+            ),
+            None,
+        );
+
+        let (_, data) = result.expect("Option must be passed without known options");
+        assert_eq!(Some(json!({ "option": "Eco" })), data);
+    }
+
+    #[rstest]
+    #[case("select_option", Some(json!({ "option": "Extreme" })))]
+    #[case("select_option", Some(json!({ "option": "eco" })))]
+    #[case("select_option", Some(json!({ "option": "" })))]
+    #[case("select_option", None)]
+    #[case("on", None)]
+    fn invalid_cmd_returns_bad_request(#[case] cmd_id: &str, #[case] params: Option<Value>) {
+        let ha_state = ha_state();
+        let result = handle_select(
+            &new_entity_command("sensor", "input_select.washing_program", cmd_id, params),
+            Some(&ha_state),
+        );
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid command must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn media_source_routed_to_select() {
+        let ha_state = ha_state_with_options(&["TV", "Blu-ray", "Phono"]);
+        let result = handle_media_source(
+            &new_entity_command(
+                "media_player",
+                "media_player.receiver",
+                "select_source",
+                Some(json!({ "source": "Phono" })),
+            ),
+            "select.receiver_input",
+            Some(&ha_state),
+        );
+
+        let (domain, service, data) = result.expect("Expected successful cmd mapping");
+        assert_eq!("select", domain);
+        assert_eq!("select_option", service);
+        assert_eq!(Some(json!({ "option": "Phono" })), data);
+    }
+
+    #[test]
+    fn media_source_routed_to_input_select_without_known_options() {
+        let result = handle_media_source(
+            &new_entity_command(
+                "media_player",
+                "media_player.receiver",
+                "select_source",
+                Some(json!({ "source": "HDMI 1" })),
+            ),
+            "input_select.tv_input",
+            None,
+        );
+
+        let (domain, _, data) = result.expect("Expected successful cmd mapping");
+        assert_eq!("input_select", domain);
+        assert_eq!(Some(json!({ "option": "HDMI 1" })), data);
+    }
+
+    #[rstest]
+    #[case("select.receiver_input", Some(json!({ "source": "Tape" })))]
+    #[case("select.receiver_input", Some(json!({ "source": "" })))]
+    #[case("select.receiver_input", None)]
+    #[case("media_player.receiver_zone2", Some(json!({ "source": "TV" })))]
+    fn invalid_media_source_returns_bad_request(
+        #[case] select_id: &str,
+        #[case] params: Option<Value>,
+    ) {
+        let ha_state = ha_state_with_options(&["TV", "Blu-ray"]);
+        let result = handle_media_source(
+            &new_entity_command(
+                "media_player",
+                "media_player.receiver",
+                "select_source",
+                params,
+            ),
+            select_id,
+            Some(&ha_state),
+        );
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid source must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    fn ha_state_with_options(options: &[&str]) -> EventState {
+        serde_json::from_value(json!({
+            "state": options[0],
+            "attributes": { "options": options }
+        }))
+        .expect("invalid test data")
+    }
+}
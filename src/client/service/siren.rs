@@ -0,0 +1,214 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Siren entity specific HA service call logic.
+//!
+//! Sirens are exposed as switch entities: the `on` command accepts optional `tone`,
+//! `volume_level` and `duration` parameters.
+
+use crate::client::model::EventState;
+use crate::client::service::{cmd_from_str, unknown_value, validate_list_value};
+use crate::errors::ServiceError;
+use serde_json::{Map, Value};
+use uc_api::intg::EntityCommand;
+use uc_api::SwitchCommand;
+
+pub(crate) fn handle_siren(
+    msg: &EntityCommand,
+    ha_state: Option<&EventState>,
+) -> Result<(String, Option<Value>), ServiceError> {
+    let cmd: SwitchCommand = cmd_from_str(&msg.cmd_id)?;
+
+    let result = match cmd {
+        SwitchCommand::On => {
+            let data = turn_on_data(msg.params.as_ref(), ha_state)?;
+            ("turn_on".into(), (!data.is_empty()).then(|| data.into()))
+        }
+        SwitchCommand::Off => ("turn_off".into(), None),
+        SwitchCommand::Toggle => ("toggle".into(), None),
+    };
+
+    Ok(result)
+}
+
+/// Create the optional `turn_on` service data from the command parameters.
+fn turn_on_data(
+    params: Option<&Map<String, Value>>,
+    ha_state: Option<&EventState>,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut data = Map::new();
+    let params = match params {
+        None => return Ok(data),
+        Some(params) => params,
+    };
+
+    if let Some(tone) = params.get("tone") {
+        let tone = match tone.as_str() {
+            Some(tone) if !tone.is_empty() => tone,
+            _ => {
+                return Err(ServiceError::BadRequest(
+                    "Invalid params.tone attribute".into(),
+                ))
+            }
+        };
+        data.insert("tone".into(), validate_tone(ha_state, tone)?.into());
+    }
+    if let Some(volume) = params.get("volume_level") {
+        match volume.as_f64() {
+            Some(volume) if (0.0..=1.0).contains(&volume) => {
+                data.insert("volume_level".into(), volume.into());
+            }
+            _ => {
+                return Err(ServiceError::BadRequest(
+                    "Invalid params.volume_level attribute. Valid: 0.0..1.0".into(),
+                ))
+            }
+        }
+    }
+    if let Some(duration) = params.get("duration") {
+        match duration.as_u64() {
+            Some(duration) => {
+                data.insert("duration".into(), duration.into());
+            }
+            None => {
+                return Err(ServiceError::BadRequest(
+                    "Invalid params.duration attribute: seconds required".into(),
+                ))
+            }
+        }
+    }
+
+    Ok(data)
+}
+
+/// Validate a tone against the `available_tones` attribute of the last known HA entity state.
+///
+/// The available tones are either a list, or a map of tone id to tone name. A tone name is
+/// translated to its id. The tone is passed through if the available tones are not known.
+fn validate_tone(ha_state: Option<&EventState>, tone: &str) -> Result<String, ServiceError> {
+    let tones = match ha_state
+        .and_then(|s| s.attributes.as_ref())
+        .and_then(|attr| attr.get("available_tones"))
+    {
+        Some(Value::Array(_)) => {
+            validate_list_value(ha_state, "available_tones", "tone", tone)?;
+            return Ok(tone.to_string());
+        }
+        Some(Value::Object(tones)) => tones
+            .iter()
+            .map(|(id, name)| (id.clone(), name.as_str().unwrap_or(id).to_string()))
+            .collect::<Vec<_>>(),
+        _ => return Ok(tone.to_string()),
+    };
+
+    tones
+        .iter()
+        .find(|(id, name)| id == tone || name == tone)
+        .map(|(id, _)| id.clone())
+        .ok_or_else(|| unknown_value("tone", tone, tones.iter().map(|(_, name)| name.as_str())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case("on", "turn_on")]
+    #[case("off", "turn_off")]
+    #[case("toggle", "toggle")]
+    fn on_off_toggle(#[case] cmd_id: &str, #[case] service: &str) {
+        let result = handle_siren(
+            &new_entity_command("switch", "siren.hallway", cmd_id, None),
+            None,
+        );
+        assert_eq!(Some((service.to_string(), None)), result.ok());
+    }
+
+    #[rstest]
+    #[case(json!({ "tone": "alarm" }), json!({ "tone": "alarm" }))]
+    #[case(json!({ "volume_level": 0.5 }), json!({ "volume_level": 0.5 }))]
+    #[case(json!({ "duration": 10 }), json!({ "duration": 10 }))]
+    #[case(
+        json!({ "tone": "doorbell", "volume_level": 1.0, "duration": 5 }),
+        json!({ "tone": "doorbell", "volume_level": 1.0, "duration": 5 })
+    )]
+    fn turn_on_with_service_data(#[case] params: Value, #[case] output: Value) {
+        let result = handle_siren(
+            &new_entity_command("switch", "siren.hallway", "on", Some(params)),
+            Some(&ha_state(json!(["ding", "alarm", "doorbell"]))),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("turn_on", cmd);
+        assert_eq!(Some(output), data);
+    }
+
+    #[rstest]
+    #[case("1", "1")]
+    #[case("Fire alarm", "2")]
+    fn tone_name_is_mapped_to_tone_id(#[case] tone: &str, #[case] tone_id: &str) {
+        let result = handle_siren(
+            &new_entity_command(
+                "switch",
+                "siren.hallway",
+                "on",
+                Some(json!({ "tone": tone })),
+            ),
+            Some(&ha_state(json!({ "1": "Doorbell", "2": "Fire alarm" }))),
+        );
+        assert_eq!(
+            Some(("turn_on".to_string(), Some(json!({ "tone": tone_id })))),
+            result.ok()
+        );
+    }
+
+    #[test]
+    fn unknown_tones_are_passed_through() {
+        let result = handle_siren(
+            &new_entity_command(
+                "switch",
+                "siren.hallway",
+                "on",
+                Some(json!({ "tone": "ding" })),
+            ),
+            None,
+        );
+        assert_eq!(
+            Some(("turn_on".to_string(), Some(json!({ "tone": "ding" })))),
+            result.ok()
+        );
+    }
+
+    #[rstest]
+    #[case(json!({ "tone": "siren" }))]
+    #[case(json!({ "tone": "" }))]
+    #[case(json!({ "volume_level": 1.5 }))]
+    #[case(json!({ "volume_level": "loud" }))]
+    #[case(json!({ "duration": -1 }))]
+    fn invalid_params_return_bad_request(#[case] params: Value) {
+        let result = handle_siren(
+            &new_entity_command("switch", "siren.hallway", "on", Some(params)),
+            Some(&ha_state(json!(["ding", "alarm", "doorbell"]))),
+        );
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid params must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    fn ha_state(available_tones: Value) -> EventState {
+        serde_json::from_value(json!({
+            "state": "off",
+            "attributes": { "available_tones": available_tones }
+        }))
+        .expect("invalid test data")
+    }
+}
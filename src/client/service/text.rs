@@ -0,0 +1,130 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Text and input_text entity specific HA service call logic.
+
+use crate::client::entity::TEXT_CMD_SET_VALUE;
+use crate::client::model::EventState;
+use crate::client::service::get_required_params;
+use crate::errors::ServiceError;
+use serde_json::{json, Value};
+use uc_api::intg::EntityCommand;
+
+pub(crate) fn handle_text(
+    msg: &EntityCommand,
+    ha_state: Option<&EventState>,
+) -> Result<(String, Option<Value>), ServiceError> {
+    if msg.cmd_id != TEXT_CMD_SET_VALUE {
+        return Err(ServiceError::BadRequest(format!(
+            "Invalid cmd_id: {}. Valid commands: {TEXT_CMD_SET_VALUE}",
+            msg.cmd_id
+        )));
+    }
+
+    let params = get_required_params(msg)?;
+    let value = match params.get("value").and_then(|v| v.as_str()) {
+        Some(value) => value,
+        None => {
+            return Err(ServiceError::BadRequest(
+                "Invalid or missing params.value attribute".into(),
+            ))
+        }
+    };
+    validate_length(value, ha_state)?;
+
+    Ok(("set_value".into(), Some(json!({ "value": value }))))
+}
+
+/// Validate the text length against the `min` and `max` attributes of the last known entity state.
+///
+/// The optional `pattern` is validated by HA.
+fn validate_length(value: &str, ha_state: Option<&EventState>) -> Result<(), ServiceError> {
+    let attr = ha_state.and_then(|s| s.attributes.as_ref());
+    let length = |key: &str| attr.and_then(|a| a.get(key)).and_then(|v| v.as_u64());
+    let len = value.chars().count() as u64;
+
+    if length("min").is_some_and(|min| len < min) || length("max").is_some_and(|max| len > max) {
+        return Err(ServiceError::BadRequest(format!(
+            "Text length {len} is out of range: {}..{}",
+            length("min").map(|v| v.to_string()).unwrap_or_default(),
+            length("max").map(|v| v.to_string()).unwrap_or_default()
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+
+    fn ha_state() -> EventState {
+        serde_json::from_value(json!({
+            "state": "1234",
+            "attributes": { "min": 4, "max": 8, "pattern": "[0-9]*", "mode": "password" }
+        }))
+        .expect("invalid test data")
+    }
+
+    #[rstest]
+    #[case("5678")]
+    #[case("12345678")]
+    #[case("äöüéàèçñ")]
+    fn set_value(#[case] value: &str) {
+        let ha_state = ha_state();
+        let result = handle_text(
+            &new_entity_command(
+                "sensor",
+                "text.door_code",
+                "set_value",
+                Some(json!({ "value": value })),
+            ),
+            Some(&ha_state),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("set_value", cmd);
+        assert_eq!(Some(json!({ "value": value })), data);
+    }
+
+    #[test]
+    fn set_value_without_known_constraints() {
+        let result = handle_text(
+            &new_entity_command(
+                "sensor",
+                "text.door_code",
+                "set_value",
+                Some(json!({ "value": "" })),
+            ),
+            None,
+        );
+
+        let (_, data) = result.expect("Value must be passed without known constraints");
+        assert_eq!(Some(json!({ "value": "" })), data);
+    }
+
+    #[rstest]
+    #[case("set_value", Some(json!({ "value": "123" })))]
+    #[case("set_value", Some(json!({ "value": "123456789" })))]
+    #[case("set_value", Some(json!({ "value": 1234 })))]
+    #[case("set_value", None)]
+    #[case("on", None)]
+    fn invalid_cmd_returns_bad_request(#[case] cmd_id: &str, #[case] params: Option<Value>) {
+        let ha_state = ha_state();
+        let result = handle_text(
+            &new_entity_command("sensor", "text.door_code", cmd_id, params),
+            Some(&ha_state),
+        );
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid command must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+}
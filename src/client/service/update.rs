@@ -0,0 +1,55 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Update entity specific HA service call logic.
+
+use crate::client::entity::UPDATE_CMD_INSTALL;
+use crate::errors::ServiceError;
+use serde_json::Value;
+use uc_api::intg::EntityCommand;
+
+pub(crate) fn handle_update(msg: &EntityCommand) -> Result<(String, Option<Value>), ServiceError> {
+    if msg.cmd_id != UPDATE_CMD_INSTALL {
+        return Err(ServiceError::BadRequest(format!(
+            "Invalid cmd_id: {}. Valid commands: {UPDATE_CMD_INSTALL}",
+            msg.cmd_id
+        )));
+    }
+
+    Ok(("install".into(), None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+
+    #[test]
+    fn install() {
+        let result = handle_update(&new_entity_command(
+            "sensor",
+            "update.zigbee_bridge",
+            "install",
+            None,
+        ));
+        assert_eq!(Some(("install".to_string(), None)), result.ok());
+    }
+
+    #[rstest]
+    #[case("on")]
+    #[case("skip")]
+    fn invalid_cmd_returns_bad_request(#[case] cmd_id: &str) {
+        let result = handle_update(&new_entity_command(
+            "sensor",
+            "update.zigbee_bridge",
+            cmd_id,
+            None,
+        ));
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid command must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Vacuum entity specific HA service call logic.
+//!
+//! Vacuums are exposed as remote entities: the remote entity commands are mapped to vacuum
+//! services.
+
+use crate::client::entity::{
+    VACUUM_CMD_PAUSE, VACUUM_CMD_RETURN_HOME, VACUUM_CMD_START, VACUUM_CMD_STOP,
+};
+use crate::client::service::{cmd_from_str, get_required_params};
+use crate::errors::ServiceError;
+use serde_json::{json, Value};
+use uc_api::intg::{EntityCommand, IntgRemoteCommand};
+
+pub(crate) fn handle_vacuum(msg: &EntityCommand) -> Result<(String, Option<Value>), ServiceError> {
+    // vacuum specific command not defined in the Integration-API IntgRemoteCommand enum
+    if msg.cmd_id == "fan_speed" {
+        return set_fan_speed(msg);
+    }
+
+    let cmd: IntgRemoteCommand = cmd_from_str(&msg.cmd_id)?;
+
+    let result = match cmd {
+        IntgRemoteCommand::On => ("start".into(), None),
+        IntgRemoteCommand::Off => ("return_to_base".into(), None),
+        IntgRemoteCommand::SendCmd => {
+            let params = get_required_params(msg)?;
+            let command = params
+                .get("command")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            let service = match command {
+                VACUUM_CMD_START => "start",
+                VACUUM_CMD_STOP => "stop",
+                VACUUM_CMD_PAUSE => "pause",
+                VACUUM_CMD_RETURN_HOME => "return_to_base",
+                _ => {
+                    return Err(ServiceError::BadRequest(format!(
+                        "Invalid or missing params.command attribute: {command}"
+                    )))
+                }
+            };
+            (service.into(), None)
+        }
+        IntgRemoteCommand::Toggle | IntgRemoteCommand::SendCmdSequence => {
+            return Err(ServiceError::BadRequest(format!(
+                "Command not supported for vacuum: {}",
+                msg.cmd_id
+            )))
+        }
+    };
+
+    Ok(result)
+}
+
+fn set_fan_speed(msg: &EntityCommand) -> Result<(String, Option<Value>), ServiceError> {
+    let params = get_required_params(msg)?;
+    match params.get("fan_speed").and_then(|v| v.as_str()) {
+        Some(speed) if !speed.is_empty() => {
+            Ok(("set_fan_speed".into(), Some(json!({ "fan_speed": speed }))))
+        }
+        _ => Err(ServiceError::BadRequest(
+            "Invalid or missing params.fan_speed attribute".into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("START", "start")]
+    #[case("STOP", "stop")]
+    #[case("PAUSE", "pause")]
+    #[case("RETURN_HOME", "return_to_base")]
+    fn send_cmd(#[case] command: &str, #[case] service: &str) {
+        let result = handle_vacuum(&new_entity_command(
+            "remote",
+            "vacuum.roborock",
+            "send_cmd",
+            Some(json!({ "command": command })),
+        ));
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!(service, cmd);
+        assert!(data.is_none(), "no cmd data allowed");
+    }
+
+    #[rstest]
+    #[case("on", "start")]
+    #[case("off", "return_to_base")]
+    fn on_off(#[case] cmd_id: &str, #[case] service: &str) {
+        let result = handle_vacuum(&new_entity_command(
+            "remote",
+            "vacuum.roborock",
+            cmd_id,
+            None,
+        ));
+        assert_eq!(Some(service.to_string()), result.ok().map(|(cmd, _)| cmd));
+    }
+
+    #[test]
+    fn set_fan_speed() {
+        let result = handle_vacuum(&new_entity_command(
+            "remote",
+            "vacuum.roborock",
+            "fan_speed",
+            Some(json!({ "fan_speed": "Turbo" })),
+        ));
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("set_fan_speed", cmd);
+        assert_eq!(Some(json!({ "fan_speed": "Turbo" })), data);
+    }
+
+    #[rstest]
+    #[case("send_cmd", Some(json!({ "command": "LOCATE" })))]
+    #[case("send_cmd", None)]
+    #[case("fan_speed", Some(json!({ "fan_speed": "" })))]
+    #[case("toggle", None)]
+    fn invalid_cmd_returns_bad_request(#[case] cmd_id: &str, #[case] params: Option<Value>) {
+        let result = handle_vacuum(&new_entity_command(
+            "remote",
+            "vacuum.roborock",
+            cmd_id,
+            params,
+        ));
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid command must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Water heater entity specific HA service call logic.
+//!
+//! Water heaters are exposed as climate entities with an additional operation mode command.
+
+use crate::client::entity::WATER_HEATER_CMD_OPERATION_MODE;
+use crate::client::model::EventState;
+use crate::client::service::{cmd_from_str, get_required_params, validate_list_value};
+use crate::errors::ServiceError;
+use serde_json::{json, Value};
+use uc_api::intg::EntityCommand;
+use uc_api::ClimateCommand;
+
+pub(crate) fn handle_water_heater(
+    msg: &EntityCommand,
+    ha_state: Option<&EventState>,
+) -> Result<(String, Option<Value>), ServiceError> {
+    // water heater specific command not defined in the Integration-API ClimateCommand enum
+    if msg.cmd_id == WATER_HEATER_CMD_OPERATION_MODE {
+        return set_operation_mode(msg, ha_state);
+    }
+
+    let cmd: ClimateCommand = cmd_from_str(&msg.cmd_id)?;
+
+    let result = match cmd {
+        ClimateCommand::On => ("turn_on".into(), None),
+        ClimateCommand::Off => ("turn_off".into(), None),
+        ClimateCommand::HvacMode => {
+            let params = get_required_params(msg)?;
+            match params.get("hvac_mode").and_then(|v| v.as_str()) {
+                Some("HEAT") => ("turn_on".into(), None),
+                Some("OFF") => ("turn_off".into(), None),
+                mode => {
+                    return Err(ServiceError::BadRequest(format!(
+                        "Invalid or missing params.hvac_mode attribute: {}. Valid: HEAT, OFF",
+                        mode.unwrap_or_default()
+                    )))
+                }
+            }
+        }
+        ClimateCommand::TargetTemperature => {
+            let params = get_required_params(msg)?;
+            if let Some(temp) = params.get("temperature").and_then(|v| v.as_f64()) {
+                (
+                    "set_temperature".into(),
+                    Some(json!({ "temperature": temp })),
+                )
+            } else {
+                return Err(ServiceError::BadRequest(
+                    "Invalid or missing params.temperature attribute".into(),
+                ));
+            }
+        }
+    };
+
+    Ok(result)
+}
+
+fn set_operation_mode(
+    msg: &EntityCommand,
+    ha_state: Option<&EventState>,
+) -> Result<(String, Option<Value>), ServiceError> {
+    let params = get_required_params(msg)?;
+    let mode = match params.get("operation_mode").and_then(|v| v.as_str()) {
+        Some(mode) if !mode.is_empty() => mode,
+        _ => {
+            return Err(ServiceError::BadRequest(
+                "Invalid or missing params.operation_mode attribute".into(),
+            ))
+        }
+    };
+
+    // validate against the available operation modes, if known
+    validate_list_value(ha_state, "operation_list", "operation mode", mode)?;
+
+    Ok((
+        "set_operation_mode".into(),
+        Some(json!({ "operation_mode": mode })),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("on", None, "turn_on")]
+    #[case("off", None, "turn_off")]
+    #[case("hvac_mode", Some(json!({ "hvac_mode": "HEAT" })), "turn_on")]
+    #[case("hvac_mode", Some(json!({ "hvac_mode": "OFF" })), "turn_off")]
+    fn on_off(#[case] cmd_id: &str, #[case] params: Option<Value>, #[case] service: &str) {
+        let result = handle_water_heater(
+            &new_entity_command("climate", "water_heater.boiler", cmd_id, params),
+            None,
+        );
+        assert_eq!(Some(service.to_string()), result.ok().map(|(cmd, _)| cmd));
+    }
+
+    #[test]
+    fn target_temperature() {
+        let result = handle_water_heater(
+            &new_entity_command(
+                "climate",
+                "water_heater.boiler",
+                "target_temperature",
+                Some(json!({ "temperature": 52.5 })),
+            ),
+            None,
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("set_temperature", cmd);
+        assert_eq!(Some(json!({ "temperature": 52.5 })), data);
+    }
+
+    #[test]
+    fn operation_mode() {
+        let result = handle_water_heater(
+            &new_entity_command(
+                "climate",
+                "water_heater.boiler",
+                "operation_mode",
+                Some(json!({ "operation_mode": "performance" })),
+            ),
+            Some(&ha_state()),
+        );
+        assert!(
+            result.is_ok(),
+            "Expected successful cmd mapping but got: {:?}",
+            result.unwrap_err()
+        );
+        let (cmd, data) = result.unwrap();
+        assert_eq!("set_operation_mode", cmd);
+        assert_eq!(Some(json!({ "operation_mode": "performance" })), data);
+    }
+
+    #[rstest]
+    #[case("operation_mode", Some(json!({ "operation_mode": "gas" })))]
+    #[case("operation_mode", Some(json!({ "operation_mode": "" })))]
+    #[case("operation_mode", None)]
+    #[case("hvac_mode", Some(json!({ "hvac_mode": "COOL" })))]
+    #[case("target_temperature", Some(json!({ "temperature": "hot" })))]
+    fn invalid_cmd_returns_bad_request(#[case] cmd_id: &str, #[case] params: Option<Value>) {
+        let result = handle_water_heater(
+            &new_entity_command("climate", "water_heater.boiler", cmd_id, params),
+            Some(&ha_state()),
+        );
+        assert!(
+            matches!(result, Err(ServiceError::BadRequest(_))),
+            "Invalid command must return BadRequest, but got: {:?}",
+            result
+        );
+    }
+
+    fn ha_state() -> EventState {
+        serde_json::from_value(json!({
+            "state": "eco",
+            "attributes": {
+                "operation_list": ["eco", "electric", "performance", "off"]
+            }
+        }))
+        .expect("invalid test data")
+    }
+}
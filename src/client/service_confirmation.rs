@@ -0,0 +1,238 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Confirm service calls of the integration with HA `call_service` events.
+//!
+//! HA fires a `call_service` event when a service is called, and returns the context of the
+//! service call in the `call_service` result. The event is usually received before the result,
+//! therefore the correlation by context id works in both directions.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use uc_api::intg::EntityCommand;
+use uc_api::EntityType;
+
+/// Time to keep pending requests and service call contexts for the correlation.
+const CONTEXT_TTL: Duration = Duration::from_secs(30);
+
+/// Entity command of a pending service call.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PendingCommand {
+    pub entity_id: String,
+    pub entity_type: EntityType,
+    pub cmd_id: String,
+    created: Instant,
+}
+
+/// Correlate `call_service` events with the service calls issued by the integration.
+pub(crate) struct ServiceConfirmation {
+    enabled: bool,
+    /// Pending `call_service` requests by request id
+    pending_requests: HashMap<u32, PendingCommand>,
+    /// Executed service calls by context id, waiting for the `call_service` event
+    pending_contexts: HashMap<String, PendingCommand>,
+    /// Context ids of received `call_service` events, waiting for the service call result
+    event_contexts: HashMap<String, Instant>,
+}
+
+impl ServiceConfirmation {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            pending_requests: Default::default(),
+            pending_contexts: Default::default(),
+            event_contexts: Default::default(),
+        }
+    }
+
+    /// Track a sent `call_service` request of an entity command.
+    pub fn track_request(&mut self, id: u32, command: &EntityCommand, now: Instant) {
+        if !self.enabled {
+            return;
+        }
+        self.expire(now);
+        self.pending_requests.insert(
+            id,
+            PendingCommand {
+                entity_id: command.entity_id.clone(),
+                entity_type: command.entity_type.clone(),
+                cmd_id: command.cmd_id.clone(),
+                created: now,
+            },
+        );
+    }
+
+    /// Handle a result message.
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: request id of the result message.
+    /// * `success`: `success` field of the result message.
+    /// * `result`: `result` field of the result message containing the service call context.
+    /// * `now`: time of the result message.
+    ///
+    /// returns: the confirmed command, if the `call_service` event has already been received.
+    pub fn handle_result(
+        &mut self,
+        id: u32,
+        success: bool,
+        result: Option<&Value>,
+        now: Instant,
+    ) -> Option<PendingCommand> {
+        let command = self.pending_requests.remove(&id)?;
+        if !success {
+            return None;
+        }
+        let context_id = result
+            .and_then(|v| v.pointer("/context/id"))
+            .and_then(|v| v.as_str())?;
+
+        if self.event_contexts.remove(context_id).is_some() {
+            return Some(command);
+        }
+        self.pending_contexts.insert(
+            context_id.to_string(),
+            PendingCommand {
+                created: now,
+                ..command
+            },
+        );
+        None
+    }
+
+    /// Handle a `call_service` event with the given context id.
+    ///
+    /// returns: the confirmed command, if the service call result has already been received.
+    pub fn handle_event(
+        &mut self,
+        context_id: Option<&str>,
+        now: Instant,
+    ) -> Option<PendingCommand> {
+        let context_id = context_id?;
+        if let Some(command) = self.pending_contexts.remove(context_id) {
+            return Some(command);
+        }
+        // only remember events while own service calls are in flight
+        if !self.pending_requests.is_empty() {
+            self.event_contexts.insert(context_id.to_string(), now);
+        }
+        None
+    }
+
+    fn expire(&mut self, now: Instant) {
+        self.pending_requests
+            .retain(|_, cmd| now.saturating_duration_since(cmd.created) < CONTEXT_TTL);
+        self.pending_contexts
+            .retain(|_, cmd| now.saturating_duration_since(cmd.created) < CONTEXT_TTL);
+        self.event_contexts
+            .retain(|_, created| now.saturating_duration_since(*created) < CONTEXT_TTL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::service::new_entity_command;
+    use serde_json::json;
+
+    fn service_result(context_id: &str) -> Value {
+        json!({
+            "context": {
+                "id": context_id,
+                "parent_id": null,
+                "user_id": "b1f6a0e0"
+            }
+        })
+    }
+
+    #[test]
+    fn event_before_result_confirms_command() {
+        let now = Instant::now();
+        let mut confirmation = ServiceConfirmation::new(true);
+        confirmation.track_request(
+            5,
+            &new_entity_command("light", "light.kitchen", "on", None),
+            now,
+        );
+
+        assert_eq!(None, confirmation.handle_event(Some("01HX"), now));
+        let result = confirmation.handle_result(5, true, Some(&service_result("01HX")), now);
+
+        let command = result.expect("Expected a confirmed command");
+        assert_eq!("light.kitchen", command.entity_id);
+        assert_eq!(EntityType::Light, command.entity_type);
+        assert_eq!("on", command.cmd_id);
+    }
+
+    #[test]
+    fn event_after_result_confirms_command() {
+        let now = Instant::now();
+        let mut confirmation = ServiceConfirmation::new(true);
+        confirmation.track_request(
+            5,
+            &new_entity_command("light", "light.kitchen", "on", None),
+            now,
+        );
+
+        assert_eq!(
+            None,
+            confirmation.handle_result(5, true, Some(&service_result("01HX")), now)
+        );
+        let result = confirmation.handle_event(Some("01HX"), now + Duration::from_secs(1));
+
+        assert_eq!(
+            Some("light.kitchen"),
+            result.as_ref().map(|c| c.entity_id.as_str())
+        );
+    }
+
+    #[test]
+    fn event_with_other_context_is_not_correlated() {
+        let now = Instant::now();
+        let mut confirmation = ServiceConfirmation::new(true);
+        confirmation.track_request(
+            5,
+            &new_entity_command("light", "light.kitchen", "on", None),
+            now,
+        );
+        confirmation.handle_result(5, true, Some(&service_result("01HX")), now);
+
+        assert_eq!(None, confirmation.handle_event(Some("01HY"), now));
+        assert_eq!(None, confirmation.handle_event(None, now));
+    }
+
+    #[test]
+    fn failed_service_call_is_not_confirmed() {
+        let now = Instant::now();
+        let mut confirmation = ServiceConfirmation::new(true);
+        confirmation.track_request(
+            5,
+            &new_entity_command("light", "light.kitchen", "on", None),
+            now,
+        );
+
+        confirmation.handle_event(Some("01HX"), now);
+        assert_eq!(
+            None,
+            confirmation.handle_result(5, false, Some(&service_result("01HX")), now)
+        );
+    }
+
+    #[test]
+    fn disabled_confirmation_never_confirms_commands() {
+        let now = Instant::now();
+        let mut confirmation = ServiceConfirmation::new(false);
+        confirmation.track_request(
+            5,
+            &new_entity_command("light", "light.kitchen", "on", None),
+            now,
+        );
+
+        confirmation.handle_event(Some("01HX"), now);
+        assert_eq!(
+            None,
+            confirmation.handle_result(5, true, Some(&service_result("01HX")), now)
+        );
+    }
+}
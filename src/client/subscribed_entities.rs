@@ -1,7 +1,20 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Actix actor handler implementation for the `SubscribedEntities` message and domain
+//! subscription handling.
+
+use crate::client::attribute_entities::with_parent_entities;
+use crate::client::climate_setpoint::with_setpoint_numbers;
+use crate::client::favorites::with_favorites;
 use crate::client::messages::SubscribedEntities;
 use crate::client::HomeAssistantClient;
-use actix::Handler;
+use actix::{Context, Handler};
 use log::debug;
+use std::collections::HashSet;
+
+/// Suffix of a domain subscription entry. E.g. `light.*` subscribes to all light entities.
+pub const DOMAIN_SUBSCRIPTION_SUFFIX: &str = ".*";
 
 impl Handler<SubscribedEntities> for HomeAssistantClient {
     type Result = ();
@@ -26,3 +39,175 @@ impl Handler<SubscribedEntities> for HomeAssistantClient {
         self.subscribe_uc_events(ctx);
     }
 }
+
+impl HomeAssistantClient {
+    /// Subscribed entity ids including the favorite entities with expanded domain subscriptions.
+    ///
+    /// Domain subscriptions are expanded against the currently known HA entities. Derived
+    /// attribute entities are replaced by their HA entity, and the associated setpoint numbers of
+    /// climate entities are added.
+    pub(crate) fn expanded_subscribed_entities(&self) -> HashSet<String> {
+        with_setpoint_numbers(
+            with_parent_entities(expand_subscriptions(
+                &with_favorites(&self.subscribed_entities, &self.settings.favorite_entities),
+                self.entity_states.keys(),
+            )),
+            &self.settings.climate_setpoint_numbers,
+        )
+    }
+
+    /// Renew the UC HA component event subscription if domain subscriptions are used.
+    ///
+    /// Must be called after the known HA entities changed, to include new entities of a
+    /// subscribed domain.
+    pub(crate) fn refresh_domain_subscriptions(&mut self, ctx: &mut Context<HomeAssistantClient>) {
+        if !self.uc_ha_component
+            || self.subscribe_uc_events_id.is_none()
+            || !has_domain_subscription(&self.subscribed_entities)
+        {
+            return;
+        }
+        debug!("[{}] Renewing domain subscriptions", self.id);
+        self.unsubscribe_uc_events(ctx);
+        self.subscribe_uc_events(ctx);
+    }
+}
+
+/// Check if the entity states of the subscriptions can be requested from the UC HA component.
+///
+/// The UC HA component only returns the states of the given entity ids, and domain subscriptions
+/// are expanded against the known HA entities. Therefore, all HA entities have to be loaded with
+/// `get_states` before, e.g. for the first request after connecting.
+pub(crate) fn uc_states_request(
+    uc_ha_component: bool,
+    subscriptions: &HashSet<String>,
+    entity_list_loaded: bool,
+) -> bool {
+    uc_ha_component && (entity_list_loaded || !has_domain_subscription(subscriptions))
+}
+
+/// Check if the subscriptions contain at least one domain subscription.
+fn has_domain_subscription(subscriptions: &HashSet<String>) -> bool {
+    subscriptions
+        .iter()
+        .any(|s| s.ends_with(DOMAIN_SUBSCRIPTION_SUFFIX))
+}
+
+/// Expand domain subscriptions to the entity ids of the given domains.
+///
+/// # Arguments
+///
+/// * `subscriptions`: subscribed entity ids and domains, e.g. `light.*`.
+/// * `entity_ids`: known entity ids.
+///
+/// returns: subscribed entity ids without domain entries.
+pub(crate) fn expand_subscriptions<'a>(
+    subscriptions: &HashSet<String>,
+    entity_ids: impl IntoIterator<Item = &'a String>,
+) -> HashSet<String> {
+    let domains: HashSet<&str> = subscriptions
+        .iter()
+        .filter_map(|s| s.strip_suffix(DOMAIN_SUBSCRIPTION_SUFFIX))
+        .collect();
+
+    let mut expanded: HashSet<String> = subscriptions
+        .iter()
+        .filter(|s| !s.ends_with(DOMAIN_SUBSCRIPTION_SUFFIX))
+        .cloned()
+        .collect();
+
+    if !domains.is_empty() {
+        expanded.extend(
+            entity_ids
+                .into_iter()
+                .filter(|id| {
+                    id.split_once('.')
+                        .map(|(domain, _)| domains.contains(domain))
+                        .unwrap_or_default()
+                })
+                .cloned(),
+        );
+    }
+
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity_ids() -> Vec<String> {
+        vec![
+            "light.kitchen".into(),
+            "light.office".into(),
+            "switch.fan".into(),
+            "media_player.tv".into(),
+        ]
+    }
+
+    fn subscriptions(entries: &[&str]) -> HashSet<String> {
+        entries.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn expand_domain_subscription() {
+        let result = expand_subscriptions(&subscriptions(&["light.*"]), &entity_ids());
+
+        assert_eq!(subscriptions(&["light.kitchen", "light.office"]), result);
+    }
+
+    #[test]
+    fn expand_domain_and_entity_subscriptions() {
+        let result = expand_subscriptions(
+            &subscriptions(&["light.*", "switch.fan", "light.kitchen"]),
+            &entity_ids(),
+        );
+
+        assert_eq!(
+            subscriptions(&["light.kitchen", "light.office", "switch.fan"]),
+            result
+        );
+    }
+
+    #[test]
+    fn expand_without_domain_subscription_keeps_entities() {
+        let result = expand_subscriptions(
+            &subscriptions(&["switch.fan", "climate.unknown"]),
+            &entity_ids(),
+        );
+
+        assert_eq!(subscriptions(&["switch.fan", "climate.unknown"]), result);
+    }
+
+    #[test]
+    fn expand_unknown_domain_returns_no_entities() {
+        let result = expand_subscriptions(&subscriptions(&["cover.*"]), &entity_ids());
+
+        assert!(result.is_empty(), "Unexpected entities: {result:?}");
+    }
+
+    #[test]
+    fn expand_domain_does_not_match_domain_prefix() {
+        let entity_ids = vec!["light_group.all".to_string(), "light.kitchen".to_string()];
+        let result = expand_subscriptions(&subscriptions(&["light.*"]), &entity_ids);
+
+        assert_eq!(subscriptions(&["light.kitchen"]), result);
+    }
+
+    #[test]
+    fn first_get_states_after_connect_loads_all_entities_for_domain_subscription() {
+        let subscriptions = subscriptions(&["light.*", "switch.fan"]);
+
+        assert!(!uc_states_request(true, &subscriptions, false));
+        // all HA entities are known after the first request
+        assert!(uc_states_request(true, &subscriptions, true));
+    }
+
+    #[test]
+    fn get_states_without_domain_subscription_uses_uc_component() {
+        let subscriptions = subscriptions(&["light.kitchen", "switch.fan"]);
+
+        assert!(uc_states_request(true, &subscriptions, false));
+        assert!(!uc_states_request(false, &subscriptions, false));
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Temperature unit conversion of climate & water heater entities.
+//!
+//! If a temperature unit is forced in the configuration, the temperature values of entities using
+//! a different unit in HA are converted between Celsius and Fahrenheit.
+
+use serde_json::{Map, Value};
+use uc_api::intg::{AvailableIntgEntity, EntityCommand};
+use uc_api::ClimateOptionField;
+
+/// Temperature attributes of an entity.
+const TEMPERATURE_ATTRIBUTES: [&str; 4] = [
+    "current_temperature",
+    "target_temperature",
+    "target_temperature_high",
+    "target_temperature_low",
+];
+/// Temperature command parameters.
+const TEMPERATURE_PARAMS: [&str; 3] = ["temperature", "target_temp_high", "target_temp_low"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    /// Get the temperature unit of a HA temperature unit symbol.
+    pub fn from_ha_unit(unit: &str) -> Option<Self> {
+        match unit {
+            "°C" => Some(Self::Celsius),
+            "°F" => Some(Self::Fahrenheit),
+            _ => None,
+        }
+    }
+
+    /// Get the HA temperature unit symbol.
+    pub fn ha_unit(&self) -> &'static str {
+        match self {
+            Self::Celsius => "°C",
+            Self::Fahrenheit => "°F",
+        }
+    }
+
+    /// Get the Integration-API temperature unit.
+    fn api_unit(&self) -> &'static str {
+        match self {
+            Self::Celsius => "CELSIUS",
+            Self::Fahrenheit => "FAHRENHEIT",
+        }
+    }
+}
+
+/// Conversion of temperature values from one unit to another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct TemperatureConversion {
+    from: TemperatureUnit,
+    to: TemperatureUnit,
+}
+
+impl TemperatureConversion {
+    /// Create a conversion between two units. Returns `None` if the units are the same.
+    pub fn new(from: TemperatureUnit, to: TemperatureUnit) -> Option<Self> {
+        (from != to).then_some(Self { from, to })
+    }
+
+    /// Get the conversion in the opposite direction.
+    pub fn reverse(&self) -> Self {
+        Self {
+            from: self.to,
+            to: self.from,
+        }
+    }
+
+    /// Convert a temperature value, rounded to one decimal place.
+    pub fn convert(&self, value: f64) -> f64 {
+        let value = match (self.from, self.to) {
+            (TemperatureUnit::Celsius, TemperatureUnit::Fahrenheit) => value * 9.0 / 5.0 + 32.0,
+            (TemperatureUnit::Fahrenheit, TemperatureUnit::Celsius) => (value - 32.0) * 5.0 / 9.0,
+            _ => value,
+        };
+        (value * 10.0).round() / 10.0
+    }
+
+    /// Convert the numeric temperature fields of a JSON object. Other values are left as is.
+    fn convert_fields(
+        &self,
+        map: &mut Map<String, Value>,
+        keys: impl IntoIterator<Item = impl AsRef<str>>,
+    ) {
+        for key in keys {
+            let key = key.as_ref();
+            if let Some(value) = map.get(key).and_then(|v| v.as_f64()) {
+                map.insert(key.to_string(), self.convert(value).into());
+            }
+        }
+    }
+
+    /// Convert the temperature attributes of an entity change or entity.
+    pub fn convert_attributes(&self, attributes: &mut Map<String, Value>) {
+        self.convert_fields(attributes, TEMPERATURE_ATTRIBUTES);
+    }
+
+    /// Convert the temperature options and attributes of an available entity.
+    ///
+    /// The temperature step is not converted.
+    pub fn convert_entity(&self, entity: &mut AvailableIntgEntity) {
+        let options = entity.options.get_or_insert_with(Default::default);
+        self.convert_fields(
+            options,
+            [
+                ClimateOptionField::MinTemperature.to_string(),
+                ClimateOptionField::MaxTemperature.to_string(),
+            ],
+        );
+        options.insert(
+            ClimateOptionField::TemperatureUnit.to_string(),
+            self.to.api_unit().into(),
+        );
+        if let Some(attributes) = entity.attributes.as_mut() {
+            self.convert_attributes(attributes);
+        }
+    }
+
+    /// Convert the temperature parameters of an entity command.
+    pub fn convert_command(&self, command: &mut EntityCommand) {
+        if let Some(params) = command.params.as_mut() {
+            self.convert_fields(params, TEMPERATURE_PARAMS);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+    use uc_api::EntityType;
+
+    const C_TO_F: TemperatureConversion = TemperatureConversion {
+        from: TemperatureUnit::Celsius,
+        to: TemperatureUnit::Fahrenheit,
+    };
+
+    #[rstest]
+    #[case(0.0, 32.0)]
+    #[case(21.5, 70.7)]
+    #[case(-40.0, -40.0)]
+    #[case(100.0, 212.0)]
+    fn celsius_to_fahrenheit(#[case] celsius: f64, #[case] fahrenheit: f64) {
+        assert_eq!(fahrenheit, C_TO_F.convert(celsius));
+        assert_eq!(celsius, C_TO_F.reverse().convert(fahrenheit));
+    }
+
+    #[test]
+    fn same_unit_has_no_conversion() {
+        assert_eq!(
+            None,
+            TemperatureConversion::new(TemperatureUnit::Celsius, TemperatureUnit::Celsius)
+        );
+    }
+
+    #[test]
+    fn convert_all_entity_temperature_fields() {
+        let mut entity = AvailableIntgEntity {
+            entity_id: "climate.living_room".into(),
+            device_id: None,
+            entity_type: EntityType::Climate,
+            device_class: None,
+            name: Default::default(),
+            features: None,
+            area: None,
+            options: json!({
+                "min_temperature": 7,
+                "max_temperature": 35,
+                "target_temperature_step": 0.5,
+                "temperature_unit": "CELSIUS"
+            })
+            .as_object()
+            .cloned(),
+            attributes: json!({
+                "state": "HEAT",
+                "current_temperature": 20.0,
+                "target_temperature": 21.5,
+                "target_temperature_high": 25,
+                "target_temperature_low": 18
+            })
+            .as_object()
+            .cloned(),
+        };
+
+        C_TO_F.convert_entity(&mut entity);
+
+        assert_eq!(
+            Some(json!({
+                "min_temperature": 44.6,
+                "max_temperature": 95.0,
+                "target_temperature_step": 0.5,
+                "temperature_unit": "FAHRENHEIT"
+            })),
+            entity.options.map(Value::Object)
+        );
+        assert_eq!(
+            Some(json!({
+                "state": "HEAT",
+                "current_temperature": 68.0,
+                "target_temperature": 70.7,
+                "target_temperature_high": 77.0,
+                "target_temperature_low": 64.4
+            })),
+            entity.attributes.map(Value::Object)
+        );
+    }
+
+    #[test]
+    fn null_temperature_is_not_converted() {
+        let mut attributes = json!({ "target_temperature": null, "current_temperature": 10 })
+            .as_object()
+            .cloned()
+            .unwrap();
+
+        C_TO_F.convert_attributes(&mut attributes);
+
+        assert_eq!(
+            json!({ "target_temperature": null, "current_temperature": 50.0 }),
+            Value::Object(attributes)
+        );
+    }
+
+    #[test]
+    fn convert_command_temperature_to_ha_unit() {
+        let mut command: EntityCommand = serde_json::from_value(json!({
+            "cmd_id": "target_temperature",
+            "entity_id": "climate.living_room",
+            "entity_type": "climate",
+            "params": { "temperature": 70.7 }
+        }))
+        .unwrap();
+
+        C_TO_F.reverse().convert_command(&mut command);
+
+        assert_eq!(
+            Some(&json!(21.5)),
+            command.params.as_ref().and_then(|p| p.get("temperature"))
+        );
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Bounded retry of the UC HA component `unfoldedcircle/info` request.
+//!
+//! The custom messages of the UC HA component aren't available right after a HA restart. Instead
+//! of immediately falling back to the standard HA events, a failed info request is retried with
+//! the next component check, until the configured number of retries is exhausted.
+
+/// Action after an `unfoldedcircle/info` result.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum UcInfoAction {
+    /// The UC HA component is available.
+    Available,
+    /// Wait for the next component check without falling back to standard events.
+    Retry,
+    /// Fall back to the standard HA events.
+    Unavailable,
+}
+
+/// Retry counter of failed `unfoldedcircle/info` requests after authentication.
+#[derive(Debug)]
+pub(crate) struct UcInfoRetry {
+    max_retries: u16,
+    retries: u16,
+}
+
+impl UcInfoRetry {
+    pub fn new(max_retries: u16) -> Self {
+        Self {
+            max_retries,
+            retries: 0,
+        }
+    }
+
+    /// Reset the retry counter, e.g. after a new authentication.
+    pub fn reset(&mut self) {
+        self.retries = 0;
+    }
+
+    /// Get the action for an `unfoldedcircle/info` result.
+    pub fn handle_result(&mut self, success: bool) -> UcInfoAction {
+        if success {
+            self.retries = 0;
+            return UcInfoAction::Available;
+        }
+        if self.retries < self.max_retries {
+            self.retries += 1;
+            UcInfoAction::Retry
+        } else {
+            UcInfoAction::Unavailable
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_then_success() {
+        let mut retry = UcInfoRetry::new(3);
+
+        assert_eq!(UcInfoAction::Retry, retry.handle_result(false));
+        assert_eq!(UcInfoAction::Retry, retry.handle_result(false));
+        assert_eq!(UcInfoAction::Available, retry.handle_result(true));
+    }
+
+    #[test]
+    fn exhausted_retries_fall_back_to_standard_events() {
+        let mut retry = UcInfoRetry::new(2);
+
+        assert_eq!(UcInfoAction::Retry, retry.handle_result(false));
+        assert_eq!(UcInfoAction::Retry, retry.handle_result(false));
+        assert_eq!(UcInfoAction::Unavailable, retry.handle_result(false));
+        assert_eq!(UcInfoAction::Unavailable, retry.handle_result(false));
+        // the component might still be loaded later
+        assert_eq!(UcInfoAction::Available, retry.handle_result(true));
+    }
+
+    #[test]
+    fn no_retries_falls_back_immediately() {
+        let mut retry = UcInfoRetry::new(0);
+
+        assert_eq!(UcInfoAction::Unavailable, retry.handle_result(false));
+    }
+
+    #[test]
+    fn reset_restarts_retries() {
+        let mut retry = UcInfoRetry::new(1);
+        assert_eq!(UcInfoAction::Retry, retry.handle_result(false));
+        assert_eq!(UcInfoAction::Unavailable, retry.handle_result(false));
+
+        retry.reset();
+
+        assert_eq!(UcInfoAction::Retry, retry.handle_result(false));
+    }
+}
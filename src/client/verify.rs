@@ -0,0 +1,203 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Home Assistant WebSocket connection verification.
+//!
+//! Used in the driver setup flow to verify the user provided URL and access token with a
+//! short-lived connection before saving the configuration.
+
+use crate::client::json_object_from_text_msg;
+use crate::util::redact_url;
+use awc::ws;
+use futures::{SinkExt, StreamExt};
+use log::{debug, info};
+use serde_json::{json, Value};
+use std::time::Duration;
+use uc_api::model::intg::IntegrationSetupError;
+use url::Url;
+
+/// Connection verification error.
+#[derive(Debug, PartialEq)]
+pub enum VerifyError {
+    /// Home Assistant server could not be reached or closed the connection.
+    Unreachable(String),
+    /// Access token was rejected.
+    AuthenticationFailed(String),
+    /// No authentication result within the timeout.
+    Timeout,
+}
+
+impl From<&VerifyError> for IntegrationSetupError {
+    fn from(error: &VerifyError) -> Self {
+        match error {
+            VerifyError::Unreachable(_) => IntegrationSetupError::ConnectionRefused,
+            VerifyError::AuthenticationFailed(_) => IntegrationSetupError::AuthorizationError,
+            VerifyError::Timeout => IntegrationSetupError::Timeout,
+        }
+    }
+}
+
+/// Next step of the authentication phase.
+#[derive(Debug, PartialEq)]
+enum AuthStep {
+    /// Send the given auth message.
+    Send(Value),
+    /// Authentication successful with the reported HA version.
+    Authenticated(String),
+    /// Ignore the message and wait for the next one.
+    Continue,
+}
+
+/// Verify the Home Assistant WebSocket API connection and access token.
+///
+/// Opens a WebSocket connection, sends the `auth` message and waits for `auth_ok` or
+/// `auth_invalid`. The connection is closed afterwards.
+///
+/// # Arguments
+///
+/// * `client`: WebSocket client.
+/// * `url`: Home Assistant WebSocket API URL.
+/// * `token`: long-lived access token.
+/// * `timeout`: maximum time to connect and authenticate.
+///
+/// returns: Home Assistant version if the connection is ok.
+pub async fn verify_connection(
+    client: &awc::Client,
+    url: &Url,
+    token: &str,
+    timeout: Duration,
+) -> Result<String, VerifyError> {
+    let verification = async {
+        let (_, mut framed) = client
+            .ws(url.as_str())
+            .connect()
+            .await
+            .map_err(|e| VerifyError::Unreachable(e.to_string()))?;
+
+        while let Some(frame) = framed.next().await {
+            let txt = match frame.map_err(|e| VerifyError::Unreachable(e.to_string()))? {
+                ws::Frame::Text(txt) => txt,
+                ws::Frame::Close(reason) => {
+                    return Err(VerifyError::Unreachable(format!(
+                        "Connection closed: {reason:?}"
+                    )))
+                }
+                _ => continue,
+            };
+            let msg = json_object_from_text_msg("setup", txt.as_ref())
+                .map_err(|e| VerifyError::Unreachable(e.to_string()))?;
+
+            match handle_auth_message(&msg, token)? {
+                AuthStep::Send(auth) => framed
+                    .send(ws::Message::Text(auth.to_string().into()))
+                    .await
+                    .map_err(|e| VerifyError::Unreachable(e.to_string()))?,
+                AuthStep::Authenticated(version) => {
+                    let _ = framed.send(ws::Message::Close(None)).await;
+                    return Ok(version);
+                }
+                AuthStep::Continue => {}
+            }
+        }
+
+        Err(VerifyError::Unreachable("Connection closed".into()))
+    };
+
+    debug!("Verifying connection to {}", redact_url(url));
+    let version = actix_web::rt::time::timeout(timeout, verification)
+        .await
+        .map_err(|_| VerifyError::Timeout)??;
+    info!(
+        "Connection verified to {}, HA version: {version}",
+        redact_url(url)
+    );
+    Ok(version)
+}
+
+/// Handle a message of the authentication phase.
+fn handle_auth_message(msg: &Value, token: &str) -> Result<AuthStep, VerifyError> {
+    match msg.get("type").and_then(|v| v.as_str()).unwrap_or_default() {
+        "auth_required" => Ok(AuthStep::Send(
+            json!({ "type": "auth", "access_token": token }),
+        )),
+        "auth_ok" => Ok(AuthStep::Authenticated(
+            msg.get("ha_version")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string(),
+        )),
+        "auth_invalid" => Err(VerifyError::AuthenticationFailed(
+            msg.get("message")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string(),
+        )),
+        _ => Ok(AuthStep::Continue),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_required_sends_token() {
+        let result = handle_auth_message(
+            &json!({ "type": "auth_required", "ha_version": "2024.3.0" }),
+            "secret",
+        );
+
+        assert_eq!(
+            Ok(AuthStep::Send(
+                json!({ "type": "auth", "access_token": "secret" })
+            )),
+            result
+        );
+    }
+
+    #[test]
+    fn auth_ok_returns_version() {
+        let result = handle_auth_message(
+            &json!({ "type": "auth_ok", "ha_version": "2024.3.0" }),
+            "secret",
+        );
+
+        assert_eq!(Ok(AuthStep::Authenticated("2024.3.0".into())), result);
+    }
+
+    #[test]
+    fn auth_invalid_returns_authentication_error() {
+        let result = handle_auth_message(
+            &json!({ "type": "auth_invalid", "message": "Invalid password" }),
+            "secret",
+        );
+
+        assert_eq!(
+            Err(VerifyError::AuthenticationFailed("Invalid password".into())),
+            result
+        );
+    }
+
+    #[test]
+    fn other_messages_are_ignored() {
+        let result = handle_auth_message(&json!({ "type": "pong", "id": 1 }), "secret");
+
+        assert_eq!(Ok(AuthStep::Continue), result);
+    }
+
+    #[test]
+    fn verify_errors_map_to_setup_errors() {
+        assert!(matches!(
+            IntegrationSetupError::from(&VerifyError::AuthenticationFailed("".into())),
+            IntegrationSetupError::AuthorizationError
+        ));
+        assert!(matches!(
+            IntegrationSetupError::from(&VerifyError::Unreachable("".into())),
+            IntegrationSetupError::ConnectionRefused
+        ));
+        assert!(matches!(
+            IntegrationSetupError::from(&VerifyError::Timeout),
+            IntegrationSetupError::Timeout
+        ));
+    }
+}
@@ -8,11 +8,12 @@ use crate::APP_VERSION;
 use config::Config;
 use log::{error, info, warn};
 use serde_with::{serde_as, DurationMilliSeconds, DurationSeconds};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use std::{env, fs, io};
+use strum_macros::{AsRefStr, EnumString};
 use uc_api::intg::IntegrationDriverUpdate;
 use url::Url;
 
@@ -20,9 +21,18 @@ use url::Url;
 pub const DEF_CONFIG_FILE: &str = "configuration.yaml";
 
 pub const DEF_HA_URL: &str = "ws://homeassistant.local:8123/api/websocket";
+/// Device identifier of the main Home Assistant server connection.
+pub const DEFAULT_HA_DEVICE_ID: &str = "main";
+/// Separator of the device identifier prefix in the entity ids of additional HA servers.
+///
+/// Must not be used in HA entity ids, or in the ids of derived attribute entities.
+pub const DEVICE_ENTITY_SEPARATOR: char = '@';
 
 pub const ENV_SETUP_TIMEOUT: &str = "UC_SETUP_TIMEOUT";
 pub const DEF_SETUP_TIMEOUT_SEC: u64 = 300;
+/// Maximum time in seconds to defer the setup timeout while entities are still being loaded.
+pub const ENV_SETUP_TIMEOUT_GRACE: &str = "UC_SETUP_TIMEOUT_GRACE";
+pub const DEF_SETUP_TIMEOUT_GRACE_SEC: u64 = 120;
 
 const ENV_USER_CFG_FILENAME: &str = "UC_USER_CFG_FILENAME";
 const DEV_USER_CFG_FILENAME: &str = "home-assistant.json";
@@ -43,6 +53,11 @@ const TOKEN_ID: &str = "ws-ha-api";
 /// When running on the Remote device, service publishing is not required.
 pub const ENV_DISABLE_MDNS_PUBLISH: &str = "UC_DISABLE_MDNS_PUBLISH";
 
+/// Environment variable for an instance name prefix of all log lines.
+///
+/// Allows to distinguish the logs of multiple running instances, e.g. with different HA servers.
+pub const ENV_LOG_PREFIX: &str = "UC_LOG_PREFIX";
+
 /// Environment variable to enable Home Assistant server WebSocket message tracing.
 ///
 /// Valid values:
@@ -73,11 +88,18 @@ const DRIVER_METADATA: &str = include_str!("../resources/driver.json");
 pub struct Settings {
     pub integration: IntegrationSettings,
     pub hass: HomeAssistantSettings,
+    /// Identifier of the last connected remote, sent as `client_id` to the UC HA component.
+    #[serde(default)]
+    pub remote_id: Option<String>,
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct IntegrationSettings {
     pub interface: String,
+    /// Path prefix of the HTTP endpoints if the integration is reverse-proxied under a sub-path,
+    /// e.g. `/hass`. Empty = no prefix.
+    #[serde(default)]
+    pub base_path: String,
     pub http: WebServerSettings,
     pub https: WebServerSettings,
     pub certs: Option<CertificateSettings>,
@@ -88,6 +110,7 @@ impl Default for IntegrationSettings {
     fn default() -> Self {
         Self {
             interface: "0.0.0.0".to_string(),
+            base_path: String::new(),
             http: WebServerSettings {
                 enabled: true,
                 port: 8000,
@@ -118,6 +141,9 @@ pub struct CertificateSettings {
 pub struct WebSocketSettings {
     pub token: Option<String>,
     pub heartbeat: HeartbeatSettings,
+    /// Maximum number of concurrent Remote Two WebSocket sessions. 0 = unlimited.
+    #[serde(default)]
+    pub max_sessions: u16,
 }
 
 #[derive(Clone, serde::Deserialize, serde::Serialize)]
@@ -133,6 +159,11 @@ pub struct HomeAssistantSettings {
     // simplifies data migration: missing value in existing configuration will be set with a default!
     #[serde(default = "default_request_timeout")]
     pub request_timeout: u8,
+    /// Entity state request timeout in seconds.
+    /// Loading all entity states of a large or slow HA installation can take much longer than the
+    /// `request_timeout` of other requests.
+    #[serde(default = "default_entity_request_timeout")]
+    pub entity_request_timeout: u16,
     pub max_frame_size_kb: usize,
     pub reconnect: ReconnectSettings,
     pub heartbeat: HeartbeatSettings,
@@ -141,6 +172,236 @@ pub struct HomeAssistantSettings {
     // for data migration of existing configurations
     #[serde(default = "default_disconnect_in_standby")]
     pub disconnect_in_standby: bool,
+    /// Queue the latest `device_state` event while a remote is in standby and send it when the
+    /// remote exits standby.
+    #[serde(default)]
+    pub standby_device_state: bool,
+    /// Keep the HA connection if a remote disconnects, as long as another connected remote still
+    /// wants to be connected.
+    #[serde(default)]
+    pub shared_connection: bool,
+    /// Temperature unit of climate entities not providing a `temperature_unit` attribute, or a
+    /// forced temperature unit.
+    #[serde(default)]
+    pub climate_temperature_unit: TemperatureUnitSource,
+    /// Debounce `unavailable` entity states after (re)connecting to HA.
+    #[serde(default)]
+    pub unavailable_debounce: UnavailableDebounceSettings,
+    /// Entity ids whose state change events are not forwarded to the remote.
+    /// The entities can still be controlled.
+    #[serde(default)]
+    pub disabled_event_entities: HashSet<String>,
+    /// Favorite entity ids in priority order: always subscribed and listed first.
+    #[serde(default)]
+    pub favorite_entities: Vec<String>,
+    /// Exposed HA entity domains. All supported domains are exposed if empty.
+    #[serde(default)]
+    pub entity_domains: HashSet<String>,
+    /// HA entity domains which are not exposed.
+    #[serde(default)]
+    pub exclude_domains: HashSet<String>,
+    /// Attributes of HA entities exposed as separate read-only sensor entities, key: entity id.
+    #[serde(default)]
+    pub attribute_entities: HashMap<String, Vec<String>>,
+    /// Number or input_number entity with the target temperature of a climate entity, by climate
+    /// entity id. For thermostats implemented as `number` + `climate` combination.
+    #[serde(default)]
+    pub climate_setpoint_numbers: HashMap<String, String>,
+    /// Don't forward state change events caused by service calls of the integration.
+    #[serde(default)]
+    pub suppress_echo_events: bool,
+    /// Confirm service calls of the integration with HA `call_service` events.
+    #[serde(default)]
+    pub confirm_service_calls: bool,
+    /// Send a dedicated `entity_available` event when the availability of an entity flips.
+    #[serde(default)]
+    pub entity_available_events: bool,
+    /// Entity name if HA doesn't provide a friendly name.
+    #[serde(default)]
+    pub name_fallback: EntityNameFallback,
+    /// Time in seconds the available entities are cached for repeated `get_available_entities`
+    /// requests. 0 = disabled.
+    #[serde(default = "default_entity_cache_ttl_sec")]
+    pub entity_cache_ttl_sec: u16,
+    /// Additional Home Assistant servers, connected in parallel to the main server.
+    /// All other connection settings of the main server apply. The entity ids of an additional
+    /// server are prefixed with the server identifier, e.g. `cabin@light.kitchen`.
+    #[serde(default)]
+    pub additional_servers: Vec<HomeAssistantServerSettings>,
+    #[serde(default)]
+    pub sensor: SensorSettings,
+    #[serde(default)]
+    pub media_player: MediaPlayerSettings,
+    #[serde(default)]
+    pub tcp_keepalive: TcpKeepaliveSettings,
+    /// Token query parameter of the main server's WebSocket URL, e.g. for an authenticating reverse
+    /// proxy. Additional servers define their own `url_token`.
+    #[serde(default)]
+    pub url_token: UrlTokenSettings,
+    /// Include entities hidden or disabled in the HA entity registry in the available entities.
+    #[serde(default)]
+    pub include_hidden_entities: bool,
+    /// Send an optimistic on / off state for entities with an assumed state right after a command.
+    #[serde(default)]
+    pub optimistic_assumed_state: bool,
+    /// Number of failed UC HA component info requests after authentication before falling back to
+    /// the standard HA events. The component isn't loaded yet right after a HA restart.
+    /// 0 = fall back immediately.
+    #[serde(default)]
+    pub uc_component_retries: u16,
+    /// Delay in milliseconds before an entity command failing with a transient HA error is
+    /// retried once. 0 = no retry.
+    #[serde(default)]
+    pub command_retry_delay_ms: u16,
+    /// Maximum number of concurrent in-flight entity commands on the HA connection. Further
+    /// commands are rejected until a result is received. 0 = unlimited.
+    #[serde(default)]
+    pub max_concurrent_commands: u16,
+}
+
+/// Connection settings of a Home Assistant server.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct HomeAssistantServerSettings {
+    /// Unique device identifier of the server connection.
+    pub id: String,
+    pub url: Url,
+    pub token: String,
+    /// Token query parameter of the WebSocket URL of this server.
+    #[serde(default)]
+    pub url_token: UrlTokenSettings,
+}
+
+/// Sensor entity settings.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct SensorSettings {
+    /// Add the `alert_severity` hint attribute to binary sensors with a problem or safety related
+    /// device class.
+    #[serde(default)]
+    pub alert_severity: bool,
+}
+
+/// Media player entity settings.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct MediaPlayerSettings {
+    /// Volume step in percent for the volume up & down commands.
+    /// 0 = use the HA `volume_up` & `volume_down` services with the step size of the media player.
+    #[serde(default)]
+    pub volume_step: u8,
+    /// Service of the remote's off command.
+    #[serde(default)]
+    pub off_mode: MediaPlayerOffMode,
+    /// Named activities combining an input source and sound mode of a media player.
+    #[serde(default)]
+    pub activities: Vec<MediaActivity>,
+    /// Select entities representing the inputs of a media player, key: media player entity id.
+    /// The source selection of the media player is routed to the select entity.
+    #[serde(default)]
+    pub source_selects: HashMap<String, String>,
+    /// Upgrade absolute `http://` media image URLs to `https://` if the HA server is connected
+    /// with a secure WebSocket connection.
+    #[serde(default)]
+    pub https_image_url: bool,
+    /// Serve the media images through the integration: the image is fetched from HA with the
+    /// access token and the `media_image_url` attribute points to the integration's
+    /// `/media_proxy/{entity_id}` endpoint.
+    #[serde(default)]
+    pub image_proxy: bool,
+    /// Base URL of the integration as reachable by the remote for the image proxy.
+    /// Empty = `http://127.0.0.1:{http port}{base_path}`.
+    #[serde(default)]
+    pub image_proxy_url: String,
+}
+
+/// Media player activity, triggered with the `activity` command as one action.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct MediaActivity {
+    /// Activity name, used as `activity` command parameter.
+    pub name: String,
+    /// Media player entity identifier.
+    pub entity_id: String,
+    /// Turn on the media player before selecting the input source and sound mode.
+    #[serde(default = "default_activity_turn_on")]
+    pub turn_on: bool,
+    /// Input source to select.
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Sound mode to select.
+    #[serde(default)]
+    pub sound_mode: Option<String>,
+}
+
+/// Mapping of the media player off command.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    AsRefStr,
+    EnumString,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum MediaPlayerOffMode {
+    /// Always use the HA `turn_off` service.
+    #[default]
+    TurnOff,
+    /// Only stop the playback with the HA `media_stop` service and keep the device in standby.
+    /// The `turn_off` service is used if the media player doesn't support stop.
+    Standby,
+}
+
+/// Source of the temperature unit for climate entities.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    AsRefStr,
+    EnumString,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TemperatureUnitSource {
+    /// Use the unit system of the Home Assistant configuration.
+    #[default]
+    Ha,
+    /// Use the temperature unit configured in the remote.
+    Remote,
+    /// Always use Celsius, temperatures in Fahrenheit are converted.
+    Celsius,
+    /// Always use Fahrenheit, temperatures in Celsius are converted.
+    Fahrenheit,
+}
+
+/// Name of entities without a friendly name.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    AsRefStr,
+    EnumString,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum EntityNameFallback {
+    /// Use the entity id, e.g. `light.living_room`.
+    #[default]
+    EntityId,
+    /// Use the object id of the entity id as title, e.g. `Living Room`.
+    ObjectId,
 }
 
 impl Default for HomeAssistantSettings {
@@ -150,10 +411,36 @@ impl Default for HomeAssistantSettings {
             token: "".to_string(),
             connection_timeout: 6,
             request_timeout: default_request_timeout(),
+            entity_request_timeout: default_entity_request_timeout(),
             max_frame_size_kb: 5120,
             reconnect: Default::default(),
             heartbeat: Default::default(),
             disconnect_in_standby: default_disconnect_in_standby(),
+            standby_device_state: false,
+            shared_connection: false,
+            climate_temperature_unit: Default::default(),
+            unavailable_debounce: Default::default(),
+            disabled_event_entities: Default::default(),
+            favorite_entities: Default::default(),
+            entity_domains: Default::default(),
+            exclude_domains: Default::default(),
+            attribute_entities: Default::default(),
+            climate_setpoint_numbers: Default::default(),
+            suppress_echo_events: false,
+            confirm_service_calls: false,
+            entity_available_events: false,
+            name_fallback: Default::default(),
+            entity_cache_ttl_sec: default_entity_cache_ttl_sec(),
+            additional_servers: Default::default(),
+            sensor: Default::default(),
+            media_player: Default::default(),
+            tcp_keepalive: Default::default(),
+            url_token: Default::default(),
+            include_hidden_entities: false,
+            optimistic_assumed_state: false,
+            uc_component_retries: 0,
+            command_retry_delay_ms: 0,
+            max_concurrent_commands: 0,
         }
     }
 }
@@ -195,6 +482,34 @@ impl HomeAssistantSettings {
             .unwrap_or_else(|| self.token.clone())
     }
 
+    /// Get the connection settings of all configured Home Assistant servers, the main server
+    /// first with device identifier [`DEFAULT_HA_DEVICE_ID`].
+    ///
+    /// Additional servers with an empty, duplicate or invalid identifier, missing host or token are
+    /// skipped.
+    pub fn servers(&self) -> Vec<HomeAssistantServerSettings> {
+        let mut servers = Vec::with_capacity(1 + self.additional_servers.len());
+        servers.push(HomeAssistantServerSettings {
+            id: DEFAULT_HA_DEVICE_ID.into(),
+            url: self.get_url(),
+            token: self.get_token(),
+            url_token: self.url_token.clone(),
+        });
+        for server in &self.additional_servers {
+            if server.id.is_empty()
+                || server.id.contains(DEVICE_ENTITY_SEPARATOR)
+                || servers.iter().any(|s| s.id == server.id)
+                || !server.url.has_host()
+                || server.token.is_empty()
+            {
+                warn!("Ignoring invalid additional HA server: '{}'", server.id);
+                continue;
+            }
+            servers.push(server.clone());
+        }
+        servers
+    }
+
     /// Update the local configuration URL.
     pub fn set_url(&mut self, url: Url) {
         self.url = url;
@@ -233,9 +548,18 @@ impl HomeAssistantSettings {
 fn default_request_timeout() -> u8 {
     6
 }
+fn default_entity_request_timeout() -> u16 {
+    120
+}
+fn default_entity_cache_ttl_sec() -> u16 {
+    30
+}
 fn default_disconnect_in_standby() -> bool {
     true
 }
+fn default_activity_turn_on() -> bool {
+    true
+}
 
 #[serde_as]
 #[derive(Clone, serde::Deserialize, serde::Serialize)]
@@ -248,6 +572,9 @@ pub struct ReconnectSettings {
     #[serde(rename = "duration_max_ms")]
     pub duration_max: Duration,
     pub backoff_factor: f32,
+    /// Random jitter factor applied to the reconnect delay, e.g. 0.2 = ±20%. 0 = disabled.
+    #[serde(default)]
+    pub jitter: f32,
 }
 
 impl Default for ReconnectSettings {
@@ -257,10 +584,73 @@ impl Default for ReconnectSettings {
             duration: Duration::from_secs(1),
             duration_max: Duration::from_secs(30),
             backoff_factor: 1.5,
+            jitter: 0.0,
+        }
+    }
+}
+
+/// Debounce settings for `unavailable` entity states during a HA restart.
+#[serde_as]
+#[derive(Clone, Copy, serde::Deserialize, serde::Serialize)]
+pub struct UnavailableDebounceSettings {
+    /// Debounce window after connecting to HA.
+    #[serde_as(as = "DurationSeconds")]
+    #[serde(rename = "window_sec")]
+    pub window: Duration,
+    /// Delay before an `unavailable` entity state is sent to the remote. 0 = disabled.
+    #[serde_as(as = "DurationMilliSeconds")]
+    #[serde(rename = "delay_ms")]
+    pub delay: Duration,
+}
+
+impl Default for UnavailableDebounceSettings {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(60),
+            delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// TCP keepalive settings of the Home Assistant connection socket.
+///
+/// Missing fields use their default value.
+#[serde_as]
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TcpKeepaliveSettings {
+    /// Idle time before the first keepalive probe is sent. 0 = disabled.
+    #[serde_as(as = "DurationSeconds")]
+    #[serde(rename = "time_sec")]
+    pub time: Duration,
+    /// Interval between keepalive probes.
+    #[serde_as(as = "DurationSeconds")]
+    #[serde(rename = "interval_sec")]
+    pub interval: Duration,
+}
+
+impl Default for TcpKeepaliveSettings {
+    fn default() -> Self {
+        Self {
+            time: Duration::from_secs(30),
+            interval: Duration::from_secs(10),
         }
     }
 }
 
+/// Token query parameter appended to the WebSocket URL.
+///
+/// This is only used by a proxy in front of Home Assistant, the HA authentication is always
+/// performed with the auth message.
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct UrlTokenSettings {
+    /// Query parameter name. Disabled if empty.
+    #[serde(default)]
+    pub param: String,
+    #[serde(default)]
+    pub token: String,
+}
+
 /// WebSocket heartbeat settings for sending ping frames.
 #[serde_as]
 #[derive(Clone, Copy, serde::Deserialize, serde::Serialize)]
@@ -410,16 +800,42 @@ pub fn get_driver_metadata() -> Result<IntegrationDriverUpdate, io::Error> {
     Ok(driver)
 }
 
-/// Wrapper to add the `hass` root property to make it compatible with the main configuration file.
-#[derive(serde::Deserialize, serde::Serialize)]
-struct UserSettingsWrapper {
-    hass: HomeAssistantSettings,
-}
-
 /// Store user configuration from the setup flow.
 pub fn save_user_settings(cfg: &HomeAssistantSettings) -> Result<(), ServiceError> {
-    let cfg = UserSettingsWrapper { hass: cfg.clone() };
-    fs::write(user_settings_path(), serde_json::to_string_pretty(&cfg)?).map_err(|e| {
+    update_user_settings("hass", serde_json::to_value(cfg)?)
+}
+
+/// Store the identifier of the connected remote.
+pub fn save_remote_id(remote_id: &str) -> Result<(), ServiceError> {
+    update_user_settings("remote_id", serde_json::json!(remote_id))
+}
+
+/// Store the user configured integration server listen ports from the setup flow.
+///
+/// Only the ports are stored, all other integration settings are taken from the main
+/// configuration file.
+pub fn save_user_listen_ports(cfg: &IntegrationSettings) -> Result<(), ServiceError> {
+    update_user_settings(
+        "integration",
+        serde_json::json!({
+            "http": { "port": cfg.http.port },
+            "https": { "port": cfg.https.port }
+        }),
+    )
+}
+
+/// Set a root property in the user configuration file and keep all other properties.
+///
+/// The root properties are compatible with the main configuration file.
+fn update_user_settings(key: &str, value: serde_json::Value) -> Result<(), ServiceError> {
+    let path = user_settings_path();
+    let mut cfg = fs::read_to_string(&path)
+        .ok()
+        .and_then(|v| serde_json::from_str::<serde_json::Map<_, _>>(&v).ok())
+        .unwrap_or_default();
+    cfg.insert(key.into(), value);
+
+    fs::write(path, serde_json::to_string_pretty(&cfg)?).map_err(|e| {
         let msg = format!("Error saving user configuration: {e}");
         error!("{msg}");
         ServiceError::InternalServerError(msg)
@@ -429,7 +845,8 @@ pub fn save_user_settings(cfg: &HomeAssistantSettings) -> Result<(), ServiceErro
 
 /// Get user configuration file path.
 ///
-/// This configuration file is updatable with [`save_user_settings`] from the driver setup flow.
+/// This configuration file is updatable with [`save_user_settings`] and [`save_user_listen_ports`]
+/// from the driver setup flow.
 ///
 /// The configuration file is located in the configuration directory specified in the env variable
 /// `UC_CONFIG_HOME`. If not set, the current directory is used.
@@ -437,3 +854,47 @@ fn user_settings_path() -> PathBuf {
     let file = env::var(ENV_USER_CFG_FILENAME).unwrap_or(DEV_USER_CFG_FILENAME.into());
     Path::new(&env::var(ENV_CONFIG_HOME).unwrap_or_default()).join(file)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::{json, Value};
+
+    #[rstest]
+    #[case(json!({ "time_sec": 60 }), 60, 10)]
+    #[case(json!({ "interval_sec": 5 }), 30, 5)]
+    #[case(json!({}), 30, 10)]
+    fn partial_tcp_keepalive_settings_use_defaults(
+        #[case] config: Value,
+        #[case] time_sec: u64,
+        #[case] interval_sec: u64,
+    ) {
+        let settings: TcpKeepaliveSettings =
+            serde_json::from_value(config).expect("valid keepalive settings");
+
+        assert_eq!(Duration::from_secs(time_sec), settings.time);
+        assert_eq!(Duration::from_secs(interval_sec), settings.interval);
+    }
+
+    #[test]
+    fn url_token_is_only_used_for_the_configuring_server() {
+        let mut settings = HomeAssistantSettings::default();
+        settings.url_token = UrlTokenSettings {
+            param: "access_token".into(),
+            token: "secret".into(),
+        };
+        settings.additional_servers = vec![HomeAssistantServerSettings {
+            id: "cabin".into(),
+            url: Url::parse("ws://cabin.local:8123/api/websocket").unwrap(),
+            token: "token".into(),
+            url_token: Default::default(),
+        }];
+
+        let servers = settings.servers();
+
+        assert_eq!(2, servers.len());
+        assert_eq!(settings.url_token, servers[0].url_token);
+        assert_eq!(UrlTokenSettings::default(), servers[1].url_token);
+    }
+}
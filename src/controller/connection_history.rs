@@ -0,0 +1,160 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! History of recent Home Assistant connection events for troubleshooting connection drops.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// Default number of connection events kept in the history.
+pub const DEF_CONNECTION_HISTORY_SIZE: usize = 20;
+
+/// Home Assistant connection event type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConnectionEventType {
+    Connected,
+    Disconnected,
+    ConnectFailed,
+}
+
+/// A recorded connection event.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ConnectionHistoryEntry {
+    /// RFC 3339 UTC timestamp of the event.
+    pub timestamp: String,
+    pub event: ConnectionEventType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Ring buffer of the most recent connection events.
+///
+/// The oldest event is dropped if the history is full.
+#[derive(Debug)]
+pub struct ConnectionHistory {
+    capacity: usize,
+    entries: VecDeque<ConnectionHistoryEntry>,
+}
+
+impl Default for ConnectionHistory {
+    fn default() -> Self {
+        Self::new(DEF_CONNECTION_HISTORY_SIZE)
+    }
+}
+
+impl ConnectionHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Record a connection event with the current time.
+    pub fn push(&mut self, event: ConnectionEventType, reason: Option<String>) {
+        self.push_at(OffsetDateTime::now_utc(), event, reason)
+    }
+
+    /// Record a connection event with the given time.
+    pub fn push_at(
+        &mut self,
+        timestamp: OffsetDateTime,
+        event: ConnectionEventType,
+        reason: Option<String>,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(ConnectionHistoryEntry {
+            timestamp: timestamp.format(&Rfc3339).unwrap_or_default(),
+            event,
+            reason,
+        });
+    }
+
+    /// Recorded events, oldest event first.
+    pub fn entries(&self) -> Vec<ConnectionHistoryEntry> {
+        self.entries.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn history_keeps_events_in_order() {
+        let mut history = ConnectionHistory::new(5);
+        history.push(ConnectionEventType::Connected, None);
+        history.push(
+            ConnectionEventType::Disconnected,
+            Some("connection closed".into()),
+        );
+
+        let entries = history.entries();
+        assert_eq!(2, entries.len());
+        assert_eq!(ConnectionEventType::Connected, entries[0].event);
+        assert_eq!(ConnectionEventType::Disconnected, entries[1].event);
+        assert_eq!(Some("connection closed".into()), entries[1].reason);
+    }
+
+    #[test]
+    fn full_history_drops_oldest_event() {
+        let mut history = ConnectionHistory::new(2);
+        history.push(ConnectionEventType::ConnectFailed, Some("1".into()));
+        history.push(ConnectionEventType::ConnectFailed, Some("2".into()));
+        history.push(ConnectionEventType::ConnectFailed, Some("3".into()));
+
+        let reasons: Vec<_> = history
+            .entries()
+            .into_iter()
+            .filter_map(|e| e.reason)
+            .collect();
+        assert_eq!(vec!["2", "3"], reasons);
+    }
+
+    #[test]
+    fn zero_capacity_history_is_disabled() {
+        let mut history = ConnectionHistory::new(0);
+        history.push(ConnectionEventType::Connected, None);
+
+        assert!(history.entries().is_empty());
+    }
+
+    #[test]
+    fn entry_serialization() {
+        let mut history = ConnectionHistory::new(2);
+        history.push_at(
+            OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap(),
+            ConnectionEventType::Disconnected,
+            Some("authentication failed".into()),
+        );
+        history.push_at(
+            OffsetDateTime::from_unix_timestamp(1_700_000_060).unwrap(),
+            ConnectionEventType::Connected,
+            None,
+        );
+
+        assert_eq!(
+            json!([
+                {
+                    "timestamp": "2023-11-14T22:13:20Z",
+                    "event": "DISCONNECTED",
+                    "reason": "authentication failed"
+                },
+                {
+                    "timestamp": "2023-11-14T22:14:20Z",
+                    "event": "CONNECTED"
+                }
+            ]),
+            serde_json::to_value(history.entries()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,154 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Home Assistant server discovery with mDNS for the setup flow.
+
+use crate::server::{discover_services, DiscoveredService};
+use log::{info, warn};
+use std::net::IpAddr;
+use std::time::Duration;
+use url::Url;
+
+/// mDNS service name advertised by Home Assistant.
+const HA_SERVICE_NAME: &str = "home-assistant";
+/// Duration to browse for Home Assistant servers.
+pub const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A discovered Home Assistant server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HomeAssistantServer {
+    /// Location name of the Home Assistant installation.
+    pub name: String,
+    /// WebSocket API URL.
+    pub url: Url,
+}
+
+/// Discover Home Assistant servers in the local network.
+///
+/// Attention: blocks the calling thread for the given timeout.
+pub fn discover_home_assistant(timeout: Duration) -> Vec<HomeAssistantServer> {
+    let mut servers: Vec<HomeAssistantServer> = Vec::new();
+    for service in discover_services(HA_SERVICE_NAME, "tcp", timeout) {
+        match home_assistant_server(&service) {
+            Some(server) if !servers.iter().any(|s| s.url == server.url) => {
+                info!("Discovered Home Assistant server: {server:?}");
+                servers.push(server);
+            }
+            Some(_) => {}
+            None => warn!("Ignoring Home Assistant service without address: {service:?}"),
+        }
+    }
+    servers
+}
+
+/// Convert a discovered `_home-assistant._tcp` service to a Home Assistant server.
+///
+/// The WebSocket URL is derived from the `internal_url` or `base_url` TXT record properties. If
+/// they are missing, the service address and port is used.
+fn home_assistant_server(service: &DiscoveredService) -> Option<HomeAssistantServer> {
+    let url = ["internal_url", "base_url"]
+        .iter()
+        .filter_map(|key| service.txt.get(*key))
+        .find_map(|url| websocket_url(url))
+        .or_else(|| {
+            let address = service
+                .addresses
+                .iter()
+                .find(|a| a.is_ipv4())
+                .or(service.addresses.first())?;
+            let host = match address {
+                IpAddr::V4(ip) => ip.to_string(),
+                IpAddr::V6(ip) => format!("[{ip}]"),
+            };
+            websocket_url(&format!("http://{host}:{}", service.port))
+        })?;
+
+    let name = service
+        .txt
+        .get("location_name")
+        .filter(|v| !v.is_empty())
+        .unwrap_or(&service.name)
+        .clone();
+
+    Some(HomeAssistantServer { name, url })
+}
+
+/// Convert a Home Assistant base URL to the WebSocket API URL.
+fn websocket_url(base_url: &str) -> Option<Url> {
+    let mut url = Url::parse(base_url.trim()).ok()?;
+    match url.scheme() {
+        "http" => url.set_scheme("ws").ok()?,
+        "https" => url.set_scheme("wss").ok()?,
+        _ => return None,
+    }
+    url.set_path("/api/websocket");
+    Some(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::net::Ipv4Addr;
+
+    fn service(txt: &[(&str, &str)]) -> DiscoveredService {
+        DiscoveredService {
+            name: "Home".into(),
+            hostname: "homeassistant.local.".into(),
+            addresses: vec![IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20))],
+            port: 8123,
+            txt: txt
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn server_from_internal_url() {
+        let result = home_assistant_server(&service(&[
+            ("location_name", "My Home"),
+            ("internal_url", "http://homeassistant.local:8123"),
+            ("base_url", "https://example.com"),
+        ]));
+
+        assert_eq!(
+            Some(HomeAssistantServer {
+                name: "My Home".into(),
+                url: Url::parse("ws://homeassistant.local:8123/api/websocket").unwrap()
+            }),
+            result
+        );
+    }
+
+    #[test]
+    fn server_from_https_base_url() {
+        let result = home_assistant_server(&service(&[("base_url", "https://ha.example.com")]));
+
+        assert_eq!(
+            Some(Url::parse("wss://ha.example.com/api/websocket").unwrap()),
+            result.map(|s| s.url)
+        );
+    }
+
+    #[test]
+    fn server_without_urls_uses_service_address() {
+        let result = home_assistant_server(&service(&[("internal_url", "")]));
+
+        assert_eq!(
+            Some(HomeAssistantServer {
+                name: "Home".into(),
+                url: Url::parse("ws://192.168.1.20:8123/api/websocket").unwrap()
+            }),
+            result
+        );
+    }
+
+    #[test]
+    fn server_without_address_is_ignored() {
+        let mut service = service(&[]);
+        service.addresses.clear();
+
+        assert_eq!(None, home_assistant_server(&service));
+    }
+}
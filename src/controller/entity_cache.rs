@@ -0,0 +1,175 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Short-lived cache of the available entities per HA server connection.
+//!
+//! Repeated `get_available_entities` requests are served from the cache while it's fresh, instead
+//! of fetching and converting all HA entity states again. Entity changes are merged into the
+//! cached entities, a configure event or a disconnect invalidates the cache of the connection.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use uc_api::intg::{AvailableIntgEntity, EntityChange};
+
+#[derive(Debug)]
+struct CacheEntry {
+    created: Instant,
+    entities: Vec<AvailableIntgEntity>,
+}
+
+/// Available entities cache, keyed by the device identifier of the HA server connection.
+#[derive(Debug, Default)]
+pub(crate) struct EntityCache {
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl EntityCache {
+    /// Store the available entities of a HA server connection.
+    pub fn insert(&mut self, device_id: &str, entities: Vec<AvailableIntgEntity>, now: Instant) {
+        self.entries.insert(
+            device_id.to_string(),
+            CacheEntry {
+                created: now,
+                entities,
+            },
+        );
+    }
+
+    /// Get the cached available entities of the given HA server connections.
+    ///
+    /// Returns `None` if no connection is given, or if the entities of any connection are not
+    /// cached or older than the `ttl`.
+    pub fn get<'a>(
+        &self,
+        device_ids: impl IntoIterator<Item = &'a String>,
+        ttl: Duration,
+        now: Instant,
+    ) -> Option<Vec<AvailableIntgEntity>> {
+        let mut entities = Vec::new();
+        let mut found = false;
+        for device_id in device_ids {
+            let entry = self
+                .entries
+                .get(device_id)
+                .filter(|e| now.saturating_duration_since(e.created) < ttl)?;
+            entities.extend(entry.entities.iter().cloned());
+            found = true;
+        }
+        found.then_some(entities)
+    }
+
+    /// Merge the attributes of an entity change into the cached entity.
+    pub fn update(&mut self, change: &EntityChange) {
+        for entry in self.entries.values_mut() {
+            if let Some(entity) = entry
+                .entities
+                .iter_mut()
+                .find(|e| e.entity_id == change.entity_id)
+            {
+                let attributes = entity.attributes.get_or_insert_with(Default::default);
+                for (key, value) in &change.attributes {
+                    attributes.insert(key.clone(), value.clone());
+                }
+                return;
+            }
+        }
+    }
+
+    /// Invalidate the cached entities of a HA server connection.
+    pub fn invalidate(&mut self, device_id: &str) {
+        self.entries.remove(device_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uc_api::EntityType;
+
+    const TTL: Duration = Duration::from_secs(30);
+
+    fn entity(entity_id: &str) -> AvailableIntgEntity {
+        AvailableIntgEntity {
+            entity_id: entity_id.into(),
+            device_id: None,
+            entity_type: EntityType::Switch,
+            device_class: None,
+            name: Default::default(),
+            features: None,
+            area: None,
+            options: None,
+            attributes: json!({ "state": "OFF" }).as_object().cloned(),
+        }
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn fresh_entities_of_all_connections_are_returned() {
+        let now = Instant::now();
+        let mut cache = EntityCache::default();
+        cache.insert("main", vec![entity("switch.a")], now);
+        cache.insert("cabin", vec![entity("switch.b")], now);
+
+        let result = cache.get(&ids(&["main", "cabin"]), TTL, now + Duration::from_secs(29));
+
+        let entity_ids: Vec<String> = result
+            .expect("Expected cached entities")
+            .into_iter()
+            .map(|e| e.entity_id)
+            .collect();
+        assert_eq!(vec!["switch.a", "switch.b"], entity_ids);
+    }
+
+    #[test]
+    fn expired_entities_are_not_returned() {
+        let now = Instant::now();
+        let mut cache = EntityCache::default();
+        cache.insert("main", vec![entity("switch.a")], now);
+
+        assert!(cache
+            .get(&ids(&["main"]), TTL, now + Duration::from_secs(30))
+            .is_none());
+        assert!(cache.get(&ids(&["main"]), Duration::ZERO, now).is_none());
+    }
+
+    #[test]
+    fn missing_connection_is_a_cache_miss() {
+        let now = Instant::now();
+        let mut cache = EntityCache::default();
+        cache.insert("main", vec![entity("switch.a")], now);
+
+        assert!(cache.get(&ids(&["main", "cabin"]), TTL, now).is_none());
+        assert!(cache.get(&ids(&[]), TTL, now).is_none());
+
+        cache.invalidate("main");
+        assert!(cache.get(&ids(&["main"]), TTL, now).is_none());
+    }
+
+    #[test]
+    fn entity_change_is_merged_into_cached_entity() {
+        let now = Instant::now();
+        let mut cache = EntityCache::default();
+        cache.insert("main", vec![entity("switch.a"), entity("switch.b")], now);
+
+        cache.update(&EntityChange {
+            device_id: None,
+            entity_type: EntityType::Switch,
+            entity_id: "switch.b".into(),
+            attributes: json!({ "state": "ON" }).as_object().cloned().unwrap(),
+        });
+
+        let entities = cache.get(&ids(&["main"]), TTL, now).unwrap();
+        assert_eq!(
+            Some(&json!("OFF")),
+            entities[0].attributes.as_ref().unwrap().get("state")
+        );
+        assert_eq!(
+            Some(&json!("ON")),
+            entities[1].attributes.as_ref().unwrap().get("state")
+        );
+    }
+}
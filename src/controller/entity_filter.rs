@@ -0,0 +1,167 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Filter of the `get_available_entities` request.
+//!
+//! The remote can restrict the available entities to an entity type and / or an area. An absent
+//! or empty filter returns all available entities.
+
+use crate::errors::ServiceError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uc_api::intg::ws::AvailableEntitiesMsgData;
+use uc_api::intg::AvailableIntgEntity;
+use uc_api::EntityType;
+
+/// Available entities filter.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub(crate) struct AvailableEntitiesFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity_type: Option<EntityType>,
+    /// Area name of the entity
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub area: Option<String>,
+}
+
+impl AvailableEntitiesFilter {
+    /// Get the filter of the `get_available_entities` request message data.
+    ///
+    /// Returns `None` if the filter is absent or empty, a [`ServiceError::BadRequest`] if the
+    /// filter is invalid.
+    pub fn from_msg_data(msg_data: Option<&Value>) -> Result<Option<Self>, ServiceError> {
+        let filter = match msg_data.and_then(|v| v.get("filter")) {
+            None | Some(Value::Null) => return Ok(None),
+            Some(filter) => filter,
+        };
+        let filter: Self = serde_json::from_value(filter.clone())
+            .map_err(|e| ServiceError::BadRequest(format!("Invalid filter: {e}")))?;
+
+        Ok((filter != Self::default()).then_some(filter))
+    }
+
+    /// Check if the entity matches all filter criteria.
+    pub fn matches(&self, entity: &AvailableIntgEntity) -> bool {
+        self.entity_type
+            .as_ref()
+            .map_or(true, |t| &entity.entity_type == t)
+            && self
+                .area
+                .as_ref()
+                .map_or(true, |area| entity.area.as_ref() == Some(area))
+    }
+}
+
+/// Create the `available_entities` response message data with the entities matching the filter.
+pub(crate) fn available_entities_msg_data(
+    entities: &[AvailableIntgEntity],
+    filter: Option<&AvailableEntitiesFilter>,
+) -> Result<Value, serde_json::Error> {
+    let msg_data = AvailableEntitiesMsgData {
+        filter: None,
+        available_entities: entities
+            .iter()
+            .filter(|e| filter.map_or(true, |f| f.matches(e)))
+            .cloned()
+            .collect(),
+    };
+    let mut msg_data = serde_json::to_value(msg_data)?;
+    // the applied filter is returned in the response
+    if let (Some(filter), Some(obj)) = (filter, msg_data.as_object_mut()) {
+        obj.insert("filter".into(), serde_json::to_value(filter)?);
+    }
+    Ok(msg_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn entity(entity_id: &str, entity_type: EntityType, area: Option<&str>) -> AvailableIntgEntity {
+        AvailableIntgEntity {
+            entity_id: entity_id.into(),
+            device_id: None,
+            entity_type,
+            device_class: None,
+            name: Default::default(),
+            features: None,
+            area: area.map(|v| v.to_string()),
+            options: None,
+            attributes: None,
+        }
+    }
+
+    fn entities() -> Vec<AvailableIntgEntity> {
+        vec![
+            entity("light.kitchen", EntityType::Light, Some("Kitchen")),
+            entity("light.living_room", EntityType::Light, Some("Living Room")),
+            entity("switch.kitchen", EntityType::Switch, Some("Kitchen")),
+            entity("media_player.tv", EntityType::MediaPlayer, None),
+        ]
+    }
+
+    fn filtered_ids(msg_data: Option<Value>) -> Vec<String> {
+        let filter = AvailableEntitiesFilter::from_msg_data(msg_data.as_ref())
+            .expect("Expected a valid filter");
+        let msg_data = available_entities_msg_data(&entities(), filter.as_ref()).unwrap();
+        msg_data["available_entities"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["entity_id"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[rstest]
+    #[case(None)]
+    #[case(Some(json!({})))]
+    #[case(Some(json!({ "filter": null })))]
+    #[case(Some(json!({ "filter": {} })))]
+    fn absent_or_empty_filter_returns_all_entities(#[case] msg_data: Option<Value>) {
+        assert_eq!(4, filtered_ids(msg_data).len());
+    }
+
+    #[test]
+    fn filter_by_entity_type() {
+        let result = filtered_ids(Some(json!({ "filter": { "entity_type": "light" } })));
+
+        assert_eq!(vec!["light.kitchen", "light.living_room"], result);
+    }
+
+    #[test]
+    fn filter_by_area() {
+        let result = filtered_ids(Some(json!({ "filter": { "area": "Kitchen" } })));
+
+        assert_eq!(vec!["light.kitchen", "switch.kitchen"], result);
+    }
+
+    #[test]
+    fn filter_by_entity_type_and_area() {
+        let result = filtered_ids(Some(
+            json!({ "filter": { "entity_type": "light", "area": "Kitchen" } }),
+        ));
+
+        assert_eq!(vec!["light.kitchen"], result);
+    }
+
+    #[test]
+    fn applied_filter_is_returned() {
+        let filter = AvailableEntitiesFilter {
+            entity_type: Some(EntityType::Light),
+            area: None,
+        };
+        let msg_data = available_entities_msg_data(&entities(), Some(&filter)).unwrap();
+
+        assert_eq!(json!({ "entity_type": "light" }), msg_data["filter"]);
+    }
+
+    #[test]
+    fn invalid_filter_returns_bad_request() {
+        let result = AvailableEntitiesFilter::from_msg_data(Some(
+            &json!({ "filter": { "entity_type": "toaster" } }),
+        ));
+
+        assert!(matches!(result, Err(ServiceError::BadRequest(_))));
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Entity identifier namespace of multiple Home Assistant server connections.
+//!
+//! Entities of the main HA server keep their HA entity id. Entities of an additional HA server are
+//! prefixed with the device identifier of the server connection: `{device_id}@{entity_id}`.
+//! Entity commands are routed with the prefix, without a prior `get_available_entities` request,
+//! and equal entity ids on different servers don't collide.
+
+use crate::configuration::{DEFAULT_HA_DEVICE_ID, DEVICE_ENTITY_SEPARATOR};
+use std::collections::HashSet;
+
+/// Get the entity id as exposed to the remote of a HA entity id.
+pub(crate) fn remote_entity_id(device_id: &str, entity_id: &str) -> String {
+    if device_id == DEFAULT_HA_DEVICE_ID {
+        entity_id.to_string()
+    } else {
+        format!("{device_id}{DEVICE_ENTITY_SEPARATOR}{entity_id}")
+    }
+}
+
+/// Split an entity id of the remote into the device identifier of the HA server connection and
+/// the HA entity id.
+///
+/// Entity ids without a device prefix belong to the main server connection.
+pub(crate) fn split_entity_id(entity_id: &str) -> (&str, &str) {
+    match entity_id.split_once(DEVICE_ENTITY_SEPARATOR) {
+        Some((device_id, entity_id)) => (device_id, entity_id),
+        None => (DEFAULT_HA_DEVICE_ID, entity_id),
+    }
+}
+
+/// Get the HA entity ids of a HA server connection from the entity ids of the remote.
+pub(crate) fn device_entity_ids(device_id: &str, entity_ids: &HashSet<String>) -> HashSet<String> {
+    entity_ids
+        .iter()
+        .map(|entity_id| split_entity_id(entity_id))
+        .filter(|(id, _)| *id == device_id)
+        .map(|(_, entity_id)| entity_id.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("light.kitchen", DEFAULT_HA_DEVICE_ID, "light.kitchen")]
+    #[case("upstairs@light.kitchen", "upstairs", "light.kitchen")]
+    #[case("upstairs@light.*", "upstairs", "light.*")]
+    #[case(
+        "sensor.outdoor:battery",
+        DEFAULT_HA_DEVICE_ID,
+        "sensor.outdoor:battery"
+    )]
+    #[case(
+        "upstairs@sensor.outdoor:battery",
+        "upstairs",
+        "sensor.outdoor:battery"
+    )]
+    fn entity_is_routed_by_prefix(
+        #[case] entity_id: &str,
+        #[case] device_id: &str,
+        #[case] ha_entity_id: &str,
+    ) {
+        // no prior entity mapping required, e.g. after a restart
+        assert_eq!((device_id, ha_entity_id), split_entity_id(entity_id));
+    }
+
+    #[test]
+    fn equal_entity_ids_of_different_servers_do_not_collide() {
+        let main = remote_entity_id(DEFAULT_HA_DEVICE_ID, "light.kitchen");
+        let upstairs = remote_entity_id("upstairs", "light.kitchen");
+
+        assert_ne!(main, upstairs);
+        assert_eq!(
+            (DEFAULT_HA_DEVICE_ID, "light.kitchen"),
+            split_entity_id(&main)
+        );
+        assert_eq!(("upstairs", "light.kitchen"), split_entity_id(&upstairs));
+    }
+
+    #[test]
+    fn attribute_entity_of_main_server_is_not_split() {
+        // derived attribute entity id of `sensor.outdoor`
+        let entity_id = "sensor.outdoor:battery".to_string();
+        let entity_ids = HashSet::from([remote_entity_id(DEFAULT_HA_DEVICE_ID, &entity_id)]);
+
+        assert_eq!(
+            HashSet::from([entity_id]),
+            device_entity_ids(DEFAULT_HA_DEVICE_ID, &entity_ids)
+        );
+    }
+
+    #[test]
+    fn subscribed_entities_are_split_by_server() {
+        let entity_ids = HashSet::from([
+            "light.kitchen".to_string(),
+            "switch.*".to_string(),
+            "upstairs@light.kitchen".to_string(),
+            "garage@cover.door".to_string(),
+        ]);
+
+        assert_eq!(
+            HashSet::from(["light.kitchen".to_string(), "switch.*".to_string()]),
+            device_entity_ids(DEFAULT_HA_DEVICE_ID, &entity_ids)
+        );
+        assert_eq!(
+            HashSet::from(["light.kitchen".to_string()]),
+            device_entity_ids("upstairs", &entity_ids)
+        );
+    }
+}
@@ -3,16 +3,20 @@
 
 //! Actix message handler for Home Assistant client connection messages.
 
-use crate::client::messages::{
-    Close, ConnectionEvent, ConnectionState, SetRemoteId, SubscribedEntities,
-};
+use crate::client::messages::{Close, ConnectionEvent, ConnectionState, SubscribedEntities};
 use crate::client::HomeAssistantClient;
+use crate::controller::connection_history::ConnectionEventType;
+use crate::controller::entity_namespace::device_entity_ids;
 use crate::controller::handler::{ConnectMsg, DisconnectMsg};
 use crate::controller::OperationModeInput::{AbortSetup, Connected};
 use crate::controller::{Controller, OperationModeState};
+use crate::server::{image_proxy_base_url, normalize_base_path};
+use crate::util::{redact_url, ws_url_with_token};
 use actix::{fut, ActorFutureExt, AsyncContext, Context, Handler, ResponseActFuture, WrapFuture};
 use futures::StreamExt;
 use log::{debug, error, info, warn};
+use rand::Rng;
+use std::collections::HashSet;
 use std::io::{Error, ErrorKind};
 use uc_api::intg::DeviceState;
 
@@ -24,18 +28,32 @@ impl Handler<ConnectionEvent> for Controller {
         //      This patched-up implementation might still contain race conditions!
         match msg.state {
             ConnectionState::AuthenticationFailed => {
-                // error state prevents auto-reconnect in upcoming Closed event
-                self.set_device_state(DeviceState::Error);
+                self.connection_history.push(
+                    ConnectionEventType::Disconnected,
+                    Some(format!("[{}] authentication failed", msg.device_id)),
+                );
+                // error state prevents auto-reconnect in upcoming Closed event.
+                // Other HA server connections are still available and must keep reconnecting.
+                if !self.has_other_ha_connection(&msg.device_id) {
+                    self.set_device_state(DeviceState::Error);
+                }
             }
             ConnectionState::Connected => {
-                self.ha_client_id = Some(msg.client_id);
+                self.connection_history
+                    .push(ConnectionEventType::Connected, None);
+                self.ha_client_ids.insert(msg.device_id, msg.client_id);
                 self.set_device_state(DeviceState::Connected);
             }
             ConnectionState::Closed => {
-                if Some(&msg.client_id) == self.ha_client_id.as_ref() {
+                if self.ha_client_ids.get(&msg.device_id) == Some(&msg.client_id) {
                     info!("[{}] HA client disconnected", msg.client_id);
-                    self.ha_client = None;
-                    self.ha_client_id = None;
+                    self.connection_history.push(
+                        ConnectionEventType::Disconnected,
+                        Some(format!("[{}] connection closed", msg.device_id)),
+                    );
+                    self.ha_clients.remove(&msg.device_id);
+                    self.ha_client_ids.remove(&msg.device_id);
+                    self.entity_cache.invalidate(&msg.device_id);
                 } else {
                     info!("[{}] Old HA client disconnected: ignoring", msg.client_id);
                     return;
@@ -46,10 +64,12 @@ impl Handler<ConnectionEvent> for Controller {
                     DeviceState::Connecting | DeviceState::Connected
                 ) {
                     info!("[{}] Start reconnecting to HA", msg.client_id);
-                    self.set_device_state(DeviceState::Connecting);
+                    // other HA server connections are still available
+                    if !self.has_other_ha_connection(&msg.device_id) {
+                        self.set_device_state(DeviceState::Connecting);
+                    }
 
-                    self.reconnect_handle =
-                        Some(ctx.notify_later(ConnectMsg::default(), self.ha_reconnect_duration));
+                    self.schedule_reconnect(&msg.device_id, ctx);
                 }
             }
         };
@@ -59,34 +79,107 @@ impl Handler<ConnectionEvent> for Controller {
 impl Handler<DisconnectMsg> for Controller {
     type Result = ();
 
-    fn handle(&mut self, _msg: DisconnectMsg, ctx: &mut Self::Context) -> Self::Result {
-        info!("Disconnect request: forcing immediate disconnect from HA server");
-        self.disconnect(ctx)
+    fn handle(&mut self, msg: DisconnectMsg, ctx: &mut Self::Context) -> Self::Result {
+        match msg.device_id {
+            Some(device_id) => {
+                info!("[{device_id}] Disconnect request: disconnecting from HA server");
+                self.connection_history.push(
+                    ConnectionEventType::Disconnected,
+                    Some(format!("[{device_id}] disconnect request")),
+                );
+                self.disconnect_device(&device_id, ctx);
+                if self.ha_clients.is_empty() {
+                    self.set_device_state(DeviceState::Disconnected);
+                }
+            }
+            None => {
+                info!("Disconnect request: forcing immediate disconnect from HA server");
+                self.connection_history.push(
+                    ConnectionEventType::Disconnected,
+                    Some("disconnect request".into()),
+                );
+                self.disconnect(ctx)
+            }
+        }
     }
 }
 
 impl Controller {
+    /// Disconnect from all HA servers.
     pub(crate) fn disconnect(&mut self, ctx: &mut Context<Controller>) {
         // this prevents automatic reconnects. TODO #39 this should be handled with a state machine!
         self.set_device_state(DeviceState::Disconnected);
 
-        if let Some(handle) = self.reconnect_handle.take() {
+        let device_ids: HashSet<String> = self
+            .ha_clients
+            .keys()
+            .chain(self.ha_reconnect.keys())
+            .cloned()
+            .collect();
+        for device_id in device_ids {
+            self.disconnect_device(&device_id, ctx);
+        }
+    }
+
+    /// Schedule a reconnect attempt to the HA server of the given device identifier with the
+    /// current reconnect delay and jitter.
+    fn schedule_reconnect(&mut self, device_id: &str, ctx: &mut Context<Controller>) {
+        let settings = self.settings.hass.reconnect.clone();
+        let delay = self
+            .reconnect_state(device_id)
+            .schedule(&settings, rand::thread_rng().gen_range(-1.0..=1.0));
+        info!("[{device_id}] Reconnecting in {}ms", delay.as_millis());
+        let handle = ctx.notify_later(
+            ConnectMsg {
+                device_id: Some(device_id.to_string()),
+            },
+            delay,
+        );
+        self.reconnect_state(device_id).handle = Some(handle);
+        self.broadcast_reconnect_state(device_id);
+    }
+
+    /// Disconnect from the HA server of the given device identifier without changing the
+    /// device state.
+    fn disconnect_device(&mut self, device_id: &str, ctx: &mut Context<Controller>) {
+        if let Some(handle) = self
+            .ha_reconnect
+            .get_mut(device_id)
+            .and_then(|r| r.handle.take())
+        {
             ctx.cancel_future(handle);
         }
-        if let Some(addr) = self.ha_client.as_ref() {
+        if let Some(addr) = self.ha_clients.get(device_id) {
             addr.do_send(Close::default());
         }
         // Make sure the old connection is no longer used and doesn't interfere with reconnection
-        self.ha_client = None;
-        self.ha_client_id = None;
+        self.ha_clients.remove(device_id);
+        self.ha_client_ids.remove(device_id);
     }
 }
 
 impl Handler<ConnectMsg> for Controller {
     type Result = ResponseActFuture<Self, Result<(), Error>>;
 
-    fn handle(&mut self, _msg: ConnectMsg, ctx: &mut Self::Context) -> Self::Result {
-        if let Some(handle) = self.reconnect_handle.take() {
+    fn handle(&mut self, msg: ConnectMsg, ctx: &mut Self::Context) -> Self::Result {
+        let device_id = match msg.device_id {
+            Some(device_id) => device_id,
+            None => {
+                // connect to all configured HA servers
+                for server in self.settings.hass.servers() {
+                    ctx.notify(ConnectMsg {
+                        device_id: Some(server.id),
+                    });
+                }
+                return Box::pin(fut::ok(()));
+            }
+        };
+
+        if let Some(handle) = self
+            .ha_reconnect
+            .get_mut(&device_id)
+            .and_then(|r| r.handle.take())
+        {
             ctx.cancel_future(handle);
         }
         if !matches!(
@@ -100,18 +193,35 @@ impl Handler<ConnectMsg> for Controller {
             ))));
         }
 
-        if let Some(client_id) = self.ha_client_id.as_ref() {
-            if self.ha_client.is_some() {
+        if let Some(client_id) = self.ha_client_ids.get(&device_id) {
+            if self.ha_clients.contains_key(&device_id) {
                 warn!("[{client_id}] Ignoring connect request: already connected to HA server");
                 return Box::pin(fut::ok(()));
             }
         }
 
-        let url = self.settings.hass.get_url();
-        let token = self.settings.hass.get_token();
+        let server = match self
+            .settings
+            .hass
+            .servers()
+            .into_iter()
+            .find(|s| s.id == device_id)
+        {
+            Some(server) => server,
+            None => {
+                warn!("[{device_id}] Ignoring connect request: HA server is no longer configured");
+                return Box::pin(fut::result(Err(Error::new(
+                    ErrorKind::NotFound,
+                    "HA server not configured",
+                ))));
+            }
+        };
+        let url = server.url;
+        let token = server.token;
+        let url_token = server.url_token;
 
         if url.host_str().is_none() || token.is_empty() {
-            error!("Cannot connect: HA url or token missing");
+            error!("[{device_id}] Cannot connect: HA url or token missing");
             let dummy_ws_id = "0"; // we don't have a WS request msg id
             if let Err(e) = self.sm_consume(dummy_ws_id, &AbortSetup, ctx) {
                 error!("{e}");
@@ -122,17 +232,29 @@ impl Handler<ConnectMsg> for Controller {
             ))));
         }
 
-        self.set_device_state(DeviceState::Connecting);
+        if !self.has_other_ha_connection(&device_id) {
+            self.set_device_state(DeviceState::Connecting);
+        }
 
-        let ws_request = self.ws_client.ws(url.as_str());
+        // the URL token of a server is not shared with other servers
+        let ws_url = ws_url_with_token(&url, &url_token);
+        let log_url = redact_url(&ws_url);
+        let ws_request = self.ws_client.ws(ws_url.as_str());
         // align frame size to Home Assistant
         let ws_request = ws_request.max_frame_size(self.settings.hass.max_frame_size_kb * 1024);
         let client_address = ctx.address();
         let heartbeat = self.settings.hass.heartbeat;
+        let mut settings = self.settings.hass.clone();
+        settings.media_player.image_proxy_url = image_proxy_base_url(
+            &settings.media_player.image_proxy_url,
+            self.settings.integration.http.port,
+            &normalize_base_path(&self.settings.integration.base_path),
+        );
         let remote_id = self.remote_id.clone();
+        let client_device_id = device_id.clone();
 
         info!(
-            "Connecting to: {url} (timeout: {}s, request_timeout: {}s)",
+            "[{device_id}] Connecting to: {log_url} (timeout: {}s, request_timeout: {}s)",
             self.settings.hass.connection_timeout, self.settings.hass.request_timeout
         );
         Box::pin(
@@ -140,21 +262,29 @@ impl Handler<ConnectMsg> for Controller {
                 let (_, framed) = match ws_request.connect().await {
                     Ok((r, f)) => (r, f),
                     Err(e) => {
-                        warn!("Could not connect to {url}: {e:?}");
+                        warn!("Could not connect to {log_url}: {e:?}");
                         return Err(Error::new(ErrorKind::Other, e.to_string()));
                     }
                 };
-                info!("Connected to: {url} ({heartbeat})");
+                info!("Connected to: {log_url} ({heartbeat})");
 
                 let (sink, stream) = framed.split();
-                let addr =
-                    HomeAssistantClient::start(url, client_address, token, sink, stream, heartbeat);
+                let addr = HomeAssistantClient::start(
+                    client_device_id,
+                    url,
+                    client_address,
+                    token,
+                    remote_id,
+                    sink,
+                    stream,
+                    &settings,
+                );
 
                 Ok(addr)
             }
             .into_actor(self) // converts future to ActorFuture
             .map(move |result, act, ctx| {
-                act.ha_client_id = None; // will be set with Connected event
+                act.ha_client_ids.remove(&device_id); // will be set with Connected event
                 match result {
                     Ok(addr) => {
                         let dummy_ws_id = "0"; // we don't have a WS request msg id
@@ -162,45 +292,42 @@ impl Handler<ConnectMsg> for Controller {
                             error!("{e}");
                         }
 
-                        act.ha_client = Some(addr);
-                        act.ha_reconnect_duration = act.settings.hass.reconnect.duration;
-                        act.ha_reconnect_attempt = 0;
+                        let duration = act.settings.hass.reconnect.duration;
+                        act.reconnect_state(&device_id).reset(duration);
                         debug!("Sending subscribed entities to client for events subscriptions");
                         if let Some(session) = act.sessions.values().next() {
-                            let entities = session.subscribed_entities.clone();
-                            if let Some(ha_client) = &act.ha_client {
-                                if let Err(e) = ha_client.try_send(SetRemoteId { remote_id }) {
-                                    error!("Error sending remote identifier to client: {:?}", e);
-                                }
-
-                                if let Err(e) = ha_client.try_send(SubscribedEntities {
-                                    entity_ids: entities,
-                                }) {
-                                    error!("Error updating subscribed entities to client: {:?}", e);
-                                }
+                            let entities =
+                                device_entity_ids(&device_id, &session.subscribed_entities);
+                            if let Err(e) = addr.try_send(SubscribedEntities {
+                                entity_ids: entities,
+                            }) {
+                                error!("Error updating subscribed entities to client: {:?}", e);
                             }
                         }
+                        act.ha_clients.insert(device_id, addr);
                         Ok(())
                     }
                     Err(e) => {
-                        act.ha_client = None;
+                        act.connection_history.push(
+                            ConnectionEventType::ConnectFailed,
+                            Some(format!("[{device_id}] {e}")),
+                        );
+                        act.ha_clients.remove(&device_id);
                         // TODO #39 quick and dirty: simply send Connect message as simple reconnect mechanism. Needs to be refined!
                         if act.device_state != DeviceState::Disconnected {
-                            act.ha_reconnect_attempt += 1;
-                            if act.settings.hass.reconnect.attempts > 0
-                                && act.ha_reconnect_attempt > act.settings.hass.reconnect.attempts
-                            {
+                            let max_attempts = act.settings.hass.reconnect.attempts;
+                            let reconnect = act.reconnect_state(&device_id);
+                            reconnect.attempt += 1;
+                            if max_attempts > 0 && reconnect.attempt > max_attempts {
                                 info!(
-                                    "Max reconnect attempts reached ({}). Giving up!",
-                                    act.settings.hass.reconnect.attempts
+                                    "[{device_id}] Max reconnect attempts reached ({max_attempts}). Giving up!"
                                 );
-                                act.set_device_state(DeviceState::Error);
+                                if !act.has_other_ha_connection(&device_id) {
+                                    act.set_device_state(DeviceState::Error);
+                                }
                             } else {
-                                act.reconnect_handle = Some(ctx.notify_later(
-                                    ConnectMsg::default(),
-                                    act.ha_reconnect_duration,
-                                ));
-                                act.increment_reconnect_timeout();
+                                act.schedule_reconnect(&device_id, ctx);
+                                act.increment_reconnect_timeout(&device_id);
                             }
                         }
                         Err(e)
@@ -210,3 +337,75 @@ impl Handler<ConnectMsg> for Controller {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::{get_driver_metadata, Settings};
+    use crate::controller::GetHealth;
+    use actix::{Actor, Addr};
+
+    fn connection_event(
+        device_id: &str,
+        client_id: &str,
+        state: ConnectionState,
+    ) -> ConnectionEvent {
+        ConnectionEvent {
+            client_id: client_id.into(),
+            device_id: device_id.into(),
+            state,
+        }
+    }
+
+    async fn device_state(controller: &Addr<Controller>) -> String {
+        controller
+            .send(GetHealth)
+            .await
+            .expect("controller must be running")
+            .expect("health status")
+            .state
+    }
+
+    #[actix::test]
+    async fn failed_authentication_of_one_server_keeps_other_connection() {
+        let metadata = get_driver_metadata().expect("driver metadata");
+        let controller = Controller::new(Settings::default(), metadata).start();
+        controller.do_send(connection_event("main", "1", ConnectionState::Connected));
+
+        controller.do_send(connection_event(
+            "cabin",
+            "2",
+            ConnectionState::AuthenticationFailed,
+        ));
+        controller.do_send(connection_event("cabin", "2", ConnectionState::Closed));
+        assert_eq!(
+            DeviceState::Connected.to_string(),
+            device_state(&controller).await
+        );
+
+        // a later connection drop of the main server is reconnected
+        controller.do_send(connection_event("main", "1", ConnectionState::Closed));
+        assert_eq!(
+            DeviceState::Connecting.to_string(),
+            device_state(&controller).await
+        );
+    }
+
+    #[actix::test]
+    async fn failed_authentication_without_other_connection_is_an_error() {
+        let metadata = get_driver_metadata().expect("driver metadata");
+        let controller = Controller::new(Settings::default(), metadata).start();
+
+        controller.do_send(connection_event(
+            "main",
+            "1",
+            ConnectionState::AuthenticationFailed,
+        ));
+        controller.do_send(connection_event("main", "1", ConnectionState::Closed));
+
+        assert_eq!(
+            DeviceState::Error.to_string(),
+            device_state(&controller).await
+        );
+    }
+}
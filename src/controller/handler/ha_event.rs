@@ -4,24 +4,37 @@
 //! Actix message handler for Home Assistant events.
 
 use crate::client::messages::{
-    AvailableEntities, EntityEvent, SetAvailableEntities, SubscribedEntities,
+    AvailableEntities, EntityAvailability, EntityEvent, ServiceCallConfirmation,
+    SetAvailableEntities, SubscribedEntities,
+};
+use crate::controller::entity_filter::available_entities_msg_data;
+use crate::controller::entity_namespace::{device_entity_ids, remote_entity_id};
+use crate::controller::handler::{
+    EntityRequestFailed, SubscribeHaEventsMsg, UnsubscribeHaEventsMsg,
 };
-use crate::controller::handler::{SubscribeHaEventsMsg, UnsubscribeHaEventsMsg};
 use crate::controller::{Controller, OperationModeState, SendWsMessage};
 use crate::errors::ServiceError;
 use crate::util::DeserializeMsgData;
 use actix::Handler;
 use log::{debug, error};
-use uc_api::intg::ws::AvailableEntitiesMsgData;
-use uc_api::intg::{EntityChange, SubscribeEvents};
+use serde_json::json;
+use std::time::Instant;
+use uc_api::intg::{AvailableIntgEntity, EntityChange, SubscribeEvents};
 use uc_api::ws::{EventCategory, WsMessage};
 
 impl Handler<EntityEvent> for Controller {
     type Result = ();
 
     fn handle(&mut self, msg: EntityEvent, _ctx: &mut Self::Context) -> Self::Result {
+        let mut entity_change = msg.entity_change;
+        entity_change.entity_id = remote_entity_id(&msg.device_id, &entity_change.entity_id);
+        self.entity_cache.update(&entity_change);
         // TODO keep an entity subscription per remote session and filter out non-subscribed remotes?
-        if let Ok(msg_data) = serde_json::to_value(msg.entity_change) {
+        if let Ok(msg_data) = serde_json::to_value(entity_change) {
+            // remotes in standby get the latest entity changes when exiting standby
+            for session in self.sessions.values_mut().filter(|s| s.standby) {
+                session.standby_queue.push_entity_change(msg_data.clone());
+            }
             for session in self.sessions.keys() {
                 self.send_r2_msg(
                     WsMessage::event("entity_change", EventCategory::Entity, msg_data.clone()),
@@ -32,22 +45,131 @@ impl Handler<EntityEvent> for Controller {
     }
 }
 
+impl Handler<ServiceCallConfirmation> for Controller {
+    type Result = ();
+
+    fn handle(&mut self, msg: ServiceCallConfirmation, _ctx: &mut Self::Context) -> Self::Result {
+        // Custom event, not defined in the Integration-API
+        let msg_data = json!({
+            "entity_type": msg.entity_type,
+            "entity_id": remote_entity_id(&msg.device_id, &msg.entity_id),
+            "cmd_id": msg.cmd_id
+        });
+        for (ws_id, session) in self.sessions.iter() {
+            if session.standby {
+                debug!("[{ws_id}] Remote is in standby, not sending command confirmation");
+                continue;
+            }
+            self.send_r2_msg(
+                WsMessage::event(
+                    "command_confirmation",
+                    EventCategory::Entity,
+                    msg_data.clone(),
+                ),
+                ws_id,
+            );
+        }
+    }
+}
+
+impl Handler<EntityAvailability> for Controller {
+    type Result = ();
+
+    fn handle(&mut self, msg: EntityAvailability, _ctx: &mut Self::Context) -> Self::Result {
+        // Custom event, not defined in the Integration-API
+        let msg_data = json!({
+            "entity_type": msg.entity_type,
+            "entity_id": remote_entity_id(&msg.device_id, &msg.entity_id),
+            "available": msg.available
+        });
+        for (ws_id, session) in self.sessions.iter() {
+            // remotes in standby get the latest entity changes when exiting standby
+            if session.standby {
+                debug!("[{ws_id}] Remote is in standby, not sending entity availability");
+                continue;
+            }
+            self.send_r2_msg(
+                WsMessage::event("entity_available", EventCategory::Entity, msg_data.clone()),
+                ws_id,
+            );
+        }
+    }
+}
+
 impl Handler<AvailableEntities> for Controller {
     type Result = ();
 
     fn handle(&mut self, msg: AvailableEntities, _ctx: &mut Self::Context) -> Self::Result {
-        // TODO just a quick implementation. Implement request filter! (also caching?)
+        let mut entities = msg.entities;
+        for entity in entities.iter_mut() {
+            entity.entity_id = remote_entity_id(&msg.device_id, &entity.entity_id);
+        }
+        if self.entity_cache_devices.remove(&msg.device_id) {
+            self.entity_cache
+                .insert(&msg.device_id, entities.clone(), Instant::now());
+        }
+        // wait for the entities of all HA server connections of the pending request
+        let entities = if self.pending_entity_devices.remove(&msg.device_id) {
+            self.pending_entities.extend(entities);
+            if !self.pending_entity_devices.is_empty() {
+                debug!(
+                    "[{}] Waiting for entities of other HA servers: {:?}",
+                    msg.device_id, self.pending_entity_devices
+                );
+                return;
+            }
+            std::mem::take(&mut self.pending_entities)
+        } else {
+            entities
+        };
+
+        self.send_entities(entities);
+    }
+}
+
+impl Handler<EntityRequestFailed> for Controller {
+    type Result = ();
+
+    fn handle(&mut self, msg: EntityRequestFailed, _ctx: &mut Self::Context) -> Self::Result {
+        let mut removed = false;
+        for device_id in &msg.device_ids {
+            self.entity_cache_devices.remove(device_id);
+            removed |= self.pending_entity_devices.remove(device_id);
+        }
+        if msg.cancel {
+            // the error is returned as response of the remote request
+            self.pending_entity_devices.clear();
+            self.pending_entities.clear();
+            for session in self.sessions.values_mut() {
+                session.get_available_entities_id = None;
+                session.get_entity_states_id = None;
+            }
+            return;
+        }
+        if removed && self.pending_entity_devices.is_empty() {
+            debug!(
+                "Sending entities without failed HA servers: {:?}",
+                msg.device_ids
+            );
+            let entities = std::mem::take(&mut self.pending_entities);
+            self.send_entities(entities);
+        }
+    }
+}
+
+impl Controller {
+    /// Send the available entities or entity states response to the remotes waiting for it.
+    fn send_entities(&mut self, entities: Vec<AvailableIntgEntity>) {
         for (ws_id, session) in self.sessions.iter_mut() {
             if session.standby {
                 debug!("[{ws_id}] Remote is in standby, not handling available_entities from HASS");
                 continue;
             }
             if let Some(id) = session.get_available_entities_id {
-                let msg_data = AvailableEntitiesMsgData {
-                    filter: None,
-                    available_entities: msg.entities.clone(),
-                };
-                if let Ok(msg_data_json) = serde_json::to_value(msg_data) {
+                if let Ok(msg_data_json) = available_entities_msg_data(
+                    &entities,
+                    session.available_entities_filter.as_ref(),
+                ) {
                     match session
                         .recipient
                         .try_send(SendWsMessage(WsMessage::response(
@@ -60,8 +182,8 @@ impl Handler<AvailableEntities> for Controller {
                     }
                 }
             } else if let Some(id) = session.get_entity_states_id {
-                let mut msg_data = Vec::with_capacity(msg.entities.len());
-                for entity in &msg.entities {
+                let mut msg_data = Vec::with_capacity(entities.len());
+                for entity in &entities {
                     msg_data.push(EntityChange {
                         device_id: entity.device_id.clone(),
                         entity_type: entity.entity_type,
@@ -91,6 +213,12 @@ impl Handler<SetAvailableEntities> for Controller {
     type Result = ();
 
     fn handle(&mut self, msg: SetAvailableEntities, _ctx: &mut Self::Context) -> Self::Result {
+        // the available entities have been reconfigured in HA
+        self.entity_cache.invalidate(&msg.device_id);
+        let mut entities = msg.entities;
+        for entity in entities.iter_mut() {
+            entity.entity_id = remote_entity_id(&msg.device_id, &entity.entity_id);
+        }
         for (ws_id, session) in self.sessions.iter_mut() {
             if session.standby {
                 debug!(
@@ -98,10 +226,10 @@ impl Handler<SetAvailableEntities> for Controller {
                 );
                 continue;
             }
-            let entity_ids: Vec<&String> = msg.entities.iter().map(|x| &x.entity_id).collect();
+            let entity_ids: Vec<&String> = entities.iter().map(|x| &x.entity_id).collect();
             debug!("[{ws_id}] Received new available entities to send to remote: {entity_ids:?}");
             // Store the list for next call to get_available_entities
-            self.susbcribed_entity_ids = Option::from(msg.entities.clone());
+            self.susbcribed_entity_ids = Option::from(entities.clone());
         }
     }
 }
@@ -118,9 +246,9 @@ impl Handler<SubscribeHaEventsMsg> for Controller {
             let subscribe: SubscribeEvents = msg.0.deserialize()?;
             session.subscribed_entities.extend(subscribe.entity_ids);
             debug!("Sending updated subscribed entities to client for events subscriptions");
-            if let Some(ha_client) = &self.ha_client {
+            for (device_id, ha_client) in self.ha_clients.iter() {
                 ha_client.try_send(SubscribedEntities {
-                    entity_ids: session.subscribed_entities.clone(),
+                    entity_ids: device_entity_ids(device_id, &session.subscribed_entities),
                 })?;
             }
             Ok(())
@@ -143,9 +271,9 @@ impl Handler<UnsubscribeHaEventsMsg> for Controller {
             for i in unsubscribe.entity_ids {
                 session.subscribed_entities.remove(&i);
             }
-            if let Some(ha_client) = &self.ha_client {
+            for (device_id, ha_client) in self.ha_clients.iter() {
                 ha_client.try_send(SubscribedEntities {
-                    entity_ids: session.subscribed_entities.clone(),
+                    entity_ids: device_entity_ids(device_id, &session.subscribed_entities),
                 })?;
             }
             Ok(())
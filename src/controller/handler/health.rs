@@ -0,0 +1,36 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Actix message handler for the integration driver health status.
+
+use crate::controller::{Controller, GetHealth, HealthStatus, ReconnectStatus};
+use crate::errors::ServiceError;
+use actix::Handler;
+
+impl Handler<GetHealth> for Controller {
+    type Result = Result<HealthStatus, ServiceError>;
+
+    fn handle(&mut self, _msg: GetHealth, _ctx: &mut Self::Context) -> Self::Result {
+        Ok(HealthStatus {
+            state: self.device_state.to_string(),
+            uptime_sec: self.started.elapsed().as_secs(),
+            sessions: self.sessions.len(),
+            ha_connected: !self.ha_client_ids.is_empty(),
+            connection_history: self.connection_history.entries(),
+            reconnect: self.reconnect_status(),
+        })
+    }
+}
+
+impl Controller {
+    /// Get the reconnect status of all HA server connections, ordered by device identifier.
+    fn reconnect_status(&self) -> Vec<ReconnectStatus> {
+        let mut status: Vec<ReconnectStatus> = self
+            .ha_reconnect
+            .iter()
+            .map(|(device_id, state)| state.status(device_id, &self.settings.hass.reconnect))
+            .collect();
+        status.sort_by(|a, b| a.device_id.cmp(&b.device_id));
+        status
+    }
+}
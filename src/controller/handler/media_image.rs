@@ -0,0 +1,119 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Actix message handler for the media image proxy.
+//!
+//! The media image is fetched from Home Assistant with the access token of the HA server
+//! connection providing the entity, for remotes which cannot access the HA server directly.
+
+use crate::client::messages::{GetMediaImageSource, MediaImageSource};
+use crate::controller::{Controller, GetMediaImage, MediaImage};
+use crate::errors::ServiceError;
+use actix::{fut, ActorFutureExt, Handler, ResponseActFuture, WrapFuture};
+use awc::http::header::CONTENT_TYPE;
+use awc::http::StatusCode;
+use log::debug;
+use std::time::{Duration, Instant};
+
+/// Time to live of a cached media image.
+const MEDIA_IMAGE_TTL: Duration = Duration::from_secs(10);
+/// Maximum size of a media image.
+const MAX_MEDIA_IMAGE_SIZE: usize = 5 * 1024 * 1024;
+
+impl Handler<GetMediaImage> for Controller {
+    type Result = ResponseActFuture<Self, Result<MediaImage, ServiceError>>;
+
+    fn handle(&mut self, msg: GetMediaImage, _ctx: &mut Self::Context) -> Self::Result {
+        if !self.settings.hass.media_player.image_proxy {
+            return Box::pin(fut::result(Err(ServiceError::NotFound(
+                "Media image proxy is disabled".into(),
+            ))));
+        }
+        let (ha_client, ha_entity_id) = match self.ha_client_for_entity(&msg.entity_id) {
+            Some(client) => client,
+            None => return Box::pin(fut::result(Err(ServiceError::NotConnected))),
+        };
+        let source_msg = GetMediaImageSource {
+            entity_id: ha_entity_id.to_string(),
+        };
+        let entity_id = msg.entity_id;
+
+        Box::pin(
+            async move { ha_client.send(source_msg).await? }
+                .into_actor(self)
+                .then(move |result, act, _ctx| {
+                    let source = match result {
+                        Ok(source) => source,
+                        Err(e) => return Box::pin(fut::result(Err(e))) as Self::Result,
+                    };
+                    let now = Instant::now();
+                    if let Some(image) =
+                        act.media_images
+                            .get(&entity_id, &source.url, MEDIA_IMAGE_TTL, now)
+                    {
+                        debug!("Sending cached media image of {entity_id}");
+                        return Box::pin(fut::result(Ok(image)));
+                    }
+
+                    let url = source.url.clone();
+                    Box::pin(
+                        fetch_media_image(act.ws_client.clone(), source)
+                            .into_actor(act)
+                            .map(move |result, act, _ctx| {
+                                if let Ok(image) = &result {
+                                    act.media_images.insert(
+                                        &entity_id,
+                                        &url,
+                                        image.clone(),
+                                        Instant::now(),
+                                    );
+                                }
+                                result
+                            }),
+                    )
+                }),
+        )
+    }
+}
+
+/// Fetch a media image, authenticated with the access token for images served by HA.
+async fn fetch_media_image(
+    http_client: awc::Client,
+    source: MediaImageSource,
+) -> Result<MediaImage, ServiceError> {
+    let mut request = http_client.get(&source.url);
+    if let Some(token) = source.access_token.as_ref() {
+        request = request.bearer_auth(token);
+    }
+    let mut response = request.send().await.map_err(|e| {
+        ServiceError::ServiceUnavailable(format!("Error fetching media image: {e}"))
+    })?;
+
+    match response.status() {
+        StatusCode::OK => {}
+        StatusCode::NOT_FOUND => {
+            return Err(ServiceError::NotFound("Media image not found".into()));
+        }
+        status => {
+            return Err(ServiceError::ServiceUnavailable(format!(
+                "Error fetching media image: {status}"
+            )));
+        }
+    }
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("image/jpeg")
+        .to_string();
+    let data = response
+        .body()
+        .limit(MAX_MEDIA_IMAGE_SIZE)
+        .await
+        .map_err(|e| ServiceError::ServiceUnavailable(format!("Error reading media image: {e}")))?;
+    if data.is_empty() {
+        return Err(ServiceError::NotFound("Empty media image".into()));
+    }
+
+    Ok(MediaImage { content_type, data })
+}
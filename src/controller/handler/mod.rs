@@ -5,6 +5,8 @@
 
 mod ha_connection;
 mod ha_event;
+mod health;
+mod media_image;
 mod r2_connection;
 mod r2_event;
 mod r2_request;
@@ -26,20 +28,31 @@ struct SubscribeHaEventsMsg(pub R2RequestMsg);
 #[rtype(result = "Result<(), ServiceError>")]
 struct UnsubscribeHaEventsMsg(pub R2RequestMsg);
 
+/// Internal message to remove failed HA server connections from the pending entity request.
+#[derive(Message)]
+#[rtype(result = "()")]
+struct EntityRequestFailed {
+    /// Device identifiers of the failed HA server connections.
+    pub device_ids: Vec<String>,
+    /// All requests failed: the pending entity request is cancelled.
+    pub cancel: bool,
+}
+
 /// Internal message to connect to Home Assistant.
 #[derive(Message, Default)]
 #[rtype(result = "Result<(), std::io::Error>")]
 struct ConnectMsg {
-    // device identifier for multi-HA connections: feature not yet available
-    // pub device_id: String,
+    /// Device identifier of the HA server connection. All configured servers are connected if
+    /// not set.
+    pub device_id: Option<String>,
 }
 
 /// Internal message to disconnect from Home Assistant.
-#[derive(Message)]
+#[derive(Message, Default)]
 #[rtype(result = "()")]
 struct DisconnectMsg {
-    // device identifier for multi-HA connections: feature not yet available
-    // pub device_id: String,
+    /// Device identifier of the HA server connection. All servers are disconnected if not set.
+    pub device_id: Option<String>,
 }
 
 /// Internal message to start driver setup flow.
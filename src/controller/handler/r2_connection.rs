@@ -4,14 +4,25 @@
 //! Actix message handler for Remote Two connection messages.
 
 use crate::controller::{Controller, NewR2Session, R2Session, R2SessionDisconnect, SendWsMessage};
+use crate::errors::ServiceError;
 use actix::{Context, Handler};
 use log::{error, info};
 use uc_api::ws::WsMessage;
 
 impl Handler<NewR2Session> for Controller {
-    type Result = ();
+    type Result = Result<(), ServiceError>;
 
     fn handle(&mut self, msg: NewR2Session, _: &mut Context<Self>) -> Self::Result {
+        // checked and registered in one step: concurrent connections must not exceed the limit
+        let max_sessions = self
+            .settings
+            .integration
+            .websocket
+            .as_ref()
+            .map(|ws| ws.max_sessions)
+            .unwrap_or_default();
+        check_session_limit(self.sessions.len(), max_sessions)?;
+
         self.sessions
             .insert(msg.id.clone(), R2Session::new(msg.addr));
 
@@ -27,6 +38,8 @@ impl Handler<NewR2Session> for Controller {
                 Err(e) => error!("[{}] Error sending entity_states: {e:?}", msg.id),
             }
         }
+
+        Ok(())
     }
 }
 
@@ -37,3 +50,90 @@ impl Handler<R2SessionDisconnect> for Controller {
         self.sessions.remove(&msg.id);
     }
 }
+
+/// Check if another WebSocket session is allowed.
+///
+/// # Arguments
+///
+/// * `sessions`: number of active sessions.
+/// * `max_sessions`: maximum number of sessions, 0 = unlimited.
+fn check_session_limit(sessions: usize, max_sessions: u16) -> Result<(), ServiceError> {
+    if max_sessions > 0 && sessions >= max_sessions as usize {
+        return Err(ServiceError::ServiceUnavailable(
+            "Maximum number of sessions reached".into(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::{get_driver_metadata, Settings, WebSocketSettings};
+    use actix::{Actor, Addr};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0, 1)]
+    #[case(2, 3)]
+    #[case(1000, 0)]
+    fn session_is_accepted(#[case] sessions: usize, #[case] max_sessions: u16) {
+        assert_eq!(Ok(()), check_session_limit(sessions, max_sessions));
+    }
+
+    #[rstest]
+    #[case(1, 1)]
+    #[case(3, 3)]
+    fn session_above_limit_is_refused(#[case] sessions: usize, #[case] max_sessions: u16) {
+        assert!(matches!(
+            check_session_limit(sessions, max_sessions),
+            Err(ServiceError::ServiceUnavailable(_))
+        ));
+    }
+
+    /// Remote WebSocket session ignoring the sent messages.
+    struct RemoteSession;
+
+    impl Actor for RemoteSession {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<SendWsMessage> for RemoteSession {
+        type Result = ();
+
+        fn handle(&mut self, _: SendWsMessage, _: &mut Self::Context) -> Self::Result {}
+    }
+
+    async fn connect(controller: &Addr<Controller>, id: &str) -> Result<(), ServiceError> {
+        controller
+            .send(NewR2Session {
+                addr: RemoteSession.start().recipient(),
+                id: id.into(),
+            })
+            .await
+            .expect("controller must be running")
+    }
+
+    #[actix::test]
+    async fn concurrent_sessions_are_limited_when_registering() {
+        let mut settings = Settings::default();
+        settings.integration.websocket = Some(WebSocketSettings {
+            max_sessions: 1,
+            ..Default::default()
+        });
+        let metadata = get_driver_metadata().expect("driver metadata");
+        let controller = Controller::new(settings, metadata).start();
+
+        // both connections are sent before the first one is registered
+        let (first, second) =
+            futures::join!(connect(&controller, "ws-1"), connect(&controller, "ws-2"));
+        assert_eq!(Ok(()), first);
+        assert!(matches!(second, Err(ServiceError::ServiceUnavailable(_))));
+
+        controller
+            .send(R2SessionDisconnect { id: "ws-1".into() })
+            .await
+            .expect("controller must be running");
+        assert_eq!(Ok(()), connect(&controller, "ws-3").await);
+    }
+}
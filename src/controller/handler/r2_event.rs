@@ -5,8 +5,8 @@
 
 use crate::controller::handler::{AbortDriverSetup, ConnectMsg, DisconnectMsg};
 use crate::controller::{Controller, R2EventMsg};
-use actix::{AsyncContext, Handler};
-use log::error;
+use actix::{AsyncContext, Context, Handler};
+use log::{error, info};
 use uc_api::intg::ws::R2Event;
 use uc_api::intg::DeviceState;
 
@@ -24,6 +24,7 @@ impl Handler<R2EventMsg> for Controller {
 
         match msg.event {
             R2Event::Connect => {
+                session.ha_connect = true;
                 if self.device_state != DeviceState::Connected {
                     ctx.notify(ConnectMsg::default());
                 }
@@ -31,17 +32,22 @@ impl Handler<R2EventMsg> for Controller {
                 self.send_device_state(&msg.ws_id);
             }
             R2Event::Disconnect => {
-                ctx.notify(DisconnectMsg {});
+                session.ha_connect = false;
+                self.disconnect_unless_shared(ctx);
             }
             R2Event::EnterStandby => {
                 session.standby = true;
                 if self.settings.hass.disconnect_in_standby {
-                    ctx.notify(DisconnectMsg {});
+                    session.ha_connect = false;
+                    self.disconnect_unless_shared(ctx);
                 }
             }
             R2Event::ExitStandby => {
-                session.standby = false;
+                self.exit_standby(&msg.ws_id);
                 if self.settings.hass.disconnect_in_standby {
+                    if let Some(session) = self.sessions.get_mut(&msg.ws_id) {
+                        session.ha_connect = true;
+                    }
                     ctx.notify(ConnectMsg::default());
                     self.send_device_state(&msg.ws_id);
                 }
@@ -55,3 +61,56 @@ impl Handler<R2EventMsg> for Controller {
         }
     }
 }
+
+impl Controller {
+    /// Disconnect from HA, unless the connection is shared and another remote wants to stay
+    /// connected.
+    fn disconnect_unless_shared(&mut self, ctx: &mut Context<Controller>) {
+        let connect_intents = self.sessions.values().map(|s| s.ha_connect);
+        if keep_connected(self.settings.hass.shared_connection, connect_intents) {
+            info!("Keeping HA connection: another remote is still connected");
+        } else {
+            ctx.notify(DisconnectMsg::default());
+        }
+    }
+}
+
+/// Check if the HA connection must be kept after a remote disconnected.
+///
+/// # Arguments
+///
+/// * `shared_connection`: connection is shared between remotes.
+/// * `connect_intents`: HA connection intent of all remote sessions.
+fn keep_connected(
+    shared_connection: bool,
+    connect_intents: impl IntoIterator<Item = bool>,
+) -> bool {
+    shared_connection && connect_intents.into_iter().any(|connect| connect)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(vec![true])]
+    #[case(vec![false, true])]
+    #[case(vec![true, true, false])]
+    fn shared_connection_is_kept_while_a_remote_wants_it(#[case] intents: Vec<bool>) {
+        assert!(keep_connected(true, intents));
+    }
+
+    #[rstest]
+    #[case(vec![])]
+    #[case(vec![false])]
+    #[case(vec![false, false])]
+    fn shared_connection_is_closed_if_no_remote_wants_it(#[case] intents: Vec<bool>) {
+        assert!(!keep_connected(true, intents));
+    }
+
+    #[test]
+    fn connection_is_closed_if_not_shared() {
+        assert!(!keep_connected(false, [true, true]));
+    }
+}
@@ -5,20 +5,26 @@
 
 use crate::built_info;
 use crate::client::messages::{CallService, GetAvailableEntities, GetStates};
+use crate::client::HomeAssistantClient;
 use crate::configuration::get_driver_metadata;
+use crate::controller::entity_filter::{available_entities_msg_data, AvailableEntitiesFilter};
+use crate::controller::entity_namespace::device_entity_ids;
 use crate::controller::handler::{
-    SetDriverUserDataMsg, SetupDriverMsg, SubscribeHaEventsMsg, UnsubscribeHaEventsMsg,
+    EntityRequestFailed, SetDriverUserDataMsg, SetupDriverMsg, SubscribeHaEventsMsg,
+    UnsubscribeHaEventsMsg,
 };
 use crate::controller::{Controller, OperationModeInput, R2RequestMsg, SendWsMessage};
 use crate::errors::ServiceError;
 use crate::util::{return_fut_err, return_fut_ok, DeserializeMsgData};
 use crate::APP_VERSION;
-use actix::{fut, AsyncContext, Handler, ResponseFuture};
+use actix::{fut, Addr, AsyncContext, Handler, MailboxError, ResponseFuture};
+use futures::future::join_all;
 use lazy_static::lazy_static;
 use log::{debug, error};
 use serde_json::{json, Value};
+use std::time::{Duration, Instant};
 use strum::EnumMessage;
-use uc_api::intg::ws::{AvailableEntitiesMsgData, DriverVersionMsgData, R2Request};
+use uc_api::intg::ws::{DriverVersionMsgData, R2Request};
 use uc_api::intg::{EntityCommand, IntegrationVersion};
 use uc_api::ws::{EventCategory, WsMessage, WsResultMsgData};
 
@@ -37,11 +43,15 @@ impl Handler<R2RequestMsg> for Controller {
     fn handle(&mut self, msg: R2RequestMsg, ctx: &mut Self::Context) -> Self::Result {
         debug!("R2RequestMsg: {:?}", msg.request);
         // extra safety: if we get a request, the remote is certainly not in standby mode
-        if let Some(session) = self.sessions.get_mut(&msg.ws_id) {
-            session.standby = false;
-        } else {
-            return_fut_err!(ServiceError::NotFound("No session found".into()));
+        let standby = match self.sessions.get(&msg.ws_id) {
+            Some(session) => session.standby,
+            None => {
+                return_fut_err!(ServiceError::NotFound("No session found".into()));
+            }
         };
+        if standby {
+            self.exit_standby(&msg.ws_id);
+        }
 
         let controller = ctx.address();
         let req_id = msg.req_id;
@@ -116,26 +126,32 @@ impl Handler<R2RequestMsg> for Controller {
             ))));
         }
 
-        // prepare async context
-        let ha_client = self.ha_client.clone();
-
         // FIXME quick & dirty request id "mapping". This requires a rewrite with proper callback & timeout handling!
         let mut entity_ids = Default::default();
         let remote_id = self.remote_id.clone();
+        let filter = if msg.request == R2Request::GetAvailableEntities {
+            match AvailableEntitiesFilter::from_msg_data(msg.msg_data.as_ref()) {
+                Ok(filter) => filter,
+                Err(e) => {
+                    return_fut_err!(e);
+                }
+            }
+        } else {
+            None
+        };
         if let Some(session) = self.sessions.get_mut(&msg.ws_id) {
             if msg.request == R2Request::GetAvailableEntities {
                 session.get_available_entities_id = Some(msg.req_id);
+                session.available_entities_filter = filter.clone();
                 // Check if available entities have been set (through a previous push from client)
                 // let id = Some(session.get_available_entities_id);
                 if let (Some(available_entities), Some(id)) = (
                     &self.susbcribed_entity_ids,
                     session.get_available_entities_id,
                 ) {
-                    let msg_data = AvailableEntitiesMsgData {
-                        filter: None,
-                        available_entities: available_entities.clone(),
-                    };
-                    if let Ok(msg_data_json) = serde_json::to_value(msg_data) {
+                    if let Ok(msg_data_json) =
+                        available_entities_msg_data(available_entities, filter.as_ref())
+                    {
                         let message =
                             WsMessage::response(id, "available_entities", msg_data_json.clone());
                         match session.recipient.try_send(SendWsMessage(message.clone())) {
@@ -157,6 +173,58 @@ impl Handler<R2RequestMsg> for Controller {
             }
         }
 
+        // prepare async context
+        let ha_clients: Vec<(String, Addr<HomeAssistantClient>)> = self
+            .ha_clients
+            .iter()
+            .map(|(device_id, client)| (device_id.clone(), client.clone()))
+            .collect();
+        if msg.request == R2Request::GetAvailableEntities {
+            let ttl = Duration::from_secs(self.settings.hass.entity_cache_ttl_sec as u64);
+            if let Some(entities) =
+                self.entity_cache
+                    .get(self.ha_clients.keys(), ttl, Instant::now())
+            {
+                debug!("[{}] Sending cached available entities", msg.ws_id);
+                if let Ok(msg_data_json) = available_entities_msg_data(&entities, filter.as_ref()) {
+                    if let Some(session) = self.sessions.get_mut(&msg.ws_id) {
+                        session.get_available_entities_id = None;
+                    }
+                    return_fut_ok!(Some(WsMessage::response(
+                        msg.req_id,
+                        "available_entities",
+                        msg_data_json
+                    )));
+                }
+            }
+            // cache miss: cache the result of the live fetch
+            if !ttl.is_zero() {
+                self.entity_cache_devices = self.ha_clients.keys().cloned().collect();
+            }
+        } else if msg.request == R2Request::GetEntityStates {
+            // entity states are filtered by the subscribed entities: don't cache the result
+            self.entity_cache_devices.clear();
+        }
+        if matches!(
+            msg.request,
+            R2Request::GetEntityStates | R2Request::GetAvailableEntities
+        ) {
+            // entities of all HA server connections are collected before responding
+            self.pending_entity_devices = self.ha_clients.keys().cloned().collect();
+            self.pending_entities.clear();
+        }
+        // entity commands are routed to the HA server connection providing the entity
+        let entity_client = if msg.request == R2Request::EntityCommand {
+            msg.msg_data
+                .as_ref()
+                .and_then(|v| v.get("entity_id"))
+                .and_then(|v| v.as_str())
+                .and_then(|entity_id| self.ha_client_for_entity(entity_id))
+                .map(|(client, entity_id)| (client, entity_id.to_string()))
+        } else {
+            None
+        };
+
         Box::pin(async move {
             match msg.request {
                 // just for safety: include all request variants and not a catch all!
@@ -179,18 +247,20 @@ impl Handler<R2RequestMsg> for Controller {
                     // get states from Home Assistant. Response from HA will call AvailableEntities handler
                     // or call custom UC HA component command if available
                     // to get entity states on subscribed entities only
-                    if let Some(ha_client) = ha_client {
+                    if !ha_clients.is_empty() {
                         debug!(
                             "[{}] Requesting subscribed entities states from HA: {entity_ids:?}",
                             msg.ws_id
                         );
-                        ha_client
-                            .send(GetStates {
-                                remote_id,
-                                entity_ids,
+                        let requests = ha_clients.iter().map(|(device_id, ha_client)| {
+                            ha_client.send(GetStates {
+                                remote_id: remote_id.clone(),
+                                entity_ids: device_entity_ids(device_id, &entity_ids),
                             })
-                            .await??;
-                        Ok(None) // asynchronous response message. TODO check if GetStates could return the response
+                        });
+                        let results = join_all(requests).await;
+                        // asynchronous response message. TODO check if GetStates could return the response
+                        entity_request_results(&controller, &ha_clients, results).await
                     } else {
                         error!(
                             "Unable to request available entities: HA client connection not available!"
@@ -199,15 +269,21 @@ impl Handler<R2RequestMsg> for Controller {
                     }
                 }
                 R2Request::GetAvailableEntities => {
-                    // We don't cache entities in this integration so we have to request them from HASS.
+                    // Cache miss: the entities have to be requested from HASS.
                     // I'm not aware of a different way to just retrieve the attributes. The get_states
                     // call returns everything, so we have to filter our response to UCR2.
 
                     // get states from Home Assistant. Response from HA will call AvailableEntities handler
-                    if let Some(ha_client) = ha_client {
+                    if !ha_clients.is_empty() {
                         debug!("[{}] Requesting available entities from HA", msg.ws_id);
-                        ha_client.send(GetAvailableEntities { remote_id }).await??;
-                        Ok(None) // asynchronous response message. TODO check if GetStates could return the response
+                        let requests = ha_clients.iter().map(|(_, ha_client)| {
+                            ha_client.send(GetAvailableEntities {
+                                remote_id: remote_id.clone(),
+                            })
+                        });
+                        let results = join_all(requests).await;
+                        // asynchronous response message. TODO check if GetStates could return the response
+                        entity_request_results(&controller, &ha_clients, results).await
                     } else {
                         error!(
                             "Unable to request available entities: HA client connection not available!"
@@ -224,20 +300,21 @@ impl Handler<R2RequestMsg> for Controller {
                     .await?
                     .map(|_| ok),
                 R2Request::EntityCommand => {
-                    if let Some(addr) = ha_client {
+                    if let Some((addr, entity_id)) = entity_client {
                         let req_id = msg.req_id;
-                        let command: EntityCommand = msg.deserialize()?;
+                        let mut command: EntityCommand = msg.deserialize()?;
+                        command.entity_id = entity_id;
                         match addr.send(CallService { command }).await? {
                             Err(e) => {
                                 error!("CallService failed: {:?}", e);
                                 Err(e)
                             }
                             Ok(_) => {
-                                // plain and simple for now. We could (or better should) also wait for the HA response message...
+                                // HA confirmed the service call
                                 let response = WsMessage::response(
                                     req_id,
                                     "result",
-                                    WsResultMsgData::new("OK", "Service call sent"),
+                                    WsResultMsgData::new("OK", "Service call successful"),
                                 );
                                 Ok(Some(response))
                             }
@@ -250,3 +327,114 @@ impl Handler<R2RequestMsg> for Controller {
         })
     }
 }
+
+/// Evaluate the entity requests sent to all HA server connections.
+///
+/// The requests are sent to all connections independently. Failed connections are removed from
+/// the pending entity request, so that the entities of the other connections are still sent.
+///
+/// returns: `Ok(None)` for the asynchronous response message, or the error of the first failed
+/// request if all requests failed.
+async fn entity_request_results(
+    controller: &Addr<Controller>,
+    ha_clients: &[(String, Addr<HomeAssistantClient>)],
+    results: Vec<Result<Result<(), ServiceError>, MailboxError>>,
+) -> Result<Option<WsMessage>, ServiceError> {
+    let device_ids: Vec<&String> = ha_clients.iter().map(|(device_id, _)| device_id).collect();
+    let (failed, error) = failed_entity_requests(&device_ids, results);
+    if failed.is_empty() {
+        return Ok(None);
+    }
+    let cancel = error.is_some();
+    controller
+        .send(EntityRequestFailed {
+            device_ids: failed,
+            cancel,
+        })
+        .await?;
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(None),
+    }
+}
+
+/// Get the device identifiers of the failed entity requests.
+///
+/// returns: the failed device identifiers, and the first error if all requests failed.
+fn failed_entity_requests(
+    device_ids: &[&String],
+    results: Vec<Result<Result<(), ServiceError>, MailboxError>>,
+) -> (Vec<String>, Option<ServiceError>) {
+    let total = results.len();
+    let mut failed = Vec::new();
+    let mut first_error = None;
+    for (device_id, result) in device_ids.iter().zip(results) {
+        let error = match result {
+            Ok(Ok(_)) => continue,
+            Ok(Err(e)) => e,
+            Err(e) => e.into(),
+        };
+        error!("[{device_id}] Entity request failed: {error:?}");
+        failed.push(device_id.to_string());
+        first_error.get_or_insert(error);
+    }
+    if failed.len() < total {
+        first_error = None;
+    }
+    (failed, first_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_entity_requests_successful() {
+        let (main, cabin) = ("main".to_string(), "cabin".to_string());
+
+        let (failed, error) =
+            failed_entity_requests(&[&main, &cabin], vec![Ok(Ok(())), Ok(Ok(()))]);
+
+        assert!(failed.is_empty());
+        assert_eq!(None, error);
+    }
+
+    #[test]
+    fn failed_entity_request_returns_partial_result() {
+        let (main, cabin, garage) = (
+            "main".to_string(),
+            "cabin".to_string(),
+            "garage".to_string(),
+        );
+
+        // a failing server must not prevent the requests of the following servers
+        let (failed, error) = failed_entity_requests(
+            &[&main, &cabin, &garage],
+            vec![
+                Ok(Err(ServiceError::ServiceUnavailable("timeout".into()))),
+                Ok(Ok(())),
+                Err(MailboxError::Closed),
+            ],
+        );
+
+        assert_eq!(vec![main, garage], failed);
+        assert_eq!(None, error);
+    }
+
+    #[test]
+    fn all_entity_requests_failed_returns_error() {
+        let (main, cabin) = ("main".to_string(), "cabin".to_string());
+
+        let (failed, error) = failed_entity_requests(
+            &[&main, &cabin],
+            vec![
+                Ok(Err(ServiceError::NotConnected)),
+                Ok(Err(ServiceError::ServiceUnavailable("timeout".into()))),
+            ],
+        );
+
+        assert_eq!(vec![main, cabin], failed);
+        assert_eq!(Some(ServiceError::NotConnected), error);
+    }
+}
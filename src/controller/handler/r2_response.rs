@@ -4,6 +4,7 @@
 //! Actix message handler for [R2ResponseMsg].
 
 use crate::client::messages::SetRemoteId;
+use crate::configuration::save_remote_id;
 use crate::controller::{Controller, R2ResponseMsg};
 use actix::Handler;
 use log::{error, info};
@@ -29,8 +30,13 @@ impl Handler<R2ResponseMsg> for Controller {
                     .and_then(|v| v.as_str())
                 {
                     info!("Remote identifier: '{remote_id}'");
+                    if self.remote_id != remote_id {
+                        if let Err(e) = save_remote_id(remote_id) {
+                            error!("Error saving remote identifier: {e:?}");
+                        }
+                    }
                     self.remote_id = remote_id.to_string();
-                    if let Some(ha_client) = &self.ha_client {
+                    for ha_client in self.ha_clients.values() {
                         if let Err(e) = ha_client.try_send(SetRemoteId {
                             remote_id: self.remote_id.clone(),
                         }) {
@@ -3,18 +3,31 @@
 
 //! Driver setup flow handling.
 
-use crate::configuration::save_user_settings;
+use crate::client::verify::verify_connection;
+use crate::configuration::{
+    save_user_listen_ports, save_user_settings, EntityNameFallback, HomeAssistantServerSettings,
+    HomeAssistantSettings, MediaPlayerOffMode, TemperatureUnitSource, DEFAULT_HA_DEVICE_ID,
+    DEF_SETUP_TIMEOUT_GRACE_SEC, DEVICE_ENTITY_SEPARATOR, ENV_SETUP_TIMEOUT_GRACE,
+};
+use crate::controller::discovery::{
+    discover_home_assistant, HomeAssistantServer, DISCOVERY_TIMEOUT,
+};
 use crate::controller::handler::{
     AbortDriverSetup, ConnectMsg, SetDriverUserDataMsg, SetupDriverMsg,
 };
-use crate::controller::{Controller, OperationModeInput::*, OperationModeState};
+use crate::controller::{
+    Controller, ListenPortsChanged, OperationModeInput::*, OperationModeState,
+};
 use crate::errors::{ServiceError, ServiceError::BadRequest};
+use crate::server::ListenPorts;
+use crate::util::{new_websocket_client, ws_url_with_token};
 use actix::clock::sleep;
 use actix::{fut, ActorFutureExt, AsyncContext, Handler, Message, ResponseActFuture, WrapFuture};
 use derive_more::Constructor;
-use log::{debug, info, warn};
+use log::{debug, error, info, warn};
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::str::FromStr;
 use std::time::Duration;
 use uc_api::intg::{DriverSetupChange, IntegrationSetup};
@@ -27,6 +40,8 @@ use url::Url;
 #[rtype(result = "()")]
 struct RequestOptionsMsg {
     pub ws_id: String,
+    /// Discovered Home Assistant servers
+    pub servers: Vec<HomeAssistantServer>,
 }
 
 /// Local Actix message to request further user data.
@@ -49,6 +64,8 @@ struct FinishSetupFlowMsg {
 /// Disconnect an active HA connection to start a new client connection with the changed data later.   
 /// Either continue the normal configuration with [RequestOptionsMsg], or the expert configuration
 /// options with [RequestExpertOptionsMsg] if selected in initial configuration screen.
+/// The normal configuration first discovers Home Assistant servers with mDNS, unless the URL has
+/// been provided by the HA UC component.
 impl Handler<SetupDriverMsg> for Controller {
     type Result = Result<(), ServiceError>;
 
@@ -82,8 +99,28 @@ impl Handler<SetupDriverMsg> for Controller {
         {
             // start expert setup with a different configuration screen
             ctx.notify_later(RequestExpertOptionsMsg::new(msg.ws_id), delay);
+        } else if self.settings.hass.has_external_url_and_token() {
+            ctx.notify_later(RequestOptionsMsg::new(msg.ws_id, Vec::new()), delay);
         } else {
-            ctx.notify_later(RequestOptionsMsg::new(msg.ws_id), delay);
+            if let Some(handle) = self.discovery_handle.take() {
+                ctx.cancel_future(handle);
+            }
+            let ws_id = msg.ws_id;
+            let handle = ctx.spawn(
+                async {
+                    actix_web::rt::task::spawn_blocking(|| {
+                        discover_home_assistant(DISCOVERY_TIMEOUT)
+                    })
+                    .await
+                    .unwrap_or_default()
+                }
+                .into_actor(self)
+                .map(move |servers, act, ctx| {
+                    act.discovery_handle = None;
+                    ctx.notify(RequestOptionsMsg::new(ws_id, servers));
+                }),
+            );
+            self.discovery_handle = Some(handle);
         }
 
         // this will acknowledge the setup_driver request message
@@ -93,7 +130,9 @@ impl Handler<SetupDriverMsg> for Controller {
 
 /// Handle driver setup input data from the normal configuration or expert configuration screen.
 ///
-/// Validate and save entered data, then trigger the end of the setup flow with [FinishSetupFlowMsg].
+/// Validate entered data and verify the Home Assistant connection with the new settings. The data
+/// is only saved if the connection could be established, then the end of the setup flow is
+/// triggered with [FinishSetupFlowMsg].
 impl Handler<SetDriverUserDataMsg> for Controller {
     type Result = Result<(), ServiceError>;
 
@@ -110,12 +149,28 @@ impl Handler<SetDriverUserDataMsg> for Controller {
         // Plain and simple: same for all setup pages. If it gets more complex, keep track of current
         // page as for example in the ATV integration, and only check expected fields.
         let mut cfg = self.settings.hass.clone();
+        let mut listen_ports = ListenPorts::default();
         if let IntegrationSetup::InputValues(values) = msg.data {
-            if values.contains_key("url") {
-                // TODO verify WebSocket connection to make sure user provided URL & token are ok! #3
-                // Right now the core will just send a Connect request after setup...
-                let url = parse_value::<String>(&values, "url");
-                cfg.set_url(validate_url(url.as_deref())?);
+            if values.contains_key("discovered_url") || values.contains_key("url") {
+                let discovered_url = match values
+                    .get("discovered_url")
+                    .map(|v| v.trim())
+                    .filter(|v| !v.is_empty())
+                {
+                    Some(url) => Some(validate_url(url)?),
+                    None => None,
+                };
+                let manual_url = match (values.get("url"), &discovered_url) {
+                    (Some(url), None) => Some(validate_url(url.as_str())?),
+                    // an invalid manual URL is ignored if a discovered server is selected
+                    (Some(url), Some(_)) => validate_url(url.as_str()).ok(),
+                    (None, _) => None,
+                };
+                if let Some(url) = setup_url(discovered_url, manual_url, &cfg.get_url()) {
+                    cfg.set_url(url);
+                } else {
+                    return Err(BadRequest("Missing field: url".into()));
+                }
             }
 
             if let Some(token) = parse_value::<String>(&values, "token") {
@@ -144,6 +199,12 @@ impl Handler<SetDriverUserDataMsg> for Controller {
             if let Some(value) = parse_value(&values, "disconnect_in_standby") {
                 cfg.disconnect_in_standby = value;
             }
+            if let Some(value) = parse_value(&values, "standby_device_state") {
+                cfg.standby_device_state = value;
+            }
+            if let Some(value) = parse_value(&values, "shared_connection") {
+                cfg.shared_connection = value;
+            }
             if let Some(value) = parse_value(&values, "max_frame_size_kb") {
                 if value >= 1024 {
                     cfg.max_frame_size_kb = value;
@@ -155,9 +216,87 @@ impl Handler<SetDriverUserDataMsg> for Controller {
             if let Some(value) = parse_value(&values, "heartbeat_timeout") {
                 cfg.heartbeat.timeout = Duration::from_secs(value);
             }
+            if let Some(value) = parse_value(&values, "tcp_keepalive.time_sec") {
+                cfg.tcp_keepalive.time = Duration::from_secs(value);
+            }
+            if let Some(value) = parse_value(&values, "tcp_keepalive.interval_sec") {
+                cfg.tcp_keepalive.interval = Duration::from_secs(value);
+            }
             if let Some(value) = parse_value(&values, "ping_frames") {
                 cfg.heartbeat.ping_frames = value;
             }
+            if let Some(value) = parse_value(&values, "climate_temperature_unit") {
+                cfg.climate_temperature_unit = value;
+            }
+            if let Some(value) = parse_value(&values, "entity_cache_ttl_sec") {
+                cfg.entity_cache_ttl_sec = value;
+            }
+            if let Some(value) = parse_value(&values, "name_fallback") {
+                cfg.name_fallback = value;
+            }
+            if let Some(value) = parse_value(&values, "unavailable_debounce.window_sec") {
+                cfg.unavailable_debounce.window = Duration::from_secs(value);
+            }
+            if let Some(value) = parse_value(&values, "unavailable_debounce.delay_ms") {
+                cfg.unavailable_debounce.delay = Duration::from_millis(value);
+            }
+            if let Some(value) = values.get("disabled_event_entities") {
+                cfg.disabled_event_entities = parse_entity_ids(value);
+            }
+            if let Some(value) = values.get("favorite_entities") {
+                cfg.favorite_entities = parse_entity_id_list(value);
+            }
+            if let Some(value) = values.get("entity_domains") {
+                cfg.entity_domains = parse_domains(value);
+            }
+            if let Some(value) = values.get("exclude_domains") {
+                cfg.exclude_domains = parse_domains(value);
+            }
+            if let Some(value) = values.get("attribute_entities") {
+                cfg.attribute_entities = parse_attribute_entities(value);
+            }
+            if let Some(value) = values.get("additional_servers") {
+                cfg.additional_servers = parse_additional_servers(value, &cfg.additional_servers)?;
+            }
+            if let Some(value) = parse_value(&values, "include_hidden_entities") {
+                cfg.include_hidden_entities = value;
+            }
+            if let Some(value) = parse_value(&values, "optimistic_assumed_state") {
+                cfg.optimistic_assumed_state = value;
+            }
+            if let Some(value) = parse_value(&values, "uc_component_retries") {
+                cfg.uc_component_retries = value;
+            }
+            if let Some(value) = parse_value(&values, "command_retry_delay_ms") {
+                cfg.command_retry_delay_ms = value;
+            }
+            if let Some(value) = parse_value(&values, "max_concurrent_commands") {
+                cfg.max_concurrent_commands = value;
+            }
+            if let Some(value) = parse_value(&values, "suppress_echo_events") {
+                cfg.suppress_echo_events = value;
+            }
+            if let Some(value) = parse_value(&values, "confirm_service_calls") {
+                cfg.confirm_service_calls = value;
+            }
+            if let Some(value) = parse_value(&values, "entity_available_events") {
+                cfg.entity_available_events = value;
+            }
+            if let Some(value) = parse_value(&values, "media_player.volume_step") {
+                cfg.media_player.volume_step = value;
+            }
+            if let Some(value) = parse_value(&values, "media_player.off_mode") {
+                cfg.media_player.off_mode = value;
+            }
+            if let Some(value) = parse_value(&values, "media_player.https_image_url") {
+                cfg.media_player.https_image_url = value;
+            }
+            if let Some(value) = parse_value(&values, "media_player.image_proxy") {
+                cfg.media_player.image_proxy = value;
+            }
+            if let Some(value) = values.get("media_player.image_proxy_url") {
+                cfg.media_player.image_proxy_url = value.trim().to_string();
+            }
             if let Some(value) = parse_value(&values, "reconnect.attempts") {
                 cfg.reconnect.attempts = value;
             }
@@ -172,17 +311,53 @@ impl Handler<SetDriverUserDataMsg> for Controller {
                     cfg.reconnect.backoff_factor = value;
                 }
             }
+            if let Some(value) = parse_value::<f32>(&values, "reconnect.jitter") {
+                if (0f32..=1f32).contains(&value) {
+                    cfg.reconnect.jitter = value;
+                }
+            }
+            listen_ports.http = parse_listen_port(&values, "http_port")?
+                .filter(|port| *port != self.settings.integration.http.port);
+            listen_ports.https = parse_listen_port(&values, "https_port")?
+                .filter(|port| *port != self.settings.integration.https.port);
         } else {
             return Err(BadRequest("Invalid response: require input_values".into()));
         }
 
-        save_user_settings(&cfg)?;
-        self.settings.hass = cfg;
-
-        // use a delay that the ack response will be sent first
-        ctx.notify_later(
-            FinishSetupFlowMsg::new(msg.ws_id, None),
-            Duration::from_millis(100),
+        // verify the WebSocket connection to make sure the provided URL & token are ok before saving
+        let url = ws_url_with_token(&cfg.get_url(), &cfg.url_token);
+        let token = cfg.get_token();
+        let timeout = Duration::from_secs(cfg.connection_timeout as u64);
+        let ws_client = new_websocket_client(
+            timeout,
+            Duration::from_secs(cfg.request_timeout as u64),
+            matches!(url.scheme(), "wss" | "https"),
+            &cfg.tcp_keepalive,
+        );
+        let ws_id = msg.ws_id;
+        ctx.spawn(
+            async move { verify_connection(&ws_client, &url, &token, timeout).await }
+                .into_actor(self)
+                .map(move |result, act, ctx| {
+                    let error = match result {
+                        Ok(_) => match act.save_setup_settings(cfg, listen_ports) {
+                            Ok(_) => None,
+                            Err(e) => {
+                                error!("[{ws_id}] Failed to save setup settings: {e:?}");
+                                Some(IntegrationSetupError::Other)
+                            }
+                        },
+                        Err(e) => {
+                            warn!("[{ws_id}] Home Assistant connection verification failed: {e:?}");
+                            Some(IntegrationSetupError::from(&e))
+                        }
+                    };
+                    // use a delay that the ack response will be sent first
+                    ctx.notify_later(
+                        FinishSetupFlowMsg::new(ws_id, error),
+                        Duration::from_millis(100),
+                    );
+                }),
         );
 
         // this will acknowledge the set_driver_user_data request message
@@ -193,7 +368,8 @@ impl Handler<SetDriverUserDataMsg> for Controller {
 /// Request configuration options.
 ///
 /// - If the external token & URL has been set by the HA UC component, just show the configured URL.
-/// - Otherwise, show URL and token input fields.
+/// - Otherwise, show URL and token input fields. Discovered Home Assistant servers are offered in
+///   an additional dropdown, the URL input field is used if no server is selected.
 impl Handler<RequestOptionsMsg> for Controller {
     type Result = ();
 
@@ -256,7 +432,7 @@ impl Handler<RequestOptionsMsg> for Controller {
             )
         } else {
             let token_missing = self.settings.hass.get_token().is_empty();
-            WsMessage::event(
+            let mut event = WsMessage::event(
                 "driver_setup_change",
                 EventCategory::Device,
                 json!({
@@ -313,7 +489,22 @@ impl Handler<RequestOptionsMsg> for Controller {
                         }
                     }
                 }),
-            )
+            );
+            if !msg.servers.is_empty() {
+                if let Some(settings) = event
+                    .msg_data
+                    .as_mut()
+                    .and_then(|v| v.pointer_mut("/require_user_action/input/settings"))
+                    .and_then(|v| v.as_array_mut())
+                {
+                    // insert before the manual URL input field
+                    settings.insert(
+                        1,
+                        discovered_servers_setting(&msg.servers, &self.settings.hass.get_url()),
+                    );
+                }
+            }
+            event
         };
 
         self.send_r2_msg(event, &msg.ws_id);
@@ -331,8 +522,53 @@ impl Handler<RequestExpertOptionsMsg> for Controller {
             return;
         }
 
+        let mut disabled_event_entities: Vec<&str> = self
+            .settings
+            .hass
+            .disabled_event_entities
+            .iter()
+            .map(|v| v.as_str())
+            .collect();
+        disabled_event_entities.sort_unstable();
+        let mut entity_domains: Vec<&str> = self
+            .settings
+            .hass
+            .entity_domains
+            .iter()
+            .map(|v| v.as_str())
+            .collect();
+        entity_domains.sort_unstable();
+        let mut exclude_domains: Vec<&str> = self
+            .settings
+            .hass
+            .exclude_domains
+            .iter()
+            .map(|v| v.as_str())
+            .collect();
+        exclude_domains.sort_unstable();
+        let mut attribute_entities: Vec<String> = self
+            .settings
+            .hass
+            .attribute_entities
+            .iter()
+            .flat_map(|(entity_id, attributes)| {
+                attributes
+                    .iter()
+                    .map(move |attribute| format!("{entity_id}:{attribute}"))
+            })
+            .collect();
+        attribute_entities.sort_unstable();
+        // tokens are not exposed: an entry without token keeps the existing token
+        let additional_servers: Vec<String> = self
+            .settings
+            .hass
+            .additional_servers
+            .iter()
+            .map(|s| format!("{} {}", s.id, s.url))
+            .collect();
+
         // TODO externalize i18n
-        let event = WsMessage::event(
+        let mut event = WsMessage::event(
             "driver_setup_change",
             EventCategory::Device,
             json!({
@@ -387,6 +623,30 @@ impl Handler<RequestExpertOptionsMsg> for Controller {
                                     }
                                 }
                             },
+                            {
+                                "id": "standby_device_state",
+                                "label": {
+                                    "en": "Send the device state changed during standby when waking up",
+                                    "de": "Den im Standby-Modus geänderten Gerätestatus beim Aufwachen senden"
+                                },
+                                "field": {
+                                    "checkbox": {
+                                      "value": self.settings.hass.standby_device_state
+                                    }
+                                }
+                            },
+                            {
+                                "id": "shared_connection",
+                                "label": {
+                                    "en": "Stay connected while another remote is connected",
+                                    "de": "Verbunden bleiben, solange eine andere Fernbedienung verbunden ist"
+                                },
+                                "field": {
+                                    "checkbox": {
+                                      "value": self.settings.hass.shared_connection
+                                    }
+                                }
+                            },
                             {
                                 "id": "max_frame_size_kb",
                                 "label": {
@@ -460,6 +720,21 @@ impl Handler<RequestExpertOptionsMsg> for Controller {
                                     }
                                 }
                             },
+                            {
+                                "id": "reconnect.jitter",
+                                "label": {
+                                    "en": "Reconnect delay jitter factor (0.2 = ±20%, 0 = disabled)",
+                                    "de": "Zufällige Abweichung der Wiederverbindungsverzögerung (0.2 = ±20%, 0 = deaktiviert)"
+                                },
+                                "field": {
+                                    "number": {
+                                        "value": self.settings.hass.reconnect.jitter,
+                                        "min": 0,
+                                        "max": 1,
+                                        "decimals": 2,
+                                    }
+                                }
+                            },
                             {
                                 "id": "heartbeat_interval",
                                 "label": {
@@ -501,12 +776,419 @@ impl Handler<RequestExpertOptionsMsg> for Controller {
                                       "value": self.settings.hass.heartbeat.ping_frames
                                     }
                                 }
+                            },
+                            {
+                                "id": "tcp_keepalive.time_sec",
+                                "label": {
+                                    "en": "TCP keepalive idle time in seconds (0 = disabled)",
+                                    "de": "TCP Keepalive Leerlaufzeit in Sekunden (0 = deaktiviert)"
+                                },
+                                "field": {
+                                    "number": {
+                                        "value": self.settings.hass.tcp_keepalive.time.as_secs(),
+                                        "min": 0,
+                                        "max": 7200,
+                                        "unit": { "en": "sec", "de": "Sek" }
+                                    }
+                                }
+                            },
+                            {
+                                "id": "tcp_keepalive.interval_sec",
+                                "label": {
+                                    "en": "TCP keepalive probe interval in seconds",
+                                    "de": "TCP Keepalive Prüfintervall in Sekunden"
+                                },
+                                "field": {
+                                    "number": {
+                                        "value": self.settings.hass.tcp_keepalive.interval.as_secs(),
+                                        "min": 1,
+                                        "max": 600,
+                                        "unit": { "en": "sec", "de": "Sek" }
+                                    }
+                                }
+                            },
+                            {
+                                "id": "unavailable_debounce.window_sec",
+                                "label": {
+                                    "en": "Unavailable entity debounce window after connecting in seconds",
+                                    "de": "Entprellzeitfenster für nicht verfügbare Entitäten nach dem Verbinden in Sekunden"
+                                },
+                                "field": {
+                                    "number": {
+                                        "value": self.settings.hass.unavailable_debounce.window.as_secs(),
+                                        "min": 0,
+                                        "max": 600,
+                                        "unit": { "en": "sec", "de": "Sek" }
+                                    }
+                                }
+                            },
+                            {
+                                "id": "unavailable_debounce.delay_ms",
+                                "label": {
+                                    "en": "Unavailable entity debounce delay in milliseconds (0 = disabled)",
+                                    "de": "Entprellverzögerung für nicht verfügbare Entitäten in ms (0 = deaktiviert)"
+                                },
+                                "field": {
+                                    "number": {
+                                        "value": self.settings.hass.unavailable_debounce.delay.as_millis(),
+                                        "min": 0,
+                                        "max": 60000,
+                                        "unit": { "en": "ms" }
+                                    }
+                                }
+                            },
+                            {
+                                "id": "disabled_event_entities",
+                                "label": {
+                                    "en": "Entities without state change events (comma separated entity ids)",
+                                    "de": "Entitäten ohne Statusänderungs-Events (Entity-IDs mit Komma getrennt)"
+                                },
+                                "field": {
+                                    "text": {
+                                        "value": disabled_event_entities.join(", ")
+                                    }
+                                }
+                            },
+                            {
+                                "id": "favorite_entities",
+                                "label": {
+                                    "en": "Favorite entities, always subscribed and listed first (comma separated entity ids)",
+                                    "de": "Favorisierte Entitäten, immer abonniert und zuerst aufgelistet (Entity-IDs mit Komma getrennt)"
+                                },
+                                "field": {
+                                    "text": {
+                                        "value": self.settings.hass.favorite_entities.join(", ")
+                                    }
+                                }
+                            },
+                            {
+                                "id": "entity_domains",
+                                "label": {
+                                    "en": "Exposed entity domains, all if empty (comma separated, e.g. light, switch)",
+                                    "de": "Verfügbare Entitäts-Domains, alle falls leer (mit Komma getrennt, z.B. light, switch)"
+                                },
+                                "field": {
+                                    "text": {
+                                        "value": entity_domains.join(", ")
+                                    }
+                                }
+                            },
+                            {
+                                "id": "exclude_domains",
+                                "label": {
+                                    "en": "Excluded entity domains (comma separated, e.g. sensor)",
+                                    "de": "Ausgeschlossene Entitäts-Domains (mit Komma getrennt, z.B. sensor)"
+                                },
+                                "field": {
+                                    "text": {
+                                        "value": exclude_domains.join(", ")
+                                    }
+                                }
+                            },
+                            {
+                                "id": "attribute_entities",
+                                "label": {
+                                    "en": "Entity attributes exposed as separate sensors (comma separated, e.g. sensor.outdoor:battery)",
+                                    "de": "Entitätsattribute als separate Sensoren (mit Komma getrennt, z.B. sensor.outdoor:battery)"
+                                },
+                                "field": {
+                                    "text": {
+                                        "value": attribute_entities.join(", ")
+                                    }
+                                }
+                            },
+                            {
+                                "id": "additional_servers",
+                                "label": {
+                                    "en": "Additional HA servers, separated by semicolon: <id> <url> <token> (empty token: old token)",
+                                    "de": "Zusätzliche HA Server, mit Semikolon getrennt: <id> <url> <token> (leeres Token: altes Token)"
+                                },
+                                "field": {
+                                    "text": {
+                                        "value": additional_servers.join("; ")
+                                    }
+                                }
+                            },
+                            {
+                                "id": "entity_cache_ttl_sec",
+                                "label": {
+                                    "en": "Available entities cache time in seconds (0 = disabled)",
+                                    "de": "Cache-Dauer der verfügbaren Entitäten in Sekunden (0 = deaktiviert)"
+                                },
+                                "field": {
+                                    "number": {
+                                        "value": self.settings.hass.entity_cache_ttl_sec,
+                                        "min": 0,
+                                        "max": 3600,
+                                        "unit": { "en": "sec" }
+                                    }
+                                }
+                            },
+                            {
+                                "id": "include_hidden_entities",
+                                "label": {
+                                    "en": "Include hidden and disabled Home Assistant entities",
+                                    "de": "Versteckte und deaktivierte Home Assistant Entitäten einbeziehen"
+                                },
+                                "field": {
+                                    "checkbox": {
+                                      "value": self.settings.hass.include_hidden_entities
+                                    }
+                                }
+                            },
+                            {
+                                "id": "optimistic_assumed_state",
+                                "label": {
+                                    "en": "Optimistic state for entities without state feedback",
+                                    "de": "Optimistischer Status für Entitäten ohne Statusrückmeldung"
+                                },
+                                "field": {
+                                    "checkbox": {
+                                      "value": self.settings.hass.optimistic_assumed_state
+                                    }
+                                }
+                            },
+                            {
+                                "id": "uc_component_retries",
+                                "label": {
+                                    "en": "Retries of the UC Home Assistant component check after a restart",
+                                    "de": "Wiederholungen der UC Home Assistant Komponenten-Prüfung nach einem Neustart"
+                                },
+                                "field": {
+                                    "number": {
+                                        "value": self.settings.hass.uc_component_retries,
+                                        "min": 0,
+                                        "max": 20
+                                    }
+                                }
+                            },
+                            {
+                                "id": "command_retry_delay_ms",
+                                "label": {
+                                    "en": "Retry delay of commands failing with a temporary error (0 = no retry)",
+                                    "de": "Wiederholungsverzögerung von Befehlen mit vorübergehendem Fehler (0 = keine Wiederholung)"
+                                },
+                                "field": {
+                                    "number": {
+                                        "value": self.settings.hass.command_retry_delay_ms,
+                                        "min": 0,
+                                        "max": 5000,
+                                        "unit": { "en": "ms" }
+                                    }
+                                }
+                            },
+                            {
+                                "id": "max_concurrent_commands",
+                                "label": {
+                                    "en": "Maximum number of concurrent commands (0 = unlimited)",
+                                    "de": "Maximale Anzahl gleichzeitiger Befehle (0 = unbegrenzt)"
+                                },
+                                "field": {
+                                    "number": {
+                                        "value": self.settings.hass.max_concurrent_commands,
+                                        "min": 0,
+                                        "max": 100
+                                    }
+                                }
+                            },
+                            {
+                                "id": "suppress_echo_events",
+                                "label": {
+                                    "en": "Don't send state changes caused by commands from the remote",
+                                    "de": "Keine Statusänderungen senden, die durch Befehle der Fernbedienung ausgelöst wurden"
+                                },
+                                "field": {
+                                    "checkbox": {
+                                      "value": self.settings.hass.suppress_echo_events
+                                    }
+                                }
+                            },
+                            {
+                                "id": "confirm_service_calls",
+                                "label": {
+                                    "en": "Confirm commands with Home Assistant service call events",
+                                    "de": "Befehle mit Home Assistant Service-Call-Ereignissen bestätigen"
+                                },
+                                "field": {
+                                    "checkbox": {
+                                      "value": self.settings.hass.confirm_service_calls
+                                    }
+                                }
+                            },
+                            {
+                                "id": "entity_available_events",
+                                "label": {
+                                    "en": "Send separate events when an entity becomes available or unavailable",
+                                    "de": "Separate Ereignisse senden, wenn eine Entität verfügbar oder nicht verfügbar wird"
+                                },
+                                "field": {
+                                    "checkbox": {
+                                      "value": self.settings.hass.entity_available_events
+                                    }
+                                }
+                            },
+                            {
+                                "id": "media_player.volume_step",
+                                "label": {
+                                    "en": "Media player volume step in percent (0 = use HA volume up & down)",
+                                    "de": "Media-Player Lautstärkeschritt in Prozent (0 = HA Lautstärke auf & ab verwenden)"
+                                },
+                                "field": {
+                                    "number": {
+                                        "value": self.settings.hass.media_player.volume_step,
+                                        "min": 0,
+                                        "max": 20,
+                                        "unit": { "en": "%" }
+                                    }
+                                }
+                            },
+                            {
+                                "id": "media_player.off_mode",
+                                "label": {
+                                    "en": "Media player off command",
+                                    "de": "Media-Player Aus-Befehl"
+                                },
+                                "field": {
+                                    "dropdown": {
+                                        "value": self.settings.hass.media_player.off_mode.as_ref(),
+                                        "items": [
+                                            {
+                                                "id": MediaPlayerOffMode::TurnOff.as_ref(),
+                                                "label": {
+                                                    "en": "Turn off",
+                                                    "de": "Ausschalten"
+                                                }
+                                            },
+                                            {
+                                                "id": MediaPlayerOffMode::Standby.as_ref(),
+                                                "label": {
+                                                    "en": "Stop playback (standby)",
+                                                    "de": "Wiedergabe stoppen (Standby)"
+                                                }
+                                            }
+                                        ]
+                                    }
+                                }
+                            },
+                            {
+                                "id": "media_player.https_image_url",
+                                "label": {
+                                    "en": "Use https for media images if connected with wss",
+                                    "de": "https für Medienbilder verwenden, wenn mit wss verbunden"
+                                },
+                                "field": {
+                                    "checkbox": {
+                                      "value": self.settings.hass.media_player.https_image_url
+                                    }
+                                }
+                            },
+                            {
+                                "id": "media_player.image_proxy",
+                                "label": {
+                                    "en": "Serve media images through the integration",
+                                    "de": "Medienbilder über die Integration bereitstellen"
+                                },
+                                "field": {
+                                    "checkbox": {
+                                      "value": self.settings.hass.media_player.image_proxy
+                                    }
+                                }
+                            },
+                            {
+                                "id": "media_player.image_proxy_url",
+                                "label": {
+                                    "en": "Integration URL for media images (empty: local integration)",
+                                    "de": "Integrations-URL für Medienbilder (leer: lokale Integration)"
+                                },
+                                "field": {
+                                    "text": {
+                                        "value": self.settings.hass.media_player.image_proxy_url
+                                    }
+                                }
+                            },
+                            {
+                                "id": "climate_temperature_unit",
+                                "label": {
+                                    "en": "Climate temperature unit",
+                                    "de": "Klima Temperatureinheit"
+                                },
+                                "field": {
+                                    "dropdown": {
+                                        "value": self.settings.hass.climate_temperature_unit.as_ref(),
+                                        "items": [
+                                            {
+                                                "id": TemperatureUnitSource::Ha.as_ref(),
+                                                "label": {
+                                                    "en": "Use Home Assistant unit",
+                                                    "de": "Home Assistant Einheit verwenden"
+                                                }
+                                            },
+                                            {
+                                                "id": TemperatureUnitSource::Remote.as_ref(),
+                                                "label": {
+                                                    "en": "Use remote unit",
+                                                    "de": "Einheit der Fernbedienung verwenden"
+                                                }
+                                            },
+                                            {
+                                                "id": TemperatureUnitSource::Celsius.as_ref(),
+                                                "label": {
+                                                    "en": "Always use Celsius",
+                                                    "de": "Immer Celsius verwenden"
+                                                }
+                                            },
+                                            {
+                                                "id": TemperatureUnitSource::Fahrenheit.as_ref(),
+                                                "label": {
+                                                    "en": "Always use Fahrenheit",
+                                                    "de": "Immer Fahrenheit verwenden"
+                                                }
+                                            }
+                                        ]
+                                    }
+                                }
+                            },
+                            {
+                                "id": "name_fallback",
+                                "label": {
+                                    "en": "Entity name without Home Assistant friendly name",
+                                    "de": "Entitätsname ohne Home Assistant Anzeigename"
+                                },
+                                "field": {
+                                    "dropdown": {
+                                        "value": self.settings.hass.name_fallback.as_ref(),
+                                        "items": [
+                                            {
+                                                "id": EntityNameFallback::EntityId.as_ref(),
+                                                "label": {
+                                                    "en": "Entity ID (light.living_room)",
+                                                    "de": "Entitäts-ID (light.living_room)"
+                                                }
+                                            },
+                                            {
+                                                "id": EntityNameFallback::ObjectId.as_ref(),
+                                                "label": {
+                                                    "en": "Object ID as title (Living Room)",
+                                                    "de": "Objekt-ID als Titel (Living Room)"
+                                                }
+                                            }
+                                        ]
+                                    }
+                                }
                             }
                         ]
                     }
                 }
             }),
         );
+        if let Some(settings) = event
+            .msg_data
+            .as_mut()
+            .and_then(|v| v.pointer_mut("/require_user_action/input/settings"))
+            .and_then(|v| v.as_array_mut())
+        {
+            settings.extend(self.listen_port_settings());
+        }
         self.send_r2_msg(event, &msg.ws_id);
     }
 }
@@ -568,11 +1250,22 @@ impl Handler<FinishSetupFlowMsg> for Controller {
             .map(move |_, act, _ctx| {
                 info!("Setup flow finished: sending driver_setup_change STOP with state {state}");
                 act.send_r2_msg(event, &msg.ws_id);
+                // rebinding closes all active connections: setup flow must be finished
+                act.rebind_listeners();
             }),
         )
     }
 }
 
+impl Handler<ListenPortsChanged> for Controller {
+    type Result = ();
+
+    fn handle(&mut self, msg: ListenPortsChanged, _ctx: &mut Self::Context) -> Self::Result {
+        info!("Integration server listen ports changed: {:?}", msg.0);
+        self.save_listen_ports(msg.0);
+    }
+}
+
 impl Handler<AbortDriverSetup> for Controller {
     type Result = ();
 
@@ -583,6 +1276,26 @@ impl Handler<AbortDriverSetup> for Controller {
         );
 
         if msg.timeout {
+            // don't abort a slow initial entity load, as long as the grace period isn't used up
+            let max_grace = env::var(ENV_SETUP_TIMEOUT_GRACE)
+                .ok()
+                .and_then(|v| u64::from_str(&v).ok())
+                .unwrap_or(DEF_SETUP_TIMEOUT_GRACE_SEC);
+            if let Some(delay) = setup_timeout_deferral(
+                self.entity_load_in_progress(),
+                self.setup_timeout_deferred,
+                Duration::from_secs(max_grace),
+            ) {
+                info!(
+                    "[{}] Entity load in progress: deferring setup timeout by {} sec",
+                    msg.ws_id,
+                    delay.as_secs()
+                );
+                self.setup_timeout_deferred += delay;
+                self.setup_timeout = Some(ctx.notify_later(msg, delay));
+                return;
+            }
+
             if self.sm_consume(&msg.ws_id, &SetupError, ctx).is_err() {
                 return;
             }
@@ -617,9 +1330,164 @@ impl Handler<AbortDriverSetup> for Controller {
             ctx.cancel_future(handle);
         }
 
-        // Note: this is the place to cleanup any setup activities
-        // e.g. stopping the planned Home Assistant mDNS server discovery etc
-        // For now it's just a state transition
+        // cleanup any setup activities
+        if let Some(handle) = self.discovery_handle.take() {
+            ctx.cancel_future(handle);
+        }
+    }
+}
+
+/// Interval in which the setup timeout is deferred while entities are loaded.
+const SETUP_TIMEOUT_GRACE_STEP: Duration = Duration::from_secs(10);
+
+/// Get the delay to defer an expired setup timeout, or `None` if the setup should time out.
+///
+/// The timeout is only deferred while an entity load is in progress, up to a total of `max_grace`.
+fn setup_timeout_deferral(
+    loading: bool,
+    deferred: Duration,
+    max_grace: Duration,
+) -> Option<Duration> {
+    if !loading || deferred >= max_grace {
+        return None;
+    }
+    Some(SETUP_TIMEOUT_GRACE_STEP.min(max_grace - deferred))
+}
+
+impl Controller {
+    /// Check if a get_states request for the available entities or entity states is in progress.
+    fn entity_load_in_progress(&self) -> bool {
+        self.sessions.values().any(|session| {
+            session.get_available_entities_id.is_some() || session.get_entity_states_id.is_some()
+        })
+    }
+
+    /// Save the verified setup settings and apply them.
+    fn save_setup_settings(
+        &mut self,
+        cfg: HomeAssistantSettings,
+        listen_ports: ListenPorts,
+    ) -> Result<(), ServiceError> {
+        save_user_settings(&cfg)?;
+        self.settings.hass = cfg;
+
+        if !listen_ports.is_empty() {
+            // persisted after the integration server has been rebound
+            self.pending_listen_ports = Some(listen_ports);
+        }
+
+        Ok(())
+    }
+
+    /// Apply and persist the changed listen ports.
+    pub(crate) fn save_listen_ports(&mut self, listen_ports: ListenPorts) {
+        if let Some(port) = listen_ports.http {
+            self.settings.integration.http.port = port;
+        }
+        if let Some(port) = listen_ports.https {
+            self.settings.integration.https.port = port;
+        }
+        if let Err(e) = save_user_listen_ports(&self.settings.integration) {
+            error!("Failed to save listen ports {listen_ports:?}: {e:?}");
+        }
+    }
+
+    /// Expert configuration settings for the enabled integration server listen ports.
+    fn listen_port_settings(&self) -> Vec<serde_json::Value> {
+        let mut settings = Vec::with_capacity(2);
+        if self.settings.integration.http.enabled {
+            settings.push(json!({
+                "id": "http_port",
+                "label": {
+                    "en": "Integration HTTP listen port (closes active connections)",
+                    "de": "Integration HTTP Port (trennt aktive Verbindungen)"
+                },
+                "field": {
+                    "number": {
+                        "value": self.settings.integration.http.port,
+                        "min": 1,
+                        "max": 65535
+                    }
+                }
+            }));
+        }
+        if self.settings.integration.https.enabled {
+            settings.push(json!({
+                "id": "https_port",
+                "label": {
+                    "en": "Integration HTTPS listen port (closes active connections)",
+                    "de": "Integration HTTPS Port (trennt aktive Verbindungen)"
+                },
+                "field": {
+                    "number": {
+                        "value": self.settings.integration.https.port,
+                        "min": 1,
+                        "max": 65535
+                    }
+                }
+            }));
+        }
+        settings
+    }
+}
+
+/// Dropdown setting to select a discovered Home Assistant server.
+///
+/// The configured URL is preselected if it belongs to a discovered server, otherwise the manual
+/// URL input.
+fn discovered_servers_setting(
+    servers: &[HomeAssistantServer],
+    configured_url: &Url,
+) -> serde_json::Value {
+    let value = if servers.iter().any(|s| &s.url == configured_url) {
+        configured_url.as_str()
+    } else {
+        ""
+    };
+    let mut items = vec![json!({
+        "id": "",
+        "label": {
+            "en": "Enter URL manually",
+            "de": "URL manuell eingeben",
+            "fr": "Saisir l'URL manuellement"
+        }
+    })];
+    items.extend(servers.iter().map(|server| {
+        json!({
+            "id": server.url.as_str(),
+            "label": { "en": format!("{} ({})", server.name, server.url) }
+        })
+    }));
+
+    json!({
+        "id": "discovered_url",
+        "label": {
+            "en": "Discovered Home Assistant servers",
+            "de": "Gefundene Home Assistant Server",
+            "fr": "Serveurs Home Assistant découverts"
+        },
+        "field": {
+            "dropdown": {
+                "value": value,
+                "items": items
+            }
+        }
+    })
+}
+
+/// Select the Home Assistant server URL from the setup input values.
+///
+/// A selected discovered server has priority over the manual URL input, unless the manual URL
+/// has been changed from the configured URL.
+fn setup_url(
+    discovered_url: Option<Url>,
+    manual_url: Option<Url>,
+    configured_url: &Url,
+) -> Option<Url> {
+    match (discovered_url, manual_url) {
+        (Some(_), Some(manual)) if &manual != configured_url => Some(manual),
+        (Some(discovered), _) => Some(discovered),
+        (None, manual) => manual,
     }
 }
 
@@ -627,6 +1495,108 @@ fn parse_value<T: FromStr>(map: &HashMap<String, String>, key: &str) -> Option<T
     map.get(key).and_then(|v| T::from_str(v).ok())
 }
 
+/// Parse a comma separated list of entity ids. Empty entries are ignored.
+fn parse_entity_ids(value: &str) -> HashSet<String> {
+    parse_entity_id_list(value).into_iter().collect()
+}
+
+/// Parse a comma separated list of entity domains. Empty entries are ignored.
+fn parse_domains(value: &str) -> HashSet<String> {
+    parse_entity_ids(value)
+        .into_iter()
+        .map(|v| v.to_lowercase())
+        .collect()
+}
+
+/// Parse a comma separated list of `<entity_id>:<attribute>` entries into the attribute names per
+/// entity id. Invalid and duplicate entries are ignored.
+fn parse_attribute_entities(value: &str) -> HashMap<String, Vec<String>> {
+    let mut attribute_entities: HashMap<String, Vec<String>> = HashMap::new();
+    for entry in parse_entity_id_list(value) {
+        match entry.split_once(':') {
+            Some((entity_id, attribute)) if !entity_id.is_empty() && !attribute.is_empty() => {
+                attribute_entities
+                    .entry(entity_id.trim().to_string())
+                    .or_default()
+                    .push(attribute.trim().to_string());
+            }
+            _ => warn!("Ignoring invalid attribute entity: {entry}"),
+        }
+    }
+    attribute_entities
+}
+
+/// Parse a comma separated list of entity ids and keep the order. Empty and duplicate entries
+/// are ignored.
+fn parse_entity_id_list(value: &str) -> Vec<String> {
+    let mut entity_ids: Vec<String> = Vec::new();
+    for entity_id in value.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+        if !entity_ids.iter().any(|v| v == entity_id) {
+            entity_ids.push(entity_id.to_string());
+        }
+    }
+    entity_ids
+}
+
+/// Parse the additional HA servers: `<id> <url> <token>` entries separated by a semicolon.
+///
+/// The token of an existing server is kept if an entry doesn't contain a token. The main server
+/// identifier [`DEFAULT_HA_DEVICE_ID`], duplicate identifiers and identifiers containing the
+/// entity id separator are rejected.
+fn parse_additional_servers(
+    value: &str,
+    existing: &[HomeAssistantServerSettings],
+) -> Result<Vec<HomeAssistantServerSettings>, ServiceError> {
+    let mut servers: Vec<HomeAssistantServerSettings> = Vec::new();
+    for entry in value.split(';').map(str::trim).filter(|v| !v.is_empty()) {
+        let mut parts = entry.split_whitespace();
+        let (id, url, token) = (parts.next(), parts.next(), parts.next());
+        let id = id.unwrap_or_default();
+        if id == DEFAULT_HA_DEVICE_ID
+            || id.contains(DEVICE_ENTITY_SEPARATOR)
+            || servers.iter().any(|s| s.id == id)
+        {
+            return Err(BadRequest(format!(
+                "Invalid or duplicate HA server id: {id}"
+            )));
+        }
+        if url.is_none() || parts.next().is_some() {
+            return Err(BadRequest(format!(
+                "Invalid HA server entry '{id}': expected <id> <url> <token>"
+            )));
+        }
+        let existing = existing.iter().find(|s| s.id == id);
+        let token = match token {
+            Some(token) => token.to_string(),
+            None => existing
+                .map(|s| s.token.clone())
+                .ok_or_else(|| BadRequest(format!("Missing token of HA server: {id}")))?,
+        };
+        servers.push(HomeAssistantServerSettings {
+            id: id.to_string(),
+            url: validate_url(url)?,
+            token,
+            // not part of the setup entry
+            url_token: existing.map(|s| s.url_token.clone()).unwrap_or_default(),
+        });
+    }
+    Ok(servers)
+}
+
+/// Parse an optional listen port value. An invalid port number returns a [BadRequest] error.
+fn parse_listen_port(
+    map: &HashMap<String, String>,
+    key: &str,
+) -> Result<Option<u16>, ServiceError> {
+    match map.get(key).map(|v| v.trim()) {
+        None | Some("") => Ok(None),
+        Some(value) => match u16::from_str(value) {
+            Ok(port) if port > 0 => Ok(Some(port)),
+            _ => Err(BadRequest(format!("Invalid listen port {key}: {value}"))),
+        },
+    }
+}
+
 /// Validate and convert Home Assistant WebSocket URL
 fn validate_url<'a>(addr: impl Into<Option<&'a str>>) -> Result<Url, ServiceError> {
     let addr = match addr.into() {
@@ -678,8 +1648,16 @@ fn parse_with_ws_scheme(address: &str) -> Result<Url, url::ParseError> {
 
 #[cfg(test)]
 mod tests {
-    use super::validate_url;
+    use super::{
+        discovered_servers_setting, parse_additional_servers, parse_attribute_entities,
+        parse_domains, parse_entity_id_list, parse_entity_ids, setup_timeout_deferral, setup_url,
+        validate_url, SETUP_TIMEOUT_GRACE_STEP,
+    };
+    use crate::configuration::HomeAssistantServerSettings;
+    use crate::controller::discovery::HomeAssistantServer;
     use crate::errors::{ServiceError, ServiceError::BadRequest};
+    use rstest::rstest;
+    use std::time::Duration;
     use url::Url;
 
     fn url(url: &str) -> Result<Url, ServiceError> {
@@ -754,4 +1732,193 @@ mod tests {
         let result = validate_url("foo://test");
         assert!(matches!(result, Err(BadRequest(_))));
     }
+
+    #[test]
+    fn parse_entity_ids_ignores_empty_entries() {
+        let result = parse_entity_ids(" sensor.power, ,switch.plug,, ");
+
+        assert_eq!(2, result.len());
+        assert!(result.contains("sensor.power"));
+        assert!(result.contains("switch.plug"));
+    }
+
+    #[test]
+    fn parse_domains_lower_cases_domains() {
+        let result = parse_domains(" Light, ,media_player,, ");
+
+        assert_eq!(2, result.len());
+        assert!(result.contains("light"));
+        assert!(result.contains("media_player"));
+    }
+
+    #[test]
+    fn parse_attribute_entities_groups_attributes_by_entity() {
+        let result = parse_attribute_entities(
+            "sensor.outdoor:battery, weather.home:forecast,, sensor.outdoor:signal_strength, sensor.invalid, :battery",
+        );
+
+        assert_eq!(2, result.len());
+        assert_eq!(
+            Some(&vec!["battery".to_string(), "signal_strength".to_string()]),
+            result.get("sensor.outdoor")
+        );
+        assert_eq!(
+            Some(&vec!["forecast".to_string()]),
+            result.get("weather.home")
+        );
+    }
+
+    #[test]
+    fn parse_entity_id_list_keeps_order_without_duplicates() {
+        let result = parse_entity_id_list("switch.plug, sensor.power,, switch.plug");
+
+        assert_eq!(vec!["switch.plug", "sensor.power"], result);
+    }
+
+    #[test]
+    fn parse_additional_servers_with_token() {
+        let result = parse_additional_servers(
+            " upstairs ha-upstairs.local:8123 token1;; cabin wss://cabin.example.com/api/websocket token2 ",
+            &[],
+        );
+
+        assert_eq!(
+            Some(vec![
+                HomeAssistantServerSettings {
+                    id: "upstairs".into(),
+                    url: Url::parse("ws://ha-upstairs.local:8123").unwrap(),
+                    token: "token1".into(),
+                    url_token: Default::default()
+                },
+                HomeAssistantServerSettings {
+                    id: "cabin".into(),
+                    url: Url::parse("wss://cabin.example.com/api/websocket").unwrap(),
+                    token: "token2".into(),
+                    url_token: Default::default()
+                }
+            ]),
+            result.ok()
+        );
+    }
+
+    #[test]
+    fn parse_additional_servers_keeps_existing_token() {
+        let existing = [HomeAssistantServerSettings {
+            id: "cabin".into(),
+            url: Url::parse("ws://192.168.1.2:8123").unwrap(),
+            token: "old".into(),
+            url_token: Default::default(),
+        }];
+
+        let result = parse_additional_servers("cabin ws://192.168.1.3:8123", &existing);
+
+        assert_eq!(
+            Some("old".to_string()),
+            result.ok().and_then(|s| s.first().map(|s| s.token.clone()))
+        );
+    }
+
+    #[rstest]
+    #[case("main ws://ha.local:8123 token")]
+    #[case("cabin ws://ha.local:8123 token; cabin ws://ha2.local:8123 token")]
+    #[case("cabin ws://ha.local:8123")]
+    #[case("cab@in ws://ha.local:8123 token")]
+    #[case("cabin")]
+    #[case("cabin ws://ha.local:8123 token foo")]
+    #[case("cabin foo://ha.local token")]
+    fn parse_invalid_additional_servers_returns_error(#[case] value: &str) {
+        let result = parse_additional_servers(value, &[]);
+        assert!(matches!(result, Err(BadRequest(_))));
+    }
+
+    fn servers() -> Vec<HomeAssistantServer> {
+        vec![
+            HomeAssistantServer {
+                name: "Home".into(),
+                url: Url::parse("ws://192.168.1.20:8123/api/websocket").unwrap(),
+            },
+            HomeAssistantServer {
+                name: "Office".into(),
+                url: Url::parse("ws://192.168.1.30:8123/api/websocket").unwrap(),
+            },
+        ]
+    }
+
+    #[test]
+    fn discovered_servers_setting_preselects_configured_server() {
+        let configured = Url::parse("ws://192.168.1.30:8123/api/websocket").unwrap();
+        let setting = discovered_servers_setting(&servers(), &configured);
+
+        assert_eq!(
+            Some("ws://192.168.1.30:8123/api/websocket"),
+            setting
+                .pointer("/field/dropdown/value")
+                .and_then(|v| v.as_str())
+        );
+        assert_eq!(
+            Some(3),
+            setting
+                .pointer("/field/dropdown/items")
+                .and_then(|v| v.as_array())
+                .map(|v| v.len())
+        );
+    }
+
+    #[test]
+    fn discovered_servers_setting_preselects_manual_url_for_unknown_server() {
+        let configured = Url::parse("ws://homeassistant.local:8123/api/websocket").unwrap();
+
+        let setting = discovered_servers_setting(&servers(), &configured);
+        assert_eq!(
+            Some(""),
+            setting
+                .pointer("/field/dropdown/value")
+                .and_then(|v| v.as_str())
+        );
+    }
+
+    const CONFIGURED: &str = "ws://homeassistant.local:8123/api/websocket";
+    const HA_1: &str = "ws://192.168.1.20:8123/api/websocket";
+    const HA_2: &str = "ws://192.168.1.40:8123/api/websocket";
+
+    #[rstest]
+    #[case(Some(HA_1), Some(CONFIGURED), Some(HA_1))]
+    #[case(Some(HA_1), Some(HA_2), Some(HA_2))]
+    #[case(Some(HA_1), None, Some(HA_1))]
+    #[case(None, Some(HA_2), Some(HA_2))]
+    #[case(None, None, None)]
+    fn setup_url_prefers_changed_manual_url(
+        #[case] discovered_url: Option<&str>,
+        #[case] manual_url: Option<&str>,
+        #[case] expected: Option<&str>,
+    ) {
+        let url = |v: Option<&str>| v.map(|v| Url::parse(v).unwrap());
+        let configured = Url::parse(CONFIGURED).unwrap();
+
+        let result = setup_url(url(discovered_url), url(manual_url), &configured);
+
+        assert_eq!(url(expected), result);
+    }
+
+    #[test]
+    fn in_progress_entity_load_defers_setup_timeout() {
+        let result = setup_timeout_deferral(true, Duration::ZERO, Duration::from_secs(120));
+        assert_eq!(Some(SETUP_TIMEOUT_GRACE_STEP), result);
+    }
+
+    #[test]
+    fn setup_timeout_without_entity_load_is_not_deferred() {
+        let result = setup_timeout_deferral(false, Duration::ZERO, Duration::from_secs(120));
+        assert_eq!(None, result);
+    }
+
+    #[test]
+    fn setup_timeout_deferral_is_limited_by_grace_period() {
+        let max_grace = Duration::from_secs(25);
+        assert_eq!(
+            Some(Duration::from_secs(5)),
+            setup_timeout_deferral(true, Duration::from_secs(20), max_grace)
+        );
+        assert_eq!(None, setup_timeout_deferral(true, max_grace, max_grace));
+    }
 }
@@ -0,0 +1,122 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Short-lived cache of the last media image per entity for the media image proxy.
+//!
+//! The remote might request the same image multiple times, e.g. when switching between screens.
+//! A cached image is only used if the media image URL of the entity hasn't changed.
+
+use crate::controller::MediaImage;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+struct CacheEntry {
+    created: Instant,
+    url: String,
+    image: MediaImage,
+}
+
+/// Media image cache, keyed by entity identifier.
+#[derive(Debug, Default)]
+pub(crate) struct MediaImageCache {
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl MediaImageCache {
+    /// Store the last media image of an entity, replacing a previous image.
+    pub fn insert(&mut self, entity_id: &str, url: &str, image: MediaImage, now: Instant) {
+        // drop expired images of other entities, the cache only needs to hold the current images
+        self.entries
+            .retain(|_, e| now.saturating_duration_since(e.created) < MEDIA_IMAGE_CACHE_MAX_AGE);
+        self.entries.insert(
+            entity_id.to_string(),
+            CacheEntry {
+                created: now,
+                url: url.to_string(),
+                image,
+            },
+        );
+    }
+
+    /// Get the cached media image of an entity.
+    ///
+    /// Returns `None` if no image is cached, the image was fetched from a different URL, or it is
+    /// older than the `ttl`.
+    pub fn get(
+        &self,
+        entity_id: &str,
+        url: &str,
+        ttl: Duration,
+        now: Instant,
+    ) -> Option<MediaImage> {
+        self.entries
+            .get(entity_id)
+            .filter(|e| e.url == url && now.saturating_duration_since(e.created) < ttl)
+            .map(|e| e.image.clone())
+    }
+}
+
+/// Maximum age of a cached image before it is removed.
+const MEDIA_IMAGE_CACHE_MAX_AGE: Duration = Duration::from_secs(300);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    const TTL: Duration = Duration::from_secs(10);
+    const URL: &str = "http://homeassistant.local:8123/api/media_player_proxy/media_player.tv";
+
+    fn image() -> MediaImage {
+        MediaImage {
+            content_type: "image/jpeg".into(),
+            data: Bytes::from_static(b"jpeg"),
+        }
+    }
+
+    #[test]
+    fn cached_image_is_returned() {
+        let mut cache = MediaImageCache::default();
+        let now = Instant::now();
+        cache.insert("media_player.tv", URL, image(), now);
+
+        let cached = cache.get("media_player.tv", URL, TTL, now + Duration::from_secs(5));
+
+        assert_eq!(Some(image().data), cached.map(|i| i.data));
+    }
+
+    #[test]
+    fn expired_image_is_not_returned() {
+        let mut cache = MediaImageCache::default();
+        let now = Instant::now();
+        cache.insert("media_player.tv", URL, image(), now);
+
+        assert!(cache.get("media_player.tv", URL, TTL, now + TTL).is_none());
+    }
+
+    #[test]
+    fn changed_image_url_is_not_returned() {
+        let mut cache = MediaImageCache::default();
+        let now = Instant::now();
+        cache.insert("media_player.tv", URL, image(), now);
+
+        assert!(cache
+            .get("media_player.tv", &format!("{URL}?cache=2"), TTL, now)
+            .is_none());
+        assert!(cache.get("media_player.kitchen", URL, TTL, now).is_none());
+    }
+
+    #[test]
+    fn old_images_are_removed() {
+        let mut cache = MediaImageCache::default();
+        let now = Instant::now();
+        cache.insert("media_player.tv", URL, image(), now);
+
+        let later = now + MEDIA_IMAGE_CACHE_MAX_AGE;
+        cache.insert("media_player.kitchen", URL, image(), later);
+
+        assert_eq!(1, cache.entries.len());
+        assert!(cache.get("media_player.kitchen", URL, TTL, later).is_some());
+    }
+}
@@ -6,11 +6,15 @@
 //! These are the Actix messages used for the Remote Two WebSocket server connections and the
 //! Home Assistant client connections to interact with the Controller.
 
+use crate::controller::connection_history::ConnectionHistoryEntry;
 #[allow(unused_imports)] // used for doc links
 use crate::controller::Controller;
 use crate::errors::ServiceError;
+use crate::server::ListenPorts;
 use crate::util::DeserializeMsgData;
 use actix::prelude::{Message, Recipient};
+use bytes::Bytes;
+use serde::Serialize;
 use uc_api::intg::ws::{R2Event, R2Request, R2Response};
 use uc_api::ws::WsMessage;
 
@@ -24,8 +28,9 @@ pub struct SendWsMessage(pub WsMessage);
 /// New WebSocket connection from Remote Two established.
 ///
 /// Event to notify the [`Controller`] that a new WS integration client connected.
+/// The session is rejected with `ServiceUnavailable` if the maximum number of sessions is reached.
 #[derive(Message)]
-#[rtype(result = "()")]
+#[rtype(result = "Result<(), ServiceError>")]
 pub struct NewR2Session {
     /// Actor address of the WS session to send messages to
     pub addr: Recipient<SendWsMessage>,
@@ -92,3 +97,63 @@ pub struct R2EventMsg {
     pub event: R2Event,
     pub msg_data: Option<serde_json::Value>,
 }
+
+/// The integration server listeners have been rebound to the given listen ports.
+///
+/// The listen ports are only persisted after a successful rebind.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ListenPortsChanged(pub ListenPorts);
+
+/// Get the health status of the integration driver.
+#[derive(Message)]
+#[rtype(result = "Result<HealthStatus, ServiceError>")]
+pub struct GetHealth;
+
+/// Get the media image of a media player entity for the media image proxy.
+#[derive(Message)]
+#[rtype(result = "Result<MediaImage, ServiceError>")]
+pub struct GetMediaImage {
+    pub entity_id: String,
+}
+
+/// Media image fetched from Home Assistant.
+#[derive(Clone, Debug)]
+pub struct MediaImage {
+    pub content_type: String,
+    pub data: Bytes,
+}
+
+/// Health status of the integration driver.
+#[derive(Debug, Serialize)]
+pub struct HealthStatus {
+    /// Home Assistant connection state
+    pub state: String,
+    /// Integration driver uptime in seconds
+    pub uptime_sec: u64,
+    /// Number of active Remote Two WebSocket sessions
+    pub sessions: usize,
+    /// At least one Home Assistant server connection is established
+    pub ha_connected: bool,
+    /// Recent HA connection events, oldest event first
+    pub connection_history: Vec<ConnectionHistoryEntry>,
+    /// Reconnect status of the HA server connections
+    pub reconnect: Vec<ReconnectStatus>,
+}
+
+/// Reconnect status of a Home Assistant server connection.
+#[derive(Debug, Serialize)]
+pub struct ReconnectStatus {
+    /// Device identifier of the HA server connection
+    pub device_id: String,
+    /// Number of failed connection attempts since the last successful connection
+    pub attempt: u32,
+    /// Maximum number of reconnect attempts, 0 = unlimited
+    pub max_attempts: u32,
+    /// Delay in milliseconds of the scheduled or last reconnect attempt, 0 if connected
+    pub delay_ms: u64,
+    /// Maximum reconnect delay in milliseconds
+    pub max_delay_ms: u64,
+    /// A reconnect attempt is scheduled
+    pub pending: bool,
+}
@@ -3,25 +3,42 @@
 
 //! Central controller handling integration WS requests and HA client connection.
 
+mod connection_history;
+mod discovery;
+mod entity_cache;
+mod entity_filter;
+pub(crate) mod entity_namespace;
 mod handler;
+mod media_image_cache;
 mod messages;
+mod reconnect;
+mod standby_queue;
 
 pub use messages::*;
 
 use crate::client::HomeAssistantClient;
 use crate::configuration::{Settings, DEF_SETUP_TIMEOUT_SEC, ENV_SETUP_TIMEOUT};
+use crate::controller::connection_history::ConnectionHistory;
+use crate::controller::entity_cache::EntityCache;
+use crate::controller::entity_filter::AvailableEntitiesFilter;
+use crate::controller::entity_namespace::split_entity_id;
 use crate::controller::handler::AbortDriverSetup;
+use crate::controller::media_image_cache::MediaImageCache;
+use crate::controller::reconnect::ReconnectState;
+use crate::controller::standby_queue::StandbyQueue;
 use crate::errors::ServiceError;
+use crate::server::ListenPorts;
 use crate::util::new_websocket_client;
 use actix::prelude::{Actor, Context, Recipient};
 use actix::{Addr, AsyncContext, SpawnHandle};
+use futures::channel::mpsc::UnboundedSender;
 use log::{debug, error, info, warn};
 use rust_fsm::*;
-use serde_json::json;
+use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet};
 use std::env;
 use std::str::FromStr;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use uc_api::intg::{AvailableIntgEntity, DeviceState, IntegrationDriverUpdate};
 use uc_api::ws::{EventCategory, WsMessage};
 
@@ -67,10 +84,17 @@ struct R2Session {
     /// Request message id from driver to remote
     ws_id: u32,
     standby: bool,
+    /// Remote wants to be connected to HA: set with the `connect` and cleared with the
+    /// `disconnect` event.
+    ha_connect: bool,
+    /// Device state and entity changes while the remote is in standby
+    standby_queue: StandbyQueue,
     subscribed_entities: HashSet<String>,
     // TODO replace with request id map & oneshot notification
     /// quick and dirty request id mapping for get_available_entities request.
     get_available_entities_id: Option<u32>,
+    /// Filter of the pending get_available_entities request.
+    available_entities_filter: Option<AvailableEntitiesFilter>,
     /// quick and dirty request id mapping for get_entity_states request.
     get_entity_states_id: Option<u32>,
     /// Flag if currently in setup or reconfiguration mode.
@@ -83,8 +107,11 @@ impl R2Session {
             recipient,
             ws_id: 0,
             standby: false,
+            ha_connect: false,
+            standby_queue: Default::default(),
             subscribed_entities: Default::default(),
             get_available_entities_id: None,
+            available_entities_filter: None,
             get_entity_states_id: None,
             reconfiguring: None,
         }
@@ -109,23 +136,45 @@ pub struct Controller {
     /// WebSocket client
     // creating an expensive client is sufficient once per process and can be used to create multiple connections
     ws_client: awc::Client,
-    /// HomeAssistant client actor
-    ha_client: Option<Addr<HomeAssistantClient>>,
-    /// HomeAssistant client identifier
-    ha_client_id: Option<String>,
-    ha_reconnect_duration: Duration,
-    ha_reconnect_attempt: u32,
+    /// HomeAssistant client actors by device identifier of the HA server connection
+    ha_clients: HashMap<String, Addr<HomeAssistantClient>>,
+    /// HomeAssistant client identifiers of connected clients by device identifier
+    ha_client_ids: HashMap<String, String>,
+    /// Reconnect state of the HA server connections by device identifier
+    ha_reconnect: HashMap<String, ReconnectState>,
+    /// HA server connections (device identifiers) with a pending entity request
+    pending_entity_devices: HashSet<String>,
+    /// Collected entities of the pending entity request
+    pending_entities: Vec<AvailableIntgEntity>,
+    /// Cached available entities per HA server connection
+    entity_cache: EntityCache,
+    /// HA server connections (device identifiers) with a pending available entities request,
+    /// whose result is stored in the entity cache
+    entity_cache_devices: HashSet<String>,
+    /// Last media image per entity of the media image proxy
+    media_images: MediaImageCache,
     drv_metadata: IntegrationDriverUpdate,
     /// State machine for driver state: setup flow or running state
     machine: StateMachine<OperationMode>,
     /// Driver setup timeout handle
     setup_timeout: Option<SpawnHandle>,
-    /// Handle to a scheduled connect message for a reconnect attempt.
-    reconnect_handle: Option<SpawnHandle>,
+    /// Total time the driver setup timeout has been deferred for in-progress entity loads
+    setup_timeout_deferred: Duration,
+    /// Handle of a running Home Assistant server discovery in the setup flow
+    discovery_handle: Option<SpawnHandle>,
     /// List of subscribed entities sent by HA component
     susbcribed_entity_ids: Option<Vec<AvailableIntgEntity>>,
     /// Request id sent to the remote to get the version information
     remote_id: String,
+    /// Channel to request a rebind of the integration server listeners
+    listen_port_sender: Option<UnboundedSender<ListenPorts>>,
+    /// Changed listen ports from the setup flow, applied when the setup flow is finished and only
+    /// persisted after a successful rebind
+    pending_listen_ports: Option<ListenPorts>,
+    /// Start time of the controller for the uptime
+    started: Instant,
+    /// Recent HA connection events
+    connection_history: ConnectionHistory,
 }
 
 impl Controller {
@@ -138,25 +187,69 @@ impl Controller {
         } else {
             info!("Home Assistant connection requires setup");
         }
+        let remote_id = settings.remote_id.clone().unwrap_or_default();
+        let tls = settings
+            .hass
+            .servers()
+            .iter()
+            .any(|server| matches!(server.url.scheme(), "wss" | "https"));
         Self {
             sessions: Default::default(),
             device_state: DeviceState::Disconnected,
             ws_client: new_websocket_client(
                 Duration::from_secs(settings.hass.connection_timeout as u64),
                 Duration::from_secs(settings.hass.request_timeout as u64),
-                matches!(url.scheme(), "wss" | "https"),
+                tls,
+                &settings.hass.tcp_keepalive,
             ),
-            ha_reconnect_duration: settings.hass.reconnect.duration,
             settings,
-            ha_client: None,
-            ha_client_id: None,
-            ha_reconnect_attempt: 0,
+            ha_clients: Default::default(),
+            ha_client_ids: Default::default(),
+            ha_reconnect: Default::default(),
+            pending_entity_devices: Default::default(),
+            pending_entities: Default::default(),
+            entity_cache: Default::default(),
+            entity_cache_devices: Default::default(),
+            media_images: Default::default(),
             drv_metadata,
             machine,
             setup_timeout: None,
-            reconnect_handle: None,
+            setup_timeout_deferred: Duration::ZERO,
+            discovery_handle: None,
             susbcribed_entity_ids: None,
-            remote_id: "".to_string(),
+            remote_id,
+            listen_port_sender: None,
+            pending_listen_ports: None,
+            started: Instant::now(),
+            connection_history: Default::default(),
+        }
+    }
+
+    /// Set the channel to request a rebind of the integration server listen ports at runtime.
+    ///
+    /// Without a channel, changed listen ports are only applied after a restart.
+    pub fn set_listen_port_sender(&mut self, sender: UnboundedSender<ListenPorts>) {
+        self.listen_port_sender = Some(sender);
+    }
+
+    /// Request the integration server to rebind its listeners to the pending listen ports.
+    fn rebind_listeners(&mut self) {
+        let ports = match self.pending_listen_ports.take() {
+            Some(ports) if !ports.is_empty() => ports,
+            _ => return,
+        };
+
+        match self.listen_port_sender.as_ref() {
+            Some(sender) => {
+                info!("Requesting integration server rebind: {ports:?}");
+                if let Err(e) = sender.unbounded_send(ports) {
+                    error!("Error requesting integration server rebind: {e}");
+                }
+            }
+            None => {
+                warn!("Changed listen ports are applied after a restart: {ports:?}");
+                self.save_listen_ports(ports);
+            }
         }
     }
 
@@ -179,6 +272,33 @@ impl Controller {
         }
     }
 
+    /// Leave standby mode of a remote and send the events queued during standby.
+    fn exit_standby(&mut self, ws_id: &str) {
+        let messages = match self.sessions.get_mut(ws_id) {
+            Some(session) => {
+                session.standby = false;
+                session.standby_queue.drain()
+            }
+            None => return,
+        };
+        if !messages.is_empty() {
+            debug!("[{ws_id}] sending {} queued events", messages.len());
+        }
+        for message in messages {
+            self.send_r2_msg(message, ws_id);
+        }
+    }
+
+    /// Queue a `device_state` event for all remotes in standby, if enabled in the settings.
+    fn queue_standby_device_state(&mut self, msg_data: &Value) {
+        if !self.settings.hass.standby_device_state {
+            return;
+        }
+        for session in self.sessions.values_mut().filter(|s| s.standby) {
+            session.standby_queue.push_device_state(msg_data.clone());
+        }
+    }
+
     /// Send a `device_state` event message with the current state to the given WebSocket client identifier.
     ///
     /// # Arguments
@@ -199,13 +319,36 @@ impl Controller {
     }
 
     /// Broadcast a `device_state` event message with the current state to all connected Remotes
-    fn broadcast_device_state(&self) {
+    fn broadcast_device_state(&mut self) {
+        self.queue_standby_device_state(&json!({ "state": self.device_state }));
         for session in self.sessions.keys() {
             // TODO filter out remotes which don't require an active HA connection?
             self.send_device_state(session);
         }
     }
 
+    /// Broadcast a `device_state` event message with the reconnect status of a HA server
+    /// connection to all connected Remotes.
+    ///
+    /// The additional `reconnect` object is not defined in the Integration-API.
+    fn broadcast_reconnect_state(&mut self, device_id: &str) {
+        let reconnect = match self.ha_reconnect.get(device_id) {
+            Some(reconnect) => reconnect,
+            None => return,
+        };
+        let msg_data = json!({
+            "state": self.device_state,
+            "reconnect": reconnect.status(device_id, &self.settings.hass.reconnect)
+        });
+        self.queue_standby_device_state(&msg_data);
+        for session in self.sessions.keys() {
+            self.send_r2_msg(
+                WsMessage::event("device_state", EventCategory::Device, msg_data.clone()),
+                session,
+            );
+        }
+    }
+
     /// Set integration device state and broadcast state to all connected Remotes
     ///
     /// # Arguments
@@ -218,23 +361,42 @@ impl Controller {
         self.broadcast_device_state();
     }
 
-    fn increment_reconnect_timeout(&mut self) {
-        let new_timeout = Duration::from_millis(
-            (self.ha_reconnect_duration.as_millis() as f32
-                * self.settings.hass.reconnect.backoff_factor) as u64,
-        );
+    /// Get the reconnect state of a HA server connection.
+    fn reconnect_state(&mut self, device_id: &str) -> &mut ReconnectState {
+        let duration = self.settings.hass.reconnect.duration;
+        self.ha_reconnect
+            .entry(device_id.to_string())
+            .or_insert_with(|| ReconnectState::new(duration))
+    }
 
-        self.ha_reconnect_duration = if new_timeout.gt(&self.settings.hass.reconnect.duration_max) {
-            self.settings.hass.reconnect.duration_max
-        } else {
-            new_timeout
-        };
+    fn increment_reconnect_timeout(&mut self, device_id: &str) {
+        let reconnect = self.settings.hass.reconnect.clone();
+        let state = self.reconnect_state(device_id);
+        state.increment_duration(&reconnect);
         info!(
-            "New reconnect timeout: {}",
-            self.ha_reconnect_duration.as_millis()
+            "[{device_id}] New reconnect timeout: {}",
+            state.duration.as_millis()
         )
     }
 
+    /// Get the HA client of the server connection the entity originates from.
+    ///
+    /// returns: the HA client and the HA entity id without the device prefix.
+    fn ha_client_for_entity<'a>(
+        &self,
+        entity_id: &'a str,
+    ) -> Option<(Addr<HomeAssistantClient>, &'a str)> {
+        let (device_id, entity_id) = split_entity_id(entity_id);
+        self.ha_clients
+            .get(device_id)
+            .map(|client| (client.clone(), entity_id))
+    }
+
+    /// Check if any HA client is connected, except the client of the given device identifier.
+    fn has_other_ha_connection(&self, device_id: &str) -> bool {
+        self.ha_client_ids.keys().any(|id| id != device_id)
+    }
+
     /// Perform a state machine transition for the given input.
     ///
     /// An error is returned, if a state transition with the current state and the provided input
@@ -264,6 +426,7 @@ impl Controller {
                     .and_then(|v| u64::from_str(&v).ok())
                     .unwrap_or(DEF_SETUP_TIMEOUT_SEC);
                 debug!("Starting SetupFlowTimer: {timeout} sec");
+                self.setup_timeout_deferred = Duration::ZERO;
                 self.setup_timeout = Some(ctx.notify_later(
                     AbortDriverSetup {
                         ws_id: ws_id.to_string(),
@@ -0,0 +1,242 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Reconnect delay calculation with exponential backoff and random jitter.
+
+use crate::configuration::ReconnectSettings;
+use crate::controller::ReconnectStatus;
+use actix::SpawnHandle;
+use std::time::Duration;
+
+/// Reconnect state of a Home Assistant server connection.
+pub struct ReconnectState {
+    /// Current reconnect delay without jitter
+    pub duration: Duration,
+    /// Delay of the scheduled reconnect attempt, including the jitter
+    pub delay: Duration,
+    /// Number of failed connection attempts
+    pub attempt: u32,
+    /// Handle to a scheduled connect message for a reconnect attempt.
+    pub handle: Option<SpawnHandle>,
+}
+
+impl ReconnectState {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            delay: Duration::ZERO,
+            attempt: 0,
+            handle: None,
+        }
+    }
+
+    /// Reset the reconnect delay and attempts after a successful connection.
+    pub fn reset(&mut self, duration: Duration) {
+        self.duration = duration;
+        self.delay = Duration::ZERO;
+        self.attempt = 0;
+    }
+
+    /// Use the current reconnect delay with jitter for the next reconnect attempt.
+    ///
+    /// See [`jittered_delay`] for the arguments.
+    pub fn schedule(&mut self, settings: &ReconnectSettings, random: f32) -> Duration {
+        self.delay = jittered_delay(self.duration, settings, random);
+        self.delay
+    }
+
+    /// Increase the reconnect delay for the following reconnect attempt.
+    pub fn increment_duration(&mut self, settings: &ReconnectSettings) {
+        self.duration = next_reconnect_duration(self.duration, settings);
+    }
+
+    /// Get the reconnect status for the health endpoint.
+    pub fn status(&self, device_id: &str, settings: &ReconnectSettings) -> ReconnectStatus {
+        ReconnectStatus {
+            device_id: device_id.to_string(),
+            attempt: self.attempt,
+            max_attempts: settings.attempts,
+            delay_ms: self.delay.as_millis() as u64,
+            max_delay_ms: settings.duration_max.as_millis() as u64,
+            pending: self.handle.is_some(),
+        }
+    }
+}
+
+/// Calculate the next reconnect delay without jitter.
+///
+/// The current delay is multiplied with the backoff factor. The result is limited to the max
+/// reconnect duration.
+///
+/// # Arguments
+///
+/// * `current`: current reconnect delay.
+/// * `settings`: reconnect settings.
+pub fn next_reconnect_duration(current: Duration, settings: &ReconnectSettings) -> Duration {
+    let millis = current.as_millis() as f32 * settings.backoff_factor;
+    let duration = Duration::from_millis(millis.max(0.0) as u64);
+
+    if duration > settings.duration_max {
+        settings.duration_max
+    } else {
+        duration
+    }
+}
+
+/// Apply the jitter to a reconnect delay to avoid many clients reconnecting in lockstep.
+///
+/// The jittered delay is limited to the max reconnect duration.
+///
+/// # Arguments
+///
+/// * `delay`: reconnect delay without jitter.
+/// * `settings`: reconnect settings.
+/// * `random`: random value between -1.0 and 1.0 for the jitter.
+pub fn jittered_delay(delay: Duration, settings: &ReconnectSettings, random: f32) -> Duration {
+    if settings.jitter <= 0.0 {
+        return delay;
+    }
+    let millis =
+        delay.as_millis() as f32 * (1.0 + settings.jitter.min(1.0) * random.clamp(-1.0, 1.0));
+    Duration::from_millis(millis.max(0.0) as u64).min(settings.duration_max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn settings(jitter: f32) -> ReconnectSettings {
+        ReconnectSettings {
+            attempts: 0,
+            duration: Duration::from_secs(1),
+            duration_max: Duration::from_secs(30),
+            backoff_factor: 1.5,
+            jitter,
+        }
+    }
+
+    #[rstest]
+    #[case(-1.0)]
+    #[case(0.0)]
+    #[case(1.0)]
+    fn zero_jitter_keeps_delay(#[case] random: f32) {
+        let result = jittered_delay(Duration::from_secs(3), &settings(0.0), random);
+
+        assert_eq!(Duration::from_secs(3), result);
+    }
+
+    #[rstest]
+    #[case(-1.0, 2400)]
+    #[case(0.0, 3000)]
+    #[case(0.5, 3300)]
+    #[case(1.0, 3600)]
+    fn jitter_is_applied(#[case] random: f32, #[case] expected_ms: u64) {
+        let result = jittered_delay(Duration::from_secs(3), &settings(0.2), random);
+
+        assert_eq!(Duration::from_millis(expected_ms), result);
+    }
+
+    #[test]
+    fn next_duration_uses_backoff_factor() {
+        let result = next_reconnect_duration(Duration::from_secs(2), &settings(0.2));
+
+        assert_eq!(Duration::from_secs(3), result);
+    }
+
+    #[test]
+    fn first_reconnect_attempt_is_jittered() {
+        let settings = settings(0.2);
+        let mut state = ReconnectState::new(settings.duration);
+
+        assert_eq!(Duration::from_millis(800), state.schedule(&settings, -1.0));
+        assert_eq!(Duration::from_millis(1200), state.schedule(&settings, 1.0));
+    }
+
+    #[test]
+    fn jitter_does_not_compound() {
+        let settings = settings(0.2);
+        let mut state = ReconnectState::new(settings.duration);
+        for _ in 0..3 {
+            state.schedule(&settings, 1.0);
+            state.increment_duration(&settings);
+        }
+
+        // 1s * 1.5 * 1.5 * 1.5, without the jitter of the previous attempts
+        assert_eq!(Duration::from_millis(3375), state.duration);
+        assert_eq!(Duration::from_millis(4050), state.schedule(&settings, 1.0));
+    }
+
+    #[rstest]
+    #[case(-1.0, 24000)]
+    #[case(0.5, 30000)]
+    #[case(1.0, 30000)]
+    fn jitter_is_applied_to_max_duration(#[case] random: f32, #[case] expected_ms: u64) {
+        let settings = settings(0.2);
+        let mut state = ReconnectState::new(settings.duration_max);
+        state.increment_duration(&settings);
+
+        assert_eq!(settings.duration_max, state.duration);
+        assert_eq!(
+            Duration::from_millis(expected_ms),
+            state.schedule(&settings, random)
+        );
+    }
+
+    #[rstest]
+    #[case(0.2)]
+    #[case(1.0)]
+    fn jittered_delay_does_not_exceed_max_duration(#[case] jitter: f32) {
+        let settings = settings(jitter);
+        let mut state = ReconnectState::new(settings.duration);
+        for _ in 0..20 {
+            assert!(state.schedule(&settings, 1.0) <= settings.duration_max);
+            state.increment_duration(&settings);
+        }
+
+        assert_eq!(settings.duration_max, state.duration);
+        assert_eq!(settings.duration_max, state.schedule(&settings, 1.0));
+    }
+
+    #[test]
+    fn reported_delay_matches_backoff() {
+        let settings = settings(0.0);
+        let mut state = ReconnectState::new(settings.duration);
+        for _ in 0..3 {
+            state.attempt += 1;
+            state.schedule(&settings, 0.0);
+            state.increment_duration(&settings);
+        }
+
+        let status = state.status("main", &settings);
+
+        assert_eq!(3, status.attempt);
+        // 1s * 1.5 * 1.5
+        assert_eq!(2250, status.delay_ms);
+        assert_eq!(30000, status.max_delay_ms);
+        assert!(!status.pending);
+
+        assert_eq!(
+            serde_json::json!({
+                "device_id": "main",
+                "attempt": 3,
+                "max_attempts": 0,
+                "delay_ms": 2250,
+                "max_delay_ms": 30000,
+                "pending": false
+            }),
+            serde_json::to_value(&status).unwrap()
+        );
+
+        state.reset(settings.duration);
+        let status = state.status("main", &settings);
+        assert_eq!((0, 0), (status.attempt, status.delay_ms));
+    }
+
+    #[test]
+    fn result_is_limited_to_max_duration() {
+        let result = next_reconnect_duration(Duration::from_secs(25), &settings(0.2));
+
+        assert_eq!(Duration::from_secs(30), result);
+    }
+}
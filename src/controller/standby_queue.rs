@@ -0,0 +1,301 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Queue of `device_state` and `entity_change` events while a remote is in standby.
+//!
+//! Only the latest device state and the latest change per entity are kept. The queued events are
+//! sent when the remote exits standby, so the UI reflects the current states immediately.
+
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use uc_api::ws::{EventCategory, WsMessage};
+
+/// Default max number of queued entities per remote session.
+pub const DEF_STANDBY_QUEUE_SIZE: usize = 500;
+
+/// Event messages of a remote in standby.
+#[derive(Debug, Default)]
+pub(crate) struct StandbyQueue {
+    /// Latest `device_state` event message data
+    device_state: Option<Value>,
+    entity_changes: EntityChangeQueue,
+}
+
+impl StandbyQueue {
+    /// Queue a `device_state` message data object, replacing an already queued device state.
+    pub fn push_device_state(&mut self, msg_data: Value) {
+        self.device_state = Some(msg_data);
+    }
+
+    /// Queue an `entity_change` message data object, see [`EntityChangeQueue::push`].
+    pub fn push_entity_change(&mut self, msg_data: Value) {
+        self.entity_changes.push(msg_data);
+    }
+
+    /// Take all queued event messages: the device state first, followed by the entity changes.
+    pub fn drain(&mut self) -> Vec<WsMessage> {
+        self.device_state
+            .take()
+            .map(|msg_data| WsMessage::event("device_state", EventCategory::Device, msg_data))
+            .into_iter()
+            .chain(
+                self.entity_changes.drain().into_iter().map(|msg_data| {
+                    WsMessage::event("entity_change", EventCategory::Entity, msg_data)
+                }),
+            )
+            .collect()
+    }
+}
+
+/// Coalescing queue of `entity_change` event message data.
+#[derive(Debug)]
+pub(crate) struct EntityChangeQueue {
+    capacity: usize,
+    /// Entity ids in order of their last change, oldest first
+    order: VecDeque<String>,
+    /// Latest change message data per entity id
+    changes: HashMap<String, Value>,
+}
+
+impl Default for EntityChangeQueue {
+    fn default() -> Self {
+        Self::new(DEF_STANDBY_QUEUE_SIZE)
+    }
+}
+
+impl EntityChangeQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::new(),
+            changes: HashMap::new(),
+        }
+    }
+
+    /// Queue an `entity_change` message data object.
+    ///
+    /// A queued change of the same entity is replaced. Its attributes are merged, the newer
+    /// attribute values have priority. If the queue is full, the oldest change is dropped.
+    pub fn push(&mut self, msg_data: Value) {
+        if self.capacity == 0 {
+            return;
+        }
+        let entity_id = match msg_data.get("entity_id").and_then(|v| v.as_str()) {
+            Some(id) => id.to_string(),
+            None => return,
+        };
+
+        let msg_data = match self.changes.remove(&entity_id) {
+            Some(old) => {
+                self.order.retain(|id| id != &entity_id);
+                merge_entity_change(old, msg_data)
+            }
+            None => msg_data,
+        };
+
+        while self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.changes.remove(&oldest);
+            }
+        }
+        self.order.push_back(entity_id.clone());
+        self.changes.insert(entity_id, msg_data);
+    }
+
+    /// Take all queued changes, oldest change first.
+    pub fn drain(&mut self) -> Vec<Value> {
+        let changes = self
+            .order
+            .drain(..)
+            .filter_map(|id| self.changes.remove(&id))
+            .collect();
+        self.changes.clear();
+        changes
+    }
+}
+
+/// Merge the attributes of an older entity change into a newer change of the same entity.
+fn merge_entity_change(old: Value, mut new: Value) -> Value {
+    if let (Some(Value::Object(mut attributes)), Some(new_attributes)) = (
+        old.get("attributes").cloned(),
+        new.get_mut("attributes").and_then(|v| v.as_object_mut()),
+    ) {
+        attributes.extend(std::mem::take(new_attributes));
+        *new_attributes = attributes;
+    }
+    new
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::{get_driver_metadata, Settings};
+    use crate::controller::{Controller, R2Session, SendWsMessage};
+    use actix::{Actor, Context, Handler, Message};
+    use serde_json::json;
+    use uc_api::intg::DeviceState;
+
+    fn change(entity_id: &str, attributes: Value) -> Value {
+        json!({
+            "entity_type": "light",
+            "entity_id": entity_id,
+            "attributes": attributes
+        })
+    }
+
+    #[test]
+    fn latest_change_per_entity_is_kept() {
+        let mut queue = EntityChangeQueue::new(10);
+        queue.push(change(
+            "light.kitchen",
+            json!({ "state": "ON", "brightness": 10 }),
+        ));
+        queue.push(change("light.office", json!({ "state": "OFF" })));
+        queue.push(change("light.kitchen", json!({ "brightness": 200 })));
+
+        assert_eq!(
+            vec![
+                change("light.office", json!({ "state": "OFF" })),
+                change("light.kitchen", json!({ "state": "ON", "brightness": 200 })),
+            ],
+            queue.drain()
+        );
+    }
+
+    #[test]
+    fn full_queue_drops_oldest_change() {
+        let mut queue = EntityChangeQueue::new(2);
+        queue.push(change("light.a", json!({ "state": "ON" })));
+        queue.push(change("light.b", json!({ "state": "ON" })));
+        queue.push(change("light.c", json!({ "state": "ON" })));
+
+        let entity_ids: Vec<_> = queue
+            .drain()
+            .iter()
+            .filter_map(|v| v.get("entity_id").and_then(|v| v.as_str()))
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(vec!["light.b", "light.c"], entity_ids);
+    }
+
+    #[test]
+    fn updated_entity_is_not_dropped_first() {
+        let mut queue = EntityChangeQueue::new(2);
+        queue.push(change("light.a", json!({ "state": "ON" })));
+        queue.push(change("light.b", json!({ "state": "ON" })));
+        queue.push(change("light.a", json!({ "state": "OFF" })));
+        queue.push(change("light.c", json!({ "state": "ON" })));
+
+        let entity_ids: Vec<_> = queue
+            .drain()
+            .iter()
+            .filter_map(|v| v.get("entity_id").and_then(|v| v.as_str()))
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(vec!["light.a", "light.c"], entity_ids);
+    }
+
+    #[test]
+    fn latest_device_state_is_delivered_first() {
+        let mut queue = StandbyQueue::default();
+        queue.push_device_state(json!({ "state": "DISCONNECTED" }));
+        queue.push_entity_change(change("light.a", json!({ "state": "ON" })));
+        queue.push_device_state(json!({ "state": "CONNECTED" }));
+
+        let messages = queue.drain();
+
+        assert_eq!(2, messages.len());
+        assert_eq!(Some("device_state"), messages[0].msg.as_deref());
+        assert_eq!(Some(json!({ "state": "CONNECTED" })), messages[0].msg_data);
+        assert_eq!(Some("entity_change"), messages[1].msg.as_deref());
+        assert!(queue.drain().is_empty());
+    }
+
+    #[test]
+    fn no_device_state_without_change() {
+        let mut queue = StandbyQueue::default();
+        queue.push_entity_change(change("light.a", json!({ "state": "ON" })));
+
+        let messages = queue.drain();
+
+        assert_eq!(1, messages.len());
+        assert_eq!(Some("entity_change"), messages[0].msg.as_deref());
+    }
+
+    #[test]
+    fn drain_empties_queue() {
+        let mut queue = EntityChangeQueue::new(2);
+        queue.push(change("light.a", json!({ "state": "ON" })));
+
+        assert_eq!(1, queue.drain().len());
+        assert!(queue.drain().is_empty());
+    }
+
+    /// Remote WebSocket session collecting the sent messages.
+    #[derive(Default)]
+    struct RemoteSession {
+        messages: Vec<WsMessage>,
+    }
+
+    impl Actor for RemoteSession {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<SendWsMessage> for RemoteSession {
+        type Result = ();
+
+        fn handle(&mut self, msg: SendWsMessage, _ctx: &mut Self::Context) -> Self::Result {
+            self.messages.push(msg.0);
+        }
+    }
+
+    /// Take the messages received so far.
+    #[derive(Message)]
+    #[rtype(result = "Vec<WsMessage>")]
+    struct TakeMessages;
+
+    impl Handler<TakeMessages> for RemoteSession {
+        type Result = Vec<WsMessage>;
+
+        fn handle(&mut self, _msg: TakeMessages, _ctx: &mut Self::Context) -> Self::Result {
+            std::mem::take(&mut self.messages)
+        }
+    }
+
+    #[actix::test]
+    async fn queued_device_state_is_sent_first_when_exiting_standby() {
+        let mut settings = Settings::default();
+        settings.hass.standby_device_state = true;
+        let metadata = get_driver_metadata().expect("driver metadata");
+        let mut controller = Controller::new(settings, metadata);
+        let remote = RemoteSession::default().start();
+        let mut session = R2Session::new(remote.clone().recipient());
+        session.standby = true;
+        session
+            .standby_queue
+            .push_entity_change(change("light.a", json!({ "state": "ON" })));
+        controller.sessions.insert("ws-1".into(), session);
+
+        // device state changes while the remote is in standby
+        controller.device_state = DeviceState::Connected;
+        controller.broadcast_device_state();
+        let messages = remote.send(TakeMessages).await.expect("remote session");
+        assert!(messages.is_empty(), "Remote in standby got: {messages:?}");
+
+        controller.exit_standby("ws-1");
+        let messages = remote.send(TakeMessages).await.expect("remote session");
+
+        assert_eq!(2, messages.len());
+        assert_eq!(Some("device_state"), messages[0].msg.as_deref());
+        assert_eq!(
+            Some(json!({ "state": DeviceState::Connected })),
+            messages[0].msg_data
+        );
+        assert_eq!(Some("entity_change"), messages[1].msg.as_deref());
+        assert_eq!(
+            Some(change("light.a", json!({ "state": "ON" }))),
+            messages[1].msg_data
+        );
+        assert!(!controller.sessions["ws-1"].standby);
+    }
+}
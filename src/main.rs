@@ -16,19 +16,27 @@
 #![deny(unsafe_code)]
 
 use crate::configuration::{
-    get_configuration, CertificateSettings, IntegrationSettings, ENV_DISABLE_MDNS_PUBLISH,
+    get_configuration, CertificateSettings, IntegrationSettings, WebSocketSettings,
+    ENV_DISABLE_MDNS_PUBLISH, ENV_LOG_PREFIX,
 };
-use crate::controller::Controller;
-use crate::server::publish_service;
-use crate::util::{bool_from_env, create_single_cert_server_config};
-use actix::Actor;
+use crate::controller::{Controller, ListenPortsChanged};
+use crate::server::{
+    api_scope, normalize_base_path, publish_service, rebind_listener, ws_path, ListenPorts,
+    PublishedService,
+};
+use crate::util::{bool_from_env, create_single_cert_server_config, init_logger};
+use actix::{Actor, Addr};
+use actix_web::dev::Server;
 use actix_web::{middleware, web, App, HttpServer};
 use clap::{arg, Command};
 use configuration::DEF_CONFIG_FILE;
+use futures::channel::mpsc;
+use futures::future::{select, Either};
+use futures::StreamExt;
 use log::{error, info};
-use std::io;
 use std::net::TcpListener;
 use std::path::Path;
+use std::{env, io};
 use uc_api::intg::IntegrationDriverUpdate;
 use uc_api::util::text_from_language_map;
 use uc_intg_hass::{built_info, APP_VERSION};
@@ -49,7 +57,7 @@ async fn main() -> io::Result<()> {
         .arg(arg!(-c --config <FILE> ... "Configuration file").required(false))
         .get_matches();
 
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    init_logger("info", env::var(ENV_LOG_PREFIX).ok().as_deref());
 
     let cfg_file: Option<&str> =
         args.get_one("config")
@@ -65,13 +73,120 @@ async fn main() -> io::Result<()> {
 
     let cfg = get_configuration(cfg_file).expect("Failed to read configuration");
 
-    let listeners = create_tcp_listeners(&cfg.integration)?;
-    let api_port = cfg.integration.http.port;
+    let mut listeners = create_tcp_listeners(&cfg.integration)?;
+    let interface = cfg.integration.interface.clone();
+    let mut api_port = cfg.integration.http.port;
+    let base_path = normalize_base_path(&cfg.integration.base_path);
     let websocket_settings = web::Data::new(cfg.integration.websocket.clone().unwrap_or_default());
     let driver_metadata = configuration::get_driver_metadata()?;
 
-    let controller = web::Data::new(Controller::new(cfg, driver_metadata.clone()).start());
+    let (listen_port_tx, mut listen_port_rx) = mpsc::unbounded::<ListenPorts>();
+    let mut controller = Controller::new(cfg, driver_metadata.clone());
+    controller.set_listen_port_sender(listen_port_tx);
+    let controller = web::Data::new(controller.start());
+
+    let mdns_publish = !bool_from_env(ENV_DISABLE_MDNS_PUBLISH);
+    // The service is published once and only replaced if the advertised port changes
+    let mut mdns_service = None;
+
+    // The server is restarted with new listeners if the listen ports are changed at runtime
+    loop {
+        let mut http_server = create_http_server(
+            &listeners,
+            base_path.clone(),
+            websocket_settings.clone(),
+            controller.clone(),
+        )?;
+        let handle = http_server.handle();
+
+        if mdns_publish && mdns_service.is_none() {
+            mdns_service = publish_mdns(api_port, &base_path, driver_metadata.clone());
+        }
+
+        loop {
+            match select(http_server, listen_port_rx.next()).await {
+                Either::Left((result, _)) => return result,
+                Either::Right((None, server)) => return server.await,
+                Either::Right((Some(ports), server)) => {
+                    http_server = server;
+                    match listeners.rebind(&interface, ports) {
+                        Ok(true) => {
+                            // only persist the listen ports if they could be bound
+                            controller.do_send(ListenPortsChanged(ports));
+                            break;
+                        }
+                        Ok(false) => info!("Listen ports not changed: {ports:?}"),
+                        Err(e) => error!("Error rebinding listen ports {ports:?}: {e}"),
+                    }
+                }
+            }
+        }
+
+        info!("Restarting server with new listen ports");
+        // the server future must be polled to process the stop command
+        let (result, _) = futures::join!(http_server, handle.stop(true));
+        result?;
+        if let Some(port) = listeners.listener.as_ref().map(TcpListener::local_addr) {
+            let port = port?.port();
+            if port != api_port {
+                api_port = port;
+                // unregister the old port before publishing the new one
+                drop(mdns_service.take());
+            }
+        }
+    }
+}
+
+struct Listeners {
+    pub listener: Option<TcpListener>,
+    pub listener_tls: Option<TcpListener>,
+    pub certs: CertificateSettings,
+}
 
+impl Listeners {
+    /// Replace the listeners with new listeners bound to the given ports.
+    ///
+    /// The listeners are only replaced if all new ports could be bound.
+    ///
+    /// returns: true if at least one listener has been replaced.
+    fn rebind(&mut self, interface: &str, ports: ListenPorts) -> Result<bool, io::Error> {
+        let listener = match (self.listener.as_ref(), ports.http) {
+            (Some(current), Some(port)) => rebind_listener(current, interface, port)?,
+            _ => None,
+        };
+        let listener_tls = match (self.listener_tls.as_ref(), ports.https) {
+            (Some(current), Some(port)) => rebind_listener(current, interface, port)?,
+            _ => None,
+        };
+
+        let changed = listener.is_some() || listener_tls.is_some();
+        if let Some(listener) = listener {
+            println!(
+                "{} listening on: {}",
+                built_info::PKG_NAME,
+                listener.local_addr()?
+            );
+            self.listener = Some(listener);
+        }
+        if let Some(listener) = listener_tls {
+            println!(
+                "{} listening on: {}",
+                built_info::PKG_NAME,
+                listener.local_addr()?
+            );
+            self.listener_tls = Some(listener);
+        }
+
+        Ok(changed)
+    }
+}
+
+fn create_http_server(
+    listeners: &Listeners,
+    base_path: String,
+    websocket_settings: web::Data<WebSocketSettings>,
+    controller: web::Data<Addr<Controller>>,
+) -> Result<Server, io::Error> {
     let mut http_server = HttpServer::new(move || {
         App::new()
             .wrap(middleware::Logger::default())
@@ -82,34 +197,24 @@ async fn main() -> io::Result<()> {
             )
             .app_data(websocket_settings.clone())
             .app_data(controller.clone())
-            // Websockets
-            .service(server::ws_index)
+            // Websockets & health endpoints
+            .service(api_scope(&base_path))
     })
-    .workers(1);
+    .workers(1)
+    // WebSocket connections are long-lived: don't wait too long when restarting the server
+    .shutdown_timeout(5);
 
-    if let Some(listener) = listeners.listener_tls {
+    if let Some(listener) = listeners.listener_tls.as_ref() {
         let server_cfg =
             create_single_cert_server_config(&listeners.certs.public, &listeners.certs.private)?;
-        http_server = http_server.listen_rustls_0_21(listener, server_cfg)?;
-    }
-
-    if let Some(listener) = listeners.listener {
-        http_server = http_server.listen(listener)?;
+        http_server = http_server.listen_rustls_0_21(listener.try_clone()?, server_cfg)?;
     }
 
-    if !bool_from_env(ENV_DISABLE_MDNS_PUBLISH) {
-        publish_mdns(api_port, driver_metadata);
+    if let Some(listener) = listeners.listener.as_ref() {
+        http_server = http_server.listen(listener.try_clone()?)?;
     }
 
-    http_server.run().await?;
-
-    Ok(())
-}
-
-struct Listeners {
-    pub listener: Option<TcpListener>,
-    pub listener_tls: Option<TcpListener>,
-    pub certs: CertificateSettings,
+    Ok(http_server.run())
 }
 
 fn create_tcp_listeners(cfg: &IntegrationSettings) -> Result<Listeners, io::Error> {
@@ -153,8 +258,14 @@ fn create_tcp_listeners(cfg: &IntegrationSettings) -> Result<Listeners, io::Erro
 }
 
 /// Advertise integration driver with mDNS.
-fn publish_mdns(api_port: u16, drv_metadata: IntegrationDriverUpdate) {
-    if let Err(e) = publish_service(
+///
+/// returns: the published service, which is unregistered when dropped.
+fn publish_mdns(
+    api_port: u16,
+    base_path: &str,
+    drv_metadata: IntegrationDriverUpdate,
+) -> Option<PublishedService> {
+    match publish_service(
         drv_metadata
             .driver_id
             .expect("driver_id must be set in driver metadata"),
@@ -175,13 +286,18 @@ fn publish_mdns(api_port: u16, drv_metadata: IntegrationDriverUpdate) {
                     .unwrap_or("Unfolded Circle ApS".into())
             ),
             // "ws_url=wss://localhost:8008".into(), // to override the complete WS url. Ignores ws_path, wss, wss_port!
-            "ws_path=/ws".into(), // otherwise `/` is used and the remote can't connect
+            // otherwise `/` is used and the remote can't connect
+            format!("ws_path={}", ws_path(base_path)),
             //"wss=false".into(), // if wss is required
             //format!("wss_port={}", cfg.integration.https.port), // if https port if different from the published service port above
             format!("pwd={}", drv_metadata.pwd_protected.unwrap_or_default()),
             format!("ver={APP_VERSION}"),
         ],
     ) {
-        error!("Error publishing mDNS service: {e}");
+        Ok(service) => Some(service),
+        Err(e) => {
+            error!("Error publishing mDNS service: {e}");
+            None
+        }
     }
 }
@@ -0,0 +1,47 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! HTTP health & readiness endpoints for troubleshooting and monitoring.
+
+use crate::controller::GetHealth;
+use crate::Controller;
+use actix::Addr;
+use actix_web::{get, web, HttpResponse};
+use log::error;
+use uc_api::core::web::ApiResponse;
+
+/// Health status with the uptime and recent Home Assistant connection events.
+#[get("/health")]
+pub async fn health(controller: web::Data<Addr<Controller>>) -> HttpResponse {
+    match controller.send(GetHealth).await {
+        Ok(Ok(status)) => HttpResponse::Ok().json(status),
+        Ok(Err(e)) => {
+            error!("Error retrieving health status: {e:?}");
+            HttpResponse::InternalServerError().json(ApiResponse::new("ERROR", &e.to_string()[..]))
+        }
+        Err(e) => {
+            error!("Error retrieving health status: {e:?}");
+            HttpResponse::ServiceUnavailable()
+                .json(ApiResponse::new("ERROR", "Service unavailable"))
+        }
+    }
+}
+
+/// Readiness status: the health status is returned with `503 Service Unavailable` if no Home
+/// Assistant server connection is established.
+#[get("/ready")]
+pub async fn ready(controller: web::Data<Addr<Controller>>) -> HttpResponse {
+    match controller.send(GetHealth).await {
+        Ok(Ok(status)) if status.ha_connected => HttpResponse::Ok().json(status),
+        Ok(Ok(status)) => HttpResponse::ServiceUnavailable().json(status),
+        Ok(Err(e)) => {
+            error!("Error retrieving readiness status: {e:?}");
+            HttpResponse::InternalServerError().json(ApiResponse::new("ERROR", &e.to_string()[..]))
+        }
+        Err(e) => {
+            error!("Error retrieving readiness status: {e:?}");
+            HttpResponse::ServiceUnavailable()
+                .json(ApiResponse::new("ERROR", "Service unavailable"))
+        }
+    }
+}
@@ -1,14 +1,16 @@
 // Copyright (c) 2023 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
 // SPDX-License-Identifier: MPL-2.0
 
-//! mDNS advertisement with mdns-sd Rust crate
+//! mDNS advertisement and discovery with mdns-sd Rust crate
 
 use crate::errors::ServiceError;
+use crate::server::DiscoveredService;
 use crate::util::my_ipv4_interfaces;
 use lazy_static::lazy_static;
-use log::{error, info};
-use mdns_sd::{ServiceDaemon, ServiceInfo};
+use log::{debug, error, info, warn};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
 use std::net::IpAddr;
+use std::time::{Duration, Instant};
 
 lazy_static! {
     pub static ref MDNS_SERVICE: Option<ServiceDaemon> = match ServiceDaemon::new() {
@@ -22,6 +24,33 @@ lazy_static! {
     };
 }
 
+/// Get the fully qualified mDNS service type, e.g. `_http._tcp.local.`.
+fn service_type(service_name: &str, protocol: &str) -> String {
+    let mut reg_type = format!("_{service_name}._{protocol}");
+    if !reg_type.ends_with(".local.") {
+        reg_type.push_str(".local.");
+    }
+    reg_type
+}
+
+/// Handle of a published mDNS service.
+///
+/// The service is unregistered when the handle is dropped.
+pub struct PublishedService {
+    fullname: String,
+}
+
+impl Drop for PublishedService {
+    fn drop(&mut self) {
+        if let Some(mdns_service) = &*MDNS_SERVICE {
+            match mdns_service.unregister(&self.fullname) {
+                Ok(_) => info!("Unregistered service: {}", self.fullname),
+                Err(e) => warn!("Failed to unregister service {}: {e}", self.fullname),
+            }
+        }
+    }
+}
+
 /// Publish a service on all available network interfaces with the default hostname.
 ///
 /// # Arguments
@@ -37,12 +66,9 @@ pub fn publish_service(
     protocol: impl AsRef<str>,
     port: u16,
     txt: Vec<String>,
-) -> Result<(), ServiceError> {
+) -> Result<PublishedService, ServiceError> {
     if let Some(mdns_service) = &*MDNS_SERVICE {
-        let mut reg_type = format!("_{}._{}", service_name.as_ref(), protocol.as_ref());
-        if !reg_type.ends_with(".local.") {
-            reg_type.push_str(".local.");
-        }
+        let reg_type = service_type(service_name.as_ref(), protocol.as_ref());
         let my_addrs: Vec<IpAddr> = my_ipv4_interfaces().iter().map(|i| i.ip()).collect();
         let hostname = hostname::get().map(|name| name.to_string_lossy().to_string())?;
 
@@ -51,7 +77,7 @@ pub fn publish_service(
             .filter_map(|v| v.split_once('='))
             .map(|(k, v)| (k.into(), v.into()))
             .collect();
-        if let Err(e) = ServiceInfo::new(
+        ServiceInfo::new(
             &reg_type,
             instance_name.as_ref(),
             &hostname,
@@ -63,17 +89,75 @@ pub fn publish_service(
             let fullname = service_info.get_fullname().to_string();
             mdns_service.register(service_info)?;
             info!("Registered service: {fullname}");
-            Ok(())
-        }) {
-            Err(ServiceError::InternalServerError(format!(
+            Ok(PublishedService { fullname })
+        })
+        .map_err(|e| {
+            ServiceError::InternalServerError(format!(
                 "Failed to register {reg_type} mdns service! Error: {e}"
-            )))
-        } else {
-            Ok(())
-        }
+            ))
+        })
     } else {
         Err(ServiceError::ServiceUnavailable(
             "mDNS service not available".into(),
         ))
     }
 }
+
+/// Discover service instances in the local network.
+///
+/// Attention: blocks the calling thread until the timeout expires.
+///
+/// # Arguments
+///
+/// * `service_name`: The service name (e.g. `http`).
+/// * `protocol`: The protocol of the service (e.g. `tcp`).
+/// * `timeout`: How long to browse for service instances.
+pub fn discover_services(
+    service_name: impl AsRef<str>,
+    protocol: impl AsRef<str>,
+    timeout: Duration,
+) -> Vec<DiscoveredService> {
+    let mut services = Vec::new();
+    let mdns_service = match &*MDNS_SERVICE {
+        None => {
+            warn!("mDNS service not available: services cannot be discovered!");
+            return services;
+        }
+        Some(s) => s,
+    };
+    let reg_type = service_type(service_name.as_ref(), protocol.as_ref());
+    let receiver = match mdns_service.browse(&reg_type) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Failed to browse {reg_type} mdns services! Error: {e}");
+            return services;
+        }
+    };
+
+    let deadline = Instant::now() + timeout;
+    while let Ok(event) = receiver.recv_deadline(deadline) {
+        if let ServiceEvent::ServiceResolved(info) = event {
+            debug!("Discovered service: {}", info.get_fullname());
+            services.push(DiscoveredService {
+                name: info
+                    .get_fullname()
+                    .trim_end_matches(&reg_type)
+                    .trim_end_matches('.')
+                    .to_string(),
+                hostname: info.get_hostname().to_string(),
+                addresses: info.get_addresses().iter().copied().collect(),
+                port: info.get_port(),
+                txt: info
+                    .get_properties()
+                    .iter()
+                    .map(|p| (p.key().to_string(), p.val_str().to_string()))
+                    .collect(),
+            });
+        }
+    }
+
+    if let Err(e) = mdns_service.stop_browse(&reg_type) {
+        warn!("Failed to stop browsing {reg_type} mdns services: {e}");
+    }
+    services
+}
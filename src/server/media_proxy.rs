@@ -0,0 +1,44 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Media image proxy endpoint: serves the media image of a media player entity fetched from Home
+//! Assistant, for remotes which cannot access the HA server directly.
+
+use crate::controller::GetMediaImage;
+use crate::errors::ServiceError;
+use crate::Controller;
+use actix::Addr;
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::{get, web, HttpResponse};
+use log::{debug, error};
+use uc_api::core::web::ApiResponse;
+
+/// Media image of a media player entity, fetched from Home Assistant with the access token.
+///
+/// Only available if the media image proxy is enabled.
+#[get("/media_proxy/{entity_id}")]
+pub async fn media_proxy(
+    entity_id: web::Path<String>,
+    controller: web::Data<Addr<Controller>>,
+) -> HttpResponse {
+    let entity_id = entity_id.into_inner();
+    match controller.send(GetMediaImage { entity_id }).await {
+        Ok(Ok(image)) => HttpResponse::Ok()
+            .content_type(image.content_type)
+            .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+            .body(image.data),
+        Ok(Err(ServiceError::NotFound(e))) => {
+            debug!("Media image not found: {e}");
+            HttpResponse::NotFound().json(ApiResponse::new("NOT_FOUND", &e))
+        }
+        Ok(Err(e)) => {
+            error!("Error retrieving media image: {e:?}");
+            HttpResponse::BadGateway().json(ApiResponse::new("ERROR", &e.to_string()[..]))
+        }
+        Err(e) => {
+            error!("Error retrieving media image: {e:?}");
+            HttpResponse::ServiceUnavailable()
+                .json(ApiResponse::new("ERROR", "Service unavailable"))
+        }
+    }
+}
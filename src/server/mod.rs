@@ -1,23 +1,82 @@
 // Copyright (c) 2022 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
 // SPDX-License-Identifier: MPL-2.0
 
-//! Server modules of the integration driver. Handling WebSocket and mDNS advertisement.
+//! Server modules of the integration driver. Handling WebSocket, health endpoints, mDNS
+//! advertisement & discovery and listener rebinding.
+
+use actix_web::{web, Scope};
+use std::collections::HashMap;
+use std::net::IpAddr;
 
 // zeroconf has priority over mdns-sd
 #[cfg(feature = "zeroconf")]
 mod zeroconf;
 #[cfg(feature = "zeroconf")]
-pub use self::zeroconf::publish_service;
+pub use self::zeroconf::{discover_services, publish_service, PublishedService};
 
 #[cfg(feature = "mdns-sd")]
 mod mdns;
 #[cfg(feature = "mdns-sd")]
 #[cfg(not(feature = "zeroconf"))]
-pub use mdns::publish_service;
+pub use mdns::{discover_services, publish_service, PublishedService};
 
+mod health;
+mod media_proxy;
+mod rebind;
 mod ws;
+pub use health::{health, ready};
+pub use media_proxy::media_proxy;
+pub use rebind::{rebind_listener, ListenPorts};
 pub use ws::{json_error_handler, ws_index};
 
+/// Normalize the configured base path of the HTTP endpoints.
+///
+/// returns: the base path with a leading and without a trailing slash, or an empty string if no
+/// prefix is configured.
+pub fn normalize_base_path(base_path: &str) -> String {
+    let path = base_path.trim().trim_matches('/');
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!("/{path}")
+    }
+}
+
+/// Register the HTTP endpoints under the normalized base path.
+pub fn api_scope(base_path: &str) -> Scope {
+    web::scope(base_path)
+        .service(ws_index)
+        .service(health)
+        .service(ready)
+        .service(media_proxy)
+}
+
+/// WebSocket endpoint path advertised with mDNS.
+pub fn ws_path(base_path: &str) -> String {
+    format!("{base_path}/ws")
+}
+
+/// Base URL of the integration as reachable by the remote for the media image proxy.
+///
+/// # Arguments
+///
+/// * `proxy_url`: configured base URL of the integration. Empty = local http listener.
+/// * `http_port`: port of the http listener.
+/// * `base_path`: normalized base path of the HTTP endpoints.
+pub fn image_proxy_base_url(proxy_url: &str, http_port: u16, base_path: &str) -> String {
+    let proxy_url = proxy_url.trim().trim_end_matches('/');
+    if proxy_url.is_empty() {
+        format!("http://127.0.0.1:{http_port}{base_path}")
+    } else {
+        proxy_url.to_string()
+    }
+}
+
+/// Fallback if no mDNS library is enabled
+#[cfg(not(feature = "zeroconf"))]
+#[cfg(not(feature = "mdns-sd"))]
+pub struct PublishedService;
+
 /// Fallback if no mDNS library is enabled
 #[cfg(not(feature = "zeroconf"))]
 #[cfg(not(feature = "mdns-sd"))]
@@ -27,7 +86,102 @@ pub fn publish_service(
     _reg_type: impl Into<String>,
     _port: u16,
     _txt: Vec<String>,
-) -> Result<(), crate::errors::ServiceError> {
+) -> Result<PublishedService, crate::errors::ServiceError> {
     log::warn!("No mDNS library support included: service will not be published!");
-    Ok(())
+    Ok(PublishedService)
+}
+
+/// Fallback if no mDNS library is enabled
+#[cfg(not(feature = "zeroconf"))]
+#[cfg(not(feature = "mdns-sd"))]
+pub fn discover_services(
+    _service_name: impl AsRef<str>,
+    _protocol: impl AsRef<str>,
+    _timeout: std::time::Duration,
+) -> Vec<DiscoveredService> {
+    log::warn!("No mDNS library support included: services cannot be discovered!");
+    Vec::new()
+}
+
+/// A service instance found with mDNS discovery.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DiscoveredService {
+    /// Service instance name.
+    pub name: String,
+    pub hostname: String,
+    pub addresses: Vec<IpAddr>,
+    pub port: u16,
+    /// TXT record properties.
+    pub txt: HashMap<String, String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("", "")]
+    #[case("/", "")]
+    #[case("hass", "/hass")]
+    #[case("/hass", "/hass")]
+    #[case("/hass/", "/hass")]
+    #[case(" /proxy/hass/ ", "/proxy/hass")]
+    fn base_path_is_normalized(#[case] base_path: &str, #[case] expected: &str) {
+        assert_eq!(expected, normalize_base_path(base_path));
+    }
+
+    #[rstest]
+    #[case("", "/ws")]
+    #[case("/hass", "/hass/ws")]
+    fn advertised_ws_path(#[case] base_path: &str, #[case] expected: &str) {
+        assert_eq!(expected, ws_path(base_path));
+    }
+
+    #[rstest]
+    #[case("", "", "http://127.0.0.1:8000")]
+    #[case("", "/hass", "http://127.0.0.1:8000/hass")]
+    #[case("http://192.168.1.5:8000/", "/hass", "http://192.168.1.5:8000")]
+    #[case(" https://intg.local/hass ", "", "https://intg.local/hass")]
+    fn image_proxy_url(#[case] proxy_url: &str, #[case] base_path: &str, #[case] expected: &str) {
+        assert_eq!(expected, image_proxy_base_url(proxy_url, 8000, base_path));
+    }
+
+    #[actix::test]
+    async fn routes_are_registered_under_prefix() {
+        let app = test::init_service(App::new().service(api_scope("/hass"))).await;
+
+        for path in [
+            "/hass/ws",
+            "/hass/health",
+            "/hass/ready",
+            "/hass/media_proxy/media_player.tv",
+        ] {
+            let request = test::TestRequest::get().uri(path).to_request();
+            let response = test::call_service(&app, request).await;
+
+            // missing app data: the route exists but the request fails
+            assert_ne!(StatusCode::NOT_FOUND, response.status(), "{path}");
+        }
+        for path in ["/ws", "/health", "/ready", "/hass"] {
+            let request = test::TestRequest::get().uri(path).to_request();
+            let response = test::call_service(&app, request).await;
+
+            assert_eq!(StatusCode::NOT_FOUND, response.status(), "{path}");
+        }
+    }
+
+    #[actix::test]
+    async fn routes_without_prefix() {
+        let app = test::init_service(App::new().service(api_scope(""))).await;
+
+        for path in ["/ws", "/health", "/ready"] {
+            let request = test::TestRequest::get().uri(path).to_request();
+            let response = test::call_service(&app, request).await;
+
+            assert_ne!(StatusCode::NOT_FOUND, response.status(), "{path}");
+        }
+    }
 }
@@ -0,0 +1,123 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Runtime reconfiguration of the integration server listen ports.
+
+use std::io;
+use std::net::TcpListener;
+
+/// Requested integration server listen ports.
+///
+/// A `None` value keeps the current listen port.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ListenPorts {
+    pub http: Option<u16>,
+    pub https: Option<u16>,
+}
+
+impl ListenPorts {
+    pub fn is_empty(&self) -> bool {
+        self.http.is_none() && self.https.is_none()
+    }
+}
+
+/// Bind a new TCP listener if the requested port differs from the port of the current listener.
+///
+/// The current listener is not modified. If binding the new port fails, the server can continue
+/// to use the current listener.
+///
+/// # Arguments
+///
+/// * `current`: current TCP listener of the server.
+/// * `interface`: network interface to bind to.
+/// * `port`: requested listen port.
+///
+/// returns: `None` if the current listener is already bound to the requested port, otherwise the
+/// new listener.
+pub fn rebind_listener(
+    current: &TcpListener,
+    interface: &str,
+    port: u16,
+) -> io::Result<Option<TcpListener>> {
+    if port == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid listen port: 0",
+        ));
+    }
+    if current.local_addr()?.port() == port {
+        return Ok(None);
+    }
+
+    TcpListener::bind(format!("{interface}:{port}")).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERFACE: &str = "127.0.0.1";
+
+    fn bind_any() -> TcpListener {
+        TcpListener::bind(format!("{INTERFACE}:0")).expect("failed to bind test listener")
+    }
+
+    #[test]
+    fn rebind_listener_with_same_port_returns_none() {
+        let current = bind_any();
+        let port = current.local_addr().unwrap().port();
+
+        let result = rebind_listener(&current, INTERFACE, port);
+        assert!(
+            matches!(result, Ok(None)),
+            "Expected no rebind but got: {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn rebind_listener_with_new_port_binds_new_listener() {
+        let current = bind_any();
+        let free_port = {
+            let listener = bind_any();
+            listener.local_addr().unwrap().port()
+        };
+
+        let result = rebind_listener(&current, INTERFACE, free_port);
+        assert!(
+            result.is_ok(),
+            "Expected successful rebind but got: {:?}",
+            result
+        );
+        let listener = result.unwrap().expect("new listener expected");
+        assert_eq!(free_port, listener.local_addr().unwrap().port());
+        // current listener must still be usable
+        assert!(current.local_addr().is_ok());
+    }
+
+    #[test]
+    fn rebind_listener_with_port_in_use_returns_error() {
+        let current = bind_any();
+        let other = bind_any();
+        let used_port = other.local_addr().unwrap().port();
+
+        let result = rebind_listener(&current, INTERFACE, used_port);
+        assert!(
+            matches!(result, Err(ref e) if e.kind() == io::ErrorKind::AddrInUse),
+            "Expected AddrInUse error but got: {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn rebind_listener_with_port_zero_returns_error() {
+        let current = bind_any();
+
+        let result = rebind_listener(&current, INTERFACE, 0);
+        assert!(
+            matches!(result, Err(ref e) if e.kind() == io::ErrorKind::InvalidInput),
+            "Expected InvalidInput error but got: {:?}",
+            result
+        );
+    }
+}
@@ -52,10 +52,14 @@ impl Actor for WsConn {
                 id: self.id.clone(),
             })
             .into_actor(self)
-            .then(|res, _, ctx| {
-                if let Err(e) = res {
-                    error!("Error registering new WebSocket connection: {e}");
-                    ctx.stop();
+            .then(|res, act, ctx| {
+                match res {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => act.close(CloseCode::Again, &e.to_string(), ctx),
+                    Err(e) => {
+                        error!("Error registering new WebSocket connection: {e}");
+                        ctx.stop();
+                    }
                 }
                 fut::ready(())
             })
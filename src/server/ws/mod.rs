@@ -85,7 +85,6 @@ pub async fn ws_index(
         }
     }
 
-    // TODO limit number of active ws sessions?
     // use peer IP:port as unique client identifier
     let client_id = request
         .peer_addr()
@@ -1,16 +1,43 @@
 // Copyright (c) 2023 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
 // SPDX-License-Identifier: MPL-2.0
 
-//! mDNS advertisement with Zeroconf (Avahi or Bonjour)
+//! mDNS advertisement and discovery with Zeroconf (Avahi or Bonjour)
 
 use crate::errors::ServiceError;
+use crate::server::DiscoveredService;
 
-use log::{error, info};
+use log::{debug, error, info};
 use std::any::Any;
-use std::sync::Arc;
-use std::time::Duration;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use zeroconf::prelude::*;
-use zeroconf::{MdnsService, ServiceRegistration, ServiceType, TxtRecord};
+use zeroconf::{
+    MdnsBrowser, MdnsService, ServiceDiscovery, ServiceRegistration, ServiceType, TxtRecord,
+};
+
+/// Handle of a published mDNS service.
+///
+/// The service is unregistered when the handle is dropped.
+pub struct PublishedService {
+    stop: Arc<AtomicBool>,
+    publisher: Option<JoinHandle<()>>,
+}
+
+impl Drop for PublishedService {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // wait for the unregistration, otherwise a new registration might conflict
+        if let Some(publisher) = self.publisher.take() {
+            if publisher.join().is_err() {
+                error!("mDNS service publisher thread panicked");
+            }
+        }
+    }
+}
 
 /// Publish a service on all available network interfaces with the default hostname.
 ///
@@ -27,21 +54,31 @@ pub fn publish_service(
     protocol: impl AsRef<str>,
     port: u16,
     txt: Vec<String>,
-) -> Result<(), ServiceError> {
+) -> Result<PublishedService, ServiceError> {
     let instance_name = instance_name.to_string();
     let service = ServiceType::new(service_name.as_ref(), protocol.as_ref())
         .map_err(|e| ServiceError::BadRequest(e.to_string()))?;
-    std::thread::spawn(move || service_publisher(instance_name, service, port, txt));
+    let stop = Arc::new(AtomicBool::new(false));
+    let publisher_stop = stop.clone();
+    let publisher = std::thread::spawn(move || {
+        service_publisher(instance_name, service, port, txt, publisher_stop)
+    });
 
-    Ok(())
+    Ok(PublishedService {
+        stop,
+        publisher: Some(publisher),
+    })
 }
 
-/// Publisher thread polling the event loop.
+/// Publisher thread polling the event loop until the service is unpublished.
+///
+/// The service is unregistered when the thread ends.
 fn service_publisher(
     instance_name: String,
     service_type: ServiceType,
     port: u16,
     txt: Vec<String>,
+    stop: Arc<AtomicBool>,
 ) {
     let mut service = MdnsService::new(service_type, port);
     let mut txt_record = TxtRecord::new();
@@ -64,13 +101,14 @@ fn service_publisher(
         }
     };
 
-    loop {
+    while !stop.load(Ordering::Relaxed) {
         // What is a good production timeout?
         if let Err(e) = event_loop.poll(Duration::from_secs(1)) {
             error!("mDNS event loop polling error: {e}");
             return;
         }
     }
+    info!("Unregistering mDNS service {instance_name} on port {port}");
 }
 
 fn on_service_registered(
@@ -82,3 +120,76 @@ fn on_service_registered(
         Err(e) => error!("Service registration error: {e}"),
     }
 }
+
+/// Discover service instances in the local network.
+///
+/// Attention: blocks the calling thread until the timeout expires.
+///
+/// # Arguments
+///
+/// * `service_name`: The service name (e.g. `http`).
+/// * `protocol`: The protocol of the service (e.g. `tcp`).
+/// * `timeout`: How long to browse for service instances.
+pub fn discover_services(
+    service_name: impl AsRef<str>,
+    protocol: impl AsRef<str>,
+    timeout: Duration,
+) -> Vec<DiscoveredService> {
+    let service_type = match ServiceType::new(service_name.as_ref(), protocol.as_ref()) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Invalid service type: {e}");
+            return Vec::new();
+        }
+    };
+
+    let services = Arc::new(Mutex::new(Vec::new()));
+    let discovered = services.clone();
+    let mut browser = MdnsBrowser::new(service_type);
+    browser.set_service_discovered_callback(Box::new(
+        move |result: zeroconf::Result<ServiceDiscovery>, _context: Option<Arc<dyn Any>>| {
+            match result {
+                Ok(service) => {
+                    debug!("Discovered service: {service:?}");
+                    if let Ok(mut services) = discovered.lock() {
+                        services.push(discovered_service(&service));
+                    }
+                }
+                Err(e) => error!("Service discovery error: {e}"),
+            }
+        },
+    ));
+
+    let event_loop = match browser.browse_services() {
+        Ok(el) => el,
+        Err(e) => {
+            error!("Failed to browse services! Error: {e}");
+            return Vec::new();
+        }
+    };
+
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if let Err(e) = event_loop.poll(Duration::from_millis(100)) {
+            error!("mDNS event loop polling error: {e}");
+            break;
+        }
+    }
+
+    let result = services.lock().map(|s| s.clone()).unwrap_or_default();
+    result
+}
+
+fn discovered_service(service: &ServiceDiscovery) -> DiscoveredService {
+    DiscoveredService {
+        name: service.name().clone(),
+        hostname: service.host_name().clone(),
+        addresses: IpAddr::from_str(service.address()).into_iter().collect(),
+        port: *service.port(),
+        txt: service
+            .txt()
+            .as_ref()
+            .map(|txt| txt.iter().collect())
+            .unwrap_or_default(),
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Language text helper functions.
+
+use std::collections::HashMap;
+
+/// Fallback language of a language text map.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// Create a language text map with the given text for the default language and an optional
+/// additional language.
+///
+/// # Arguments
+///
+/// * `text`: text for all languages, e.g. a HA friendly name.
+/// * `language`: optional language code, e.g. `de` or `en-GB` from the HA configuration.
+///
+/// returns: language text map, always containing the default language `en`.
+pub fn language_map(text: &str, language: Option<&str>) -> HashMap<String, String> {
+    let mut map = HashMap::with_capacity(2);
+    map.insert(DEFAULT_LANGUAGE.to_string(), text.to_string());
+    if let Some(language) = language.and_then(normalize_language_code) {
+        map.insert(language, text.to_string());
+    }
+    map
+}
+
+/// Convert a HA language code like `en-GB` to the Integration-API format `en_GB`.
+///
+/// Returns `None` for an empty language code.
+fn normalize_language_code(language: &str) -> Option<String> {
+    let language = language.trim();
+    if language.is_empty() {
+        return None;
+    }
+    Some(language.replace('-', "_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn language_map_without_language_has_default_language_only() {
+        let map = language_map("Living Room", None);
+
+        assert_eq!(1, map.len());
+        assert_eq!(Some(&"Living Room".to_string()), map.get(DEFAULT_LANGUAGE));
+    }
+
+    #[rstest]
+    #[case("de", "de")]
+    #[case("en-GB", "en_GB")]
+    #[case("zh-Hans", "zh_Hans")]
+    fn language_map_with_language(#[case] language: &str, #[case] key: &str) {
+        let map = language_map("Wohnzimmer", Some(language));
+
+        assert_eq!(Some(&"Wohnzimmer".to_string()), map.get(DEFAULT_LANGUAGE));
+        assert_eq!(Some(&"Wohnzimmer".to_string()), map.get(key));
+    }
+
+    #[rstest]
+    #[case("en")]
+    #[case("")]
+    #[case("  ")]
+    fn language_map_with_default_or_empty_language(#[case] language: &str) {
+        let map = language_map("Living Room", Some(language));
+
+        assert_eq!(1, map.len());
+        assert_eq!(Some(&"Living Room".to_string()), map.get(DEFAULT_LANGUAGE));
+    }
+}
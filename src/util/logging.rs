@@ -0,0 +1,77 @@
+// Copyright (c) 2024 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Logger initialization with an optional instance prefix in the log lines.
+
+use log::Level;
+use std::fmt::{Arguments, Display};
+use std::io::Write;
+
+/// Initialize the global logger with an optional log line prefix.
+///
+/// The prefix identifies the integration instance if multiple instances are running, e.g. one
+/// instance per Home Assistant server. Without prefix, the default `env_logger` format is used.
+///
+/// # Arguments
+///
+/// * `default_filter`: default log filter if `RUST_LOG` is not set.
+/// * `prefix`: optional instance name added to each log line.
+pub fn init_logger(default_filter: &str, prefix: Option<&str>) {
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_filter));
+
+    if let Some(prefix) = prefix.map(str::trim).filter(|p| !p.is_empty()) {
+        let prefix = prefix.to_string();
+        builder.format(move |buf, record| {
+            let line = prefixed_log_line(
+                &prefix,
+                buf.timestamp(),
+                record.level(),
+                record.target(),
+                record.args(),
+            );
+            writeln!(buf, "{line}")
+        });
+    }
+
+    builder.init();
+}
+
+/// Format a log line with the instance prefix, similar to the default `env_logger` format.
+fn prefixed_log_line(
+    prefix: &str,
+    timestamp: impl Display,
+    level: Level,
+    target: &str,
+    args: &Arguments,
+) -> String {
+    format!("[{timestamp} {level:<5} {prefix} {target}] {args}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixed_log_line_contains_prefix() {
+        let line = prefixed_log_line(
+            "ha-home",
+            "2024-06-01T12:00:00Z",
+            Level::Info,
+            "uc_intg_hass::client",
+            &format_args!("Connected to: {}", "ws://homeassistant.local:8123"),
+        );
+
+        assert_eq!(
+            "[2024-06-01T12:00:00Z INFO  ha-home uc_intg_hass::client] Connected to: ws://homeassistant.local:8123",
+            line
+        );
+    }
+
+    #[test]
+    fn prefixed_log_line_aligns_level() {
+        let line = prefixed_log_line("ha-2", "ts", Level::Warn, "target", &format_args!("msg"));
+
+        assert_eq!("[ts WARN  ha-2 target] msg", line);
+    }
+}
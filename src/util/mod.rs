@@ -8,6 +8,8 @@ mod color;
 mod env;
 mod from_msg_data;
 pub mod json;
+mod language;
+mod logging;
 mod macros;
 mod network;
 
@@ -15,5 +17,7 @@ pub use certificates::create_single_cert_server_config;
 pub use color::*;
 pub use env::*;
 pub use from_msg_data::DeserializeMsgData;
+pub use language::*;
+pub use logging::init_logger;
 pub(crate) use macros::*;
 pub use network::*;
@@ -1,12 +1,21 @@
 // Copyright (c) 2023 Unfolded Circle ApS, Markus Zehnder <markus.z@unfoldedcircle.com>
 // SPDX-License-Identifier: MPL-2.0
 
-use crate::configuration::ENV_DISABLE_CERT_VERIFICATION;
+use crate::configuration::{TcpKeepaliveSettings, UrlTokenSettings, ENV_DISABLE_CERT_VERIFICATION};
 use crate::util::bool_from_env;
+use actix_service::{always_ready, Service};
 use actix_tls::connect::rustls_0_21::webpki_roots_cert_store;
+use actix_tls::connect::{ConnectError, ConnectInfo, Connection, ConnectorService};
+use actix_web::http::Uri;
+use actix_web::rt::net::TcpStream;
+use futures::future::LocalBoxFuture;
+use log::warn;
 use rustls::ClientConfig;
+use socket2::{SockRef, TcpKeepalive};
+use std::fmt;
 use std::sync::Arc;
 use std::time::Duration;
+use url::Url;
 
 #[cfg(feature = "mdns-sd")]
 pub fn my_ipv4_interfaces() -> Vec<if_addrs::IfAddr> {
@@ -30,7 +39,13 @@ pub fn new_websocket_client(
     connection_timeout: Duration,
     request_timeout: Duration,
     tls: bool,
+    keepalive: &TcpKeepaliveSettings,
 ) -> awc::Client {
+    let tcp_connector = KeepaliveConnector {
+        inner: ConnectorService::default(),
+        keepalive: tcp_keepalive(keepalive),
+    };
+
     if tls {
         // TLS configuration: https://github.com/actix/actix-web/blob/master/awc/tests/test_rustls_client.rs
         // TODO self-signed certificate handling #4
@@ -51,6 +66,7 @@ pub fn new_websocket_client(
         }
 
         let connector = awc::Connector::new()
+            .connector(tcp_connector)
             .rustls_021(Arc::new(config))
             .timeout(connection_timeout);
         awc::ClientBuilder::new()
@@ -60,11 +76,95 @@ pub fn new_websocket_client(
     } else {
         awc::ClientBuilder::new()
             .timeout(request_timeout)
-            .connector(awc::Connector::new().timeout(connection_timeout))
+            .connector(
+                awc::Connector::new()
+                    .connector(tcp_connector)
+                    .timeout(connection_timeout),
+            )
             .finish()
     }
 }
 
+/// Get the WebSocket connection URL with the optional token query parameter.
+pub fn ws_url_with_token(url: &Url, url_token: &UrlTokenSettings) -> Url {
+    let mut url = url.clone();
+    if !url_token.param.is_empty() && !url_token.token.is_empty() {
+        url.query_pairs_mut()
+            .append_pair(&url_token.param, &url_token.token);
+    }
+    url
+}
+
+/// Redact all query parameter values of a URL for logging, since they might contain credentials.
+pub fn redact_url(url: &Url) -> String {
+    if url.query().is_none() {
+        return url.to_string();
+    }
+    let mut redacted = url.clone();
+    let params: Vec<String> = url.query_pairs().map(|(key, _)| key.into_owned()).collect();
+    redacted
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(params.iter().map(|key| (key, "***")));
+    redacted.to_string()
+}
+
+/// Convert the keepalive settings to socket options.
+///
+/// returns: `None` if keepalive is disabled.
+fn tcp_keepalive(settings: &TcpKeepaliveSettings) -> Option<TcpKeepalive> {
+    if settings.time.is_zero() {
+        return None;
+    }
+    let keepalive = TcpKeepalive::new().with_time(settings.time);
+    #[cfg(not(any(target_os = "openbsd", target_os = "redox", target_os = "solaris")))]
+    let keepalive = if settings.interval.is_zero() {
+        keepalive
+    } else {
+        keepalive.with_interval(settings.interval)
+    };
+    Some(keepalive)
+}
+
+/// TCP connector enabling keepalive on the connected socket.
+///
+/// Dead connections are detected by the OS, even if no data is sent by the WebSocket client.
+#[derive(Clone)]
+struct KeepaliveConnector {
+    inner: ConnectorService,
+    keepalive: Option<TcpKeepalive>,
+}
+
+impl fmt::Debug for KeepaliveConnector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeepaliveConnector")
+            .field("keepalive", &self.keepalive)
+            .finish()
+    }
+}
+
+impl Service<ConnectInfo<Uri>> for KeepaliveConnector {
+    type Response = Connection<Uri, TcpStream>;
+    type Error = ConnectError;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    always_ready!();
+
+    fn call(&self, req: ConnectInfo<Uri>) -> Self::Future {
+        let connect = self.inner.call(req);
+        let keepalive = self.keepalive.clone();
+        Box::pin(async move {
+            let connection = connect.await?;
+            if let Some(keepalive) = keepalive {
+                if let Err(e) = SockRef::from(connection.io_ref()).set_tcp_keepalive(&keepalive) {
+                    warn!("Failed to enable TCP keepalive: {e}");
+                }
+            }
+            Ok(connection)
+        })
+    }
+}
+
 mod danger {
     use rustls::client::{ServerCertVerified, ServerCertVerifier};
     use std::time::SystemTime;
@@ -85,3 +185,103 @@ mod danger {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tcp_keepalive_settings_from_config() {
+        let settings: TcpKeepaliveSettings =
+            serde_json::from_value(json!({ "time_sec": 45, "interval_sec": 5 })).unwrap();
+
+        assert_eq!(
+            TcpKeepaliveSettings {
+                time: Duration::from_secs(45),
+                interval: Duration::from_secs(5),
+            },
+            settings
+        );
+    }
+
+    #[test]
+    fn tcp_keepalive_is_enabled_with_time() {
+        let keepalive = tcp_keepalive(&TcpKeepaliveSettings::default());
+
+        assert!(keepalive.is_some(), "Expected enabled TCP keepalive");
+    }
+
+    #[test]
+    fn tcp_keepalive_with_zero_time_is_disabled() {
+        let keepalive = tcp_keepalive(&TcpKeepaliveSettings {
+            time: Duration::ZERO,
+            interval: Duration::from_secs(10),
+        });
+
+        assert!(keepalive.is_none(), "Expected disabled TCP keepalive");
+    }
+
+    fn url_token(param: &str, token: &str) -> UrlTokenSettings {
+        UrlTokenSettings {
+            param: param.into(),
+            token: token.into(),
+        }
+    }
+
+    #[test]
+    fn ws_url_with_query_token() {
+        let url = Url::parse("wss://proxy.example.com/api/websocket").unwrap();
+
+        let result = ws_url_with_token(&url, &url_token("access_token", "s3cr3t&x"));
+
+        assert_eq!(
+            "wss://proxy.example.com/api/websocket?access_token=s3cr3t%26x",
+            result.as_str()
+        );
+    }
+
+    #[test]
+    fn ws_url_with_query_token_keeps_existing_query() {
+        let url = Url::parse("wss://proxy.example.com/api/websocket?site=home").unwrap();
+
+        let result = ws_url_with_token(&url, &url_token("token", "s3cr3t"));
+
+        assert_eq!(
+            "wss://proxy.example.com/api/websocket?site=home&token=s3cr3t",
+            result.as_str()
+        );
+    }
+
+    #[test]
+    fn ws_url_without_query_token() {
+        let url = Url::parse("ws://homeassistant.local:8123/api/websocket").unwrap();
+
+        assert_eq!(url, ws_url_with_token(&url, &url_token("", "s3cr3t")));
+        assert_eq!(url, ws_url_with_token(&url, &url_token("token", "")));
+    }
+
+    #[test]
+    fn redact_url_hides_query_values() {
+        let url =
+            Url::parse("wss://proxy.example.com/api/websocket?site=home&token=s3cr3t").unwrap();
+
+        let result = redact_url(&url);
+
+        assert!(
+            !result.contains("s3cr3t"),
+            "Token must be redacted: {result}"
+        );
+        assert_eq!(
+            "wss://proxy.example.com/api/websocket?site=***&token=***",
+            result
+        );
+    }
+
+    #[test]
+    fn redact_url_without_query() {
+        let url = Url::parse("ws://homeassistant.local:8123/api/websocket").unwrap();
+
+        assert_eq!(url.as_str(), redact_url(&url));
+    }
+}